@@ -0,0 +1,34 @@
+// 大厅服务器：cargo run --bin server -- [端口]
+// 客户端在联机菜单里选 LOBBY SERVER，填这台机器的地址
+use std::time::Duration;
+
+use tetris_rs::net::{server::Server, SERVER_PORT};
+
+fn main() {
+    let port = match std::env::args().nth(1) {
+        Some(port) => port.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid port: {}", port);
+            std::process::exit(1);
+        }),
+        None => SERVER_PORT,
+    };
+    let mut server = Server::bind(port).unwrap_or_else(|err| {
+        eprintln!("Failed to listen on port {}: {}", port, err);
+        std::process::exit(1);
+    });
+    println!("Listening on port {}", port);
+    loop {
+        match server.step() {
+            Ok(reports) => {
+                for report in reports {
+                    println!("{}", report);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use super::bot::Driver;
+use super::mode::Mode;
+use super::targeting::{Router, Targeting};
+use super::{Engine, Event, Stats};
+
+// 两个电脑玩家对打：各玩各的 marathon，消行打出去的攻击先抵消自己排着的垃圾，
+// 剩下的排进对面的垃圾队列，先顶满的输。不画画面，一帧接一帧尽快跑完，调攻击表和 AI 参数用
+pub const MODE: Mode = Mode::Marathon;
+// 打了这么久还没分出胜负的算平局
+pub const MAX_FRAMES: u64 = 10 * 60 * Stats::FRAMES_PER_SECOND;
+
+struct Side {
+    engine: Engine,
+    driver: Driver,
+    events: Vec<Event>,
+}
+
+pub struct Battle {
+    sides: [Side; 2],
+    // 两边已经打出去了多少攻击
+    sent: [u32; 2],
+    frames: u64,
+}
+
+// 三个人以上的大乱斗：攻击抵消完自己的垃圾之后，按每个人自己的打法由 `Router` 分给对手，
+// 最后一个活着的赢。也可以组队（比如 2v2）：同一队的攻击各自抵消完垃圾之后合在一起，
+// 由这一帧打得最多的队员按他的打法分给别的队的人，一队的人都顶满了这一队才输
+pub struct Royale {
+    sides: Vec<Side>,
+    sent: Vec<u32>,
+    router: Router,
+    // 按顶满的先后
+    knocked_out: Vec<usize>,
+    frames: u64,
+}
+
+// 大乱斗的结果：顶满的先后，最后剩下的一个（组队的话是一队）是赢家；
+// 打到时间还没分出来的没有赢家
+#[derive(Clone, Debug)]
+pub struct Standings {
+    pub winner: Option<usize>,
+    pub knocked_out: Vec<usize>,
+    pub badges: Vec<u32>,
+    pub frames: u64,
+}
+
+// 一局的结果，`winner` 是 0 或 1，平局是 None
+#[derive(Clone, Copy, Debug)]
+pub struct Outcome {
+    pub seed: u64,
+    pub winner: Option<usize>,
+    pub frames: u64,
+    pub stats: [Stats; 2],
+}
+
+// 把两边从上次到现在新打出的攻击交换一下，`sent` 记着各自已经算过多少
+pub fn exchange(engines: [&mut Engine; 2], sent: &mut [u32; 2]) {
+    let [left, right] = engines;
+    let attacks = [left.stats.attack, right.stats.attack];
+    let fresh = [0, 1].map(|side| attacks[side] - sent[side]);
+    *sent = attacks;
+    let to_right = left.counter_garbage(fresh[0]);
+    let to_left = right.counter_garbage(fresh[1]);
+    left.queue_garbage(to_left);
+    right.queue_garbage(to_right);
+}
+
+// 只有一边顶满的话另一边赢；同一帧一起顶满的、或者都还活着的是 None
+pub fn winner(engines: [&Engine; 2]) -> Option<usize> {
+    match engines.map(Engine::is_game_over) {
+        [false, true] => Some(0),
+        [true, false] => Some(1),
+        _ => None,
+    }
+}
+
+impl Battle {
+    // 两边用同一个种子，方块顺序一样
+    pub fn new(seed: u64, drivers: [Driver; 2]) -> Self {
+        let sides = drivers.map(|driver| {
+            let mut engine = Engine::with_seed(MODE, seed);
+            engine.spawn_first();
+            Side {
+                engine,
+                driver,
+                events: Vec::new(),
+            }
+        });
+        Self {
+            sides,
+            sent: [0, 0],
+            frames: 0,
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.frames >= MAX_FRAMES || self.sides.iter().any(|side| side.engine.is_game_over())
+    }
+
+    // 两边各走一帧，再把这一帧打出去的攻击送给对面
+    pub fn step(&mut self) {
+        for side in &mut self.sides {
+            side.driver.update(&mut side.engine, &side.events);
+            side.engine.tick();
+            side.events = side.engine.take_events();
+        }
+        let [left, right] = &mut self.sides;
+        exchange([&mut left.engine, &mut right.engine], &mut self.sent);
+        self.frames += 1;
+    }
+
+    pub fn run(mut self, seed: u64) -> Outcome {
+        while !self.is_over() {
+            self.step();
+        }
+        let [left, right] = &self.sides;
+        Outcome {
+            seed,
+            winner: winner([&left.engine, &right.engine]),
+            frames: self.frames,
+            stats: self.sides.each_ref().map(|side| side.engine.stats),
+        }
+    }
+}
+
+impl Royale {
+    // 一人一队
+    pub fn new(seed: u64, players: Vec<(Driver, Targeting)>) -> Self {
+        let players = players.into_iter().map(|player| vec![player]).collect();
+        Self::teams(seed, players)
+    }
+
+    // 按队伍顺序给玩家编号：2v2 的话 0、1 是第 0 队，2、3 是第 1 队
+    pub fn teams(seed: u64, teams: Vec<Vec<(Driver, Targeting)>>) -> Self {
+        let team_of: Vec<usize> = teams
+            .iter()
+            .enumerate()
+            .flat_map(|(team, players)| vec![team; players.len()])
+            .collect();
+        let (drivers, targeting): (Vec<_>, Vec<_>) = teams.into_iter().flatten().unzip();
+        let sides = drivers
+            .into_iter()
+            .map(|driver| {
+                let mut engine = Engine::with_seed(MODE, seed);
+                engine.spawn_first();
+                Side {
+                    engine,
+                    driver,
+                    events: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>();
+        Self {
+            sent: vec![0; sides.len()],
+            sides,
+            router: Router::with_teams(targeting, team_of, seed),
+            knocked_out: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.frames >= MAX_FRAMES || self.router.teams_alive().len() <= 1
+    }
+
+    // 活着的各走一帧，各自抵消完垃圾之后按队伍合起来分出去，再看谁顶满了
+    pub fn step(&mut self) {
+        for (i, side) in self.sides.iter_mut().enumerate() {
+            if self.router.is_alive(i) {
+                side.driver.update(&mut side.engine, &side.events);
+                side.engine.tick();
+                side.events = side.engine.take_events();
+            }
+        }
+        // 每一队合起来的行数，和这一帧打得最多的队员
+        let mut pools: BTreeMap<usize, (u32, usize, u32)> = BTreeMap::new();
+        for i in 0..self.sides.len() {
+            if !self.router.is_alive(i) {
+                continue;
+            }
+            let engine = &mut self.sides[i].engine;
+            let attack = engine.stats.attack;
+            let fresh = attack - self.sent[i];
+            self.sent[i] = attack;
+            let lines = engine.counter_garbage(fresh);
+            let pool = pools.entry(self.router.team(i)).or_insert((0, i, 0));
+            pool.0 += lines;
+            if lines > pool.2 {
+                (pool.1, pool.2) = (i, lines);
+            }
+        }
+        for (total, from, _) in pools.into_values() {
+            for (target, lines) in self.router.route(from, total) {
+                self.sides[target].engine.queue_garbage(lines);
+            }
+        }
+        for (i, side) in self.sides.iter().enumerate() {
+            let engine = &side.engine;
+            let danger = engine.matrix().stack_height() as u32 + engine.garbage().total();
+            self.router.set_danger(i, danger);
+            if engine.is_game_over() && self.router.is_alive(i) {
+                self.router.knock_out(i);
+                self.knocked_out.push(i);
+            }
+        }
+        self.frames += 1;
+    }
+
+    pub fn run(mut self) -> Standings {
+        while !self.is_over() {
+            self.step();
+        }
+        let survivors = self.router.teams_alive();
+        Standings {
+            winner: match survivors[..] {
+                [winner] => Some(winner),
+                _ => None,
+            },
+            knocked_out: self.knocked_out,
+            badges: self.router.badges().to_vec(),
+            frames: self.frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::bot::Difficulty;
+    use crate::engine::Matrix;
+
+    #[test]
+    fn attacks_counter_garbage_and_the_rest_is_sent() {
+        let drivers = [1, 2].map(|seed| Difficulty::Expert.driver_with_seed(seed));
+        let mut battle = Battle::new(7, drivers);
+        battle.sides[0].engine.stats.attack = 3;
+        battle.step();
+        assert_eq!(battle.sides[1].engine.garbage().total(), 3);
+        assert!(battle.sides[0].engine.garbage().is_empty());
+        battle.step();
+        assert_eq!(battle.sides[1].engine.garbage().total(), 3);
+
+        // 右边打回来 4 行，先抵掉自己排着的 3 行，只送过去 1 行
+        battle.sides[1].engine.stats.attack = 4;
+        battle.step();
+        assert!(battle.sides[1].engine.garbage().is_empty());
+        assert_eq!(battle.sides[0].engine.garbage().total(), 1);
+
+        // 右边顶满了，左边赢
+        battle.sides[1]
+            .engine
+            .insert_garbage(Matrix::HEIGHT as u32, 0);
+        let outcome = battle.run(7);
+        assert_eq!(outcome.winner, Some(0));
+        assert_eq!(outcome.frames, 3);
+        assert_eq!(outcome.stats[0].attack, 3);
+    }
+
+    #[test]
+    fn royale_routes_attacks_and_credits_knockouts() {
+        let players = (1..=3)
+            .map(|seed| (Difficulty::Expert.driver_with_seed(seed), Targeting::Even))
+            .collect();
+        let mut royale = Royale::new(7, players);
+        royale.sides[0].engine.stats.attack = 4;
+        royale.step();
+        assert_eq!(royale.sides[1].engine.garbage().total(), 2);
+        assert_eq!(royale.sides[2].engine.garbage().total(), 2);
+
+        // 1 号顶满了，KO 算最后打他的 0 号
+        royale.sides[1]
+            .engine
+            .insert_garbage(Matrix::HEIGHT as u32, 0);
+        royale.step();
+        assert_eq!(royale.knocked_out, [1]);
+        assert_eq!(royale.router.badges(), [1, 0, 0]);
+        royale.sides[2]
+            .engine
+            .insert_garbage(Matrix::HEIGHT as u32, 0);
+        let standings = royale.run();
+        assert_eq!(standings.winner, Some(0));
+        assert_eq!(standings.knocked_out, [1, 2]);
+
+        // 2v2：0 号和 1 号的攻击合在一起打对面，对面两个人都顶满了才输
+        let team = |seeds: [u64; 2]| {
+            seeds
+                .map(|seed| (Difficulty::Expert.driver_with_seed(seed), Targeting::Even))
+                .into()
+        };
+        let mut royale = Royale::teams(7, vec![team([1, 2]), team([3, 4])]);
+        royale.sides[0].engine.stats.attack = 3;
+        royale.sides[1].engine.stats.attack = 1;
+        royale.step();
+        let garbage: Vec<u32> = royale
+            .sides
+            .iter()
+            .map(|side| side.engine.garbage().total())
+            .collect();
+        assert_eq!(garbage, [0, 0, 2, 2]);
+        royale.sides[2]
+            .engine
+            .insert_garbage(Matrix::HEIGHT as u32, 0);
+        royale.step();
+        assert_eq!(royale.knocked_out, [2]);
+        assert!(!royale.is_over());
+        royale.sides[3]
+            .engine
+            .insert_garbage(Matrix::HEIGHT as u32, 0);
+        let standings = royale.run();
+        assert_eq!(standings.winner, Some(0));
+        assert_eq!(standings.knocked_out, [2, 3]);
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::VecDeque;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::piece::{Kind as PieceKind, Piece, Rotation};
+use super::planner::{self, Hold, Plan};
+use super::{Color, Coordinate, Engine, Event, Matrix, MoveKind, Offset, Stats};
+
+// 电脑玩家的接口：每来一个新方块看一眼局面，给出一串操作或者想放到哪里。
+// 只看 `BotView` 这份快照，碰不到引擎内部，外面写的 AI 实现这个就能接进来
+pub trait Bot {
+    fn name(&self) -> &str;
+
+    // 每帧收到上一帧的事件，不关心的可以不管
+    fn observe(&mut self, _events: &[Event]) {}
+
+    fn decide(&mut self, view: &BotView) -> Decision;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Input {
+    Left,
+    Right,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+// 当前方块（`hold` 的话是换出来的那块）转到 `rotation`，左下角移到第 `x` 列再硬降
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placement {
+    pub hold: bool,
+    pub rotation: Rotation,
+    pub x: isize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Decision {
+    Inputs(Vec<Input>),
+    Place(Placement),
+    // 这一帧先不动，下一帧再问
+    Wait,
+}
+
+// 新方块出来时的局面
+#[derive(Clone)]
+pub struct BotView {
+    pub matrix: Matrix,
+    pub cursor: Piece,
+    pub hold: Option<PieceKind>,
+    // 这个模式能不能 hold，和当前这块还能不能 hold
+    pub hold_allowed: bool,
+    pub can_hold: bool,
+    pub next: Vec<PieceKind>,
+    pub stats: Stats,
+}
+
+impl BotView {
+    // 没有正在下落的方块，或者已经结束了的话是 None
+    pub fn of(engine: &Engine) -> Option<Self> {
+        let cursor = engine.cursor.filter(|_| !engine.is_over())?;
+        Some(Self {
+            matrix: engine.matrix.clone(),
+            cursor,
+            hold: engine.hold,
+            hold_allowed: engine.mode.rules().hold,
+            can_hold: engine.mode.rules().hold && !engine.hold_used,
+            next: engine.preview(Engine::MAX_PREVIEW).collect(),
+            stats: engine.stats,
+        })
+    }
+
+    // 方块放在这里会不会和格子或者墙重叠
+    pub fn fits(&self, piece: &Piece) -> bool {
+        !self.matrix.is_clipping(piece)
+    }
+
+    // 从这里硬降下去落在哪，放不下的话是 None
+    pub fn landed(&self, piece: &Piece) -> Option<Piece> {
+        planner::landed(&self.matrix, piece)
+    }
+
+    // 当前方块打头，后面接着预览
+    pub fn queue(&self) -> Vec<PieceKind> {
+        std::iter::once(self.cursor.kind)
+            .chain(self.next.iter().copied())
+            .collect()
+    }
+
+    pub fn hold_state(&self) -> Hold {
+        match (self.hold_allowed, self.can_hold) {
+            (false, _) => Hold::Off,
+            (true, true) => Hold::Ready(self.hold),
+            (true, false) => Hold::Used(self.hold),
+        }
+    }
+
+    // 从这个局面往后看 `depth` 块，见 `planner::plan`
+    pub fn plan(&self, depth: usize) -> Option<Plan> {
+        planner::plan(&self.matrix, &self.queue(), self.hold_state(), depth)
+    }
+}
+
+impl Placement {
+    // 从 `cursor` 出发的操作：先 hold，再顺时针转，再左右移，最后硬降。
+    // hold 出来的方块和原来的出生位置一样，所以都按 `cursor` 算
+    fn inputs(&self, cursor: &Piece) -> Vec<Input> {
+        const CLOCKWISE: [Rotation; 4] = [Rotation::N, Rotation::E, Rotation::S, Rotation::W];
+        let index = |rotation| CLOCKWISE.iter().position(|&r| r == rotation).unwrap();
+        let from = if self.hold {
+            Rotation::N
+        } else {
+            cursor.rotation
+        };
+        let turns = (index(self.rotation) + 4 - index(from)) % 4;
+        let shift = self.x - cursor.position.x;
+        let step = if shift < 0 { Input::Left } else { Input::Right };
+
+        let mut inputs = Vec::new();
+        if self.hold {
+            inputs.push(Input::Hold);
+        }
+        inputs.extend(std::iter::repeat_n(Input::Rotate, turns));
+        inputs.extend(std::iter::repeat_n(step, shift.unsigned_abs()));
+        inputs.push(Input::HardDrop);
+        inputs
+    }
+}
+
+// 把电脑玩家接到一个引擎上：每帧在 `Engine::tick` 之前调一次 `update`
+pub struct Driver {
+    bot: Box<dyn Bot>,
+    pending: VecDeque<Input>,
+    // 已经替哪一块做过决定，按锁定和 hold 的次数认
+    decided: Option<(u32, u32)>,
+    // 两个操作之间隔几帧，0 是一帧里全做完
+    delay: u32,
+    timer: u32,
+    // 新方块出来先愣几帧再动；一块至少要放多少帧，限制每秒放几块
+    reaction: u32,
+    piece_frames: u32,
+    // 这一块已经想了几帧
+    waited: u32,
+}
+
+impl Driver {
+    pub fn new(bot: Box<dyn Bot>, delay: u32) -> Self {
+        Self {
+            bot,
+            pending: VecDeque::new(),
+            decided: None,
+            delay,
+            timer: 0,
+            reaction: 0,
+            piece_frames: 0,
+            waited: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.bot.name()
+    }
+
+    // `events` 是上一帧引擎吐出来的
+    pub fn update(&mut self, engine: &mut Engine, events: &[Event]) {
+        self.bot.observe(events);
+        let Some(view) = BotView::of(engine) else {
+            self.pending.clear();
+            return;
+        };
+        let piece = (engine.stats.pieces, engine.stats.holds);
+        if self.pending.is_empty() && self.decided != Some(piece) {
+            self.decided = Some(piece);
+            match self.bot.decide(&view) {
+                Decision::Inputs(inputs) => self.pending.extend(inputs),
+                Decision::Place(placement) => self.pending.extend(placement.inputs(&view.cursor)),
+                Decision::Wait => self.decided = None,
+            }
+            self.timer = 0;
+            self.waited = 0;
+        }
+        self.waited += 1;
+
+        if self.delay == 0 {
+            while let Some(input) = self.next_input() {
+                Self::apply(engine, input);
+            }
+            return;
+        }
+        if self.timer == 0 {
+            if let Some(input) = self.next_input() {
+                Self::apply(engine, input);
+            }
+        }
+        self.timer = (self.timer + 1) % self.delay;
+    }
+
+    // 反应时间没过，或者硬降下去会超过限速的话先等着
+    fn next_input(&mut self) -> Option<Input> {
+        let input = *self.pending.front()?;
+        let ready = self.waited > self.reaction
+            && (input != Input::HardDrop || self.waited >= self.piece_frames);
+        ready.then(|| self.pending.pop_front()).flatten()
+    }
+
+    fn apply(engine: &mut Engine, input: Input) {
+        match input {
+            Input::Left => {
+                let _ = engine.move_cursor(MoveKind::Left);
+            }
+            Input::Right => {
+                let _ = engine.move_cursor(MoveKind::Right);
+            }
+            Input::Rotate => engine.rotate_clockwise(),
+            Input::SoftDrop => engine.soft_drop(),
+            Input::HardDrop => engine.hard_drop(),
+            Input::Hold => engine.hold(),
+        }
+    }
+}
+
+// 自带的电脑玩家：按 `planner::plan` 的规划放第一步。
+// `depth` 是连后面的一共看几块，`misdrop` 是放偏一格的概率
+pub struct Stacker {
+    depth: usize,
+    misdrop: f64,
+    rng: StdRng,
+}
+
+impl Default for Stacker {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            misdrop: 0.0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+// 界面上的落点提示：`Stacker` 觉得当前方块该放哪，要不要先 hold
+#[derive(Clone, Copy, Debug)]
+pub struct Hint {
+    pub hold: bool,
+    pub cells: [Coordinate; Piece::CELL_COUNT],
+    pub color: Color,
+}
+
+impl Stacker {
+    // 打分最高的放法和落下去的样子，哪都放不下是 None
+    pub fn best(&self, view: &BotView) -> Option<(Placement, Piece)> {
+        let step = view.plan(self.depth)?.steps[0];
+        Some((step.placement, step.piece))
+    }
+
+    // 算一次要把所有放法都试一遍，界面上每块只调一次
+    pub fn hint(engine: &Engine) -> Option<Hint> {
+        let (placement, landed) = Self::default().best(&BotView::of(engine)?)?;
+        Some(Hint {
+            hold: placement.hold,
+            cells: landed.cells()?,
+            color: landed.kind.color(),
+        })
+    }
+}
+
+impl Bot for Stacker {
+    fn name(&self) -> &str {
+        "STACKER"
+    }
+
+    fn decide(&mut self, view: &BotView) -> Decision {
+        let Some((mut placement, landed)) = self.best(view) else {
+            // 哪都放不下就直接落下去，反正要输了
+            return Decision::Inputs(vec![Input::HardDrop]);
+        };
+        // 手滑往旁边偏一格，偏过去放不下就算了
+        if self.rng.gen_bool(self.misdrop) {
+            let shift = if self.rng.gen() { 1 } else { -1 };
+            let piece = Piece {
+                position: Offset::new(placement.x + shift, view.cursor.position.y),
+                ..landed
+            };
+            if view.landed(&piece).is_some() {
+                placement.x += shift;
+            }
+        }
+        Decision::Place(placement)
+    }
+}
+
+// 电脑对手的难度：看几块、多容易放偏、反应多快、每秒最多放几块
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Expert,
+}
+
+struct Tuning {
+    depth: usize,
+    misdrop: f64,
+    // 都是帧数
+    reaction: u32,
+    delay: u32,
+    pps: f32,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "BEGINNER",
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+            Difficulty::Expert => "EXPERT",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&tier| tier == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn tuning(self) -> Tuning {
+        let (depth, misdrop, reaction, delay, pps) = match self {
+            Difficulty::Beginner => (1, 0.3, 40, 10, 0.4),
+            Difficulty::Easy => (1, 0.15, 20, 6, 0.8),
+            Difficulty::Normal => (1, 0.05, 10, 4, 1.5),
+            Difficulty::Hard => (2, 0.02, 4, 2, 2.5),
+            Difficulty::Expert => (2, 0.0, 0, 0, 4.0),
+        };
+        Tuning {
+            depth,
+            misdrop,
+            reaction,
+            delay,
+            pps,
+        }
+    }
+
+    // 按这个难度配好的 `Stacker`
+    pub fn driver(self) -> Driver {
+        self.driver_with(StdRng::from_entropy())
+    }
+
+    // 失误也按种子来，同样的种子打出来的一局一模一样
+    pub fn driver_with_seed(self, seed: u64) -> Driver {
+        self.driver_with(StdRng::seed_from_u64(seed))
+    }
+
+    fn driver_with(self, rng: StdRng) -> Driver {
+        let tuning = self.tuning();
+        let bot = Stacker {
+            depth: tuning.depth,
+            misdrop: tuning.misdrop,
+            rng,
+        };
+        let mut driver = Driver::new(Box::new(bot), tuning.delay);
+        driver.reaction = tuning.reaction;
+        driver.piece_frames = (Stats::FRAMES_PER_SECOND as f32 / tuning.pps) as u32;
+        driver
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::mode::Mode;
+
+    // 每一块都竖着贴最左边放，看过几帧事件
+    #[derive(Default)]
+    struct LeftWall {
+        frames: u32,
+    }
+
+    impl Bot for LeftWall {
+        fn name(&self) -> &str {
+            "LEFT WALL"
+        }
+
+        fn observe(&mut self, _events: &[Event]) {
+            self.frames += 1;
+        }
+
+        fn decide(&mut self, view: &BotView) -> Decision {
+            let mut piece = view.cursor;
+            piece.rotation = Rotation::E;
+            while view.fits(&piece.moved_by(Offset::new(-1, 0))) {
+                piece = piece.moved_by(Offset::new(-1, 0));
+            }
+            assert!(view.landed(&piece).is_some());
+            Decision::Place(Placement {
+                hold: false,
+                rotation: Rotation::E,
+                x: piece.position.x,
+            })
+        }
+    }
+
+    #[test]
+    fn drivers_turn_placements_into_inputs() {
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        engine.spawn_first();
+        // 从出生的地方直接转，落在它算好的地方
+        let view = BotView::of(&engine).unwrap();
+        let Decision::Place(placement) = LeftWall::default().decide(&view) else {
+            panic!("expected a placement");
+        };
+        let planned = Piece {
+            rotation: placement.rotation,
+            position: Offset::new(placement.x, view.cursor.position.y),
+            ..view.cursor
+        };
+        let landed = view.landed(&planned).unwrap().cells().unwrap();
+        let mut driver = Driver::new(Box::new(LeftWall::default()), 0);
+        driver.update(&mut engine, &[]);
+        assert_eq!(engine.stats().pieces, 1);
+        assert!(landed.iter().all(|&cell| engine.matrix()[cell].is_some()));
+        assert!(engine.matrix().column_heights()[0] > 0);
+
+        // 隔两帧做一个操作，一块要好几帧才放下
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        engine.spawn_first();
+        let mut driver = Driver::new(Box::new(LeftWall::default()), 2);
+        let mut frames = 0;
+        while engine.stats().pieces == 0 {
+            driver.update(&mut engine, &[]);
+            frames += 1;
+        }
+        assert!(frames > 2);
+        assert!(engine.matrix().column_heights()[0] > 0);
+        assert_eq!(driver.name(), "LEFT WALL");
+    }
+
+    #[test]
+    fn stacker_keeps_the_stack_low() {
+        let mut engine = Engine::with_seed(Mode::Marathon, 1);
+        engine.spawn_first();
+        let mut driver = Driver::new(Box::<Stacker>::default(), 0);
+        for _ in 0..2000 {
+            let events = engine.take_events();
+            driver.update(&mut engine, &events);
+            engine.tick();
+        }
+        assert!(!engine.is_game_over());
+        assert!(engine.stats().lines >= 10);
+        assert!(engine.matrix().stack_height() < Matrix::HEIGHT / 2);
+    }
+
+    #[test]
+    fn difficulty_tiers_pace_the_driver() {
+        // 新手要愣一会儿才动
+        let mut engine = Engine::with_seed(Mode::Marathon, 1);
+        engine.spawn_first();
+        let spawn = engine.cursor.map(|cursor| cursor.position);
+        let mut driver = Difficulty::Beginner.driver();
+        for _ in 0..Difficulty::Beginner.tuning().reaction {
+            driver.update(&mut engine, &[]);
+        }
+        assert_eq!(engine.cursor.map(|cursor| cursor.position), spawn);
+
+        // 高手往后多看一块，但每秒最多放四块
+        let mut engine = Engine::with_seed(Mode::Marathon, 1);
+        engine.spawn_first();
+        let mut driver = Difficulty::Expert.driver();
+        let seconds = 10;
+        for _ in 0..seconds * Stats::FRAMES_PER_SECOND {
+            let events = engine.take_events();
+            driver.update(&mut engine, &events);
+            engine.tick();
+        }
+        let pieces = engine.stats().pieces as f32;
+        assert!(pieces > seconds as f32);
+        assert!(pieces <= Difficulty::Expert.tuning().pps * seconds as f32 + 1.0);
+        assert!(!engine.is_game_over());
+        assert_eq!(Difficulty::Expert.next(), Difficulty::Beginner);
+    }
+
+    #[test]
+    fn hints_hold_for_the_piece_that_fits_the_well() {
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        for y in 0..4 {
+            for x in 0..Matrix::WIDTH - 1 {
+                engine.matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+            }
+        }
+        engine.hold = Some(PieceKind::I);
+        let spawn = engine.spawn_position();
+        engine.debug_test_cursor(PieceKind::O, spawn);
+        let hint = Stacker::hint(&engine).unwrap();
+        assert!(hint.hold);
+        assert_eq!(hint.color, PieceKind::I.color());
+        assert!(hint.cells.iter().all(|cell| cell.x == Matrix::WIDTH - 1));
+
+        // 不能 hold 的时候只看当前这块
+        engine.hold_used = true;
+        assert!(!Stacker::hint(&engine).unwrap().hold);
+    }
+}
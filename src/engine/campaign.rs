@@ -0,0 +1,104 @@
+use super::mode::Mode;
+use super::puzzle::{Goal, Puzzle};
+use super::Engine;
+
+// 一关要做的事：在某个模式下从某一级开始消够行数，或者解一道内置的谜题
+#[derive(Clone, Copy, Debug)]
+pub enum Challenge {
+    Clear { mode: Mode, level: u32, lines: u32 },
+    // 谜题文件的内容，格式见 `Puzzle`
+    Puzzle(&'static str),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Stage {
+    pub name: &'static str,
+    pub challenge: Challenge,
+}
+
+// 单人闯关，按顺序一关一关解锁
+pub const STAGES: [Stage; 7] = [
+    Stage {
+        name: "FIRST STEPS",
+        challenge: Challenge::Clear {
+            mode: Mode::Marathon,
+            level: 1,
+            lines: 10,
+        },
+    },
+    Stage {
+        name: "FIRST TETRIS",
+        challenge: Challenge::Puzzle(include_str!("../../puzzles/01-first-tetris.toml")),
+    },
+    Stage {
+        name: "SPEED UP",
+        challenge: Challenge::Clear {
+            mode: Mode::Marathon,
+            level: 8,
+            lines: 15,
+        },
+    },
+    Stage {
+        name: "DIG OUT",
+        challenge: Challenge::Clear {
+            mode: Mode::Dig,
+            level: 1,
+            lines: 10,
+        },
+    },
+    Stage {
+        name: "T SLOT",
+        challenge: Challenge::Puzzle(include_str!("../../puzzles/03-t-slot.toml")),
+    },
+    Stage {
+        name: "TOP SPEED",
+        challenge: Challenge::Clear {
+            mode: Mode::Marathon,
+            level: 15,
+            lines: 20,
+        },
+    },
+    Stage {
+        name: "MASTER CLASS",
+        challenge: Challenge::Clear {
+            mode: Mode::Master,
+            level: 1,
+            lines: 30,
+        },
+    },
+];
+
+impl Stage {
+    // 这一关的引擎；消够行数或者解出谜题就算过关（`Engine::is_finished`）
+    pub fn engine(&self) -> Engine {
+        match self.challenge {
+            Challenge::Clear { mode, level, lines } => {
+                let mut engine = Engine::with_mode(mode);
+                engine.objective = Some(Goal::Lines(lines));
+                engine.start_level = level;
+                engine.stats.level = level;
+                engine
+            }
+            Challenge::Puzzle(source) => {
+                Engine::with_puzzle(&Puzzle::parse(source).expect("built-in puzzles are valid"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stages_build_their_challenges() {
+        for stage in STAGES {
+            let engine = stage.engine();
+            assert!(!engine.is_finished(), "{}", stage.name);
+        }
+        let speed = STAGES[2].engine();
+        assert_eq!(speed.stats().level, 8);
+        assert!(speed.drop_interval() < Engine::with_mode(Mode::Marathon).drop_interval());
+        assert_eq!(STAGES[1].engine().mode(), Mode::Puzzle);
+    }
+}
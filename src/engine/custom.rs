@@ -0,0 +1,263 @@
+use std::sync::RwLock;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use super::game_mode::GameMode;
+use super::mode::Mode;
+use super::piece::Kind as PieceKind;
+use super::puzzle::Goal;
+use super::rules::Rules;
+use super::{Engine, Matrix, Stats};
+
+// 从文件读的自定义模式，不用改代码就能加新玩法。文件格式：
+//
+//     name = "CHEESE RACE"
+//     goal = { lines = 40 }       # 目标，和谜题的一样，可以不写
+//     time_limit = 120            # 限时多少秒，时间到了就结束，可以不写
+//     start_level = 5
+//
+//     [rules]                     # 不写的就和 marathon 一样
+//     hold = false
+//     gravity = "nes"             # guideline / nes / instant
+//     scoring = "guideline"       # guideline / nes / combo
+//
+//     [modifiers]
+//     garbage_interval = 3        # 每隔几秒从底下顶上来一行垃圾
+//     invisible = true            # 锁定的方块过一会儿就看不见
+//     forgive_top_out = true      # 堆满了清掉下面的行接着玩
+//     width = 6                   # matrix 的大小，不能比平常的大
+//     height = 12
+//     only_piece = "I"            # 只出这一种方块
+//     beat_bonus = 50             # 踩着音乐的拍子锁定的奖励分，乘等级
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeFile {
+    pub name: String,
+    #[serde(default)]
+    goal: Option<Goal>,
+    #[serde(default)]
+    time_limit: Option<u64>,
+    #[serde(default = "first_level")]
+    start_level: u32,
+    #[serde(default)]
+    rules: RulesFile,
+    #[serde(default)]
+    modifiers: Modifiers,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+// `Rules` 里的每一项都可以不写，不写的用 guideline 的
+#[derive(Clone, Copy, Default, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    hold: Option<bool>,
+    hard_drop: Option<bool>,
+    gravity: Option<super::rules::Gravity>,
+    scoring: Option<super::rules::Scoring>,
+}
+
+#[derive(Clone, Copy, Default, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Modifiers {
+    garbage_interval: Option<u32>,
+    invisible: bool,
+    forgive_top_out: bool,
+    width: Option<usize>,
+    height: Option<usize>,
+    only_piece: Option<PieceKind>,
+    beat_bonus: Option<u32>,
+}
+
+impl ModeFile {
+    pub fn parse(source: &str) -> Result<ModeFile, String> {
+        let file: ModeFile = toml::from_str(source).map_err(|err| err.to_string())?;
+        let Modifiers { width, height, .. } = file.modifiers;
+        if width.is_some_and(|width| !(4..=Matrix::WIDTH).contains(&width))
+            || height.is_some_and(|height| !(4..=Matrix::HEIGHT).contains(&height))
+        {
+            return Err("matrix size out of range".to_string());
+        }
+        if file.start_level == 0 {
+            return Err("start_level starts at 1".to_string());
+        }
+        Ok(file)
+    }
+}
+
+// 注册好的自定义模式；`Mode::Custom(i)` 是这里的第 i 个。
+// 读进来之后整个程序都在用，所以直接泄漏成 'static
+struct Custom {
+    name: &'static str,
+    file: ModeFile,
+    index: usize,
+}
+
+static CUSTOM: RwLock<Vec<&'static Custom>> = RwLock::new(Vec::new());
+
+// 启动时读完模式目录之后调用，和已有模式重名的跳过
+pub fn register(files: Vec<ModeFile>) {
+    let mut custom = CUSTOM.write().unwrap();
+    for file in files {
+        let taken = super::game_mode::REGISTERED
+            .iter()
+            .map(|game| game.name())
+            .chain(custom.iter().map(|mode| mode.name))
+            .any(|name| name == file.name);
+        if taken {
+            continue;
+        }
+        let index = custom.len();
+        let name = Box::leak(file.name.clone().into_boxed_str());
+        custom.push(Box::leak(Box::new(Custom { name, file, index })));
+    }
+}
+
+pub fn registered() -> Vec<&'static dyn GameMode> {
+    let custom = CUSTOM.read().unwrap();
+    custom.iter().map(|&mode| mode as &dyn GameMode).collect()
+}
+
+pub fn get(index: usize) -> Option<&'static dyn GameMode> {
+    let custom = CUSTOM.read().unwrap();
+    custom.get(index).map(|&mode| mode as &dyn GameMode)
+}
+
+impl GameMode for Custom {
+    fn mode(&self) -> Mode {
+        Mode::Custom(self.index)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn rules(&self) -> Rules {
+        let RulesFile {
+            hold,
+            hard_drop,
+            gravity,
+            scoring,
+        } = self.file.rules;
+        let base = Rules::GUIDELINE;
+        Rules {
+            hold: hold.unwrap_or(base.hold),
+            hard_drop: hard_drop.unwrap_or(base.hard_drop),
+            gravity: gravity.unwrap_or(base.gravity),
+            scoring: scoring.unwrap_or(base.scoring),
+        }
+    }
+
+    fn setup(&self, engine: &mut Engine) {
+        let Modifiers { width, height, .. } = self.file.modifiers;
+        if width.is_some() || height.is_some() {
+            engine.matrix = Matrix::with_size(
+                width.unwrap_or(Matrix::WIDTH),
+                height.unwrap_or(Matrix::HEIGHT),
+            );
+        }
+        // 目标交给谜题那一套去判断，消完所有格子这种要看棋盘的也能用
+        engine.objective = self.file.goal;
+        engine.start_level = self.file.start_level;
+        engine.stats.level = self.file.start_level;
+        if self.file.modifiers.only_piece.is_some() {
+            engine.queue.clear();
+            engine.refill_queue();
+        }
+    }
+
+    fn on_frame(&self, engine: &mut Engine) {
+        let Some(seconds) = self.file.modifiers.garbage_interval else {
+            return;
+        };
+        engine.dig_timer += 1;
+        if engine.dig_timer >= seconds * Stats::FRAMES_PER_SECOND as u32
+            && engine.line_clear.is_none()
+        {
+            engine.dig_timer = 0;
+            let hole = engine.rng.gen_range(0..engine.matrix.width());
+            engine.insert_garbage(1, hole);
+        }
+    }
+
+    fn only_piece(&self) -> Option<PieceKind> {
+        self.file.modifiers.only_piece
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        self.file
+            .time_limit
+            .is_some_and(|seconds| stats.frames >= seconds * Stats::FRAMES_PER_SECOND)
+    }
+
+    fn forgives_top_out(&self) -> bool {
+        self.file.modifiers.forgive_top_out
+    }
+
+    fn hides_cells(&self) -> bool {
+        self.file.modifiers.invisible
+    }
+
+    fn beat_bonus(&self) -> Option<u32> {
+        self.file.modifiers.beat_bonus
+    }
+
+    // 限时的倒着数，有目标的正着数
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        match self.file.time_limit {
+            Some(seconds) => {
+                Some((seconds * Stats::FRAMES_PER_SECOND).saturating_sub(stats.frames))
+            }
+            None => self.file.goal.map(|_| stats.frames),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn custom_modes_load_from_toml() {
+        let file = ModeFile::parse(
+            r#"
+            name = "TINY SPRINT"
+            goal = { lines = 5 }
+            start_level = 3
+
+            [rules]
+            hold = false
+
+            [modifiers]
+            width = 6
+            only_piece = "I"
+            beat_bonus = 20
+            "#,
+        )
+        .unwrap();
+        assert!(ModeFile::parse("name = \"BAD\"\n[modifiers]\nwidth = 20").is_err());
+        assert!(ModeFile::parse("name = \"BAD\"\nlives = 3").is_err());
+
+        register(vec![file.clone(), file]);
+        let mode = registered()
+            .into_iter()
+            .find(|game| game.name() == "TINY SPRINT")
+            .unwrap()
+            .mode();
+        assert_eq!(registered().len(), 1);
+        assert!(!mode.rules().hold);
+        assert_eq!(mode.beat_bonus(), Some(20));
+        assert!(Mode::ranked().contains(&mode));
+
+        let engine = Engine::with_mode(mode);
+        assert_eq!(engine.matrix_size(), (6, Matrix::HEIGHT));
+        assert_eq!(engine.stats().level, 3);
+        assert!(engine
+            .preview(Engine::MAX_PREVIEW)
+            .all(|kind| kind == PieceKind::I));
+        assert_eq!(engine.timer_frames(), Some(0));
+    }
+}
@@ -0,0 +1,197 @@
+use serde_json::json;
+
+use super::mode::Mode;
+use super::review::Placement;
+use super::Stats;
+
+// 一局的数据导出成 CSV 和 JSON，拿表格软件分析用：整局的统计一行，
+// 账本里每块一行（什么方块、放在哪、按了哪些键、带来多少分）。
+// 自定义模式的名字里可能有逗号和引号，CSV 里要加引号，JSON 交给 serde_json 转义
+pub struct GameData<'a> {
+    pub mode: Mode,
+    pub seed: u64,
+    pub stats: &'a Stats,
+    pub ledger: &'a [Placement],
+}
+
+const STATS_HEADER: &str = "mode,seed,score,lines,level,pieces,seconds,pps,apm,\
+                            tetrises,t_spins,back_to_backs,max_combo,holds,finesse_faults";
+const LEDGER_HEADER: &str = "piece,kind,held,frame,actions,cells,score,lines,attack";
+
+impl GameData<'_> {
+    pub fn stats_csv(&self) -> String {
+        let stats = self.stats;
+        format!(
+            "{}\n{},{},{},{},{},{},{:.3},{:.3},{:.3},{},{},{},{},{},{}\n",
+            STATS_HEADER,
+            csv_field(self.mode.game_mode().name()),
+            self.seed,
+            stats.score,
+            stats.lines,
+            stats.level,
+            stats.pieces,
+            stats.seconds(),
+            stats.pps(),
+            stats.apm(),
+            stats.tetrises,
+            stats.t_spins,
+            stats.back_to_backs,
+            stats.max_combo,
+            stats.holds,
+            stats.finesse_faults,
+        )
+    }
+
+    // 格子写成 "x:y x:y x:y x:y"，操作写成 `Action::symbol` 连起来
+    pub fn ledger_csv(&self) -> String {
+        let mut csv = format!("{}\n", LEDGER_HEADER);
+        for placement in self.ledger {
+            let cells: Vec<String> = placement
+                .cells
+                .iter()
+                .map(|cell| format!("{}:{}", cell.x, cell.y))
+                .collect();
+            csv.push_str(&format!(
+                "{},{:?},{},{},{},{},{},{},{}\n",
+                placement.piece,
+                placement.kind,
+                placement.held,
+                placement.frame,
+                actions(placement),
+                cells.join(" "),
+                placement.reward.score,
+                placement.reward.lines,
+                placement.reward.attack,
+            ));
+        }
+        csv
+    }
+
+    //     {"mode":"MARATHON","seed":42,"stats":{"score":1200,...},
+    //      "pieces":[{"piece":1,"kind":"T","held":false,"frame":64,"actions":"UrrD",
+    //                 "cells":[[4,0],...],"score":0,"lines":0,"attack":0},...]}
+    pub fn to_json(&self) -> String {
+        let stats = self.stats;
+        let pieces: Vec<_> = self
+            .ledger
+            .iter()
+            .map(|placement| {
+                let cells: Vec<_> = placement
+                    .cells
+                    .iter()
+                    .map(|cell| [cell.x, cell.y])
+                    .collect();
+                json!({
+                    "piece": placement.piece,
+                    "kind": format!("{:?}", placement.kind),
+                    "held": placement.held,
+                    "frame": placement.frame,
+                    "actions": actions(placement),
+                    "cells": cells,
+                    "score": placement.reward.score,
+                    "lines": placement.reward.lines,
+                    "attack": placement.reward.attack,
+                })
+            })
+            .collect();
+        let data = json!({
+            "mode": self.mode.game_mode().name(),
+            "seed": self.seed,
+            "stats": {
+                "score": stats.score,
+                "lines": stats.lines,
+                "level": stats.level,
+                "pieces": stats.pieces,
+                "seconds": rounded(stats.seconds()),
+                "pps": rounded(stats.pps()),
+                "apm": rounded(stats.apm()),
+                "tetrises": stats.tetrises,
+                "t_spins": stats.t_spins,
+                "back_to_backs": stats.back_to_backs,
+                "max_combo": stats.max_combo,
+                "holds": stats.holds,
+                "finesse_faults": stats.finesse_faults,
+            },
+            "pieces": pieces,
+        });
+        format!("{}\n", data)
+    }
+}
+
+// 有逗号、引号或者换行的格子整个用引号括起来，里面的引号写两遍
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// 和 CSV 里一样保留三位小数
+fn rounded(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+fn actions(placement: &Placement) -> String {
+    placement
+        .actions
+        .iter()
+        .map(|action| action.symbol())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::replay::Action;
+    use crate::engine::review::Recorder;
+    use crate::engine::Engine;
+
+    #[test]
+    fn games_export_one_row_per_piece() {
+        let mut engine = Engine::with_seed(Mode::Marathon, 3);
+        engine.spawn_first();
+        let mut recorder = Recorder::default();
+        recorder.record(&engine, &[]);
+        for frame in 0..200 {
+            if frame % 50 == 10 {
+                engine.rotate_clockwise();
+                engine.hard_drop();
+            }
+            engine.tick();
+            let events = engine.take_events();
+            recorder.record(&engine, &events);
+        }
+        let ledger = recorder.ledger();
+        assert_eq!(ledger.len(), 4);
+        assert_eq!(ledger[0].piece, 1);
+        assert!(ledger
+            .iter()
+            .all(|placement| placement.actions.ends_with(&[Action::HardDrop])));
+
+        let stats = engine.stats();
+        let data = GameData {
+            mode: Mode::Marathon,
+            seed: 3,
+            stats: &stats,
+            ledger: &ledger,
+        };
+        let stats_csv = data.stats_csv();
+        let rows: Vec<&str> = stats_csv.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].starts_with("MARATHON,3,"));
+        assert_eq!(rows[0].split(',').count(), rows[1].split(',').count());
+        let ledger_csv = data.ledger_csv();
+        assert_eq!(ledger_csv.lines().count(), 5);
+        assert!(ledger_csv.lines().nth(1).unwrap().starts_with("1,"));
+        assert!(ledger_csv.lines().nth(1).unwrap().contains(",UD,"));
+
+        // 自定义模式的名字里有逗号、引号也不会串列
+        assert_eq!(csv_field("A,\"B\""), "\"A,\"\"B\"\"\"");
+        assert_eq!(csv_field("MARATHON"), "MARATHON");
+
+        let json = data.to_json();
+        assert!(json.starts_with("{\"mode\":\"MARATHON\",\"seed\":3,\"stats\":{\"score\":"));
+        assert_eq!(json.matches("\"piece\":").count(), 4);
+    }
+}
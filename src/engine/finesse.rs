@@ -0,0 +1,117 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::piece::{Piece, Rotation};
+use super::Offset;
+
+// 在空 matrix 上搜索的高度，足够让任何朝向的方块都不越界
+const SEARCH_HEIGHT: isize = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    Left,
+    Right,
+    Rotate,
+}
+
+// 从出生位置把方块摆到 `target` 那几列、那个形状最少要按几次键（左、右、顺时针旋转）。
+// 和 guideline 的 finesse 一样只看空 matrix，不考虑已经堆起来的格子
+pub fn min_inputs(spawn: &Piece, target: &Piece) -> u32 {
+    // 到不了的话就不算失误
+    optimal_inputs(spawn, target).map_or(u32::MAX, |keys| keys.len() as u32)
+}
+
+// 按键最少的那一种按法，给玩家看标准答案用
+pub fn optimal_inputs(spawn: &Piece, target: &Piece) -> Option<Vec<Key>> {
+    let goal = footprint(target);
+    let start = Piece {
+        position: Offset::new(spawn.position.x, SEARCH_HEIGHT),
+        ..*spawn
+    };
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([(start, Vec::new())]);
+    while let Some((piece, keys)) = queue.pop_front() {
+        if !seen.insert((piece.position.x, piece.rotation as u8)) {
+            continue;
+        }
+        if footprint(&piece) == goal {
+            return Some(keys);
+        }
+        let rotated = Piece {
+            rotation: clockwise(piece.rotation),
+            ..piece
+        };
+        for (key, next) in [
+            (Key::Left, piece.moved_by(Offset::new(-1, 0))),
+            (Key::Right, piece.moved_by(Offset::new(1, 0))),
+            (Key::Rotate, rotated),
+        ] {
+            if next.cells().is_some() {
+                let mut keys = keys.clone();
+                keys.push(key);
+                queue.push_back((next, keys));
+            }
+        }
+    }
+    None
+}
+
+fn clockwise(rotation: Rotation) -> Rotation {
+    match rotation {
+        Rotation::N => Rotation::E,
+        Rotation::E => Rotation::S,
+        Rotation::S => Rotation::W,
+        Rotation::W => Rotation::N,
+    }
+}
+
+// 方块占的格子去掉高度之后的样子，朝向不同但形状一样的算同一个落点
+fn footprint(piece: &Piece) -> Option<Vec<(usize, usize)>> {
+    let piece = Piece {
+        position: Offset::new(piece.position.x, SEARCH_HEIGHT),
+        ..*piece
+    };
+    let cells = piece.cells()?;
+    let bottom = cells.iter().map(|cell| cell.y).min()?;
+    let mut cells: Vec<_> = cells.iter().map(|cell| (cell.x, cell.y - bottom)).collect();
+    cells.sort_unstable();
+    Some(cells)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::piece::Kind;
+
+    #[test]
+    fn finesse_counts_moves_and_rotations() {
+        let spawn = Piece {
+            kind: Kind::T,
+            position: Offset::new(3, 17),
+            rotation: Rotation::N,
+        };
+        assert_eq!(min_inputs(&spawn, &spawn.moved_by(Offset::new(0, -10))), 0);
+        assert_eq!(min_inputs(&spawn, &spawn.moved_by(Offset::new(-3, -5))), 3);
+
+        let flipped = Piece {
+            rotation: Rotation::S,
+            ..spawn
+        };
+        assert_eq!(min_inputs(&spawn, &flipped), 2);
+        assert_eq!(
+            optimal_inputs(&spawn, &spawn.moved_by(Offset::new(-2, 0))),
+            Some(vec![Key::Left, Key::Left])
+        );
+
+        // O 块怎么转都一样
+        let o = Piece {
+            kind: Kind::O,
+            ..spawn
+        };
+        let o_turned = Piece {
+            rotation: Rotation::W,
+            ..o
+        };
+        assert_eq!(min_inputs(&o, &o_turned), 0);
+    }
+}
@@ -0,0 +1,783 @@
+use rand::Rng;
+
+use super::mission::ActiveMission;
+use super::mode::Mode;
+use super::mystery::Effect;
+use super::piece::Kind as PieceKind;
+use super::rules::Rules;
+use super::{Color, Coordinate, Engine, Event, Matrix, Stats};
+
+// 一个游戏模式的玩法：开局怎么布置、每帧和每次锁定要做什么、什么时候算赢什么时候算输、
+// 结算界面多写些什么。`Mode` 只是名字，新模式在这里实现一个，再加进 `REGISTERED`
+pub trait GameMode: Sync {
+    fn mode(&self) -> Mode;
+
+    // 菜单和排行榜上写的名字
+    fn name(&self) -> &'static str;
+
+    // 能不能在主菜单里直接选；谜题要先选题
+    fn selectable(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::GUIDELINE
+    }
+
+    // 开局时调整引擎，比如换一个尺寸的 matrix
+    fn setup(&self, _engine: &mut Engine) {}
+
+    // 每帧调用，结束之后就不调了
+    fn on_frame(&self, _engine: &mut Engine) {}
+
+    // 只出这一种方块，不按 7-bag 抽
+    fn only_piece(&self) -> Option<PieceKind> {
+        None
+    }
+
+    // 每个方块锁定之后调用，有消行的话等消完了再调，`cleared` 是消了几行
+    fn on_lock(&self, _engine: &mut Engine, _cleared: usize) {}
+
+    // 达成目标就算赢，一局结束
+    fn goal_reached(&self, _stats: &Stats) -> bool {
+        false
+    }
+
+    // 堆到顶的时候是输掉还是清场接着玩
+    fn forgives_top_out(&self) -> bool {
+        false
+    }
+
+    // 锁定的方块过一会儿就看不见
+    fn hides_cells(&self) -> bool {
+        false
+    }
+
+    // 计时器显示的帧数，None 是不显示
+    fn timer_frames(&self, _stats: &Stats) -> Option<u64> {
+        None
+    }
+
+    // 结算界面在分数、行数这些之后多写的几行：(标题, 值)
+    fn results(&self, _stats: &Stats) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    // 上不上排行榜；谜题按关记完成情况，每日挑战按天记，练习用的模式不比分
+    fn ranked(&self) -> bool {
+        true
+    }
+
+    // 能不能录回放：回放只记按键，沙盒里指定下一块、撤销，合作里搭档的操作都不在里面
+    fn replayable(&self) -> bool {
+        true
+    }
+
+    // 能不能指定下一块、撤销、无限 hold
+    fn is_sandbox(&self) -> bool {
+        false
+    }
+
+    // 玩了 `frames` 帧之后下一次攻击隔多少帧、送几行；不自己送攻击是 None
+    fn attack_schedule(&self, _frames: u64) -> Option<(u32, u32)> {
+        None
+    }
+
+    // 踩着背景音乐的拍子锁定方块的奖励分（乘等级），None 是不看拍子
+    fn beat_bonus(&self) -> Option<u32> {
+        None
+    }
+
+    // 现在的段位，是 `Master::GRADES` 里的下标，段位越高锁定和出块越快；不算段位是 None
+    fn grade(&self, _stats: &Stats) -> Option<usize> {
+        None
+    }
+}
+
+// 内置的模式加上从文件读的模式，主菜单按这个顺序列出来
+pub fn all() -> Vec<&'static dyn GameMode> {
+    REGISTERED
+        .iter()
+        .copied()
+        .chain(super::custom::registered())
+        .collect()
+}
+
+// 所有内置的模式
+pub static REGISTERED: [&dyn GameMode; 19] = [
+    &Marathon,
+    &Sprint,
+    &AttackRace,
+    &Ultra,
+    &Zen,
+    &Dig,
+    &Master,
+    &Invisible,
+    &Classic,
+    &Daily,
+    &Survival,
+    &Big,
+    &Mission,
+    &Mystery,
+    &Training,
+    &Combo,
+    &TSpin,
+    &Coop,
+    &Puzzle,
+];
+
+pub struct Marathon;
+
+impl GameMode for Marathon {
+    fn mode(&self) -> Mode {
+        Mode::Marathon
+    }
+
+    fn name(&self) -> &'static str {
+        "MARATHON"
+    }
+}
+
+pub struct Sprint;
+
+impl GameMode for Sprint {
+    fn mode(&self) -> Mode {
+        Mode::Sprint
+    }
+
+    fn name(&self) -> &'static str {
+        "SPRINT"
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        stats.lines >= Mode::SPRINT_LINES
+    }
+
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        Some(stats.frames)
+    }
+}
+
+pub struct AttackRace;
+
+impl GameMode for AttackRace {
+    fn mode(&self) -> Mode {
+        Mode::AttackRace
+    }
+
+    fn name(&self) -> &'static str {
+        "ATTACK RACE"
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        stats.attack >= Mode::ATTACK_LINES
+    }
+
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        Some(stats.frames)
+    }
+
+    fn results(&self, stats: &Stats) -> Vec<(&'static str, String)> {
+        vec![
+            ("ATTACK", stats.attack.to_string()),
+            ("APM", format!("{:.1}", stats.apm())),
+        ]
+    }
+}
+
+pub struct Ultra;
+
+impl GameMode for Ultra {
+    fn mode(&self) -> Mode {
+        Mode::Ultra
+    }
+
+    fn name(&self) -> &'static str {
+        "ULTRA"
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        stats.frames >= Mode::ULTRA_FRAMES
+    }
+
+    // 倒着数
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        Some(Mode::ULTRA_FRAMES.saturating_sub(stats.frames))
+    }
+}
+
+pub struct Zen;
+
+impl GameMode for Zen {
+    fn mode(&self) -> Mode {
+        Mode::Zen
+    }
+
+    fn name(&self) -> &'static str {
+        "ZEN"
+    }
+
+    fn forgives_top_out(&self) -> bool {
+        true
+    }
+
+    fn beat_bonus(&self) -> Option<u32> {
+        Some(Mode::BEAT_BONUS)
+    }
+}
+
+pub struct Dig;
+
+impl GameMode for Dig {
+    fn mode(&self) -> Mode {
+        Mode::Dig
+    }
+
+    fn name(&self) -> &'static str {
+        "DIG"
+    }
+
+    fn on_frame(&self, engine: &mut Engine) {
+        engine.dig_timer += 1;
+        // 消行停顿的时候行号还要用，等消完了再涨
+        if engine.dig_timer >= engine.dig_interval && engine.line_clear.is_none() {
+            engine.dig_timer = 0;
+            let hole = engine.rng.gen_range(0..engine.matrix.width());
+            engine.insert_garbage(1, hole);
+        }
+    }
+}
+
+pub struct Master;
+
+impl Master {
+    // TGM 的段位和升段要的分数，从 9 段一直到 S9
+    pub const GRADES: [(u32, &'static str); 18] = [
+        (0, "9"),
+        (400, "8"),
+        (800, "7"),
+        (1400, "6"),
+        (2000, "5"),
+        (3500, "4"),
+        (5500, "3"),
+        (8000, "2"),
+        (12000, "1"),
+        (16000, "S1"),
+        (22000, "S2"),
+        (30000, "S3"),
+        (40000, "S4"),
+        (52000, "S5"),
+        (66000, "S6"),
+        (82000, "S7"),
+        (100000, "S8"),
+        (120000, "S9"),
+    ];
+}
+
+impl GameMode for Master {
+    fn mode(&self) -> Mode {
+        Mode::Master
+    }
+
+    fn name(&self) -> &'static str {
+        "MASTER"
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::MASTER
+    }
+
+    fn results(&self, stats: &Stats) -> Vec<(&'static str, String)> {
+        let grade = self.grade(stats).unwrap_or(0);
+        vec![("GRADE", Self::GRADES[grade].1.to_string())]
+    }
+
+    fn grade(&self, stats: &Stats) -> Option<usize> {
+        Self::GRADES
+            .iter()
+            .rposition(|&(score, _)| stats.score >= score)
+    }
+}
+
+pub struct Invisible;
+
+impl GameMode for Invisible {
+    fn mode(&self) -> Mode {
+        Mode::Invisible
+    }
+
+    fn name(&self) -> &'static str {
+        "INVISIBLE"
+    }
+
+    fn hides_cells(&self) -> bool {
+        true
+    }
+}
+
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn mode(&self) -> Mode {
+        Mode::Classic
+    }
+
+    fn name(&self) -> &'static str {
+        "CLASSIC"
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::NES
+    }
+}
+
+pub struct Puzzle;
+
+impl GameMode for Puzzle {
+    fn mode(&self) -> Mode {
+        Mode::Puzzle
+    }
+
+    fn name(&self) -> &'static str {
+        "PUZZLE"
+    }
+
+    // 目标在谜题文件里，见 `Engine::with_puzzle`
+    fn selectable(&self) -> bool {
+        false
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+}
+
+pub struct Daily;
+
+impl GameMode for Daily {
+    fn mode(&self) -> Mode {
+        Mode::Daily
+    }
+
+    fn name(&self) -> &'static str {
+        "DAILY"
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        Ultra.goal_reached(stats)
+    }
+
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        Ultra.timer_frames(stats)
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+}
+
+pub struct Survival;
+
+impl Survival {
+    // 每过一段升一级：攻击间隔从第一个数往下减到第二个数，行数从 1 往上加到 4
+    const STAGE: u64 = 30 * Stats::FRAMES_PER_SECOND;
+    const INTERVAL: (u32, u32) = (
+        8 * Stats::FRAMES_PER_SECOND as u32,
+        2 * Stats::FRAMES_PER_SECOND as u32,
+    );
+    const MAX_LINES: u32 = 4;
+}
+
+impl GameMode for Survival {
+    fn mode(&self) -> Mode {
+        Mode::Survival
+    }
+
+    fn name(&self) -> &'static str {
+        "SURVIVAL"
+    }
+
+    fn on_frame(&self, engine: &mut Engine) {
+        let Some((interval, lines)) = self.attack_schedule(engine.stats.frames) else {
+            return;
+        };
+        engine.attack_timer += 1;
+        if engine.attack_timer >= interval {
+            engine.attack_timer = 0;
+            engine.garbage.push(lines);
+        }
+    }
+
+    fn timer_frames(&self, stats: &Stats) -> Option<u64> {
+        Some(stats.frames)
+    }
+
+    fn attack_schedule(&self, frames: u64) -> Option<(u32, u32)> {
+        let stage = (frames / Self::STAGE) as u32;
+        let (slowest, fastest) = Self::INTERVAL;
+        let interval = slowest
+            .saturating_sub(stage * Stats::FRAMES_PER_SECOND as u32)
+            .max(fastest);
+        let lines = (1 + stage / 2).min(Self::MAX_LINES);
+        Some((interval, lines))
+    }
+}
+
+pub struct Big;
+
+impl GameMode for Big {
+    fn mode(&self) -> Mode {
+        Mode::Big
+    }
+
+    fn name(&self) -> &'static str {
+        "BIG"
+    }
+
+    // 一半宽、一半高，格子画出来是两倍大，整个 matrix 占的地方和平常一样
+    fn setup(&self, engine: &mut Engine) {
+        engine.matrix = Matrix::with_size(Matrix::WIDTH / 2, Matrix::HEIGHT / 2);
+    }
+}
+
+pub struct Mission;
+
+impl GameMode for Mission {
+    fn mode(&self) -> Mode {
+        Mode::Mission
+    }
+
+    fn name(&self) -> &'static str {
+        "MISSIONS"
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::MISSIONS
+    }
+
+    fn setup(&self, engine: &mut Engine) {
+        engine.mission = Some(ActiveMission::pick(&mut engine.rng, None, &engine.stats));
+    }
+
+    // 达成了加一分换下一个，时间到了还没达成就结束
+    fn on_frame(&self, engine: &mut Engine) {
+        let Some(mut active) = engine.mission else {
+            return;
+        };
+        if active.is_done(&engine.matrix, &engine.stats) {
+            engine.stats.score += 1;
+            engine.events.push(Event::MissionComplete);
+            engine.mission = Some(ActiveMission::pick(
+                &mut engine.rng,
+                Some(&active),
+                &engine.stats,
+            ));
+            return;
+        }
+        active.frames_left = active.frames_left.saturating_sub(1);
+        engine.mission = Some(active);
+        if active.frames_left == 0 {
+            engine.top_out();
+        }
+    }
+}
+
+pub struct Mystery;
+
+impl GameMode for Mystery {
+    fn mode(&self) -> Mode {
+        Mode::Mystery
+    }
+
+    fn name(&self) -> &'static str {
+        "MYSTERY"
+    }
+
+    fn on_frame(&self, engine: &mut Engine) {
+        engine.effect = engine
+            .effect
+            .filter(|&(_, frames_left)| frames_left > 1)
+            .map(|(effect, frames_left)| (effect, frames_left - 1));
+        engine.effect_timer += 1;
+        // 和 dig 一样，消行停顿的时候不动棋盘，等消完了再来
+        if engine.effect_timer < Effect::INTERVAL || engine.line_clear.is_some() {
+            return;
+        }
+        engine.effect_timer = 0;
+        let effect = Effect::ALL[engine.rng.gen_range(0..Effect::ALL.len())];
+        engine.events.push(Event::Mystery(effect));
+        match effect {
+            Effect::BonusClear => engine.matrix.clear_bottom_rows(Effect::ROWS),
+            Effect::Garbage => {
+                let hole = engine.rng.gen_range(0..engine.matrix.width());
+                engine.insert_garbage(Effect::ROWS as u32, hole);
+            }
+            _ => engine.effect = Some((effect, effect.duration())),
+        }
+    }
+}
+
+pub struct Training;
+
+impl GameMode for Training {
+    fn mode(&self) -> Mode {
+        Mode::Training
+    }
+
+    fn name(&self) -> &'static str {
+        "TRAINING"
+    }
+
+    fn forgives_top_out(&self) -> bool {
+        true
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+
+    fn is_sandbox(&self) -> bool {
+        true
+    }
+
+    fn replayable(&self) -> bool {
+        false
+    }
+}
+
+pub struct Combo;
+
+impl Combo {
+    // 中间留空的四列
+    const WELL: std::ops::Range<usize> = 3..7;
+    // 两边的墙堆多高，上面留几行给新方块出来
+    const WALL_HEIGHT: usize = Matrix::HEIGHT - 4;
+    // 井底先放三格，这样每一块放下去都能消一行
+    const RESIDUAL: [(usize, usize); 3] = [(3, 0), (4, 0), (3, 1)];
+
+    // 两边的墙补回到原来的高度，消行会把墙也消掉
+    fn build_walls(matrix: &mut Matrix) {
+        for y in 0..Self::WALL_HEIGHT {
+            for x in (0..matrix.width()).filter(|x| !Self::WELL.contains(x)) {
+                matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+            }
+        }
+    }
+
+    // 井里清空，只剩开头那三格
+    fn build_well(matrix: &mut Matrix) {
+        for y in 0..matrix.height() {
+            for x in Self::WELL {
+                matrix[Coordinate::new(x, y)] = None;
+            }
+        }
+        for (x, y) in Self::RESIDUAL {
+            matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+        }
+    }
+}
+
+impl GameMode for Combo {
+    fn mode(&self) -> Mode {
+        Mode::Combo
+    }
+
+    fn name(&self) -> &'static str {
+        "4-WIDE"
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::COMBO
+    }
+
+    fn setup(&self, engine: &mut Engine) {
+        Self::build_walls(&mut engine.matrix);
+        Self::build_well(&mut engine.matrix);
+    }
+
+    // 没消行就是连击断了，井里摆回开头的样子重新来
+    fn on_lock(&self, engine: &mut Engine, cleared: usize) {
+        if cleared == 0 {
+            Self::build_well(&mut engine.matrix);
+        }
+        Self::build_walls(&mut engine.matrix);
+    }
+
+    fn forgives_top_out(&self) -> bool {
+        true
+    }
+
+    fn results(&self, stats: &Stats) -> Vec<(&'static str, String)> {
+        // `max_combo` 从 0 开始数，连续消了几次要加 1
+        let longest = if stats.lines > 0 {
+            stats.max_combo + 1
+        } else {
+            0
+        };
+        vec![("MAX COMBO", longest.to_string())]
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+}
+
+pub struct TSpin;
+
+impl TSpin {
+    // 练够这么多块就结算
+    pub const ATTEMPTS: u32 = 20;
+    // 常见的坑，从上往下写，'X' 是格子。都是竖着的 T 放进去再顺时针转一下
+    const SETUPS: [[&'static str; 3]; 3] = [
+        // T-spin double
+        ["XXX.......", "XX...XXXXX", "XXX.XXXXXX"],
+        // T-spin single，第二行留了个洞
+        ["XXX.......", "XX...X.XXX", "XXX.XXXXXX"],
+        // 靠右的 T-spin double
+        ["XXXXXX....", "XXXXX...XX", "XXXXXX.XXX"],
+    ];
+
+    // 清空 matrix，随机摆一个坑
+    fn build_setup(engine: &mut Engine) {
+        let setup = Self::SETUPS[engine.rng.gen_range(0..Self::SETUPS.len())];
+        engine.matrix = Matrix::blank();
+        for (y, row) in setup.iter().rev().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                if cell == b'X' {
+                    engine.matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+                }
+            }
+        }
+    }
+}
+
+impl GameMode for TSpin {
+    fn mode(&self) -> Mode {
+        Mode::TSpin
+    }
+
+    fn name(&self) -> &'static str {
+        "T-SPIN"
+    }
+
+    // 没有 hold，每一块都得试
+    fn rules(&self) -> Rules {
+        Rules {
+            hold: false,
+            ..Rules::GUIDELINE
+        }
+    }
+
+    fn only_piece(&self) -> Option<PieceKind> {
+        Some(PieceKind::T)
+    }
+
+    fn setup(&self, engine: &mut Engine) {
+        engine.queue.clear();
+        engine.refill_queue();
+        Self::build_setup(engine);
+    }
+
+    // 不管成没成，放下一块就换下一个坑
+    fn on_lock(&self, engine: &mut Engine, _cleared: usize) {
+        Self::build_setup(engine);
+    }
+
+    fn goal_reached(&self, stats: &Stats) -> bool {
+        stats.pieces >= Self::ATTEMPTS
+    }
+
+    fn forgives_top_out(&self) -> bool {
+        true
+    }
+
+    fn results(&self, stats: &Stats) -> Vec<(&'static str, String)> {
+        let rate = stats.t_spins * 100 / stats.pieces.max(1);
+        vec![
+            ("T-SPINS", format!("{}/{}", stats.t_spins, stats.pieces)),
+            ("SUCCESS", format!("{}%", rate)),
+        ]
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+}
+
+pub struct Coop;
+
+impl GameMode for Coop {
+    fn mode(&self) -> Mode {
+        Mode::Coop
+    }
+
+    fn name(&self) -> &'static str {
+        "CO-OP"
+    }
+
+    // 两个人没法分一个 hold 格子，干脆不要
+    fn rules(&self) -> Rules {
+        Rules {
+            hold: false,
+            ..Rules::GUIDELINE
+        }
+    }
+
+    // 加宽的 matrix，两个人各占一半出块，方块之间互相挡
+    fn setup(&self, engine: &mut Engine) {
+        engine.matrix = Matrix::with_size(Matrix::MAX_WIDTH, Matrix::HEIGHT);
+        engine.add_partner();
+    }
+
+    fn ranked(&self) -> bool {
+        false
+    }
+
+    fn replayable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_mode_is_registered_once() {
+        let ranked = [
+            Mode::Marathon,
+            Mode::Sprint,
+            Mode::AttackRace,
+            Mode::Ultra,
+            Mode::Zen,
+            Mode::Dig,
+            Mode::Master,
+            Mode::Invisible,
+            Mode::Classic,
+            Mode::Survival,
+            Mode::Big,
+            Mode::Mission,
+            Mode::Mystery,
+        ];
+        for mode in ranked.into_iter().chain([
+            Mode::Puzzle,
+            Mode::Daily,
+            Mode::Training,
+            Mode::Combo,
+            Mode::TSpin,
+            Mode::Coop,
+        ]) {
+            let count = REGISTERED.iter().filter(|game| game.mode() == mode).count();
+            assert_eq!(count, 1, "{:?}", mode);
+            assert_eq!(mode.game_mode().mode(), mode);
+        }
+        // 自定义模式接在内置的后面
+        assert_eq!(Mode::ranked()[..ranked.len()], ranked);
+        assert!(Mode::Marathon.replayable());
+        assert!(!Mode::Training.replayable() && !Mode::Coop.replayable());
+        assert_eq!(
+            Mode::Master.game_mode().results(&Stats::default()),
+            [("GRADE", "9".to_string())]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+
+// 一次攻击送过来的垃圾行，要等 delay 帧之后才会真正进场
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Attack {
+    pub lines: u32,
+    pub delay: u32,
+}
+
+impl Attack {
+    pub fn is_ready(&self) -> bool {
+        self.delay == 0
+    }
+}
+
+// 还没进场的垃圾行，按送来的先后排队
+#[derive(Default)]
+pub struct GarbageQueue {
+    attacks: VecDeque<Attack>,
+}
+
+impl GarbageQueue {
+    pub const DELAY: u32 = 60;
+
+    pub fn push(&mut self, lines: u32) {
+        if lines > 0 {
+            self.attacks.push_back(Attack {
+                lines,
+                delay: Self::DELAY,
+            });
+        }
+    }
+
+    pub fn tick(&mut self) {
+        for attack in &mut self.attacks {
+            attack.delay = attack.delay.saturating_sub(1);
+        }
+    }
+
+    pub fn attacks(&self) -> impl Iterator<Item = &Attack> {
+        self.attacks.iter()
+    }
+
+    pub fn total(&self) -> u32 {
+        self.attacks.iter().map(|attack| attack.lines).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attacks.is_empty()
+    }
+
+    // 自己打出去的攻击先抵消排着的垃圾，最早送来的先抵；返回抵完还剩多少
+    pub fn counter(&mut self, mut lines: u32) -> u32 {
+        while let Some(attack) = self.attacks.front_mut() {
+            if lines == 0 {
+                break;
+            }
+            let cancelled = attack.lines.min(lines);
+            attack.lines -= cancelled;
+            lines -= cancelled;
+            if attack.lines == 0 {
+                self.attacks.pop_front();
+            }
+        }
+        lines
+    }
+
+    // 取出所有已经可以进场的攻击
+    pub fn take_ready(&mut self) -> Vec<u32> {
+        let mut ready = Vec::new();
+        while let Some(attack) = self.attacks.front() {
+            if !attack.is_ready() {
+                break;
+            }
+            ready.push(attack.lines);
+            self.attacks.pop_front();
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn attacks_become_ready_in_order() {
+        let mut queue = GarbageQueue::default();
+        queue.push(2);
+        for _ in 0..10 {
+            queue.tick();
+        }
+        queue.push(3);
+        queue.push(0);
+        assert_eq!(queue.total(), 5);
+
+        for _ in 0..GarbageQueue::DELAY - 10 {
+            queue.tick();
+        }
+        assert_eq!(queue.take_ready(), [2]);
+        assert_eq!(queue.total(), 3);
+
+        for _ in 0..10 {
+            queue.tick();
+        }
+        assert_eq!(queue.take_ready(), [3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn attacks_counter_the_oldest_garbage_first() {
+        let mut queue = GarbageQueue::default();
+        queue.push(2);
+        queue.push(3);
+        assert_eq!(queue.counter(3), 0);
+        assert_eq!(queue.attacks().count(), 1);
+        assert_eq!(queue.total(), 2);
+        assert_eq!(queue.counter(4), 2);
+        assert!(queue.is_empty());
+    }
+}
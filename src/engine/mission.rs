@@ -0,0 +1,122 @@
+use rand::Rng;
+
+use super::piece::Kind as PieceKind;
+use super::puzzle::Goal;
+use super::{Matrix, Stats};
+
+// 限时任务：`seconds` 秒之内达成 `goal`，`text` 是界面上显示的说明
+#[derive(Clone, Copy, Debug)]
+pub struct Mission {
+    pub text: &'static str,
+    pub goal: Goal,
+    pub seconds: u64,
+}
+
+pub const MISSIONS: [Mission; 8] = [
+    Mission {
+        text: "CLEAR 4 LINES",
+        goal: Goal::Lines(4),
+        seconds: 30,
+    },
+    Mission {
+        text: "SCORE A TETRIS",
+        goal: Goal::Tetrises(1),
+        seconds: 40,
+    },
+    Mission {
+        text: "B2B TETRIS",
+        goal: Goal::BackToBacks(1),
+        seconds: 60,
+    },
+    Mission {
+        text: "CLEAR 3 LINES WITH L",
+        goal: Goal::PieceLines {
+            piece: PieceKind::L,
+            lines: 3,
+        },
+        seconds: 45,
+    },
+    Mission {
+        text: "CLEAR 2 LINES WITH S",
+        goal: Goal::PieceLines {
+            piece: PieceKind::S,
+            lines: 2,
+        },
+        seconds: 45,
+    },
+    Mission {
+        text: "T-SPIN",
+        goal: Goal::TSpins(1),
+        seconds: 45,
+    },
+    Mission {
+        text: "COMBO 3",
+        goal: Goal::Combo(3),
+        seconds: 40,
+    },
+    Mission {
+        text: "CLEAR 8 LINES",
+        goal: Goal::Lines(8),
+        seconds: 45,
+    },
+];
+
+// 正在做的任务：从 `start` 那一刻的统计开始算，还剩多少帧
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveMission {
+    pub mission: Mission,
+    start: Stats,
+    pub frames_left: u64,
+}
+
+impl ActiveMission {
+    // 随机抽一个，不和上一个重复
+    pub fn pick(rng: &mut impl Rng, previous: Option<&ActiveMission>, stats: &Stats) -> Self {
+        let mission = loop {
+            let mission = MISSIONS[rng.gen_range(0..MISSIONS.len())];
+            if previous.is_none_or(|previous| previous.mission.text != mission.text) {
+                break mission;
+            }
+        };
+        ActiveMission {
+            mission,
+            start: *stats,
+            frames_left: mission.seconds * Stats::FRAMES_PER_SECOND,
+        }
+    }
+
+    pub fn is_done(&self, matrix: &Matrix, stats: &Stats) -> bool {
+        self.mission.goal.is_met(matrix, &stats.since(&self.start))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn missions_count_from_when_they_start() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let before = Stats {
+            lines: 10,
+            ..Stats::default()
+        };
+        let mut active = ActiveMission::pick(&mut rng, None, &before);
+        active.mission = MISSIONS[0];
+        let matrix = Matrix::blank();
+        assert!(!active.is_done(&matrix, &before));
+        let after = Stats {
+            lines: 14,
+            ..before
+        };
+        assert!(active.is_done(&matrix, &after));
+
+        for _ in 0..20 {
+            let next = ActiveMission::pick(&mut rng, Some(&active), &after);
+            assert_ne!(next.mission.text, active.mission.text);
+            active = next;
+        }
+    }
+}
@@ -1,12 +1,38 @@
+use std::collections::VecDeque;
 use std::ops::{Index, IndexMut};
 
+use self::garbage::GarbageQueue;
+use self::mission::ActiveMission;
+use self::mode::Mode;
+use self::mystery::Effect;
 use self::piece::{Kind as PieceKind, Piece, Rotation};
+use self::puzzle::{Goal, Puzzle};
+use self::replay::Action;
+use self::rules::Gravity;
 use cgmath::EuclideanSpace;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{Rng, SeedableRng};
 
+pub mod battle;
+pub mod bot;
+pub mod campaign;
+pub mod custom;
+pub mod export;
+pub mod finesse;
+pub mod game_mode;
+pub mod garbage;
+pub mod mission;
+pub mod mode;
+pub mod mystery;
+pub mod opener;
 pub mod piece;
+pub mod planner;
+pub mod puzzle;
+pub mod replay;
+pub mod review;
+pub mod rules;
+pub mod targeting;
 
 type Coordinate = cgmath::Point2<usize>;
 type Offset = cgmath::Vector2<isize>;
@@ -24,25 +50,365 @@ impl MoveKind {
             MoveKind::Right => Offset::new(1, 0),
         }
     }
+
+    fn flipped(self) -> Self {
+        match self {
+            MoveKind::Left => MoveKind::Right,
+            MoveKind::Right => MoveKind::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Stats {
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    // 连续消行的次数，第一次消行是 0，之后每接着消一次加 1
+    pub combo: u32,
+    pub max_combo: u32,
+    // 游戏进行的帧数，按 60 帧每秒计算时间
+    pub frames: u64,
+    // 锁定了多少个方块
+    pub pieces: u32,
+    pub holds: u32,
+    // 消行打出去的垃圾行数
+    pub attack: u32,
+    // 消了行的 T-spin
+    pub t_spins: u32,
+    pub tetrises: u32,
+    // 接着上一次难消（四消或者 T-spin 消行）又难消了一次，中间没有普通的消行
+    pub back_to_backs: u32,
+    // 比最少按键次数多按了的方块数
+    pub finesse_faults: u32,
+    // 每种方块锁定了几个，按 `PieceKind as usize` 索引
+    pub piece_counts: [u32; 7],
+    // 每种方块锁定时消了几行，索引同上
+    pub piece_lines: [u32; 7],
+}
+
+impl Stats {
+    pub const FRAMES_PER_SECOND: u64 = 60;
+
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / Self::FRAMES_PER_SECOND as f64
+    }
+
+    // pieces per second
+    pub fn pps(&self) -> f64 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 / self.seconds()
+    }
+
+    // 从 `start` 到现在多出来的；等级、连击这些不是累加的，就是现在的值
+    pub fn since(&self, start: &Stats) -> Stats {
+        Stats {
+            score: self.score - start.score,
+            lines: self.lines - start.lines,
+            frames: self.frames - start.frames,
+            pieces: self.pieces - start.pieces,
+            holds: self.holds - start.holds,
+            attack: self.attack - start.attack,
+            t_spins: self.t_spins - start.t_spins,
+            tetrises: self.tetrises - start.tetrises,
+            back_to_backs: self.back_to_backs - start.back_to_backs,
+            finesse_faults: self.finesse_faults - start.finesse_faults,
+            piece_counts: std::array::from_fn(|i| self.piece_counts[i] - start.piece_counts[i]),
+            piece_lines: std::array::from_fn(|i| self.piece_lines[i] - start.piece_lines[i]),
+            ..*self
+        }
+    }
+
+    // attack per minute
+    pub fn apm(&self) -> f64 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.attack as f64 * 60.0 / self.seconds()
+    }
+}
+
+// 引擎内部发生的事情，界面每帧取走，用来播放特效
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    HardDrop {
+        cells: [Coordinate; Piece::CELL_COUNT],
+        distance: usize,
+        color: Color,
+    },
+    Lock {
+        cells: [Coordinate; Piece::CELL_COUNT],
+        color: Color,
+    },
+    // 方块锁定后有满行，消行停顿开始
+    LineClear {
+        rows: Vec<usize>,
+        combo: u32,
+    },
+    // 连击中断了，combo 是中断前的连击数
+    ComboBroken {
+        combo: u32,
+    },
+    GarbageInserted {
+        lines: u32,
+    },
+    // 玩家操作成功了，播音效用；移动带上移动之后的格子，音效按位置左右声道
+    Move {
+        cells: [Coordinate; Piece::CELL_COUNT],
+    },
+    Rotate,
+    Hold,
+    LevelUp {
+        level: u32,
+    },
+    // 任务模式完成了一个任务，换下一个
+    MissionComplete,
+    // 神秘模式来了一个事件
+    Mystery(Effect),
+    // 转进去锁定的 T，`lines` 是消了几行，0 也算
+    TSpin {
+        lines: usize,
+    },
+    // 按键比标准按法多，`optimal` 是标准按法（不含硬降）
+    FinesseFault {
+        optimal: Vec<finesse::Key>,
+    },
+    GameOver,
+    // 玩家的每个操作，成没成功都算，重放用
+    Action(Action),
+}
+
+// 训练模式撤销用：一个方块刚出来时的状态
+#[derive(Clone)]
+struct Snapshot {
+    matrix: Matrix,
+    queue: VecDeque<PieceKind>,
+    hold: Option<PieceKind>,
+    kind: PieceKind,
+    stats: Stats,
+    last_lock_cleared: bool,
+    last_clear_difficult: bool,
+}
+
+// 消行时先停顿一段时间，界面可以在这段时间里播放消行动画
+struct LineClear {
+    rows: Vec<usize>,
+    timer: u32,
 }
 
 pub struct Engine {
     matrix: Matrix,
-    bag: Vec<PieceKind>,
-    rng: ThreadRng,
+    // 接下来要出的方块，至少保留 MAX_PREVIEW + 1 个，预览时不用再抽
+    queue: VecDeque<PieceKind>,
+    // 给了种子的话方块顺序和垃圾行的缺口都是定的，每日挑战靠这个让大家拿到一样的方块
+    rng: StdRng,
+    // 没给种子就随机挑一个，排行榜记下来，拿同一个种子能再打一遍
+    seed: u64,
+    pub cursor: Option<Piece>,
+    hold: Option<PieceKind>,
+    // 每个方块只能 hold 一次，锁定之后才能再用；训练模式可以打开无限 hold
+    hold_used: bool,
+    infinite_hold: bool,
+    // 训练模式每出一块记一次，最后一个是当前方块出来时的样子
+    history: Vec<Snapshot>,
+    // 当前方块按了几次移动和旋转，用来算 finesse
+    piece_inputs: u32,
+    stats: Stats,
+    gravity_timer: u32,
+    lock_timer: u32,
+    line_clear: Option<LineClear>,
+    // 上一个锁定的方块有没有消行，用来算连击
+    last_lock_cleared: bool,
+    // 上一次消行是不是难消，用来算 back-to-back
+    last_clear_difficult: bool,
+    garbage: GarbageQueue,
+    mode: Mode,
+    // 达成了模式的目标（sprint 消够行数、ultra 时间到）
+    finished: bool,
+    game_over: bool,
+    // 结束之后又过了几帧；最后的棋盘停一会儿再出结算
+    ended_frames: u32,
+    // dig 模式每隔 `dig_interval` 帧顶上来一行垃圾
+    dig_interval: u32,
+    dig_timer: u32,
+    // 离上一次模式自己送来攻击过了几帧，survival 用
+    attack_timer: u32,
+    // 上一个方块锁定之后等了几帧，到了出块延迟才出下一个
+    spawn_timer: u32,
+    // 谜题和闯关的目标，和模式自己的目标哪个先达成都算
+    objective: Option<Goal>,
+    // 从第几级开始，每消 LINES_PER_LEVEL 行再升一级
+    start_level: u32,
+    events: Vec<Event>,
+    // 当前方块最近一次旋转的结果，调试面板用
+    last_kick: Option<Kick>,
+    // 任务模式正在做的任务
+    mission: Option<ActiveMission>,
+    // 神秘模式正在起作用的效果，还剩几帧；离上一个事件过了几帧
+    effect: Option<(Effect, u64)>,
+    effect_timer: u64,
+    // 当前方块最后一个动作是不是旋转，移动或者下落一格就不算了
+    twisted: bool,
+    // 上一个锁定的方块是不是 T-spin
+    last_lock_t_spin: bool,
+    // 双人合作时另一个玩家的方块，两个人共用一个 matrix 和方块队列
+    partner: Option<Player>,
+    // 在 matrix 的哪一半出块，只有合作模式用
+    lane: usize,
+}
+
+// 每个玩家自己的那部分状态；`Engine::as_partner` 把它和引擎里的换一下，
+// 另一个玩家就能用同一套操作
+#[derive(Clone, Default, Debug)]
+struct Player {
+    cursor: Option<Piece>,
+    hold: Option<PieceKind>,
+    hold_used: bool,
+    piece_inputs: u32,
+    gravity_timer: u32,
+    lock_timer: u32,
+    spawn_timer: u32,
+    last_kick: Option<Kick>,
+    twisted: bool,
+    lane: usize,
+}
+
+// 旋转时依次试的偏移，成功的是第几个；现在只有原地转这一个
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kick {
+    Succeeded(usize),
+    Failed,
+}
+
+// 引擎内部状态的快照，给调试面板看的
+#[derive(Clone, Debug)]
+pub struct DebugInfo {
     pub cursor: Option<Piece>,
+    pub last_kick: Option<Kick>,
+    // 0 到 1，到 1 就锁定
+    pub lock_progress: f32,
+    // 0 到 1，到 1 就下落一格
+    pub gravity_progress: f32,
+    // 当前这一包里还没出的方块，按出的顺序
+    pub bag: Vec<PieceKind>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Engine {
+    const LOCK_DELAY: u32 = 30;
+    // master 模式的锁定延迟和出块延迟（ARE）：每升一段少一帧，不低于下限
+    const MASTER_LOCK_DELAY: (u32, u32) = (30, 15);
+    const MASTER_SPAWN_DELAY: (u32, u32) = (25, 8);
+    const LINES_PER_LEVEL: u32 = 10;
+    pub const LINE_CLEAR_DELAY: u32 = 20;
+    pub const END_FREEZE_FRAMES: u32 = 90;
+    const ZEN_CLEAR_ROWS: usize = Matrix::HEIGHT / 2;
+    // invisible 模式锁定之后多久开始淡出，淡出要几帧
+    const INVISIBLE_DELAY: u64 = 2 * Stats::FRAMES_PER_SECOND;
+    const INVISIBLE_FADE: u64 = 30;
+    pub const DIG_INTERVAL: u32 = 5 * Stats::FRAMES_PER_SECOND as u32;
+    pub const MAX_PREVIEW: usize = 6;
+    // 最多能撤销几块
+    const UNDO_LIMIT: usize = 100;
+
     pub fn new() -> Self {
-        Engine {
+        Self::seeded(rand::random())
+    }
+
+    fn seeded(seed: u64) -> Self {
+        let mut engine = Engine {
             matrix: Matrix::blank(),
-            bag: Vec::new(),
-            rng: thread_rng(),
+            queue: VecDeque::new(),
+            rng: StdRng::seed_from_u64(seed),
+            seed,
             cursor: None,
+            hold: None,
+            hold_used: false,
+            infinite_hold: false,
+            history: Vec::new(),
+            piece_inputs: 0,
+            stats: Stats {
+                level: 1,
+                ..Stats::default()
+            },
+            gravity_timer: 0,
+            lock_timer: 0,
+            line_clear: None,
+            last_lock_cleared: false,
+            last_clear_difficult: false,
+            garbage: GarbageQueue::default(),
+            mode: Mode::default(),
+            finished: false,
+            game_over: false,
+            ended_frames: 0,
+            dig_interval: Self::DIG_INTERVAL,
+            dig_timer: 0,
+            attack_timer: 0,
+            spawn_timer: 0,
+            objective: None,
+            start_level: 1,
+            events: Vec::new(),
+            last_kick: None,
+            mission: None,
+            effect: None,
+            effect_timer: 0,
+            twisted: false,
+            last_lock_t_spin: false,
+            partner: None,
+            lane: 0,
+        };
+        engine.refill_queue();
+        engine
+    }
+
+    pub fn with_mode(mode: Mode) -> Self {
+        Self::with_seed(mode, rand::random())
+    }
+
+    // 模式的初始局面也用这个种子摆，同一个种子开出来的两局从头到尾一样
+    pub fn with_seed(mode: Mode, seed: u64) -> Self {
+        let mut engine = Self {
+            mode,
+            ..Self::seeded(seed)
+        };
+        mode.game_mode().setup(&mut engine);
+        engine
+    }
+
+    pub fn with_puzzle(puzzle: &Puzzle) -> Self {
+        Self {
+            matrix: puzzle.matrix(),
+            queue: puzzle.pieces.iter().copied().collect(),
+            mode: Mode::Puzzle,
+            objective: Some(puzzle.goal),
+            ..Self::new()
         }
     }
 
+    // 从某个局面接着玩：`queue` 的第一个是下一个出来的方块
+    pub fn with_position(
+        mode: Mode,
+        matrix: Matrix,
+        queue: &[PieceKind],
+        hold: Option<PieceKind>,
+    ) -> Self {
+        let mut engine = Self {
+            matrix,
+            queue: queue.iter().copied().collect(),
+            hold,
+            ..Self::with_mode(mode)
+        };
+        engine.refill_queue();
+        engine
+    }
+
     pub fn with_matrix(matrix: Matrix) -> Self {
         Self {
             matrix,
@@ -50,13 +416,299 @@ impl Engine {
         }
     }
 
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // 开局前设置 dig 模式的涨行间隔，至少一帧
+    pub fn set_dig_interval(&mut self, frames: u32) {
+        self.dig_interval = frames.max(1);
+    }
+
+    // 结束后的定格放完了，可以出结算了
+    pub fn end_freeze_done(&self) -> bool {
+        self.is_over() && self.ended_frames >= Self::END_FREEZE_FRAMES
+    }
+
+    // 格子的可见度，1 是完全看得见，0 是隐藏了。invisible 模式里锁定的方块过一会儿就淡出，
+    // 结束之后全部亮出来
+    pub fn cell_visibility(&self, coord: Coordinate) -> f32 {
+        if self.has_effect(Effect::Invisible) && !self.is_over() {
+            return 0.0;
+        }
+        if !self.mode.hides_cells() || self.is_over() {
+            return 1.0;
+        }
+        let Some(locked_at) = self.matrix.locked_at(coord) else {
+            return 1.0;
+        };
+        let fading = (self.stats.frames - locked_at).saturating_sub(Self::INVISIBLE_DELAY);
+        1.0 - (fading as f32 / Self::INVISIBLE_FADE as f32).min(1.0)
+    }
+
+    // 模式的目标，或者谜题的目标
+    fn goal_reached(&self) -> bool {
+        self.mode.goal_reached(&self.stats)
+            || self
+                .objective
+                .is_some_and(|goal| goal.is_met(&self.matrix, &self.stats))
+    }
+
+    // 任务模式正在做的任务
+    pub fn mission(&self) -> Option<&ActiveMission> {
+        self.mission.as_ref()
+    }
+
+    // 计时器显示的帧数：有任务的话是任务剩下的时间，否则看模式
+    pub fn timer_frames(&self) -> Option<u64> {
+        match &self.mission {
+            Some(mission) => Some(mission.frames_left),
+            None => self.mode.timer_frames(&self.stats),
+        }
+    }
+
+    // 结束了就不再接受操作，定格的时候方块也不能动
+    fn is_over(&self) -> bool {
+        self.game_over || self.finished
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    // 开局倒数的时候先把第一个方块放出来，但不开始计时
+    pub fn spawn_first(&mut self) {
+        if self.cursor.is_none() && self.stats.frames == 0 && !self.game_over {
+            self.spawn_next();
+        }
+    }
+
+    // 每帧调用一次：没有 cursor 时出新块，否则处理重力和锁定
+    pub fn tick(&mut self) {
+        if self.is_over() {
+            // 时间停在结束的那一帧，ultra 的倒计时就停在 0
+            self.ended_frames += 1;
+            return;
+        }
+        self.stats.frames += 1;
+        self.garbage.tick();
+        if self.goal_reached() {
+            self.finished = true;
+            return;
+        }
+        self.mode.game_mode().on_frame(self);
+        if self.game_over {
+            return;
+        }
+
+        if let Some(line_clear) = self.line_clear.as_mut() {
+            line_clear.timer += 1;
+            if line_clear.timer >= Self::LINE_CLEAR_DELAY {
+                self.finish_line_clear();
+            }
+            return;
+        }
+
+        self.tick_cursor();
+        self.as_partner(Self::tick_cursor);
+    }
+
+    // 一个玩家的出块、重力和锁定；另一个玩家的锁定开始消行的话这一帧就停下
+    fn tick_cursor(&mut self) {
+        if self.line_clear.is_some() || self.is_over() {
+            return;
+        }
+        if self.cursor.is_none() {
+            self.spawn_timer += 1;
+            if self.spawn_timer >= self.spawn_delay() {
+                self.spawn_timer = 0;
+                self.spawn_next();
+            }
+            return;
+        }
+
+        if self.gravity() == Gravity::Instant {
+            while let Some(new_cursor) = self.ticked_down_cursor() {
+                self.cursor = Some(new_cursor);
+            }
+        }
+        if self.cusor_has_hit_bottom() {
+            self.lock_timer += 1;
+            if self.lock_timer >= self.lock_delay() {
+                self.lock_cursor();
+            }
+        } else {
+            self.lock_timer = 0;
+            self.gravity_timer += 1;
+            if self.gravity_timer >= self.drop_interval() {
+                self.gravity_timer = 0;
+                self.tick_down();
+            }
+        }
+    }
+
+    fn lock_delay(&self) -> u32 {
+        match self.mode.grade(&self.stats) {
+            Some(grade) => Self::tightened(Self::MASTER_LOCK_DELAY, grade),
+            None => Self::LOCK_DELAY,
+        }
+    }
+
+    // 其它模式锁定之后下一帧就出块
+    fn spawn_delay(&self) -> u32 {
+        match self.mode.grade(&self.stats) {
+            Some(grade) => Self::tightened(Self::MASTER_SPAWN_DELAY, grade),
+            None => 1,
+        }
+    }
+
+    fn tightened((start, min): (u32, u32), grade: usize) -> u32 {
+        start.saturating_sub(grade as u32).max(min)
+    }
+
+    // 下落一格需要的帧数，按模式的规则算
+    fn drop_interval(&self) -> u32 {
+        self.gravity().frames_per_row(self.stats.level)
+    }
+
+    // 神秘模式的 20G 会临时盖掉模式的重力
+    fn gravity(&self) -> Gravity {
+        if self.has_effect(Effect::Gravity) {
+            Gravity::Instant
+        } else {
+            self.mode.rules().gravity
+        }
+    }
+
+    pub fn effect(&self) -> Option<Effect> {
+        self.effect.map(|(effect, _)| effect)
+    }
+
+    fn has_effect(&self, effect: Effect) -> bool {
+        self.effect() == Some(effect)
+    }
+
+    // 合作模式：开局前加上第二个玩家，两个人各占 matrix 的一半出块
+    fn add_partner(&mut self) {
+        self.partner = Some(Player {
+            lane: 1,
+            ..Player::default()
+        });
+    }
+
+    pub fn has_partner(&self) -> bool {
+        self.partner.is_some()
+    }
+
+    // 让第二个玩家来操作：`f` 里的移动、旋转、hold 这些都是对第二个玩家的方块做的，
+    // 没有第二个玩家就什么都不做
+    pub fn as_partner(&mut self, f: impl FnOnce(&mut Engine)) {
+        if self.partner.is_none() {
+            return;
+        }
+        self.swap_players();
+        f(self);
+        self.swap_players();
+    }
+
+    fn swap_players(&mut self) {
+        let Some(partner) = self.partner.as_mut() else {
+            return;
+        };
+        std::mem::swap(&mut self.cursor, &mut partner.cursor);
+        std::mem::swap(&mut self.hold, &mut partner.hold);
+        std::mem::swap(&mut self.hold_used, &mut partner.hold_used);
+        std::mem::swap(&mut self.piece_inputs, &mut partner.piece_inputs);
+        std::mem::swap(&mut self.gravity_timer, &mut partner.gravity_timer);
+        std::mem::swap(&mut self.lock_timer, &mut partner.lock_timer);
+        std::mem::swap(&mut self.spawn_timer, &mut partner.spawn_timer);
+        std::mem::swap(&mut self.last_kick, &mut partner.last_kick);
+        std::mem::swap(&mut self.twisted, &mut partner.twisted);
+        std::mem::swap(&mut self.lane, &mut partner.lane);
+    }
+
+    // 除了 matrix 里的格子，另一个玩家正在下落的方块也挡路
+    fn is_clipping(&self, piece: &Piece) -> bool {
+        if self.matrix.is_clipping(piece) {
+            return true;
+        }
+        let other = self.partner.as_ref().and_then(|partner| partner.cursor);
+        match (piece.cells(), other.and_then(|other| other.cells())) {
+            (Some(cells), Some(other)) => cells.iter().any(|cell| other.contains(cell)),
+            _ => false,
+        }
+    }
+
+    const KICKS: [Offset; 1] = [Offset::new(0, 0)];
+
     pub fn rotate_clockwise(&mut self) {
+        self.events.push(Event::Action(Action::Rotate));
+        if self.is_over() {
+            return;
+        }
         if let Some(mut cursor) = self.cursor {
+            self.piece_inputs += 1;
             let rotations = [Rotation::N, Rotation::E, Rotation::S, Rotation::W];
-            let curr_rotation_idx = rotations.iter().position(|&x| x == cursor.rotation).unwrap();
+            let curr_rotation_idx = rotations
+                .iter()
+                .position(|&x| x == cursor.rotation)
+                .unwrap();
             let next_rotation_idx = (curr_rotation_idx + 1) % rotations.len();
             cursor.rotation = rotations[next_rotation_idx];
-            self.cursor = Some(cursor);
+            let kicked = Self::KICKS
+                .iter()
+                .map(|&offset| cursor.moved_by(offset))
+                .position(|kicked| !self.is_clipping(&kicked));
+            self.last_kick = Some(match kicked {
+                Some(index) => {
+                    self.cursor = Some(cursor.moved_by(Self::KICKS[index]));
+                    self.twisted = true;
+                    self.events.push(Event::Rotate);
+                    Kick::Succeeded(index)
+                }
+                None => Kick::Failed,
+            });
+        }
+    }
+
+    // 当前方块离下落下一格还差多少，0 到 1，`alpha` 是这一帧和下一帧之间走到哪了；
+    // 只给界面做平滑下落用，落到底或者没有方块的时候是 0
+    pub fn fall_progress(&self, alpha: f32) -> f32 {
+        if self.cursor.is_none() || self.line_clear.is_some() || self.cusor_has_hit_bottom() {
+            return 0.0;
+        }
+        let progress = (self.gravity_timer as f32 + alpha) / self.drop_interval() as f32;
+        progress.clamp(0.0, 1.0)
+    }
+
+    pub fn debug_info(&self) -> DebugInfo {
+        // 队列是一包一包接上去的，最前面不满七个的那部分就是当前这包剩下的
+        let in_bag = match self.queue.len() % PieceKind::ALL.len() {
+            0 => PieceKind::ALL.len(),
+            n => n,
+        };
+        DebugInfo {
+            cursor: self.cursor,
+            last_kick: self.last_kick,
+            lock_progress: self.lock_timer as f32 / self.lock_delay() as f32,
+            gravity_progress: self.gravity_timer as f32 / self.drop_interval() as f32,
+            bag: self.queue.iter().take(in_bag).copied().collect(),
         }
     }
 
@@ -65,6 +717,12 @@ impl Engine {
         Some((cursor.cells().unwrap(), cursor.kind.color()))
     }
 
+    // 合作模式里第二个玩家的方块
+    pub fn partner_cursor_info(&self) -> Option<([Coordinate; Piece::CELL_COUNT], Color)> {
+        let cursor = self.partner.as_ref()?.cursor?;
+        Some((cursor.cells().unwrap(), cursor.kind.color()))
+    }
+
     pub fn debug_test_cursor(&mut self, kind: PieceKind, position: Offset) {
         let piece = Piece {
             kind,
@@ -74,12 +732,376 @@ impl Engine {
         self.cursor = Some(piece);
     }
 
-    fn refill_bag(&mut self) {
-        debug_assert!(self.bag.is_empty());
-        // put all pieces in bag
-        self.bag.extend_from_slice(&PieceKind::ALL);
-        // shuffle the bag
-        self.bag.shuffle(&mut self.rng)
+    // 7-bag：每次把七种方块洗一遍接到队尾；谜题的方块顺序是定好的，不补
+    fn refill_queue(&mut self) {
+        if self.mode == Mode::Puzzle {
+            return;
+        }
+        if let Some(kind) = self.mode.game_mode().only_piece() {
+            while self.queue.len() <= Self::MAX_PREVIEW {
+                self.queue.push_back(kind);
+            }
+            return;
+        }
+        while self.queue.len() <= Self::MAX_PREVIEW {
+            // put all pieces in bag
+            let mut bag = PieceKind::ALL;
+            // shuffle the bag
+            bag.shuffle(&mut self.rng);
+            self.queue.extend(bag);
+        }
+    }
+
+    // 接下来的 `count` 个方块，最多 MAX_PREVIEW 个
+    pub fn preview(&self, count: usize) -> impl Iterator<Item = PieceKind> + '_ {
+        self.queue
+            .iter()
+            .copied()
+            .take(count.min(Self::MAX_PREVIEW))
+    }
+
+    fn next_kind(&mut self) -> Option<PieceKind> {
+        self.refill_queue();
+        let kind = self.queue.pop_front();
+        self.refill_queue();
+        kind
+    }
+
+    // 谜题的方块用完了还没达成目标就算输
+    fn spawn_next(&mut self) {
+        let Some(kind) = self.next_kind() else {
+            self.top_out();
+            return;
+        };
+        self.hold_used = false;
+        self.spawn(kind);
+        if self.mode.is_sandbox() {
+            if self.history.len() == Self::UNDO_LIMIT {
+                self.history.remove(0);
+            }
+            self.history.push(Snapshot {
+                matrix: self.matrix.clone(),
+                queue: self.queue.clone(),
+                hold: self.hold,
+                kind,
+                stats: self.stats,
+                last_lock_cleared: self.last_lock_cleared,
+                last_clear_difficult: self.last_clear_difficult,
+            });
+        }
+    }
+
+    // 训练模式：下一块换成 `kind`，原来排着的往后挪
+    pub fn force_next(&mut self, kind: PieceKind) {
+        if self.mode.is_sandbox() {
+            self.queue.push_front(kind);
+        }
+    }
+
+    pub fn set_infinite_hold(&mut self, on: bool) {
+        self.infinite_hold = on && self.mode.is_sandbox();
+        if self.infinite_hold {
+            self.hold_used = false;
+        }
+    }
+
+    pub fn infinite_hold(&self) -> bool {
+        self.infinite_hold
+    }
+
+    // 训练模式：回到上一块刚出来的时候，没有可以撤销的就返回 false
+    pub fn undo(&mut self) -> bool {
+        if self.history.len() < 2 {
+            return false;
+        }
+        self.history.pop();
+        let snapshot = self.history.last().cloned().expect("checked above");
+        self.matrix = snapshot.matrix;
+        self.queue = snapshot.queue;
+        self.hold = snapshot.hold;
+        self.stats = snapshot.stats;
+        self.last_lock_cleared = snapshot.last_lock_cleared;
+        self.last_clear_difficult = snapshot.last_clear_difficult;
+        self.line_clear = None;
+        self.hold_used = false;
+        self.spawn(snapshot.kind);
+        true
+    }
+
+    // 训练模式：清空重来，无限 hold 的开关不变
+    pub fn reset(&mut self) {
+        if !self.mode.is_sandbox() {
+            return;
+        }
+        let infinite_hold = self.infinite_hold;
+        *self = Self::with_mode(self.mode);
+        self.infinite_hold = infinite_hold;
+        self.spawn_first();
+    }
+
+    // 新方块出现的位置，让 3 x 3 和 4 x 4 的方块最上面一行都贴着 matrix 顶部，左右居中；
+    // 合作模式在自己那一半里居中
+    fn spawn_position(&self) -> Offset {
+        let width = self.matrix.width();
+        let lanes = if self.partner.is_some() { 2 } else { 1 };
+        let lane_width = width / lanes;
+        let center = self.lane * lane_width + lane_width / 2;
+        Offset::new(center as isize - 2, self.matrix.spawn_row())
+    }
+
+    // 出生位置已经被占住，就是 game over（block out）
+    fn spawn(&mut self, kind: PieceKind) {
+        let piece = Piece {
+            kind,
+            rotation: Rotation::N,
+            position: self.spawn_position(),
+        };
+        self.gravity_timer = 0;
+        self.lock_timer = 0;
+        self.piece_inputs = 0;
+        self.last_kick = None;
+        self.twisted = false;
+        // 合作模式里出生位置被另一个玩家的方块挡住也算
+        if self.is_clipping(&piece) {
+            self.top_out();
+        }
+        // zen 模式清过场之后就放得下了
+        if !self.is_clipping(&piece) {
+            self.cursor = Some(piece);
+        }
+    }
+
+    // 界面判断刚才的锁定踩在拍子上了就调这个；引擎自己不知道音乐
+    pub fn award_beat_bonus(&mut self) {
+        if let Some(bonus) = self.mode.beat_bonus() {
+            self.stats.score += bonus * self.stats.level;
+        }
+    }
+
+    // 把当前方块放进 hold，换出上次 hold 的（没有的话就出下一个）
+    pub fn hold(&mut self) {
+        self.events.push(Event::Action(Action::Hold));
+        if self.hold_used || self.is_over() || !self.mode.rules().hold {
+            return;
+        }
+        if self.hold.is_none() && self.queue.is_empty() {
+            return;
+        }
+        let Some(cursor) = self.cursor.take() else {
+            return;
+        };
+        let kind = match self.hold.replace(cursor.kind) {
+            Some(kind) => kind,
+            // 队列空的时候上面已经返回了
+            None => self.next_kind().expect("queue checked above"),
+        };
+        self.hold_used = !self.infinite_hold;
+        self.stats.holds += 1;
+        self.events.push(Event::Hold);
+        self.spawn(kind);
+    }
+
+    pub fn held(&self) -> Option<PieceKind> {
+        self.hold
+    }
+
+    // 三角判定：T 最后一下是转进来的，而且中心四个斜角至少有三个被占住（墙和地板也算）
+    fn is_t_spin(&self) -> bool {
+        let Some(cursor) = self.cursor.filter(|cursor| cursor.kind == PieceKind::T) else {
+            return false;
+        };
+        // T 在 3 x 3 的格子里转，中心一直是 (1, 1)
+        let occupied = [(0, 0), (2, 0), (0, 2), (2, 2)]
+            .into_iter()
+            .filter(|&(x, y)| {
+                let (x, y) = (cursor.position.x + x, cursor.position.y + y);
+                let outside = x < 0 || y < 0 || x >= self.matrix.width() as isize;
+                outside || self.matrix.get(x, y).is_some()
+            })
+            .count();
+        self.twisted && occupied >= 3
+    }
+
+    fn lock_cursor(&mut self) {
+        // 有格子锁在了 matrix 顶上（lock out）也是 game over
+        if self
+            .cursor
+            .is_some_and(|cursor| !self.matrix.is_placeable(&cursor))
+        {
+            self.cursor = None;
+            self.top_out();
+            if !self.game_over {
+                self.spawn_next();
+            }
+            return;
+        }
+        self.last_lock_t_spin = self.is_t_spin();
+        let kind = self.cursor.map(|cursor| cursor.kind);
+        self.place_cursor();
+        let rows = self.matrix.full_rows();
+        if let (Some(kind), false) = (kind, rows.is_empty()) {
+            self.stats.piece_lines[kind as usize] += rows.len() as u32;
+            let difficult = rows.len() >= 4 || self.last_lock_t_spin;
+            if difficult && self.last_clear_difficult {
+                self.stats.back_to_backs += 1;
+            }
+            self.last_clear_difficult = difficult;
+            if rows.len() >= 4 {
+                self.stats.tetrises += 1;
+            }
+        }
+        if self.last_lock_t_spin {
+            self.events.push(Event::TSpin { lines: rows.len() });
+            if !rows.is_empty() {
+                self.stats.t_spins += 1;
+            }
+        }
+        if rows.is_empty() {
+            if self.stats.combo > 0 {
+                self.events.push(Event::ComboBroken {
+                    combo: self.stats.combo,
+                });
+            }
+            self.stats.combo = 0;
+            self.last_lock_cleared = false;
+            self.apply_ready_garbage();
+            self.mode.game_mode().on_lock(self, 0);
+            if !self.game_over {
+                self.spawn_next();
+            }
+        } else {
+            if self.last_lock_cleared {
+                self.stats.combo += 1;
+                self.stats.max_combo = self.stats.max_combo.max(self.stats.combo);
+            }
+            self.last_lock_cleared = true;
+            self.stats.attack += Self::attack_for(rows.len(), self.stats.combo);
+            self.events.push(Event::LineClear {
+                rows: rows.clone(),
+                combo: self.stats.combo,
+            });
+            self.line_clear = Some(LineClear { rows, timer: 0 });
+        }
+    }
+
+    fn finish_line_clear(&mut self) {
+        self.line_clear = None;
+        let cleared = self.matrix.clear_full_rows();
+        self.award_lines(cleared);
+        self.mode.game_mode().on_lock(self, cleared);
+        if self.goal_reached() {
+            self.finished = true;
+            return;
+        }
+        // 合作模式里另一个玩家的方块还在半空，消完行可能要往上推
+        self.lift_cursor();
+        if self.cursor.is_none() {
+            self.spawn_next();
+        }
+        self.as_partner(|engine| {
+            engine.lift_cursor();
+            if engine.cursor.is_none() && !engine.is_over() {
+                engine.spawn_next();
+            }
+        });
+    }
+
+    // 正在消除的行（从下往上）以及消行停顿的进度，范围 [0, 1)
+    pub fn line_clear_progress(&self) -> Option<(&[usize], f32)> {
+        let line_clear = self.line_clear.as_ref()?;
+        let progress = line_clear.timer as f32 / Self::LINE_CLEAR_DELAY as f32;
+        Some((&line_clear.rows, progress))
+    }
+
+    pub fn garbage(&self) -> &GarbageQueue {
+        &self.garbage
+    }
+
+    // 对手（或者游戏模式）送来的垃圾行，先排队，等延迟过了再在锁定时进场
+    pub fn queue_garbage(&mut self, lines: u32) {
+        self.garbage.push(lines);
+    }
+
+    // 打出去的攻击先抵消还没进场的垃圾，返回剩下要送给对手的
+    pub fn counter_garbage(&mut self, lines: u32) -> u32 {
+        self.garbage.counter(lines)
+    }
+
+    // 只在没有消行的锁定之后进场，每次攻击一个随机的缺口
+    fn apply_ready_garbage(&mut self) {
+        for lines in self.garbage.take_ready() {
+            let hole = self.rng.gen_range(0..self.matrix.width());
+            self.insert_garbage(lines, hole);
+        }
+    }
+
+    // 从底部顶上来 `lines` 行垃圾，`hole` 那一列留空；
+    // 被顶出 matrix 顶部的格子意味着 game over（top out）
+    pub fn insert_garbage(&mut self, lines: u32, hole: usize) {
+        if lines == 0 {
+            return;
+        }
+        if self.matrix.insert_garbage(lines as usize, hole) {
+            self.top_out();
+        }
+        self.lift_cursor();
+        self.as_partner(Self::lift_cursor);
+        self.events.push(Event::GarbageInserted { lines });
+    }
+
+    // 正在下落的方块和格子重叠的话就往上推，推出顶部就输了
+    fn lift_cursor(&mut self) {
+        let Some(cursor) = self.cursor.filter(|cursor| self.is_clipping(cursor)) else {
+            return;
+        };
+        let mut lifted = cursor;
+        while self.is_clipping(&lifted) && lifted.position.y < self.matrix.height() as isize {
+            lifted = lifted.moved_by(Offset::new(0, 1));
+        }
+        if !self.matrix.is_placeable(&lifted) || self.is_clipping(&lifted) {
+            self.top_out();
+        } else {
+            self.cursor = Some(lifted);
+        }
+    }
+
+    // guideline 的攻击表：消 2/3/4 行送 1/2/4 行，再加上连击奖励
+    fn attack_for(cleared: usize, combo: u32) -> u32 {
+        const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+        let base = match cleared {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            _ => 4,
+        };
+        base + COMBO_ATTACK[(combo as usize).min(COMBO_ATTACK.len() - 1)]
+    }
+
+    fn award_lines(&mut self, cleared: usize) {
+        if cleared == 0 {
+            return;
+        }
+        let scoring = self.mode.rules().scoring;
+        self.stats.score += scoring.line_clear(cleared, self.stats.level, self.stats.combo);
+        self.stats.lines += cleared as u32;
+        let level = self.stats.lines / Self::LINES_PER_LEVEL + self.start_level;
+        if level > self.stats.level {
+            self.events.push(Event::LevelUp { level });
+        }
+        self.stats.level = level;
+    }
+
+    // 只在第一次 game over 的时候发事件
+    // zen 模式不结束，清掉下面一半，上面剩下的落下来
+    fn top_out(&mut self) {
+        if self.mode.forgives_top_out() {
+            self.matrix.clear_bottom_rows(Self::ZEN_CLEAR_ROWS);
+            return;
+        }
+        if !self.game_over {
+            self.events.push(Event::GameOver);
+        }
+        self.game_over = true;
     }
 
     fn place_cursor(&mut self) {
@@ -92,18 +1114,67 @@ impl Engine {
             "Tried to place cursor in an unplaceable location: {:?}",
             cursor
         );
-        for coord in cursor.cells().unwrap() {
-            self.matrix[coord] = Some(cursor.kind.color());
+        let cells = cursor.cells().unwrap();
+        for coord in cells {
+            self.matrix
+                .place(coord, cursor.kind.color(), self.stats.frames);
+        }
+        self.events.push(Event::Lock {
+            cells,
+            color: cursor.kind.color(),
+        });
+
+        self.stats.pieces += 1;
+        self.stats.piece_counts[cursor.kind as usize] += 1;
+        let spawn = Piece {
+            kind: cursor.kind,
+            rotation: Rotation::N,
+            position: self.spawn_position(),
+        };
+        if let Some(optimal) = finesse::optimal_inputs(&spawn, &cursor) {
+            if self.piece_inputs > optimal.len() as u32 {
+                self.stats.finesse_faults += 1;
+                self.events.push(Event::FinesseFault { optimal });
+            }
         }
     }
 
+    // 撞墙没有别的原因可说，调用方只关心动没动
+    #[allow(clippy::result_unit_err)]
     pub fn move_cursor(&mut self, kind: MoveKind) -> Result<(), ()> {
+        self.events.push(Event::Action(Action::Move(kind)));
+        if self.cursor.is_some() {
+            self.piece_inputs += 1;
+        }
+        self.shift(kind)
+    }
+
+    // 按住方向键时的自动移动，整个 DAS 只算一次按键，不记进 finesse
+    #[allow(clippy::result_unit_err)]
+    pub fn auto_shift(&mut self, kind: MoveKind) -> Result<(), ()> {
+        self.events.push(Event::Action(Action::Shift(kind)));
+        self.shift(kind)
+    }
+
+    fn shift(&mut self, kind: MoveKind) -> Result<(), ()> {
+        if self.is_over() {
+            return Err(());
+        }
+        let kind = if self.has_effect(Effect::Inverted) {
+            kind.flipped()
+        } else {
+            kind
+        };
         if let Some(cursor) = self.cursor.as_mut() {
             let new_cursor = cursor.moved_by(kind.offset());
-            if self.matrix.is_clipping(&new_cursor) {
+            if self.is_clipping(&new_cursor) {
                 Err(())
             } else {
                 self.cursor = Some(new_cursor);
+                self.twisted = false;
+                self.events.push(Event::Move {
+                    cells: new_cursor.cells().unwrap(),
+                });
                 Ok(())
             }
         } else {
@@ -113,6 +1184,7 @@ impl Engine {
 
     fn tick_down(&mut self) {
         self.cursor = Some(self.ticked_down_cursor().unwrap());
+        self.twisted = false;
     }
 
     pub fn cusor_has_hit_bottom(&self) -> bool {
@@ -122,24 +1194,75 @@ impl Engine {
     fn ticked_down_cursor(&self) -> Option<Piece> {
         if let Some(cursor) = &self.cursor {
             let new_cursor = cursor.moved_by(Offset::new(0, -1));
-            (!self.matrix.is_clipping(&new_cursor)).then(|| new_cursor)
+            (!self.is_clipping(&new_cursor)).then_some(new_cursor)
         } else {
             None
         }
     }
 
+    // 硬降的话方块会落到哪里
+    pub fn ghost_cells(&self) -> Option<[Coordinate; Piece::CELL_COUNT]> {
+        let mut ghost = self.cursor?;
+        loop {
+            let lower = ghost.moved_by(Offset::new(0, -1));
+            if self.is_clipping(&lower) {
+                return ghost.cells();
+            }
+            ghost = lower;
+        }
+    }
+
+    pub fn soft_drop(&mut self) {
+        self.events.push(Event::Action(Action::SoftDrop));
+        if self.is_over() {
+            return;
+        }
+        if let Some(new_cursor) = self.ticked_down_cursor() {
+            self.cursor = Some(new_cursor);
+            self.twisted = false;
+            self.gravity_timer = 0;
+            self.stats.score += self.mode.rules().scoring.drop_points(false);
+        }
+    }
+
     pub fn hard_drop(&mut self) {
+        self.events.push(Event::Action(Action::HardDrop));
+        if self.cursor.is_none() || self.is_over() || !self.mode.rules().hard_drop {
+            return;
+        }
+        let mut distance = 0;
         while let Some(new_cursor) = self.ticked_down_cursor() {
             self.cursor = Some(new_cursor);
+            self.twisted = false;
+            self.stats.score += self.mode.rules().scoring.drop_points(true);
+            distance += 1;
         }
-        self.place_cursor();
+        let (cells, color) = self.cursor_info().unwrap();
+        self.events.push(Event::HardDrop {
+            cells,
+            distance,
+            color,
+        });
+        self.lock_cursor();
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    // (列数, 行数)
+    pub fn matrix_size(&self) -> (usize, usize) {
+        (self.matrix.width(), self.matrix.height())
     }
 
-    pub fn cells(&self) -> CellIter<'_> {
+    // 只给出 matrix 用得到的那部分格子
+    pub fn cells(&self) -> impl Iterator<Item = (Coordinate, &Option<Color>)> + '_ {
+        let (width, height) = self.matrix_size();
         CellIter {
             position: Coordinate::origin(),
-            cells: self.matrix.0.iter(),
+            cells: self.matrix.cells.iter(),
         }
+        .filter(move |(coord, _)| coord.x < width && coord.y < height)
     }
 }
 
@@ -152,66 +1275,212 @@ pub enum Color {
     Blue,
     Green,
     Red,
+    // 垃圾行
+    Gray,
 }
 
-pub struct Matrix([Option<Color>; Self::SIZE]);
+impl Color {
+    pub const ALL: [Self; 8] = [
+        Self::Yellow,
+        Self::Cyan,
+        Self::Purple,
+        Self::Orange,
+        Self::Blue,
+        Self::Green,
+        Self::Red,
+        Self::Gray,
+    ];
+}
+
+// 格子总是按 `MAX_WIDTH` x `HEIGHT` 存，平常的 matrix 是 `WIDTH` 宽；小一点的（big 模式）
+// 只用左下角那一块，外面的格子一直是空的
+#[derive(Clone)]
+pub struct Matrix {
+    cells: [Option<Color>; Self::SIZE],
+    // 格子是第几帧锁定的方块，invisible 模式按这个淡出；垃圾行和直接写进去的格子是 None
+    locked_at: [Option<u64>; Self::SIZE],
+    width: usize,
+    height: usize,
+}
 
 impl Matrix {
     pub const WIDTH: usize = 10;
     pub const HEIGHT: usize = 20;
-    const SIZE: usize = Self::WIDTH * Self::HEIGHT;
+    // 双人合作的宽 matrix 最宽能到这么宽
+    pub const MAX_WIDTH: usize = 16;
+    const SIZE: usize = Self::MAX_WIDTH * Self::HEIGHT;
+
+    // 界面按这两个画，不直接用常量，换成别的尺寸的 matrix 也不用改界面
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // 新方块出生的那一行（方块左下角的 y）。I 在这里竖起来会伸出顶上一格，
+    // 顶上看不见的地方当作空的（`is_clipping`），所以出生的地方哪块都能转
+    pub fn spawn_row(&self) -> isize {
+        self.height as isize - 3
+    }
+
+    // 用到的格子在 `cells` 里的末尾（不含），上面的行都是空的
+    fn end(&self) -> usize {
+        self.height * Self::MAX_WIDTH
+    }
 
     // 还可以这样来定义参数。。
-    fn on_matrix(Coordinate { x, y }: Coordinate) -> bool {
-        x < Self::WIDTH && y < Self::HEIGHT
+    fn on_matrix(&self, Coordinate { x, y }: Coordinate) -> bool {
+        x < self.width && y < self.height
     }
 
-    fn valid_coord(Coordinate { x, y }: Coordinate) -> bool {
-        x < Self::WIDTH
+    fn valid_coord(Coordinate { x, .. }: Coordinate) -> bool {
+        x < Self::MAX_WIDTH
     }
 
     pub fn blank() -> Self {
-        Self([None; Self::SIZE])
+        Self::with_size(Self::WIDTH, Self::HEIGHT)
+    }
+
+    // 不能比 `MAX_WIDTH` x `HEIGHT` 大
+    pub fn with_size(width: usize, height: usize) -> Self {
+        assert!(width <= Self::MAX_WIDTH && height <= Self::HEIGHT);
+        Self {
+            cells: [None; Self::SIZE],
+            locked_at: [None; Self::SIZE],
+            width,
+            height,
+        }
+    }
+
+    // 越界的位置当作空格子
+    pub fn get(&self, x: isize, y: isize) -> Option<Color> {
+        let coord = Coordinate::new(x.try_into().ok()?, y.try_into().ok()?);
+        self.on_matrix(coord).then(|| self[coord]).flatten()
     }
 
     fn indexing(Coordinate { x, y }: Coordinate) -> usize {
-        y * Self::WIDTH + x
+        y * Self::MAX_WIDTH + x
+    }
+
+    fn place(&mut self, coord: Coordinate, color: Color, frame: u64) {
+        self[coord] = Some(color);
+        self.locked_at[Self::indexing(coord)] = Some(frame);
+    }
+
+    pub fn locked_at(&self, coord: Coordinate) -> Option<u64> {
+        self.locked_at[Self::indexing(coord)]
     }
 
     fn is_placeable(&self, piece: &Piece) -> bool {
         if let Some(cells) = piece.cells() {
             cells
                 .into_iter()
-                .all(|coord| Matrix::on_matrix(coord) && self[coord].is_none())
+                .all(|coord| self.on_matrix(coord) && self[coord].is_none())
         } else {
             false
         }
     }
 
+    // 左右和底下出界算撞上，顶上出界不算
     fn is_clipping(&self, piece: &Piece) -> bool {
         if let Some(cells) = piece.cells() {
-            cells
-                .into_iter()
-                .any(|coord| !Matrix::on_matrix(coord) || self[coord].is_some())
+            cells.into_iter().any(|coord| {
+                coord.x >= self.width || (coord.y < self.height && self[coord].is_some())
+            })
         } else {
             true
         }
     }
+
+    fn row_is_full(&self, y: usize) -> bool {
+        self.cells[y * Self::MAX_WIDTH..y * Self::MAX_WIDTH + self.width]
+            .iter()
+            .all(Option::is_some)
+    }
+
+    // 每一列最高的格子上面那一行，空列（和小 matrix 右边用不到的列）是 0
+    pub fn column_heights(&self) -> [usize; Self::MAX_WIDTH] {
+        std::array::from_fn(|x| {
+            (0..self.height)
+                .rev()
+                .find(|&y| self.cells[y * Self::MAX_WIDTH + x].is_some())
+                .map_or(0, |y| y + 1)
+        })
+    }
+
+    pub fn stack_height(&self) -> usize {
+        self.column_heights().into_iter().max().unwrap_or(0)
+    }
+
+    fn full_rows(&self) -> Vec<usize> {
+        (0..self.height).filter(|&y| self.row_is_full(y)).collect()
+    }
+
+    // 整体往上移 `lines` 行，底部填上只缺 `hole` 一列的垃圾行，
+    // 返回 true 表示有格子被顶出了 matrix
+    fn insert_garbage(&mut self, lines: usize, hole: usize) -> bool {
+        let (lines, end) = (lines.min(self.height), self.end());
+        let overflow = self.cells[end - lines * Self::MAX_WIDTH..end]
+            .iter()
+            .any(Option::is_some);
+        self.cells
+            .copy_within(..end - lines * Self::MAX_WIDTH, lines * Self::MAX_WIDTH);
+        self.locked_at
+            .copy_within(..end - lines * Self::MAX_WIDTH, lines * Self::MAX_WIDTH);
+        self.locked_at[..lines * Self::MAX_WIDTH].fill(None);
+        for y in 0..lines {
+            for x in 0..self.width {
+                self[Coordinate::new(x, y)] = (x != hole).then_some(Color::Gray);
+            }
+        }
+        overflow
+    }
+
+    // 去掉最下面的 `rows` 行，上面的整体往下移
+    fn clear_bottom_rows(&mut self, rows: usize) {
+        let (rows, end) = (rows.min(self.height), self.end());
+        self.cells.copy_within(rows * Self::MAX_WIDTH..end, 0);
+        self.cells[end - rows * Self::MAX_WIDTH..end].fill(None);
+        self.locked_at.copy_within(rows * Self::MAX_WIDTH..end, 0);
+        self.locked_at[end - rows * Self::MAX_WIDTH..end].fill(None);
+    }
+
+    // 消掉所有满行，上面的行整体往下移，返回消掉的行数
+    fn clear_full_rows(&mut self) -> usize {
+        let (mut cleared, end) = (0, self.end());
+        let mut y = 0;
+        while y < self.height {
+            if self.row_is_full(y) {
+                self.cells
+                    .copy_within((y + 1) * Self::MAX_WIDTH..end, y * Self::MAX_WIDTH);
+                self.cells[end - Self::MAX_WIDTH..end].fill(None);
+                self.locked_at
+                    .copy_within((y + 1) * Self::MAX_WIDTH..end, y * Self::MAX_WIDTH);
+                self.locked_at[end - Self::MAX_WIDTH..end].fill(None);
+                cleared += 1;
+            } else {
+                y += 1;
+            }
+        }
+        cleared
+    }
 }
 
 impl Index<Coordinate> for Matrix {
     type Output = Option<Color>;
 
     fn index(&self, coord: Coordinate) -> &Self::Output {
-        debug_assert!(Self::on_matrix(coord));
-        &self.0[Self::indexing(coord)]
+        debug_assert!(self.on_matrix(coord));
+        &self.cells[Self::indexing(coord)]
     }
 }
 
 impl IndexMut<Coordinate> for Matrix {
     fn index_mut(&mut self, coord: Coordinate) -> &mut Self::Output {
-        debug_assert!(Self::on_matrix(coord));
-        &mut self.0[Self::indexing(coord)]
+        debug_assert!(self.on_matrix(coord));
+        &mut self.cells[Self::indexing(coord)]
     }
 }
 
@@ -248,7 +1517,7 @@ pub trait GridIncrement: Sized {
 
 impl GridIncrement for Coordinate {
     type Width = usize;
-    const WIDTH: Self::Width = Matrix::WIDTH;
+    const WIDTH: Self::Width = Matrix::MAX_WIDTH;
 
     fn grid_incd(&mut self) {
         self.x += 1;
@@ -271,7 +1540,7 @@ mod test {
 
         let mut cell_iter = CellIter {
             position: Coordinate::origin(),
-            cells: matrix.0.iter(),
+            cells: matrix.cells.iter(),
         };
 
         // 这里使用引用可以让这个 iter 被使用多次
@@ -290,7 +1559,8 @@ mod test {
             ]
         );
 
-        let green_item = (&mut cell_iter).skip(8).next();
+        // 前五个之后隔一整行（按 `MAX_WIDTH` 存）
+        let green_item = cell_iter.nth(Matrix::MAX_WIDTH - 2);
         assert_eq!(
             green_item,
             Some((Coordinate::new(3, 1), &Some(Color::Green)))
@@ -298,4 +1568,653 @@ mod test {
 
         assert!(cell_iter.all(|(_, content)| content.is_none()));
     }
+
+    #[test]
+    fn clear_full_rows() {
+        let mut matrix = Matrix::blank();
+        for x in 0..Matrix::WIDTH {
+            matrix[Coordinate::new(x, 0)] = Some(Color::Red);
+            matrix[Coordinate::new(x, 2)] = Some(Color::Red);
+        }
+        matrix[Coordinate::new(4, 1)] = Some(Color::Blue);
+        matrix[Coordinate::new(7, 3)] = Some(Color::Green);
+
+        assert_eq!(matrix.clear_full_rows(), 2);
+        assert_eq!(matrix[Coordinate::new(4, 0)], Some(Color::Blue));
+        assert_eq!(matrix[Coordinate::new(7, 1)], Some(Color::Green));
+        assert_eq!(matrix.cells.iter().filter(|cell| cell.is_some()).count(), 2);
+    }
+
+    #[test]
+    fn line_clear_waits_for_delay() {
+        let mut matrix = Matrix::blank();
+        for x in 0..Matrix::WIDTH - 1 {
+            matrix[Coordinate::new(x, 0)] = Some(Color::Red);
+        }
+        let mut engine = Engine::with_matrix(matrix);
+        // 竖着的 I 块放进最右边一列
+        engine.debug_test_cursor(PieceKind::I, Offset::new(Matrix::WIDTH as isize - 3, 0));
+        engine.rotate_clockwise();
+        engine.hard_drop();
+
+        let (rows, progress) = engine.line_clear_progress().unwrap();
+        assert_eq!(rows, [0]);
+        assert_eq!(progress, 0.0);
+        assert!(engine.cursor.is_none());
+
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert!(engine.line_clear_progress().is_none());
+        assert_eq!(engine.stats().lines, 1);
+        assert!(engine.cursor.is_some());
+    }
+
+    #[test]
+    fn combo_counts_consecutive_clears() {
+        let mut matrix = Matrix::blank();
+        for y in 0..3 {
+            for x in 0..Matrix::WIDTH - 1 {
+                matrix[Coordinate::new(x, y)] = Some(Color::Red);
+            }
+        }
+        let mut engine = Engine::with_matrix(matrix);
+        let drop_vertical_i = |engine: &mut Engine, x: isize| {
+            engine.debug_test_cursor(PieceKind::I, Offset::new(x, 5));
+            engine.rotate_clockwise();
+            engine.hard_drop();
+            for _ in 0..Engine::LINE_CLEAR_DELAY {
+                engine.tick();
+            }
+        };
+
+        // 竖 I 一次消 3 行，剩下的一格留在第 0 行
+        drop_vertical_i(&mut engine, Matrix::WIDTH as isize - 3);
+        assert_eq!(engine.stats().combo, 0);
+        for x in 0..Matrix::WIDTH - 1 {
+            engine.matrix[Coordinate::new(x, 1)] = Some(Color::Red);
+        }
+        drop_vertical_i(&mut engine, Matrix::WIDTH as isize - 3);
+        assert_eq!(engine.stats().combo, 1);
+        assert_eq!(engine.stats().max_combo, 1);
+
+        engine.take_events();
+        drop_vertical_i(&mut engine, 0);
+        assert_eq!(engine.stats().combo, 0);
+        assert!(engine
+            .take_events()
+            .contains(&Event::ComboBroken { combo: 1 }));
+    }
+
+    #[test]
+    fn column_heights_ignore_holes() {
+        let mut matrix = Matrix::blank();
+        matrix[Coordinate::new(0, 0)] = Some(Color::Red);
+        matrix[Coordinate::new(2, 5)] = Some(Color::Red);
+        let heights = matrix.column_heights();
+        assert_eq!(heights[0], 1);
+        assert_eq!(heights[1], 0);
+        assert_eq!(heights[2], 6);
+        assert_eq!(matrix.stack_height(), 6);
+    }
+
+    #[test]
+    fn insert_garbage_pushes_stack_up() {
+        let mut matrix = Matrix::blank();
+        matrix[Coordinate::new(0, 0)] = Some(Color::Blue);
+
+        assert!(!matrix.insert_garbage(2, 3));
+        assert_eq!(matrix[Coordinate::new(0, 2)], Some(Color::Blue));
+        assert_eq!(matrix[Coordinate::new(3, 0)], None);
+        assert_eq!(matrix[Coordinate::new(3, 1)], None);
+        assert_eq!(matrix[Coordinate::new(4, 1)], Some(Color::Gray));
+
+        matrix[Coordinate::new(5, Matrix::HEIGHT - 1)] = Some(Color::Red);
+        assert!(matrix.insert_garbage(1, 0));
+    }
+
+    #[test]
+    fn queued_garbage_lands_on_next_lock() {
+        let mut engine = Engine::new();
+        engine.queue_garbage(3);
+        for _ in 0..GarbageQueue::DELAY {
+            engine.tick();
+        }
+        engine.hard_drop();
+
+        assert!(engine.garbage().is_empty());
+        assert!(engine
+            .take_events()
+            .contains(&Event::GarbageInserted { lines: 3 }));
+        let gray = engine
+            .cells()
+            .filter(|(_, cell)| **cell == Some(Color::Gray))
+            .count();
+        assert_eq!(gray, 3 * (Matrix::WIDTH - 1));
+    }
+
+    #[test]
+    fn preview_matches_spawn_order() {
+        let mut engine = Engine::new();
+        let upcoming: Vec<_> = engine.preview(Engine::MAX_PREVIEW).collect();
+        assert_eq!(upcoming.len(), Engine::MAX_PREVIEW);
+        assert_eq!(engine.preview(10).count(), Engine::MAX_PREVIEW);
+
+        for kind in upcoming {
+            engine.tick();
+            assert_eq!(engine.cursor.unwrap().kind, kind);
+            engine.hard_drop();
+        }
+    }
+
+    #[test]
+    fn debug_info_tracks_rotation_and_bag() {
+        let mut engine = Engine::new();
+        engine.spawn_first();
+        let info = engine.debug_info();
+        assert_eq!(info.last_kick, None);
+        // 第一包出了一个，还剩六个
+        assert_eq!(info.bag.len(), PieceKind::ALL.len() - 1);
+        assert_eq!(Some(info.bag[0]), engine.preview(1).next());
+
+        engine.rotate_clockwise();
+        assert_eq!(engine.debug_info().last_kick, Some(Kick::Succeeded(0)));
+    }
+
+    #[test]
+    fn every_piece_rotates_where_it_spawns() {
+        for kind in PieceKind::ALL {
+            let mut engine = Engine::new();
+            engine.cursor = Some(Piece {
+                kind,
+                rotation: Rotation::N,
+                position: engine.spawn_position(),
+            });
+            for _ in 0..4 {
+                engine.rotate_clockwise();
+                assert_eq!(engine.last_kick, Some(Kick::Succeeded(0)), "{:?}", kind);
+            }
+        }
+
+        // 竖着的 I 伸出顶上一格锁住了，是 lock out
+        let mut matrix = Matrix::blank();
+        for y in 0..matrix.spawn_row() as usize {
+            for x in 1..Matrix::WIDTH {
+                matrix[Coordinate::new(x, y)] = Some(Color::Red);
+            }
+        }
+        let mut engine = Engine::with_matrix(matrix);
+        engine.cursor = Some(Piece {
+            kind: PieceKind::I,
+            rotation: Rotation::N,
+            position: engine.spawn_position(),
+        });
+        engine.rotate_clockwise();
+        assert_eq!(engine.last_kick, Some(Kick::Succeeded(0)));
+        engine.hard_drop();
+        assert!(engine.is_game_over());
+        assert_eq!(
+            engine.matrix().stack_height(),
+            engine.matrix().spawn_row() as usize
+        );
+    }
+
+    #[test]
+    fn on_beat_locks_score_only_in_rhythm_modes() {
+        let mut engine = Engine::with_mode(Mode::Zen);
+        engine.stats.level = 3;
+        engine.award_beat_bonus();
+        assert_eq!(engine.stats().score, Mode::BEAT_BONUS * 3);
+
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        engine.award_beat_bonus();
+        assert_eq!(engine.stats().score, 0);
+    }
+
+    #[test]
+    fn fall_progress_grows_until_landing() {
+        let mut engine = Engine::new();
+        engine.spawn_first();
+        assert_eq!(engine.fall_progress(0.0), 0.0);
+        engine.tick();
+        let progress = engine.fall_progress(0.5);
+        assert!(progress > 0.0 && progress < 1.0);
+
+        while !engine.cusor_has_hit_bottom() {
+            engine.soft_drop();
+        }
+        assert_eq!(engine.fall_progress(0.5), 0.0);
+    }
+
+    #[test]
+    fn hold_swaps_once_per_piece() {
+        let mut engine = Engine::new();
+        engine.tick();
+        let first = engine.cursor.unwrap().kind;
+
+        engine.hold();
+        assert_eq!(engine.held(), Some(first));
+        let second = engine.cursor.unwrap().kind;
+        engine.hold();
+        assert_eq!(engine.cursor.unwrap().kind, second);
+        assert_eq!(engine.stats().holds, 1);
+
+        engine.hard_drop();
+        engine.tick();
+        engine.hold();
+        assert_eq!(engine.cursor.unwrap().kind, first);
+    }
+
+    #[test]
+    fn wasted_inputs_count_as_finesse_fault() {
+        let mut engine = Engine::new();
+        engine.tick();
+        engine.hard_drop();
+        assert_eq!(engine.stats().finesse_faults, 0);
+
+        engine.tick();
+        let _ = engine.move_cursor(MoveKind::Left);
+        let _ = engine.move_cursor(MoveKind::Right);
+        engine.hard_drop();
+        let stats = engine.stats();
+        assert_eq!(stats.pieces, 2);
+        assert_eq!(stats.finesse_faults, 1);
+        assert!(engine
+            .take_events()
+            .contains(&Event::FinesseFault { optimal: vec![] }));
+    }
+
+    #[test]
+    fn ultra_freezes_when_time_is_up() {
+        let mut engine = Engine::with_mode(Mode::Ultra);
+        engine.stats.frames = Mode::ULTRA_FRAMES - 2;
+        engine.tick();
+        engine.tick();
+        assert!(engine.is_finished());
+        assert!(engine.cursor.is_some());
+        assert_eq!(engine.mode().timer_frames(&engine.stats()), Some(0));
+
+        engine.hard_drop();
+        for _ in 0..Engine::END_FREEZE_FRAMES {
+            assert!(!engine.end_freeze_done());
+            engine.tick();
+        }
+        assert!(engine.end_freeze_done());
+        assert_eq!(engine.stats().frames, Mode::ULTRA_FRAMES);
+        assert_eq!(engine.stats().score, 0);
+    }
+
+    #[test]
+    fn zen_clears_the_stack_instead_of_ending() {
+        let mut matrix = Matrix::blank();
+        for y in 0..Matrix::HEIGHT {
+            matrix[Coordinate::new(0, y)] = Some(Color::Red);
+        }
+        for x in 0..Matrix::WIDTH - 1 {
+            matrix[Coordinate::new(x, Matrix::HEIGHT - 1)] = Some(Color::Red);
+        }
+        let mut engine = Engine::with_matrix(matrix);
+        engine.mode = Mode::Zen;
+        engine.tick();
+
+        assert!(!engine.is_game_over());
+        assert!(engine.cursor.is_some());
+        assert_eq!(engine.matrix().stack_height(), Matrix::HEIGHT / 2);
+        assert!(engine.take_events().is_empty());
+    }
+
+    #[test]
+    fn dig_raises_garbage_on_a_timer() {
+        let mut engine = Engine::with_mode(Mode::Dig);
+        engine.set_dig_interval(10);
+        for _ in 0..9 {
+            engine.tick();
+        }
+        assert_eq!(engine.matrix().stack_height(), 0);
+        engine.tick();
+        assert_eq!(engine.matrix().stack_height(), 1);
+        assert!(engine
+            .take_events()
+            .contains(&Event::GarbageInserted { lines: 1 }));
+
+        for _ in 0..10 * Matrix::HEIGHT {
+            engine.tick();
+        }
+        assert!(engine.is_game_over());
+    }
+
+    #[test]
+    fn big_mode_plays_on_a_smaller_matrix() {
+        let mut engine = Engine::with_mode(Mode::Big);
+        assert_eq!(engine.matrix_size(), (5, 10));
+        assert_eq!(engine.cells().count(), 5 * 10);
+        for x in 0..4 {
+            engine.matrix[Coordinate::new(x, 0)] = Some(Color::Red);
+        }
+        engine.debug_test_cursor(PieceKind::I, Offset::new(2, 0));
+        engine.rotate_clockwise();
+        assert!(engine.move_cursor(MoveKind::Right).is_err());
+        engine.hard_drop();
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert_eq!(engine.stats().lines, 1);
+        assert_eq!(engine.matrix().stack_height(), 3);
+    }
+
+    #[test]
+    fn attack_race_ends_when_enough_attack_is_sent() {
+        let mut engine = Engine::with_mode(Mode::AttackRace);
+        engine.stats.attack = Mode::ATTACK_LINES - 1;
+        for y in 0..2 {
+            for x in 0..Matrix::WIDTH - 2 {
+                engine.matrix[Coordinate::new(x, y)] = Some(Color::Red);
+            }
+        }
+        // 消两行送一行
+        engine.debug_test_cursor(PieceKind::O, Offset::new(Matrix::WIDTH as isize - 3, -1));
+        engine.hard_drop();
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert!(engine.is_finished());
+        assert_eq!(engine.stats().attack, Mode::ATTACK_LINES);
+        assert_eq!(engine.timer_frames(), Some(engine.stats().frames));
+    }
+
+    #[test]
+    fn co_op_cursors_block_each_other_on_one_board() {
+        let mut engine = Engine::with_mode(Mode::Coop);
+        assert_eq!(engine.matrix_size(), (Matrix::MAX_WIDTH, Matrix::HEIGHT));
+        engine.spawn_first();
+        engine.tick();
+        let half = Matrix::MAX_WIDTH / 2;
+        let (left, _) = engine.cursor_info().unwrap();
+        let (right, _) = engine.partner_cursor_info().unwrap();
+        assert!(left.iter().all(|cell| cell.x < half));
+        assert!(right.iter().all(|cell| cell.x >= half));
+
+        // 底下两行只空着中间四列，两个 O 挨着放进去
+        for y in 0..2 {
+            for x in (0..Matrix::MAX_WIDTH).filter(|x| !(6..10).contains(x)) {
+                engine.matrix[Coordinate::new(x, y)] = Some(Color::Red);
+            }
+        }
+        engine.debug_test_cursor(PieceKind::O, Offset::new(5, -1));
+        engine.as_partner(|engine| engine.debug_test_cursor(PieceKind::O, Offset::new(7, -1)));
+        assert!(engine.move_cursor(MoveKind::Right).is_err());
+        engine.as_partner(|engine| assert!(engine.move_cursor(MoveKind::Left).is_err()));
+
+        engine.hard_drop();
+        assert!(engine.line_clear_progress().is_none());
+        engine.as_partner(Engine::hard_drop);
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert_eq!(engine.stats().lines, 2);
+        assert_eq!(engine.matrix().stack_height(), 0);
+        assert!(engine.cursor.is_some());
+        assert!(engine.partner_cursor_info().is_some());
+    }
+
+    #[test]
+    fn four_wide_resets_when_the_combo_breaks() {
+        let mut engine = Engine::with_mode(Mode::Combo);
+        let setup = engine.matrix().clone();
+        let well = |matrix: &Matrix| {
+            (0..Matrix::HEIGHT)
+                .flat_map(|y| (3..7).map(move |x| Coordinate::new(x, y)))
+                .filter(|&coord| matrix[coord].is_some())
+                .count()
+        };
+        assert_eq!(well(&setup), 3);
+
+        // O 放在右边两列，消掉最底下一行，井里还剩三格，墙补回原来的高度
+        engine.debug_test_cursor(PieceKind::O, Offset::new(4, 10));
+        engine.hard_drop();
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert_eq!(engine.stats().lines, 1);
+        assert_eq!(engine.stats().score, 1);
+        assert_eq!(well(engine.matrix()), 3);
+        assert_eq!(engine.matrix().stack_height(), setup.stack_height());
+
+        // 叠在左边没消行，连击断了，井里摆回开头的样子
+        engine.debug_test_cursor(PieceKind::O, Offset::new(2, 10));
+        engine.hard_drop();
+        assert_eq!(engine.stats().lines, 1);
+        assert!(engine.cells().all(|(coord, cell)| *cell == setup[coord]));
+        assert!(!engine.is_game_over());
+    }
+
+    #[test]
+    fn t_spins_need_a_twist_into_three_corners() {
+        let mut engine = Engine::with_mode(Mode::TSpin);
+        assert!(engine
+            .preview(Engine::MAX_PREVIEW)
+            .all(|kind| kind == PieceKind::T));
+        // T-spin double 的坑，T 先竖着放进去再转一下
+        engine.matrix = Matrix::blank();
+        for (y, row) in ["XXX.XXXXXX", "XX...XXXXX", "XXX......."]
+            .iter()
+            .enumerate()
+        {
+            for (x, cell) in row.bytes().enumerate() {
+                if cell == b'X' {
+                    engine.matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+                }
+            }
+        }
+        engine.debug_test_cursor(PieceKind::T, Offset::new(2, 0));
+        engine.rotate_clockwise();
+        engine.rotate_clockwise();
+        engine.hard_drop();
+        assert!(engine.take_events().contains(&Event::TSpin { lines: 2 }));
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert_eq!(engine.stats().t_spins, 1);
+        assert_eq!(engine.stats().lines, 2);
+        // 换了下一个坑，直接放下去的 T 不算
+        assert!(engine.matrix().stack_height() > 0);
+        engine.spawn_first();
+        engine.hard_drop();
+        assert!(!engine
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, Event::TSpin { .. })));
+        assert_eq!(engine.stats().t_spins, 1);
+    }
+
+    #[test]
+    fn mystery_effects_come_and_go() {
+        let mut engine = Engine::with_mode(Mode::Mystery);
+        engine.spawn_first();
+        engine.effect = Some((Effect::Inverted, 2));
+        let x = engine.cursor.unwrap().position.x;
+        engine.move_cursor(MoveKind::Left).unwrap();
+        assert_eq!(engine.cursor.unwrap().position.x, x + 1);
+        engine.tick();
+        engine.tick();
+        assert_eq!(engine.effect(), None);
+        engine.move_cursor(MoveKind::Left).unwrap();
+        assert_eq!(engine.cursor.unwrap().position.x, x);
+
+        engine.effect_timer = Effect::INTERVAL - 1;
+        engine.tick();
+        assert!(engine
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, Event::Mystery(_))));
+    }
+
+    #[test]
+    fn training_forces_pieces_and_undoes_placements() {
+        let mut engine = Engine::with_mode(Mode::Training);
+        engine.spawn_first();
+        engine.force_next(PieceKind::I);
+        engine.hard_drop();
+        assert_eq!(engine.cursor.unwrap().kind, PieceKind::I);
+        let placed = engine.matrix().stack_height();
+        assert!(placed > 0);
+
+        engine.hard_drop();
+        assert!(engine.undo());
+        assert_eq!(engine.cursor.unwrap().kind, PieceKind::I);
+        assert_eq!(engine.matrix().stack_height(), placed);
+        assert_eq!(engine.stats().pieces, 1);
+        assert!(engine.undo());
+        assert!(!engine.undo());
+        assert_eq!(engine.matrix().stack_height(), 0);
+
+        engine.set_infinite_hold(true);
+        engine.hold();
+        engine.hold();
+        assert_eq!(engine.stats().holds, 2);
+        engine.reset();
+        assert!(engine.infinite_hold());
+        assert_eq!(engine.stats().holds, 0);
+
+        let mut marathon = Engine::with_mode(Mode::Marathon);
+        marathon.set_infinite_hold(true);
+        assert!(!marathon.infinite_hold());
+    }
+
+    #[test]
+    fn survival_attacks_grow_over_time() {
+        let mut engine = Engine::with_mode(Mode::Survival);
+        let (interval, lines) = Mode::Survival.attack_schedule(0).unwrap();
+        for _ in 0..interval {
+            engine.tick();
+        }
+        assert_eq!(engine.garbage().total(), lines);
+
+        let late = 10 * 60 * Stats::FRAMES_PER_SECOND;
+        let (late_interval, late_lines) = Mode::Survival.attack_schedule(late).unwrap();
+        assert!(late_interval < interval);
+        assert!(late_lines > lines);
+        assert_eq!(Mode::Marathon.attack_schedule(late), None);
+    }
+
+    #[test]
+    fn master_drops_instantly_and_speeds_up_with_grade() {
+        let mut engine = Engine::with_mode(Mode::Master);
+        // 开局也要等一个出块延迟
+        for _ in 0..=Engine::MASTER_SPAWN_DELAY.0 {
+            engine.tick();
+        }
+        assert!(engine.cusor_has_hit_bottom());
+        assert_eq!(engine.lock_delay(), 30);
+
+        engine.stats.score = 16000;
+        assert_eq!(
+            game_mode::Master::GRADES[engine.mode().grade(&engine.stats).unwrap()].1,
+            "S1"
+        );
+        assert_eq!(engine.lock_delay(), 21);
+        assert_eq!(engine.spawn_delay(), 16);
+        engine.stats.score = u32::MAX;
+        assert_eq!(engine.lock_delay(), 15);
+        assert_eq!(engine.spawn_delay(), 8);
+    }
+
+    #[test]
+    fn invisible_cells_fade_and_reveal_at_the_end() {
+        let mut engine = Engine::with_mode(Mode::Invisible);
+        engine.tick();
+        engine.hard_drop();
+        let Some(Event::Lock { cells, .. }) = engine.take_events().pop() else {
+            panic!("expected a lock");
+        };
+        assert_eq!(engine.cell_visibility(cells[0]), 1.0);
+        for _ in 0..Engine::INVISIBLE_DELAY + Engine::INVISIBLE_FADE {
+            engine.tick();
+        }
+        assert_eq!(engine.cell_visibility(cells[0]), 0.0);
+        engine.top_out();
+        assert_eq!(engine.cell_visibility(cells[0]), 1.0);
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_pieces() {
+        // 连抽三包
+        let deal = |seed| {
+            let mut engine = Engine::with_seed(Mode::Daily, seed);
+            let mut pieces = Vec::new();
+            while pieces.len() < 3 * PieceKind::ALL.len() {
+                pieces.extend(engine.queue.drain(..));
+                engine.refill_queue();
+            }
+            pieces
+        };
+        assert_eq!(deal(20_000), deal(20_000));
+        assert_ne!(deal(20_000), deal(20_001));
+    }
+
+    #[test]
+    fn classic_rules_refuse_hold_and_hard_drop() {
+        let mut engine = Engine::with_mode(Mode::Classic);
+        engine.tick();
+        engine.hold();
+        engine.hard_drop();
+        assert_eq!(engine.held(), None);
+        assert_eq!(engine.stats().pieces, 0);
+        // 按了是按了，只是什么都没发生
+        assert_eq!(
+            engine.take_events(),
+            [Event::Action(Action::Hold), Event::Action(Action::HardDrop)]
+        );
+        assert_eq!(engine.drop_interval(), 48);
+    }
+
+    #[test]
+    fn game_over_when_spawn_is_blocked() {
+        let mut matrix = Matrix::blank();
+        for x in 0..Matrix::WIDTH - 1 {
+            matrix[Coordinate::new(x, Matrix::HEIGHT - 1)] = Some(Color::Red);
+        }
+        let mut engine = Engine::with_matrix(matrix);
+        engine.tick();
+
+        assert!(engine.is_game_over());
+        assert!(engine.cursor.is_none());
+    }
+
+    #[test]
+    fn player_actions_emit_events() {
+        let mut engine = Engine::new();
+        engine.tick();
+        engine.take_events();
+        engine.move_cursor(MoveKind::Left).unwrap();
+        engine.rotate_clockwise();
+        engine.hold();
+        let events = engine.take_events();
+        assert_eq!(events[0], Event::Action(Action::Move(MoveKind::Left)));
+        assert!(matches!(events[1], Event::Move { .. }));
+        assert!(events.contains(&Event::Hold));
+        assert!(events.contains(&Event::Rotate));
+    }
+
+    #[test]
+    fn hard_drop_scores_and_spawns_next() {
+        let mut engine = Engine::new();
+        engine.tick();
+        assert!(engine.cursor.is_some());
+
+        let kind = engine.cursor.unwrap().kind;
+        engine.hard_drop();
+        assert!(engine.stats().score > 0);
+        assert_eq!(engine.stats().piece_counts[kind as usize], 1);
+        assert!(matches!(
+            engine.take_events()[..],
+            [
+                Event::Action(Action::HardDrop),
+                Event::HardDrop { distance, .. },
+                Event::Lock { .. }
+            ] if distance > 0
+        ));
+        assert!(engine.cursor.is_some());
+        assert!(!engine.is_game_over());
+    }
 }
@@ -0,0 +1,140 @@
+use super::custom;
+use super::game_mode::{self, GameMode, REGISTERED};
+use super::rules::Rules;
+use super::Stats;
+
+// 游戏模式的名字，排行榜和存档都按它记；一局什么时候结束、计时器怎么走这些玩法见 `GameMode`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Mode {
+    // 一直玩到 game over
+    #[default]
+    Marathon,
+    // 尽快消掉 SPRINT_LINES 行
+    Sprint,
+    // 在 ULTRA_FRAMES 帧里拿尽量多的分
+    Ultra,
+    // 不会输也不计时，堆满了就清掉下面的行接着玩；踩着音乐的拍子锁定有奖励分
+    Zen,
+    // 垃圾行每隔一段时间从底下顶上来，坚持到顶满为止
+    Dig,
+    // TGM 那样一开始就是 20G，分数够了升段，段位越高锁定和出块越快
+    Master,
+    // 规则和 marathon 一样，只是锁定的方块过一会儿就看不见了
+    Invisible,
+    // 红白机的规则：没有 hold 和硬降，按红白机的重力表和计分
+    Classic,
+    // 从文件读的谜题，目标和方块顺序见 `Puzzle`
+    Puzzle,
+    // 每日挑战：种子按日期定，当天所有人方块顺序一样，和 ultra 一样限时拿分
+    Daily,
+    // 随机的垃圾攻击越来越密、越来越大，比谁撑得久
+    Survival,
+    // 规则和 marathon 一样，方块放大成两倍，相当于在一半宽、一半高的 matrix 上玩
+    Big,
+    // 练习用的沙盒：能指定下一块、无限 hold、撤销上一块、一键清空，不会输也不计分
+    Training,
+    // 一个接一个的限时任务（见 `Mission`），超时就结束，分数是完成了几个
+    Mission,
+    // 练 4-wide 连击：两边堆满只留中间四列，按连击长度计分，断了连击就重新摆好
+    Combo,
+    // 每隔一段时间随机来一个事件：左右反过来、棋盘看不见、20G、白送清行或者垃圾
+    Mystery,
+    // 从文件读的模式，是 `custom::registered` 里的第几个
+    Custom(usize),
+    // 练 T-spin：每一块都是 T，每次给一个现成的坑，转进去消行才算成功，看成功率
+    TSpin,
+    // 尽快送出 ATTACK_LINES 行攻击，和 sprint 一样比时间
+    AttackRace,
+    // 两个人在一块加宽的 matrix 上同时放方块，各自操作一个，方块之间会互相挡住
+    Coop,
+}
+
+impl Mode {
+    pub const SPRINT_LINES: u32 = 40;
+    pub const ATTACK_LINES: u32 = 20;
+    pub const ULTRA_FRAMES: u64 = 2 * 60 * Stats::FRAMES_PER_SECOND;
+    // zen 踩着拍子锁定一块的奖励分，还要乘等级
+    pub const BEAT_BONUS: u32 = 50;
+
+    // 上排行榜的模式，按主菜单的顺序，见 `GameMode::ranked`
+    pub fn ranked() -> Vec<Mode> {
+        game_mode::all()
+            .into_iter()
+            .filter(|game| game.ranked())
+            .map(|game| game.mode())
+            .collect()
+    }
+
+    // 玩法都在 `GameMode` 里，`Mode` 只是个名字
+    pub fn game_mode(self) -> &'static dyn GameMode {
+        if let Mode::Custom(index) = self {
+            return custom::get(index).expect("custom modes are registered before use");
+        }
+        *REGISTERED
+            .iter()
+            .find(|game| game.mode() == self)
+            .expect("every mode is registered")
+    }
+
+    pub fn goal_reached(self, stats: &Stats) -> bool {
+        self.game_mode().goal_reached(stats)
+    }
+
+    pub fn beat_bonus(self) -> Option<u32> {
+        self.game_mode().beat_bonus()
+    }
+
+    // 堆到顶的时候是结束游戏还是清场接着玩
+    pub fn forgives_top_out(self) -> bool {
+        self.game_mode().forgives_top_out()
+    }
+
+    pub fn replayable(self) -> bool {
+        self.game_mode().replayable()
+    }
+
+    pub fn is_sandbox(self) -> bool {
+        self.game_mode().is_sandbox()
+    }
+
+    pub fn attack_schedule(self, frames: u64) -> Option<(u32, u32)> {
+        self.game_mode().attack_schedule(frames)
+    }
+
+    pub fn hides_cells(self) -> bool {
+        self.game_mode().hides_cells()
+    }
+
+    pub fn rules(self) -> Rules {
+        self.game_mode().rules()
+    }
+
+    pub fn grade(self, stats: &Stats) -> Option<usize> {
+        self.game_mode().grade(stats)
+    }
+
+    // 计时器显示的帧数：sprint、attack race 和 survival 正着数，ultra 倒着数，其它不显示
+    pub fn timer_frames(self, stats: &Stats) -> Option<u64> {
+        self.game_mode().timer_frames(stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ultra_timer_counts_down() {
+        let stats = Stats {
+            frames: 90,
+            ..Stats::default()
+        };
+        assert_eq!(Mode::Sprint.timer_frames(&stats), Some(90));
+        assert_eq!(
+            Mode::Ultra.timer_frames(&stats),
+            Some(Mode::ULTRA_FRAMES - 90)
+        );
+        assert_eq!(Mode::Marathon.timer_frames(&stats), None);
+        assert!(!Mode::Ultra.goal_reached(&stats));
+    }
+}
@@ -0,0 +1,63 @@
+use super::Stats;
+
+// 神秘模式隔一段时间随机来一下的事件。有的是持续一段时间的效果，有的当场就生效
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Effect {
+    // 左右键反过来
+    Inverted,
+    // 锁定的方块全看不见
+    Invisible,
+    // 20G，方块一出来就落到底
+    Gravity,
+    // 当场清掉底下几行垃圾
+    BonusClear,
+    // 当场从底下顶上来几行垃圾
+    Garbage,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 5] = [
+        Effect::Inverted,
+        Effect::Invisible,
+        Effect::Gravity,
+        Effect::BonusClear,
+        Effect::Garbage,
+    ];
+    // 隔多久来一次
+    pub const INTERVAL: u64 = 20 * Stats::FRAMES_PER_SECOND;
+    const DURATION: u64 = 10 * Stats::FRAMES_PER_SECOND;
+    // 清掉或者顶上来几行
+    pub const ROWS: usize = 2;
+
+    // 界面上提示的名字
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Inverted => "INVERTED CONTROLS",
+            Effect::Invisible => "INVISIBLE BOARD",
+            Effect::Gravity => "20G",
+            Effect::BonusClear => "BONUS CLEAR",
+            Effect::Garbage => "GARBAGE ATTACK",
+        }
+    }
+
+    // 持续多少帧，当场生效的是 0
+    pub fn duration(self) -> u64 {
+        match self {
+            Effect::Inverted | Effect::Invisible | Effect::Gravity => Self::DURATION,
+            Effect::BonusClear | Effect::Garbage => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lasting_effects_end_before_the_next_one() {
+        for effect in Effect::ALL {
+            assert!(effect.duration() < Effect::INTERVAL, "{:?}", effect);
+        }
+        assert_eq!(Effect::BonusClear.duration(), 0);
+    }
+}
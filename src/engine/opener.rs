@@ -0,0 +1,280 @@
+use serde::Deserialize;
+
+use super::bot::{BotView, Hint};
+use super::piece::{Kind as PieceKind, Piece, Rotation};
+use super::planner::Hold;
+use super::{Coordinate, Matrix, Offset};
+
+// 内置的开局定式，格式见文件里的说明
+const BUILTIN: &str = include_str!("../../openers/openers.toml");
+
+#[derive(Deserialize)]
+struct OpenerFile {
+    opener: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    name: String,
+    board: Vec<String>,
+}
+
+// 定式里的一块：哪种方块，放在哪几格
+type Target = (PieceKind, [Coordinate; Piece::CELL_COUNT]);
+
+// 一个开局定式：第一包方块各自放在哪
+pub struct Opener {
+    pub name: String,
+    targets: Vec<Target>,
+}
+
+pub fn builtin() -> Vec<Opener> {
+    parse(BUILTIN).expect("built-in openers are valid")
+}
+
+pub fn parse(source: &str) -> Result<Vec<Opener>, String> {
+    let file: OpenerFile = toml::from_str(source).map_err(|err| err.to_string())?;
+    file.opener.into_iter().map(Opener::new).collect()
+}
+
+// 从这个局面照着定式往下搭，第一个搭得成的定式是第几个，和下一步放哪
+pub fn suggest(openers: &[Opener], view: &BotView) -> Option<(usize, Hint)> {
+    openers
+        .iter()
+        .enumerate()
+        .find_map(|(index, opener)| Some((index, opener.next_step(view)?)))
+}
+
+impl Opener {
+    fn new(entry: Entry) -> Result<Opener, String> {
+        let Entry { name, board } = entry;
+        if board.len() > Matrix::HEIGHT {
+            return Err(format!(
+                "{}: board is taller than {} rows",
+                name,
+                Matrix::HEIGHT
+            ));
+        }
+        let mut cells: Vec<(PieceKind, Vec<Coordinate>)> = Vec::new();
+        for (y, row) in board.iter().rev().enumerate() {
+            if row.len() != Matrix::WIDTH {
+                return Err(format!(
+                    "{}: row {:?} is not {} cells wide",
+                    name,
+                    row,
+                    Matrix::WIDTH
+                ));
+            }
+            for (x, cell) in row.chars().enumerate().filter(|&(_, cell)| cell != '.') {
+                let kind = PieceKind::ALL
+                    .into_iter()
+                    .find(|kind| format!("{:?}", kind) == cell.to_string())
+                    .ok_or_else(|| format!("{}: unknown piece {:?}", name, cell))?;
+                match cells.iter_mut().find(|(placed, _)| *placed == kind) {
+                    Some((_, coords)) => coords.push(Coordinate::new(x, y)),
+                    None => cells.push((kind, vec![Coordinate::new(x, y)])),
+                }
+            }
+        }
+        let targets = cells
+            .into_iter()
+            .map(|(kind, coords)| {
+                shape(kind, &coords)
+                    .map(|shape| (kind, shape))
+                    .ok_or_else(|| format!("{}: the {:?} cells are not one {:?}", name, kind, kind))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Opener { name, targets })
+    }
+
+    // 还在照着定式搭的话，剩下没放的方块；有格子不在定式里、或者哪块只放了一半是 None
+    fn remaining(&self, matrix: &Matrix) -> Option<Vec<Target>> {
+        let in_opener =
+            |coord: &Coordinate| self.targets.iter().any(|(_, cells)| cells.contains(coord));
+        for y in 0..matrix.height() {
+            for x in 0..matrix.width() {
+                let coord = Coordinate::new(x, y);
+                if matrix[coord].is_some() && !in_opener(&coord) {
+                    return None;
+                }
+            }
+        }
+        let mut remaining = Vec::new();
+        for &(kind, cells) in &self.targets {
+            match cells.iter().filter(|&&cell| matrix[cell].is_some()).count() {
+                0 => remaining.push((kind, cells)),
+                Piece::CELL_COUNT => {}
+                _ => return None,
+            }
+        }
+        Some(remaining)
+    }
+
+    // 下一步放哪：要不要先 hold，放下去的格子。已经搭完了，或者照现在的方块顺序搭不成是 None
+    pub fn next_step(&self, view: &BotView) -> Option<Hint> {
+        let remaining = self.remaining(&view.matrix)?;
+        if remaining.is_empty() {
+            return None;
+        }
+        let queue = view.queue();
+        choices(&queue, view.hold_state())
+            .into_iter()
+            .find_map(|(held, kind, queue, hold)| {
+                let index = remaining.iter().position(|&(target, cells)| {
+                    target == kind && droppable(&view.matrix, &cells)
+                })?;
+                let mut matrix = view.matrix.clone();
+                let mut rest = remaining.clone();
+                let (_, cells) = rest.remove(index);
+                for cell in cells {
+                    matrix[cell] = Some(kind.color());
+                }
+                buildable(&matrix, &rest, queue, hold).then_some(Hint {
+                    hold: held,
+                    cells,
+                    color: kind.color(),
+                })
+            })
+    }
+}
+
+// 这几格是不是一块 `kind`
+fn shape(kind: PieceKind, coords: &[Coordinate]) -> Option<[Coordinate; Piece::CELL_COUNT]> {
+    if coords.len() != Piece::CELL_COUNT {
+        return None;
+    }
+    let rotations = [Rotation::N, Rotation::E, Rotation::S, Rotation::W];
+    let (width, height) = (Matrix::WIDTH as isize, Matrix::HEIGHT as isize);
+    rotations
+        .into_iter()
+        .flat_map(|rotation| {
+            (-2..width).flat_map(move |x| (-2..height).map(move |y| (rotation, x, y)))
+        })
+        .find_map(|(rotation, x, y)| {
+            let piece = Piece {
+                kind,
+                rotation,
+                position: Offset::new(x, y),
+            };
+            piece
+                .cells()
+                .filter(|cells| cells.iter().all(|cell| coords.contains(cell)))
+        })
+}
+
+// 这块现在放下去是不是正好停在这里：头顶上没有挡着的，底下有东西托着
+fn droppable(matrix: &Matrix, cells: &[Coordinate]) -> bool {
+    let clear_above = cells.iter().all(|cell| {
+        (cell.y + 1..matrix.height()).all(|y| matrix[Coordinate::new(cell.x, y)].is_none())
+    });
+    let resting = cells
+        .iter()
+        .any(|cell| cell.y == 0 || matrix[Coordinate::new(cell.x, cell.y - 1)].is_some());
+    clear_above && resting
+}
+
+// 这一块可以怎么出：直接放当前这块，或者先 hold 换一块出来。
+// 每种是（hold 了没有，放的是哪块，之后的队列，之后的 hold）
+fn choices(queue: &[PieceKind], hold: Hold) -> Vec<(bool, PieceKind, &[PieceKind], Hold)> {
+    let Some((&current, rest)) = queue.split_first() else {
+        return Vec::new();
+    };
+    let after = |held| match hold {
+        Hold::Off => Hold::Off,
+        _ => Hold::Ready(held),
+    };
+    let mut choices = vec![(false, current, rest, after(hold.held()))];
+    if let Hold::Ready(held) = hold {
+        match (held, rest.split_first()) {
+            (Some(held), _) => choices.push((true, held, rest, after(Some(current)))),
+            (None, Some((&next, rest))) => choices.push((true, next, rest, after(Some(current)))),
+            (None, None) => {}
+        }
+    }
+    choices
+}
+
+// 剩下的能不能按这个队列搭完；预览看不到的就当搭得成
+fn buildable(matrix: &Matrix, remaining: &[Target], queue: &[PieceKind], hold: Hold) -> bool {
+    if remaining.is_empty() || queue.is_empty() {
+        return true;
+    }
+    choices(queue, hold)
+        .into_iter()
+        .any(|(_, kind, queue, hold)| {
+            remaining
+                .iter()
+                .enumerate()
+                .any(|(index, &(target, cells))| {
+                    if target != kind || !droppable(matrix, &cells) {
+                        return false;
+                    }
+                    let mut matrix = matrix.clone();
+                    for cell in cells {
+                        matrix[cell] = Some(kind.color());
+                    }
+                    let mut rest = remaining.to_vec();
+                    rest.remove(index);
+                    buildable(&matrix, &rest, queue, hold)
+                })
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::mode::Mode;
+    use crate::engine::Engine;
+
+    #[test]
+    fn openers_follow_the_bag_order() {
+        let openers = builtin();
+        assert_eq!(openers.len(), 3);
+        assert!(openers.iter().all(|opener| opener.targets.len() >= 6));
+        assert!(parse("[[opener]]\nname = \"BAD\"\nboard = [\"TT........\"]").is_err());
+
+        // TKI 要先放 T
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        engine.queue = [
+            PieceKind::I,
+            PieceKind::L,
+            PieceKind::J,
+            PieceKind::O,
+            PieceKind::S,
+            PieceKind::Z,
+        ]
+        .into_iter()
+        .collect();
+        let spawn = engine.spawn_position();
+        engine.debug_test_cursor(PieceKind::T, spawn);
+        let view = BotView::of(&engine).unwrap();
+        let (opener, hint) = suggest(&openers, &view).unwrap();
+        assert_eq!(openers[opener].name, "TKI");
+        assert!(!hint.hold);
+        assert!(hint.cells.contains(&Coordinate::new(1, 1)));
+
+        // 这一包 T 打头、I 垫底，正好搭 PCO：T 先 hold 起来，放 J
+        engine.queue = [
+            PieceKind::J,
+            PieceKind::L,
+            PieceKind::S,
+            PieceKind::O,
+            PieceKind::Z,
+            PieceKind::I,
+        ]
+        .into_iter()
+        .collect();
+        let view = BotView::of(&engine).unwrap();
+        let (opener, hint) = suggest(&openers, &view).unwrap();
+        assert_eq!(openers[opener].name, "PCO");
+        assert!(hint.hold);
+        assert_eq!(hint.color, PieceKind::J.color());
+
+        // 放歪了就不再照着定式提示
+        engine.matrix[Coordinate::new(0, 0)] = Some(PieceKind::J.color());
+        let view = BotView::of(&engine).unwrap();
+        assert!(openers
+            .iter()
+            .all(|opener| opener.next_step(&view).is_none()));
+    }
+}
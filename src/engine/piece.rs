@@ -1,4 +1,5 @@
 use cgmath::{EuclideanSpace, Zero};
+use serde::{Deserialize, Serialize};
 
 use super::{Color, Coordinate, Matrix, Offset};
 
@@ -80,7 +81,8 @@ impl Piece {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+// 谜题文件里直接写 "T"、"I" 这样的名字
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Kind {
     O,
     I,
@@ -0,0 +1,236 @@
+use super::bot::Placement;
+use super::piece::{Kind as PieceKind, Piece, Rotation};
+use super::{Color, Coordinate, Matrix, Offset};
+
+// 往后看好几块的规划：每一层把留下来的局面的所有放法都展开打分，只留最好的
+// BEAM_WIDTH 个，再接着展开下一块。电脑玩家、落点提示和复盘都用这个
+const BEAM_WIDTH: usize = 16;
+
+// 打分的权重：消行、高度、空洞、表面平整程度
+const LINES: f32 = 0.76;
+const HEIGHT: f32 = -0.51;
+const HOLES: f32 = -0.36;
+const BUMPINESS: f32 = -0.18;
+
+// hold 能不能用，里面放着什么
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hold {
+    // 这个模式不能 hold
+    Off,
+    // 当前这块已经 hold 过了，下一块才能再用
+    Used(Option<PieceKind>),
+    Ready(Option<PieceKind>),
+}
+
+impl Hold {
+    pub fn held(self) -> Option<PieceKind> {
+        match self {
+            Hold::Off => None,
+            Hold::Used(held) | Hold::Ready(held) => held,
+        }
+    }
+}
+
+// 规划里的一步：怎么放，和落下去的样子
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub placement: Placement,
+    pub piece: Piece,
+}
+
+// `steps` 按顺序放完之后的局面打的分，加上一路消行的得分
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub steps: Vec<Step>,
+    pub score: f32,
+}
+
+#[derive(Clone)]
+struct Node {
+    matrix: Matrix,
+    hold: Hold,
+    // 下一块是 `queue` 里的第几个
+    next: usize,
+    lines: f32,
+    steps: Vec<Step>,
+    score: f32,
+}
+
+// `queue` 的第一个是当前方块，后面是预览。最多往后看 `depth` 块，预览不够的话看到预览为止；
+// 当前方块哪都放不下是 None
+pub fn plan(matrix: &Matrix, queue: &[PieceKind], hold: Hold, depth: usize) -> Option<Plan> {
+    let mut beam = vec![Node {
+        matrix: matrix.clone(),
+        hold,
+        next: 0,
+        lines: 0.0,
+        steps: Vec::new(),
+        score: 0.0,
+    }];
+    for _ in 0..depth {
+        let mut children: Vec<Node> = beam.iter().flat_map(|node| expand(node, queue)).collect();
+        if children.is_empty() {
+            break;
+        }
+        children.sort_by(|a, b| b.score.total_cmp(&a.score));
+        children.truncate(BEAM_WIDTH);
+        beam = children;
+    }
+    let best = beam
+        .into_iter()
+        .next()
+        .filter(|node| !node.steps.is_empty())?;
+    Some(Plan {
+        steps: best.steps,
+        score: best.score,
+    })
+}
+
+// 这几格放下去打几分，只看这一块，和 `depth` 是 1 的 `plan` 打的分能直接比
+pub fn rate(matrix: &Matrix, cells: &[Coordinate]) -> f32 {
+    let (matrix, lines) = lock(matrix, cells);
+    evaluate(&matrix) + lines
+}
+
+// 从这里硬降下去落在哪，放不下的话是 None
+pub fn landed(matrix: &Matrix, piece: &Piece) -> Option<Piece> {
+    if matrix.is_clipping(piece) {
+        return None;
+    }
+    let mut piece = *piece;
+    loop {
+        let lower = piece.moved_by(Offset::new(0, -1));
+        if matrix.is_clipping(&lower) {
+            // 落下去还伸出顶上的话锁不住
+            return matrix.is_placeable(&piece).then_some(piece);
+        }
+        piece = lower;
+    }
+}
+
+// 放下当前这块（或者 hold 换出来的那块）的所有走法
+fn expand(node: &Node, queue: &[PieceKind]) -> Vec<Node> {
+    let Some(&current) = queue.get(node.next) else {
+        return Vec::new();
+    };
+    // 要不要 hold，放的是哪块，放完 hold 里是什么，再下一块是第几个
+    let mut choices = vec![(false, current, node.hold.held(), node.next + 1)];
+    if let Hold::Ready(held) = node.hold {
+        match (held, queue.get(node.next + 1)) {
+            (Some(held), _) => choices.push((true, held, Some(current), node.next + 1)),
+            (None, Some(&next)) => choices.push((true, next, Some(current), node.next + 2)),
+            (None, None) => {}
+        }
+    }
+    let y = node.matrix.spawn_row();
+    choices
+        .into_iter()
+        .flat_map(|(hold, kind, held, next)| {
+            placements(&node.matrix, kind, y).map(move |(rotation, x, piece)| {
+                let cells = piece.cells().expect("landed pieces are on the matrix");
+                let (matrix, lines) = lock(&node.matrix, &cells);
+                let lines = node.lines + lines;
+                let mut steps = node.steps.clone();
+                steps.push(Step {
+                    placement: Placement { hold, rotation, x },
+                    piece,
+                });
+                Node {
+                    score: evaluate(&matrix) + lines,
+                    matrix,
+                    hold: match node.hold {
+                        Hold::Off => Hold::Off,
+                        _ => Hold::Ready(held),
+                    },
+                    next,
+                    lines,
+                    steps,
+                }
+            })
+        })
+        .collect()
+}
+
+// `kind` 从第 `y` 行出发的每种放法和落下去的样子
+fn placements(
+    matrix: &Matrix,
+    kind: PieceKind,
+    y: isize,
+) -> impl Iterator<Item = (Rotation, isize, Piece)> + '_ {
+    let rotations = [Rotation::N, Rotation::E, Rotation::S, Rotation::W];
+    let width = matrix.width() as isize;
+    rotations
+        .into_iter()
+        .flat_map(move |rotation| (-2..width).map(move |x| (rotation, x)))
+        .filter_map(move |(rotation, x)| {
+            let piece = Piece {
+                kind,
+                rotation,
+                position: Offset::new(x, y),
+            };
+            Some((rotation, x, landed(matrix, &piece)?))
+        })
+}
+
+// 把这几格填上、消掉满行之后的局面，和消行的得分
+fn lock(matrix: &Matrix, cells: &[Coordinate]) -> (Matrix, f32) {
+    let mut matrix = matrix.clone();
+    for &coord in cells {
+        matrix[coord] = Some(Color::Gray);
+    }
+    let lines = LINES * matrix.clear_full_rows() as f32;
+    (matrix, lines)
+}
+
+// 锁定之后的局面，不算消行
+fn evaluate(matrix: &Matrix) -> f32 {
+    let heights = &matrix.column_heights()[..matrix.width()];
+    let holes = (0..matrix.width())
+        .map(|x| {
+            (0..heights[x])
+                .filter(|&y| matrix.get(x as isize, y as isize).is_none())
+                .count()
+        })
+        .sum::<usize>();
+    let bumpiness = heights
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum::<usize>();
+    HEIGHT * heights.iter().sum::<usize>() as f32
+        + HOLES * holes as f32
+        + BUMPINESS * bumpiness as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plans_look_ahead_through_the_queue() {
+        // 右边留一条四格深的井
+        let mut matrix = Matrix::blank();
+        for y in 0..4 {
+            for x in 0..Matrix::WIDTH - 1 {
+                matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+            }
+        }
+        let queue = [PieceKind::O, PieceKind::I];
+        let lookahead = plan(&matrix, &queue, Hold::Off, 3).unwrap();
+        // 预览只有两块，看到第二块为止；I 竖着插进井里
+        assert_eq!(lookahead.steps.len(), 2);
+        let last = lookahead.steps[1].piece;
+        assert_eq!(last.kind, PieceKind::I);
+        assert!(last
+            .cells()
+            .unwrap()
+            .iter()
+            .all(|cell| cell.x == Matrix::WIDTH - 1));
+        assert!(lookahead.steps.iter().all(|step| !step.placement.hold));
+
+        // 能 hold 的话第一步就先换 I 出来
+        let swap = plan(&matrix, &queue, Hold::Ready(None), 1).unwrap();
+        assert!(swap.steps[0].placement.hold);
+        assert_eq!(swap.steps[0].piece.kind, PieceKind::I);
+        assert!(plan(&matrix, &[], Hold::Off, 1).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use super::piece::Kind as PieceKind;
+use super::{Color, Coordinate, Matrix, Stats};
+
+// 谜题、闯关和任务的目标：消完所有格子，或者消够几行、几个四消这些
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    ClearAll,
+    Lines(u32),
+    Tetrises(u32),
+    BackToBacks(u32),
+    TSpins(u32),
+    // 连击数到这么多，第一次消行不算
+    Combo(u32),
+    // 用这种方块消够几行
+    PieceLines { piece: PieceKind, lines: u32 },
+}
+
+impl Goal {
+    pub fn is_met(self, matrix: &Matrix, stats: &Stats) -> bool {
+        match self {
+            // 一开始就是空的棋盘不算，至少要消过一行
+            Goal::ClearAll => stats.lines > 0 && matrix.stack_height() == 0,
+            Goal::Lines(lines) => stats.lines >= lines,
+            Goal::Tetrises(count) => stats.tetrises >= count,
+            Goal::BackToBacks(count) => stats.back_to_backs >= count,
+            Goal::TSpins(count) => stats.t_spins >= count,
+            Goal::Combo(combo) => stats.combo >= combo,
+            Goal::PieceLines { piece, lines } => stats.piece_lines[piece as usize] >= lines,
+        }
+    }
+}
+
+// 一道谜题：初始棋盘、固定的方块顺序和目标，方块用完还没达成就算失败。
+// 文件格式：
+//
+//     name = "FIRST TETRIS"
+//     pieces = ["I"]
+//     goal = "clear_all"          # 或者 goal = { lines = 2 }
+//     board = [
+//         "XXXXXXXXX.",
+//         "XXXXXXXXX.",
+//     ]
+//
+// board 从上往下写，最后一行贴着底，`.` 是空格子，其它字符都是垃圾格
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub name: String,
+    pub pieces: Vec<PieceKind>,
+    pub goal: Goal,
+    board: Vec<String>,
+}
+
+impl Puzzle {
+    // 编辑器用：`matrix` 里有格子的地方都当垃圾格，只存到最高的那一行
+    pub fn new(
+        name: String,
+        matrix: &Matrix,
+        pieces: Vec<PieceKind>,
+        goal: Goal,
+    ) -> Result<Puzzle, String> {
+        let board = (0..matrix.stack_height())
+            .rev()
+            .map(|y| {
+                (0..Matrix::WIDTH)
+                    .map(|x| {
+                        if matrix[Coordinate::new(x, y)].is_some() {
+                            'X'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Puzzle {
+            name,
+            pieces,
+            goal,
+            board,
+        }
+        .validate()
+    }
+
+    pub fn parse(source: &str) -> Result<Puzzle, String> {
+        let puzzle: Puzzle = toml::from_str(source).map_err(|err| err.to_string())?;
+        puzzle.validate()
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|err| err.to_string())
+    }
+
+    fn validate(self) -> Result<Puzzle, String> {
+        let puzzle = self;
+        if puzzle.board.len() > Matrix::HEIGHT {
+            return Err(format!("board is taller than {} rows", Matrix::HEIGHT));
+        }
+        if let Some(row) = puzzle.board.iter().find(|row| row.len() != Matrix::WIDTH) {
+            return Err(format!("row {:?} is not {} cells wide", row, Matrix::WIDTH));
+        }
+        if puzzle.pieces.is_empty() {
+            return Err("no pieces".to_string());
+        }
+        Ok(puzzle)
+    }
+
+    pub fn matrix(&self) -> Matrix {
+        let mut matrix = Matrix::blank();
+        for (y, row) in self.board.iter().rev().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                if cell != b'.' {
+                    matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+                }
+            }
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::mode::Mode;
+    use crate::engine::{Engine, MoveKind};
+
+    const TETRIS: &str = r#"
+        name = "FIRST TETRIS"
+        pieces = ["I"]
+        goal = "clear_all"
+        board = [
+            "XXXXXXXXX.",
+            "XXXXXXXXX.",
+            "XXXXXXXXX.",
+            "XXXXXXXXX.",
+        ]
+    "#;
+
+    #[test]
+    fn puzzles_end_when_the_goal_is_met_or_pieces_run_out() {
+        let puzzle = Puzzle::parse(TETRIS).unwrap();
+        assert!(
+            Puzzle::parse("name = \"X\"\npieces = []\ngoal = { lines = 1 }\nboard = []").is_err()
+        );
+
+        let mut engine = Engine::with_puzzle(&puzzle);
+        assert_eq!(engine.mode(), Mode::Puzzle);
+        assert_eq!(engine.matrix().stack_height(), 4);
+        engine.tick();
+        for _ in 0..3 {
+            engine.soft_drop();
+        }
+        engine.rotate_clockwise();
+        while engine.move_cursor(MoveKind::Right).is_ok() {}
+        engine.hard_drop();
+        for _ in 0..Engine::LINE_CLEAR_DELAY {
+            engine.tick();
+        }
+        assert!(engine.is_finished());
+        assert!(!engine.is_game_over());
+
+        // 方块直接扔下去，没消行，也没有下一个了
+        let mut engine = Engine::with_puzzle(&puzzle);
+        engine.tick();
+        engine.hard_drop();
+        engine.tick();
+        assert!(engine.is_game_over());
+        assert!(!engine.is_finished());
+    }
+}
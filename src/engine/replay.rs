@@ -0,0 +1,148 @@
+use super::{Engine, Event, MoveKind};
+
+// 玩家对引擎的一次操作。引擎每做一个都发一个 `Event::Action`，
+// 同一个种子的引擎一帧一帧照着做，就能重放出一模一样的一局，联机的对手就是这样画出来的
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    // 按一下方向键；按住之后的自动移动另算，不记进 finesse
+    Move(MoveKind),
+    Shift(MoveKind),
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+impl Action {
+    pub fn apply(self, engine: &mut Engine) {
+        match self {
+            Action::Move(kind) => {
+                let _ = engine.move_cursor(kind);
+            }
+            Action::Shift(kind) => {
+                let _ = engine.auto_shift(kind);
+            }
+            Action::Rotate => engine.rotate_clockwise(),
+            Action::SoftDrop => engine.soft_drop(),
+            Action::HardDrop => engine.hard_drop(),
+            Action::Hold => engine.hold(),
+        }
+    }
+
+    // 存盘和联机用的一个字符
+    pub fn symbol(self) -> char {
+        match self {
+            Action::Move(MoveKind::Left) => 'L',
+            Action::Move(MoveKind::Right) => 'R',
+            Action::Shift(MoveKind::Left) => 'l',
+            Action::Shift(MoveKind::Right) => 'r',
+            Action::Rotate => 'U',
+            Action::SoftDrop => 'S',
+            Action::HardDrop => 'D',
+            Action::Hold => 'H',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Action> {
+        Some(match symbol {
+            'L' => Action::Move(MoveKind::Left),
+            'R' => Action::Move(MoveKind::Right),
+            'l' => Action::Shift(MoveKind::Left),
+            'r' => Action::Shift(MoveKind::Right),
+            'U' => Action::Rotate,
+            'S' => Action::SoftDrop,
+            'D' => Action::HardDrop,
+            'H' => Action::Hold,
+            _ => return None,
+        })
+    }
+}
+
+// 局面的指纹：棋盘、当前方块、hold、分数和排着的垃圾。重放出来的和原来的一比就知道对不对得上。
+// 自己算 FNV-1a，不用标准库的哈希，换了编译器版本也不会变
+pub fn state_hash(engine: &Engine) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |value: u64| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100_0000_01b3);
+        }
+    };
+    for (coord, cell) in engine.cells() {
+        if let Some(color) = cell {
+            feed(coord.x as u64);
+            feed(coord.y as u64);
+            feed(*color as u64);
+        }
+    }
+    if let Some((cells, _)) = engine.cursor_info() {
+        for coord in cells {
+            feed(coord.x as u64);
+            feed(coord.y as u64);
+        }
+    }
+    feed(engine.held().map_or(0, |kind| kind as u64 + 1));
+    let stats = engine.stats();
+    for value in [stats.score, stats.lines, stats.attack] {
+        feed(value as u64);
+    }
+    feed(stats.frames);
+    feed(engine.garbage().total() as u64);
+    hash
+}
+
+// 一帧的事件里玩家做了哪些操作，按顺序
+pub fn actions(events: &[Event]) -> Vec<Action> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Action(action) => Some(*action),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::mode::Mode;
+
+    #[test]
+    fn replaying_actions_reproduces_the_game() {
+        let mut engine = Engine::with_seed(Mode::Marathon, 11);
+        let mut replica = Engine::with_seed(Mode::Marathon, 11);
+        engine.spawn_first();
+        replica.spawn_first();
+        let inputs = [
+            Action::Move(MoveKind::Left),
+            Action::Shift(MoveKind::Left),
+            Action::Rotate,
+            Action::HardDrop,
+            Action::Hold,
+            Action::SoftDrop,
+            Action::Shift(MoveKind::Right),
+            Action::HardDrop,
+        ];
+        for frame in inputs.chunks(2) {
+            for &action in frame {
+                action.apply(&mut engine);
+            }
+            engine.tick();
+            let actions = actions(&engine.take_events());
+            assert_eq!(actions, frame);
+
+            let symbols: String = actions.iter().map(|action| action.symbol()).collect();
+            for action in symbols.chars().filter_map(Action::from_symbol) {
+                action.apply(&mut replica);
+            }
+            replica.tick();
+        }
+        assert_eq!(engine.stats().pieces, 2);
+        assert!(replica.cells().eq(engine.cells()));
+        assert_eq!(replica.held(), engine.held());
+        assert_eq!(replica.stats().score, engine.stats().score);
+        assert_eq!(state_hash(&replica), state_hash(&engine));
+        replica.hold();
+        assert_ne!(state_hash(&replica), state_hash(&engine));
+    }
+}
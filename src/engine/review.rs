@@ -0,0 +1,342 @@
+use std::io::{self, Write};
+
+use super::bot::{BotView, Hint, Stacker};
+use super::mode::Mode;
+use super::piece::{Kind as PieceKind, Piece};
+use super::planner;
+use super::replay::Action;
+use super::{Color, Coordinate, Engine, Event, Stats};
+
+// 复盘：记下一局里每块出来时的局面和最后放在哪，打完之后拿 `Stacker` 的打分比一比，
+// 比最好的放法掉分最多的几块就是失误。也能导出给训练 AI 的人用
+#[derive(Default)]
+pub struct Recorder {
+    // 正在下落的这块出来时的局面，hold 之后还是用换之前的
+    current: Option<BotView>,
+    moves: Vec<Move>,
+    // 已经导出了几块
+    exported: usize,
+    // 正在下落的这块按了哪些键
+    actions: Vec<Action>,
+    // 正在下落的这块是不是 hold 换出来的；hold 里换出一样的方块也算
+    held: bool,
+}
+
+struct Move {
+    before: BotView,
+    cells: [Coordinate; Piece::CELL_COUNT],
+    color: Color,
+    actions: Vec<Action>,
+    held: bool,
+    // 锁定在第几帧
+    frame: u64,
+    // 这一块带来的分数、消行和攻击，等消行停顿完、下一块出来（或者结束）才知道
+    reward: Option<Stats>,
+    game_over: bool,
+}
+
+impl Move {
+    // 锁定的颜色就是方块的种类
+    fn kind(&self) -> PieceKind {
+        PieceKind::ALL
+            .into_iter()
+            .find(|kind| kind.color() == self.color)
+            .unwrap_or(self.before.cursor.kind)
+    }
+}
+
+// 账本里的一块：第几块（从 1 数）、什么方块、是不是 hold 换出来的、放在哪、按了哪些键，
+// 锁定在第几帧，带来的收益（一局最后一块还没算出来的话是空的）
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub piece: usize,
+    pub kind: PieceKind,
+    pub held: bool,
+    pub cells: [Coordinate; Piece::CELL_COUNT],
+    pub actions: Vec<Action>,
+    pub frame: u64,
+    pub reward: Stats,
+}
+
+// 一块的复盘：第几块（从 1 数），放之前的局面，实际放的和更好的放法，掉了多少分。
+// 掉得多的就是失误
+#[derive(Clone)]
+pub struct Misdrop {
+    pub piece: usize,
+    pub position: BotView,
+    pub actual: Hint,
+    pub best: Hint,
+    pub loss: f32,
+}
+
+impl Misdrop {
+    pub fn is_misdrop(&self) -> bool {
+        self.loss >= Recorder::MIN_LOSS
+    }
+
+    // 从这块放之前接着玩的沙盒：同样的棋盘、hold 和预览，不会输
+    pub fn sandbox(&self) -> Engine {
+        let position = &self.position;
+        Engine::with_position(
+            Mode::Training,
+            position.matrix.clone(),
+            &position.queue(),
+            position.hold,
+        )
+    }
+}
+
+impl Recorder {
+    // 掉分不到这么多的不算失误，差不多的放法不用挑
+    const MIN_LOSS: f32 = 0.5;
+
+    // 每帧引擎走完之后调一次，`events` 是这一帧的
+    pub fn record(&mut self, engine: &Engine, events: &[Event]) {
+        for event in events {
+            let (cells, color) = match event {
+                Event::Action(action) => {
+                    self.actions.push(*action);
+                    continue;
+                }
+                Event::Hold => {
+                    self.held = true;
+                    continue;
+                }
+                Event::Lock { cells, color } => (cells, color),
+                _ => continue,
+            };
+            let actions = std::mem::take(&mut self.actions);
+            let held = std::mem::take(&mut self.held);
+            let Some(before) = self.current.take() else {
+                continue;
+            };
+            // 下落的时候底下顶上来了垃圾，局面对不上了，这块不算
+            if cells.iter().all(|&cell| before.matrix[cell].is_none()) {
+                self.moves.push(Move {
+                    before,
+                    cells: *cells,
+                    color: *color,
+                    actions,
+                    held,
+                    frame: engine.stats.frames,
+                    reward: None,
+                    game_over: false,
+                });
+            }
+        }
+        if self.current.is_none() {
+            self.current = BotView::of(engine);
+            if self.current.is_some() || engine.is_over() {
+                if let Some(last) = self.moves.last_mut().filter(|last| last.reward.is_none()) {
+                    last.reward = Some(engine.stats.since(&last.before.stats));
+                    last.game_over = engine.is_game_over();
+                }
+            }
+        }
+    }
+
+    // 把收益已经知道了、还没导出过的几块写出去，一块一行 JSON：
+    //
+    //     {"width":10,"matrix":[1023,511,...],"piece":"T","hold":null,"next":"IOS",
+    //      "placed":"T","held":false,"cells":[[4,0],...],
+    //      "reward":{"score":100,"lines":1,"attack":0},"game_over":false}
+    //
+    // `matrix` 从下往上每行一个数，第 x 列有格子就是第 x 位是 1
+    pub fn export(&mut self, out: &mut impl Write) -> io::Result<()> {
+        for placed in &self.moves[self.exported..] {
+            let Some(reward) = placed.reward else {
+                break;
+            };
+            writeln!(out, "{}", sample(placed, &reward))?;
+            self.exported += 1;
+        }
+        Ok(())
+    }
+
+    // 每一块放了什么、怎么放的，按放的顺序
+    pub fn ledger(&self) -> Vec<Placement> {
+        self.moves
+            .iter()
+            .enumerate()
+            .map(|(index, placed)| Placement {
+                piece: index + 1,
+                kind: placed.kind(),
+                held: placed.held,
+                cells: placed.cells,
+                actions: placed.actions.clone(),
+                frame: placed.frame,
+                reward: placed.reward.unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    // 每一块都拿来和 `Stacker` 比，按放的顺序
+    pub fn analysis(&self) -> Vec<Misdrop> {
+        let stacker = Stacker::default();
+        self.moves
+            .iter()
+            .enumerate()
+            .filter_map(|(index, placed)| {
+                let matrix = &placed.before.matrix;
+                let (placement, landed) = stacker.best(&placed.before)?;
+                let best = landed.cells()?;
+                let loss = planner::rate(matrix, &best) - planner::rate(matrix, &placed.cells);
+                Some(Misdrop {
+                    piece: index + 1,
+                    position: placed.before.clone(),
+                    actual: Hint {
+                        hold: placed.held,
+                        cells: placed.cells,
+                        color: placed.color,
+                    },
+                    best: Hint {
+                        hold: placement.hold,
+                        cells: best,
+                        color: landed.kind.color(),
+                    },
+                    loss,
+                })
+            })
+            .collect()
+    }
+
+    // 掉分最多的 `count` 块，掉得多的在前
+    pub fn misdrops(&self, count: usize) -> Vec<Misdrop> {
+        let mut misdrops: Vec<Misdrop> = self
+            .analysis()
+            .into_iter()
+            .filter(Misdrop::is_misdrop)
+            .collect();
+        misdrops.sort_by(|a, b| b.loss.total_cmp(&a.loss));
+        misdrops.truncate(count);
+        misdrops
+    }
+}
+
+fn sample(placed: &Move, reward: &Stats) -> String {
+    let view = &placed.before;
+    let matrix = &view.matrix;
+    let rows: Vec<String> = (0..matrix.height())
+        .map(|y| {
+            let row = (0..matrix.width())
+                .filter(|&x| matrix.get(x as isize, y as isize).is_some())
+                .fold(0u32, |row, x| row | 1 << x);
+            row.to_string()
+        })
+        .collect();
+    let name = |kind: PieceKind| format!("{:?}", kind);
+    let hold = view
+        .hold
+        .map_or("null".to_string(), |kind| format!("\"{}\"", name(kind)));
+    let next: String = view.next.iter().map(|&kind| name(kind)).collect();
+    let cells: Vec<String> = placed
+        .cells
+        .iter()
+        .map(|cell| format!("[{},{}]", cell.x, cell.y))
+        .collect();
+    format!(
+        "{{\"width\":{},\"matrix\":[{}],\"piece\":\"{}\",\"hold\":{},\"next\":\"{}\",\
+         \"placed\":\"{}\",\"held\":{},\"cells\":[{}],\
+         \"reward\":{{\"score\":{},\"lines\":{},\"attack\":{}}},\"game_over\":{}}}",
+        matrix.width(),
+        rows.join(","),
+        name(view.cursor.kind),
+        hold,
+        next,
+        name(placed.kind()),
+        placed.held,
+        cells.join(","),
+        reward.score,
+        reward.lines,
+        reward.attack,
+        placed.game_over,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Matrix;
+
+    #[test]
+    fn misdrops_are_placements_far_below_the_best() {
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        for y in 0..4 {
+            for x in 0..Matrix::WIDTH - 1 {
+                engine.matrix[Coordinate::new(x, y)] = Some(Color::Gray);
+            }
+        }
+        engine.hold_used = true;
+        let spawn = engine.spawn_position();
+        engine.debug_test_cursor(PieceKind::I, spawn);
+        let mut recorder = Recorder::default();
+        recorder.record(&engine, &[]);
+
+        // 把 I 横着放在上面，不竖着插进井里
+        engine.hard_drop();
+        let events = engine.take_events();
+        recorder.record(&engine, &events);
+        let misdrops = recorder.misdrops(3);
+        assert_eq!(misdrops.len(), 1);
+        let misdrop = &misdrops[0];
+        assert_eq!(misdrop.piece, 1);
+        assert!(misdrop.loss > Recorder::MIN_LOSS);
+        assert!(!misdrop.actual.hold);
+        assert!(misdrop
+            .best
+            .cells
+            .iter()
+            .all(|cell| cell.x == Matrix::WIDTH - 1));
+
+        // 回到放之前接着玩：还是那块 I，井还在
+        let mut sandbox = misdrop.sandbox();
+        sandbox.spawn_first();
+        assert_eq!(sandbox.mode(), Mode::Training);
+        assert_eq!(sandbox.cursor.map(|cursor| cursor.kind), Some(PieceKind::I));
+        assert_eq!(sandbox.matrix().stack_height(), 4);
+        assert_eq!(recorder.analysis().len(), 1);
+    }
+
+    #[test]
+    fn exports_one_json_line_per_lock_once_the_reward_is_known() {
+        let mut engine = Engine::with_mode(Mode::Marathon);
+        let spawn = engine.spawn_position();
+        engine.debug_test_cursor(PieceKind::T, spawn);
+        let mut recorder = Recorder::default();
+        recorder.record(&engine, &[]);
+        engine.hard_drop();
+        let events = engine.take_events();
+        recorder.record(&engine, &events);
+
+        let mut out = Vec::new();
+        // 下一块还没出来就不知道收益，先不导出
+        while engine.cursor.is_none() {
+            recorder.export(&mut out).unwrap();
+            assert!(out.is_empty());
+            engine.tick();
+            let events = engine.take_events();
+            recorder.record(&engine, &events);
+        }
+        recorder.export(&mut out).unwrap();
+        recorder.export(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let line = lines[0];
+        assert!(line.starts_with("{\"width\":10,\"matrix\":[0,0,"));
+        assert!(line.contains("\"piece\":\"T\",\"hold\":null,"));
+        assert!(line.contains("\"placed\":\"T\",\"held\":false,"));
+        assert!(line.ends_with("\"lines\":0,\"attack\":0},\"game_over\":false}"));
+
+        // hold 里换出一样的方块也记成 hold 过
+        let kind = engine.cursor.unwrap().kind;
+        engine.hold = Some(kind);
+        engine.hold();
+        engine.hard_drop();
+        let events = engine.take_events();
+        recorder.record(&engine, &events);
+        let ledger = recorder.ledger();
+        assert!(!ledger[0].held);
+        assert!(ledger[1].held && ledger[1].kind == kind);
+    }
+}
@@ -0,0 +1,147 @@
+use serde::Deserialize;
+
+use super::Stats;
+
+// 一套玩法规则：能不能 hold、能不能硬降、重力怎么算、消行怎么计分。
+// 每个模式选一套，引擎只看这个，不去判断具体是哪个模式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules {
+    pub hold: bool,
+    pub hard_drop: bool,
+    pub gravity: Gravity,
+    pub scoring: Scoring,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gravity {
+    // guideline 的公式
+    Guideline,
+    // 红白机的查表，按帧数走
+    Nes,
+    // 20G：方块一出来就直接落到底
+    Instant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    // 100/300/500/800 乘等级，再加连击奖励
+    Guideline,
+    // 红白机的 40/100/300/1200 乘等级，没有连击
+    Nes,
+    // 只看连击：每次消行得这是连续第几次消行那么多分，消几行都一样
+    Combo,
+    // 消行和下落都不给分，分数是模式自己加的（任务模式一个任务一分）
+    Missions,
+}
+
+// 红白机每一级下落一格要的帧数，从 0 级开始，29 级以后都是 1 帧
+const NES_GRAVITY: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    1,
+];
+
+impl Rules {
+    pub const GUIDELINE: Rules = Rules {
+        hold: true,
+        hard_drop: true,
+        gravity: Gravity::Guideline,
+        scoring: Scoring::Guideline,
+    };
+    pub const NES: Rules = Rules {
+        hold: false,
+        hard_drop: false,
+        gravity: Gravity::Nes,
+        scoring: Scoring::Nes,
+    };
+    pub const MASTER: Rules = Rules {
+        gravity: Gravity::Instant,
+        ..Rules::GUIDELINE
+    };
+    pub const COMBO: Rules = Rules {
+        scoring: Scoring::Combo,
+        ..Rules::GUIDELINE
+    };
+    pub const MISSIONS: Rules = Rules {
+        scoring: Scoring::Missions,
+        ..Rules::GUIDELINE
+    };
+}
+
+impl Gravity {
+    // 下落一格需要的帧数；20G 不靠这个，按 1 帧算
+    pub fn frames_per_row(self, level: u32) -> u32 {
+        match self {
+            // (0.8 - (level - 1) * 0.007) ^ (level - 1) 秒
+            Gravity::Guideline => {
+                let level = level.min(20) as f64 - 1.0;
+                let seconds = (0.8 - level * 0.007).powf(level);
+                ((seconds * Stats::FRAMES_PER_SECOND as f64) as u32).max(1)
+            }
+            // 引擎的等级从 1 开始，红白机从 0 开始
+            Gravity::Nes => {
+                let level = level.saturating_sub(1) as usize;
+                NES_GRAVITY[level.min(NES_GRAVITY.len() - 1)]
+            }
+            Gravity::Instant => 1,
+        }
+    }
+}
+
+impl Scoring {
+    // 软降、硬降每落一格的分
+    pub fn drop_points(self, hard_drop: bool) -> u32 {
+        match self {
+            Scoring::Combo | Scoring::Missions => 0,
+            _ if hard_drop => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn line_clear(self, cleared: usize, level: u32, combo: u32) -> u32 {
+        match self {
+            Scoring::Guideline => {
+                let base = match cleared {
+                    0 => return 0,
+                    1 => 100,
+                    2 => 300,
+                    3 => 500,
+                    _ => 800,
+                };
+                // 连击奖励 50 * combo * level
+                (base + 50 * combo) * level
+            }
+            Scoring::Nes => {
+                let base = match cleared {
+                    0 => return 0,
+                    1 => 40,
+                    2 => 100,
+                    3 => 300,
+                    _ => 1200,
+                };
+                base * level
+            }
+            Scoring::Combo if cleared == 0 => 0,
+            Scoring::Combo => combo + 1,
+            Scoring::Missions => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nes_rules_use_the_classic_tables() {
+        assert_eq!(Gravity::Nes.frames_per_row(1), 48);
+        assert_eq!(Gravity::Nes.frames_per_row(20), 2);
+        assert_eq!(Gravity::Nes.frames_per_row(99), 1);
+        assert_eq!(Gravity::Guideline.frames_per_row(1), 60);
+        assert_eq!(Scoring::Nes.line_clear(4, 2, 3), 2400);
+        assert_eq!(Scoring::Guideline.line_clear(4, 2, 3), 1900);
+        assert_eq!(Scoring::Nes.line_clear(0, 5, 0), 0);
+        assert_eq!(Scoring::Combo.line_clear(4, 2, 3), 4);
+    }
+}
@@ -0,0 +1,248 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+// 三个人以上对战的时候，打出去的攻击送给谁。和大逃杀里一样每个人自己选一种打法
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Targeting {
+    // 随便挑一个活着的对手
+    #[default]
+    Random,
+    // 打正在打自己的人，几个人一起打就平分；没人打自己的时候随便挑
+    Attackers,
+    // 打徽章最多的，KO 了能把他的徽章抢过来
+    Badges,
+    // 打最快要顶满的，抢 KO
+    KnockOuts,
+    // 平分给所有活着的对手
+    Even,
+}
+
+impl Targeting {
+    pub const ALL: [Targeting; 5] = [
+        Targeting::Random,
+        Targeting::Attackers,
+        Targeting::Badges,
+        Targeting::KnockOuts,
+        Targeting::Even,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Targeting::Random => "RANDOM",
+            Targeting::Attackers => "ATTACKERS",
+            Targeting::Badges => "BADGES",
+            Targeting::KnockOuts => "KOS",
+            Targeting::Even => "EVEN",
+        }
+    }
+}
+
+// 记着每个人活没活着、有几个徽章、在打谁、最后被谁打，按各自的打法分攻击。
+// 组队的话只打别的队的人
+pub struct Router {
+    targeting: Vec<Targeting>,
+    // 每个人在第几队，大乱斗里一人一队
+    teams: Vec<usize>,
+    alive: Vec<bool>,
+    badges: Vec<u32>,
+    // 堆得多高加上排着多少垃圾，越大越快顶满
+    danger: Vec<u32>,
+    // 每个人上一次主要打的是谁，用来算谁在打谁
+    targets: Vec<Option<usize>>,
+    // 每个人最后一次被谁打，顶满的时候 KO 算他的
+    last_hit: Vec<Option<usize>>,
+    rng: StdRng,
+}
+
+impl Router {
+    pub fn new(targeting: Vec<Targeting>, seed: u64) -> Self {
+        let teams = (0..targeting.len()).collect();
+        Self::with_teams(targeting, teams, seed)
+    }
+
+    pub fn with_teams(targeting: Vec<Targeting>, teams: Vec<usize>, seed: u64) -> Self {
+        let players = targeting.len();
+        Self {
+            targeting,
+            teams,
+            alive: vec![true; players],
+            badges: vec![0; players],
+            danger: vec![0; players],
+            targets: vec![None; players],
+            last_hit: vec![None; players],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn is_alive(&self, player: usize) -> bool {
+        self.alive[player]
+    }
+
+    pub fn survivors(&self) -> usize {
+        self.alive.iter().filter(|&&alive| alive).count()
+    }
+
+    pub fn team(&self, player: usize) -> usize {
+        self.teams[player]
+    }
+
+    // 还有人活着的队，从小到大
+    pub fn teams_alive(&self) -> Vec<usize> {
+        let mut teams: Vec<usize> = (0..self.alive.len())
+            .filter(|&player| self.alive[player])
+            .map(|player| self.teams[player])
+            .collect();
+        teams.sort_unstable();
+        teams.dedup();
+        teams
+    }
+
+    pub fn badges(&self) -> &[u32] {
+        &self.badges
+    }
+
+    pub fn set_targeting(&mut self, player: usize, targeting: Targeting) {
+        self.targeting[player] = targeting;
+    }
+
+    pub fn set_danger(&mut self, player: usize, danger: u32) {
+        self.danger[player] = danger;
+    }
+
+    // `from` 抵消完自己的垃圾还剩 `lines` 行，按他的打法分给哪几个人各几行
+    pub fn route(&mut self, from: usize, lines: u32) -> Vec<(usize, u32)> {
+        let opponents: Vec<usize> = (0..self.alive.len())
+            .filter(|&player| self.teams[player] != self.teams[from] && self.alive[player])
+            .collect();
+        if lines == 0 || opponents.is_empty() {
+            return Vec::new();
+        }
+        let mut targets = match self.targeting[from] {
+            Targeting::Random => Vec::new(),
+            Targeting::Attackers => opponents
+                .iter()
+                .copied()
+                .filter(|&player| self.targets[player] == Some(from))
+                .collect(),
+            Targeting::Badges => self.most(&opponents, |router, player| router.badges[player]),
+            Targeting::KnockOuts => self.most(&opponents, |router, player| router.danger[player]),
+            Targeting::Even => opponents.clone(),
+        };
+        // 没人在打自己的话随便挑一个；不平分的打法一样多的也随便挑一个。
+        // 平分的打乱顺序，除不尽的几行不要总落在同一个人头上
+        let fallback = targets.is_empty();
+        if fallback {
+            targets = opponents;
+        }
+        let splits =
+            !fallback && matches!(self.targeting[from], Targeting::Attackers | Targeting::Even);
+        if !splits {
+            targets = vec![*targets.choose(&mut self.rng).expect("not empty")];
+        }
+        targets.shuffle(&mut self.rng);
+        let share = lines / targets.len() as u32;
+        let extra = lines as usize % targets.len();
+        let routed: Vec<(usize, u32)> = targets
+            .iter()
+            .enumerate()
+            .map(|(i, &player)| (player, share + (i < extra) as u32))
+            .filter(|&(_, lines)| lines > 0)
+            .collect();
+        self.targets[from] = routed.first().map(|&(player, _)| player);
+        for &(player, _) in &routed {
+            self.last_hit[player] = Some(from);
+        }
+        routed
+    }
+
+    // 顶满了：最后打他的人拿到 KO，他的徽章也归那个人。返回是谁 KO 的
+    pub fn knock_out(&mut self, player: usize) -> Option<usize> {
+        self.alive[player] = false;
+        for target in &mut self.targets {
+            if *target == Some(player) {
+                *target = None;
+            }
+        }
+        let killer = self.last_hit[player].filter(|&killer| self.alive[killer])?;
+        self.badges[killer] += 1 + std::mem::take(&mut self.badges[player]);
+        Some(killer)
+    }
+
+    // 某一项最大的那几个对手
+    fn most(&self, opponents: &[usize], key: fn(&Router, usize) -> u32) -> Vec<usize> {
+        let best = opponents.iter().map(|&player| key(self, player)).max();
+        opponents
+            .iter()
+            .copied()
+            .filter(|&player| Some(key(self, player)) == best)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn attacks_follow_each_players_targeting() {
+        let mut router = Router::new(vec![Targeting::Even; 4], 1);
+
+        // 平分给三个对手，除不尽的一行给其中一个
+        let mut routed = router.route(0, 7);
+        routed.sort();
+        let lines: Vec<u32> = routed.iter().map(|&(_, lines)| lines).collect();
+        assert_eq!(
+            routed.iter().map(|&(player, _)| player).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(lines.iter().sum::<u32>(), 7);
+        assert!(lines.iter().all(|&lines| lines == 2 || lines == 3));
+        assert_eq!(router.route(0, 0), []);
+
+        // 1 和 2 都在打 3，3 反击的时候平分给这两个人
+        router.set_targeting(1, Targeting::Random);
+        router.targets[1] = Some(3);
+        router.targets[2] = Some(3);
+        router.set_targeting(3, Targeting::Attackers);
+        let mut routed = router.route(3, 4);
+        routed.sort();
+        assert_eq!(routed, [(1, 2), (2, 2)]);
+
+        // KO 的人拿到徽章，下一个人 KO 他就连他的徽章一起拿走
+        assert_eq!(router.knock_out(2), Some(3));
+        assert_eq!(router.badges(), [0, 0, 0, 1]);
+        router.set_targeting(0, Targeting::Badges);
+        assert_eq!(router.route(0, 2), [(3, 2)]);
+        assert_eq!(router.knock_out(3), Some(0));
+        assert_eq!(router.badges(), [2, 0, 0, 0]);
+        assert_eq!(router.survivors(), 2);
+
+        // 打最快顶满的
+        router.set_targeting(0, Targeting::KnockOuts);
+        router.set_danger(1, 15);
+        assert_eq!(router.route(0, 1), [(1, 1)]);
+        router.set_targeting(1, Targeting::Random);
+        assert_eq!(router.route(1, 5), [(0, 5)]);
+        assert_eq!(router.teams_alive(), [0, 1]);
+    }
+
+    #[test]
+    fn teammates_are_never_targeted() {
+        let mut router = Router::with_teams(vec![Targeting::Even; 4], vec![0, 0, 1, 1], 1);
+        let mut routed = router.route(0, 4);
+        routed.sort();
+        assert_eq!(routed, [(2, 2), (3, 2)]);
+        router.set_targeting(3, Targeting::Random);
+        assert!(router
+            .route(3, 6)
+            .iter()
+            .all(|&(player, _)| router.team(player) == 0));
+
+        // 一队的人都顶满了才算这一队输
+        router.knock_out(2);
+        assert_eq!(router.teams_alive(), [0, 1]);
+        router.knock_out(3);
+        assert_eq!(router.teams_alive(), [0]);
+    }
+}
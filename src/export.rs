@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::engine::export::GameData;
+use crate::engine::mode::Mode;
+use crate::engine::review::Recorder;
+use crate::replays::{self, Playback};
+
+// 结算界面上导出的和 `--export` 导出的都放这里
+pub const EXPORT_DIR: &str = "exports";
+
+// 一局写三个文件：`<stem>-game.csv`、`<stem>-pieces.csv` 和 `<stem>.json`，返回 JSON 的路径
+pub fn write(dir: &Path, stem: &str, data: &GameData) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let write = |name: String, contents: String| {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|err| err.to_string())?;
+        Ok::<_, String>(path)
+    };
+    write(format!("{}-game.csv", stem), data.stats_csv())?;
+    write(format!("{}-pieces.csv", stem), data.ledger_csv())?;
+    write(format!("{}.json", stem), data.to_json())
+}
+
+// 刚打完的一局按时间和模式起名，和录像一样（`replays::file_stem`）
+pub fn stem(mode: Mode) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    replays::file_stem(now, mode.game_mode().name())
+}
+
+// `--export REPLAY` 不开窗口，把录像文件重放一遍，导出它的数据；没有这个参数是 None
+pub fn parse(args: &[String]) -> Option<&str> {
+    args.iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.strip_prefix("--export") {
+            Some("") => Some(args.get(i + 1).map_or("", String::as_str)),
+            Some(rest) => rest.strip_prefix('='),
+            None => None,
+        })
+}
+
+// 和 `Interface::update_watch` 一样一帧一帧放，顺便记下每块放在哪
+pub fn run(path: &Path) -> Result<PathBuf, String> {
+    let replay = replays::load(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut engine = replay
+        .engine()
+        .ok_or_else(|| format!("unknown mode {:?}", replay.config.mode))?;
+    let mode = engine.mode();
+    let seed = replay.config.seed;
+    let mut recorder = Recorder::default();
+    let mut playback = Playback::new(replay);
+    while let Some((actions, beat)) = playback.next() {
+        for action in actions {
+            action.apply(&mut engine);
+        }
+        engine.tick();
+        let events = engine.take_events();
+        recorder.record(&engine, &events);
+        if beat {
+            engine.award_beat_bonus();
+        }
+    }
+    let stats = engine.stats();
+    let ledger = recorder.ledger();
+    let data = GameData {
+        mode,
+        seed,
+        stats: &stats,
+        ledger: &ledger,
+    };
+    let stem = path
+        .file_stem()
+        .map_or_else(|| stem(mode), |stem| stem.to_string_lossy().into_owned());
+    write(Path::new(EXPORT_DIR), &stem, &data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_flag_takes_a_replay_path() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            parse(&args(&["tetris", "--export", "a.toml"])),
+            Some("a.toml")
+        );
+        assert_eq!(parse(&args(&["tetris", "--export=b.toml"])), Some("b.toml"));
+        assert_eq!(parse(&args(&["tetris", "--simulate", "3"])), None);
+        assert!(stem(Mode::Marathon).ends_with("-marathon"));
+        assert!(stem(Mode::AttackRace).ends_with("-attack-race"));
+    }
+}
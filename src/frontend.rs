@@ -0,0 +1,108 @@
+// 前端（窗口、输入、绘制）和引擎之间的边界。每个后端都在自己的 feature 后面，
+// 新加一个后端（macroquad、wgpu……）只要实现 Frontend，再在 `available` 里登记
+pub trait Frontend {
+    // 跑到玩家退出为止
+    fn run(&mut self) -> Result<(), String>;
+}
+
+#[cfg(feature = "sdl")]
+struct Sdl;
+
+#[cfg(feature = "sdl")]
+impl Frontend for Sdl {
+    fn run(&mut self) -> Result<(), String> {
+        crate::interface::Interface::run();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "terminal")]
+struct Terminal;
+
+#[cfg(feature = "terminal")]
+impl Frontend for Terminal {
+    fn run(&mut self) -> Result<(), String> {
+        crate::terminal::run().map_err(|err| err.to_string())
+    }
+}
+
+type MakeFrontend = fn() -> Box<dyn Frontend>;
+
+// 编进来的后端，第一个是默认的
+fn available() -> Vec<(&'static str, MakeFrontend)> {
+    vec![
+        #[cfg(feature = "sdl")]
+        ("sdl", || Box::new(Sdl)),
+        #[cfg(feature = "terminal")]
+        ("terminal", || Box::new(Terminal)),
+    ]
+}
+
+// `--frontend NAME` 选后端，`--terminal` 是 `--frontend terminal` 的简写
+fn requested(args: &[String]) -> Option<&str> {
+    args.iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.as_str() {
+            "--terminal" => Some("terminal"),
+            "--frontend" => args.get(i + 1).map(String::as_str),
+            _ => arg.strip_prefix("--frontend="),
+        })
+}
+
+fn choose<'a>(requested: Option<&str>, available: &[&'a str]) -> Result<&'a str, String> {
+    let missing = || {
+        format!(
+            "frontend {:?} is not available, built with: {}",
+            requested.unwrap_or_default(),
+            available.join(", ")
+        )
+    };
+    match requested {
+        Some(name) => available
+            .iter()
+            .find(|&&frontend| frontend == name)
+            .copied()
+            .ok_or_else(missing),
+        None => available.first().copied().ok_or_else(missing),
+    }
+}
+
+pub fn from_args(args: &[String]) -> Result<Box<dyn Frontend>, String> {
+    let frontends = available();
+    let names: Vec<&str> = frontends.iter().map(|(name, _)| *name).collect();
+    let name = choose(requested(args), &names)?;
+    let (_, make) = frontends
+        .into_iter()
+        .find(|(frontend, _)| *frontend == name)
+        .unwrap();
+    Ok(make())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_requested_or_first_frontend() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            requested(&args(&["tetris", "--terminal"])),
+            Some("terminal")
+        );
+        assert_eq!(
+            requested(&args(&["tetris", "--frontend", "sdl"])),
+            Some("sdl")
+        );
+        assert_eq!(
+            requested(&args(&["tetris", "--frontend=wgpu"])),
+            Some("wgpu")
+        );
+        assert_eq!(requested(&args(&["tetris"])), None);
+
+        let built = ["sdl", "terminal"];
+        assert_eq!(choose(None, &built), Ok("sdl"));
+        assert_eq!(choose(Some("terminal"), &built), Ok("terminal"));
+        assert!(choose(Some("wgpu"), &built).is_err());
+        assert!(choose(None, &[]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::path::Path;
+
+use cgmath::Point2;
+use sdl2::mixer::{self, Channel, Chunk, AUDIO_S16SYS, MAX_VOLUME};
+
+use serde::Deserialize;
+
+use super::music::{BeatClock, MusicPlayer, MusicState, MUSIC_CHANNELS};
+use super::settings::Settings;
+use super::sound_pack::SoundPack;
+use crate::engine::{Event, Matrix};
+
+const FREQUENCY: i32 = 44_100;
+const CHANNELS: i32 = 16;
+// 声道偏移最多这么多，只是稍微偏一点
+const PAN_STRENGTH: f32 = 0.4;
+
+// 音效包 [sounds] 里的键就是这些名字的小写，比如 level_up
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sound {
+    Move,
+    Rotate,
+    Lock,
+    Hold,
+    Single,
+    Double,
+    Triple,
+    Tetris,
+    LevelUp,
+    GameOver,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 10] = [
+        Sound::Move,
+        Sound::Rotate,
+        Sound::Lock,
+        Sound::Hold,
+        Sound::Single,
+        Sound::Double,
+        Sound::Triple,
+        Sound::Tetris,
+        Sound::LevelUp,
+        Sound::GameOver,
+    ];
+
+    // 音效包里没写的话默认找这个文件
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Sound::Move => "move.wav",
+            Sound::Rotate => "rotate.wav",
+            Sound::Lock => "lock.wav",
+            Sound::Hold => "hold.wav",
+            Sound::Single => "single.wav",
+            Sound::Double => "double.wav",
+            Sound::Triple => "triple.wav",
+            Sound::Tetris => "tetris.wav",
+            Sound::LevelUp => "level_up.wav",
+            Sound::GameOver => "game_over.wav",
+        }
+    }
+
+    // 合成的提示音：几个音符（频率 Hz，时长毫秒）连着放
+    fn notes(self) -> &'static [(f32, u32)] {
+        match self {
+            Sound::Move => &[(880.0, 15)],
+            Sound::Rotate => &[(660.0, 20), (990.0, 20)],
+            Sound::Lock => &[(220.0, 40)],
+            Sound::Hold => &[(523.0, 30), (392.0, 30)],
+            Sound::Single => &[(523.0, 80)],
+            Sound::Double => &[(523.0, 60), (659.0, 80)],
+            Sound::Triple => &[(523.0, 50), (659.0, 50), (784.0, 80)],
+            Sound::Tetris => &[(523.0, 50), (659.0, 50), (784.0, 50), (1047.0, 150)],
+            Sound::LevelUp => &[(784.0, 60), (988.0, 60), (1175.0, 60), (1568.0, 120)],
+            Sound::GameOver => &[(392.0, 150), (330.0, 150), (262.0, 150), (196.0, 300)],
+        }
+    }
+
+    // 消行按消了几行放不同的声音，lock 由 `play_events` 决定要不要盖掉
+    pub fn for_event(event: &Event) -> Option<Sound> {
+        match event {
+            Event::Move { .. } => Some(Sound::Move),
+            Event::Rotate => Some(Sound::Rotate),
+            Event::Lock { .. } => Some(Sound::Lock),
+            Event::Hold => Some(Sound::Hold),
+            Event::LineClear { rows, .. } => Some(match rows.len() {
+                1 => Sound::Single,
+                2 => Sound::Double,
+                3 => Sound::Triple,
+                _ => Sound::Tetris,
+            }),
+            Event::LevelUp { .. } => Some(Sound::LevelUp),
+            Event::GameOver => Some(Sound::GameOver),
+            _ => None,
+        }
+    }
+}
+
+// 移动和锁定的声音按方块在棋盘上的位置偏左或偏右，返回 0（最左）到 1（最右）
+fn position_for_event(event: &Event) -> Option<f32> {
+    let cells: &[Point2<usize>] = match event {
+        Event::Move { cells } | Event::Lock { cells, .. } => cells,
+        _ => return None,
+    };
+    let mean = cells.iter().map(|cell| cell.x as f32).sum::<f32>() / cells.len() as f32;
+    // 合作模式的 matrix 比平常宽
+    Some((mean / (Matrix::WIDTH - 1) as f32).min(1.0))
+}
+
+// 左右声道的音量，中间是 (255, 255)，偏哪边另一边就小一点
+fn panning(position: f32) -> (u8, u8) {
+    let pan = (position.clamp(0.0, 1.0) * 2.0 - 1.0) * PAN_STRENGTH;
+    let left = 255.0 * (1.0 - pan.max(0.0));
+    let right = 255.0 * (1.0 + pan.min(0.0));
+    (left as u8, right as u8)
+}
+
+// 设置里的百分比换算成 (音乐, 音效) 两组通道的音量，0 到 MAX_VOLUME
+fn channel_volumes(settings: &Settings) -> (i32, i32) {
+    if settings.muted {
+        return (0, 0);
+    }
+    let scale =
+        |volume: u8| settings.master_volume as i32 * volume as i32 * MAX_VOLUME / (100 * 100);
+    (scale(settings.music_volume), scale(settings.sfx_volume))
+}
+
+// 方波加上每个音符内的线性衰减，听起来像老游戏机；音高是 0 的是休止
+fn synthesize(notes: &[(f32, u32)], frequency: i32, channels: i32) -> Vec<i16> {
+    const AMPLITUDE: f32 = 6000.0;
+    let mut samples = Vec::new();
+    for &(pitch, millis) in notes {
+        let length = (frequency as u32 * millis / 1000) as usize;
+        for i in 0..length {
+            let t = i as f32 / frequency as f32;
+            let square = if pitch == 0.0 {
+                0.0
+            } else if (t * pitch * TAU).sin() >= 0.0 {
+                1.0
+            } else {
+                -1.0
+            };
+            let envelope = 1.0 - i as f32 / length as f32;
+            let sample = (square * envelope * AMPLITUDE) as i16;
+            samples.extend(std::iter::repeat_n(sample, channels as usize));
+        }
+    }
+    samples
+}
+
+// 音效和背景音乐。打不开音频设备的话界面就不创建它，游戏照样能玩
+pub struct Audio {
+    chunks: HashMap<Sound, Chunk>,
+    music: MusicPlayer,
+    // 设备实际的 (采样率, 声道数)，合成声音用
+    spec: (i32, i32),
+}
+
+// `spec` 是设备实际的 (采样率, 声道数)
+pub fn synthesized_chunk(notes: &[(f32, u32)], spec: (i32, i32)) -> Result<Chunk, String> {
+    let (frequency, channels) = spec;
+    Chunk::from_raw_buffer(synthesize(notes, frequency, channels).into_boxed_slice())
+}
+
+// 音效包里的文件优先，没有或者读不了就用合成的
+fn load_chunk(
+    path: Option<&Path>,
+    notes: &[(f32, u32)],
+    spec: (i32, i32),
+) -> Result<Chunk, String> {
+    if let Some(path) = path {
+        match Chunk::from_file(path) {
+            Ok(chunk) => return Ok(chunk),
+            Err(err) => eprintln!("Failed to load {}: {}", path.display(), err),
+        }
+    }
+    synthesized_chunk(notes, spec)
+}
+
+fn load_sounds(pack: &SoundPack, spec: (i32, i32)) -> Result<HashMap<Sound, Chunk>, String> {
+    let mut chunks = HashMap::new();
+    for sound in Sound::ALL {
+        chunks.insert(sound, load_chunk(pack.sound(sound), sound.notes(), spec)?);
+    }
+    Ok(chunks)
+}
+
+impl Audio {
+    pub fn open(pack: &SoundPack) -> Result<Audio, String> {
+        mixer::open_audio(FREQUENCY, AUDIO_S16SYS, mixer::DEFAULT_CHANNELS, 1_024)?;
+        // 读 ogg 用；没有的话 wav 和合成的声音照样能用
+        if let Err(err) = mixer::init(mixer::InitFlag::OGG) {
+            eprintln!("Failed to initialize OGG support: {}", err);
+        }
+        mixer::allocate_channels(CHANNELS);
+        mixer::reserve_channels(MUSIC_CHANNELS as i32);
+        let (frequency, _, channels) = mixer::query_spec()?;
+        let spec = (frequency, channels);
+        Ok(Audio {
+            chunks: load_sounds(pack, spec)?,
+            music: MusicPlayer::load(&pack.soundtrack, spec)?,
+            spec,
+        })
+    }
+
+    // 设置里换了音效包
+    pub fn load_pack(&mut self, pack: &SoundPack) -> Result<(), String> {
+        // 正在放的音效用着旧的 chunk，先停掉再释放
+        Channel::all().halt();
+        self.chunks = load_sounds(pack, self.spec)?;
+        self.music.reload(&pack.soundtrack, self.spec)
+    }
+
+    // 设置改了马上生效；音乐通道的音量每帧由 `MusicPlayer` 重新设
+    pub fn set_volume(&mut self, settings: &Settings) {
+        let (music, sfx) = channel_volumes(settings);
+        Channel::all().set_volume(sfx);
+        self.music.set_volume(music);
+    }
+
+    pub fn update_music(&mut self, state: &MusicState) {
+        self.music.update(state);
+    }
+
+    pub fn beat(&self) -> Option<BeatClock> {
+        self.music.beat()
+    }
+
+    // `position` 见 `position_for_event`，None 就是放在正中间
+    pub fn play(&self, sound: Sound, position: Option<f32>) {
+        // 自己挑一个空闲的通道，先把声道设好再放，免得开头一小段从中间出来；
+        // 通道都在用的话就不放了，不是什么大事
+        let Some(channel) = (MUSIC_CHANNELS as i32..CHANNELS)
+            .map(Channel)
+            .find(|channel| !channel.is_playing())
+        else {
+            return;
+        };
+        let (left, right) = position.map_or((255, 255), panning);
+        let _ = channel.set_panning(left, right);
+        let _ = channel.play(&self.chunks[&sound], 0);
+    }
+
+    // 一帧的事件里同一种声音只放一次；消行的时候不再放 lock 的声音
+    pub fn play_events(&self, events: &[Event]) {
+        let cleared = events
+            .iter()
+            .any(|event| matches!(event, Event::LineClear { .. }));
+        let mut played = Vec::new();
+        for event in events {
+            let Some(sound) = Sound::for_event(event) else {
+                continue;
+            };
+            if played.contains(&sound) || (cleared && sound == Sound::Lock) {
+                continue;
+            }
+            self.play(sound, position_for_event(event));
+            played.push(sound);
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        // chunk 要在关掉设备之前释放
+        self.chunks.clear();
+        self.music.clear();
+        mixer::close_audio();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_clears_pick_sound_by_size() {
+        let clear = |rows: usize| Event::LineClear {
+            rows: (0..rows).collect(),
+            combo: 0,
+        };
+        assert_eq!(Sound::for_event(&clear(1)), Some(Sound::Single));
+        assert_eq!(Sound::for_event(&clear(4)), Some(Sound::Tetris));
+        assert_eq!(Sound::for_event(&Event::ComboBroken { combo: 2 }), None);
+    }
+
+    #[test]
+    fn volumes_combine_master_and_mute() {
+        let mut settings = Settings {
+            master_volume: 50,
+            music_volume: 100,
+            sfx_volume: 50,
+            ..Settings::default()
+        };
+        assert_eq!(channel_volumes(&settings), (MAX_VOLUME / 2, MAX_VOLUME / 4));
+        settings.muted = true;
+        assert_eq!(channel_volumes(&settings), (0, 0));
+    }
+
+    #[test]
+    fn sounds_pan_toward_the_piece() {
+        assert_eq!(panning(0.5), (255, 255));
+        let (left, right) = panning(0.0);
+        assert_eq!(left, 255);
+        assert!(right < 200);
+        let cells = [Point2::new(9, 0); 4];
+        assert_eq!(position_for_event(&Event::Move { cells }), Some(1.0));
+        assert_eq!(position_for_event(&Event::Hold), None);
+    }
+
+    #[test]
+    fn synthesized_notes_fade_out() {
+        let samples = synthesize(&[(440.0, 10), (880.0, 10)], 1_000, 2);
+        assert_eq!(samples.len(), 40);
+        // 每个音符一开始最响，结尾接近无声
+        assert_eq!(samples[0].unsigned_abs(), 6000);
+        assert!(samples[18].unsigned_abs() < 1000);
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::audio::Audio;
+use super::music::{BeatClock, MusicState};
+use super::settings::Settings;
+use super::sound_pack::SoundPack;
+use crate::engine::Event;
+
+// 音乐的淡入淡出按这个间隔走一步，和游戏逻辑一样是 60 Hz
+const MUSIC_TICK: Duration = Duration::from_micros(16_667);
+
+pub enum AudioCommand {
+    // 引擎一帧里发生的事件
+    Events(Vec<Event>),
+    Music(MusicState),
+    Volume(Settings),
+    LoadPack(SoundPack),
+    Quit,
+}
+
+// 声音在自己的线程里放：事件一来马上放，不用等这一帧画完，
+// 渲染卡住的时候音乐也照样淡入淡出。主线程只管往里发命令
+pub struct AudioBus {
+    sender: Sender<AudioCommand>,
+    // 音频线程每步更新一次，游戏循环拿来判断锁定是不是踩在拍子上
+    beat: Arc<Mutex<Option<BeatClock>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioBus {
+    // 设备在音频线程里打开；打不开的话线程直接结束，之后发的命令都被丢掉
+    pub fn spawn(pack: SoundPack, settings: Settings) -> AudioBus {
+        let (sender, receiver) = mpsc::channel();
+        let beat = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&beat);
+        let thread = thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || match Audio::open(&pack) {
+                Ok(mut audio) => {
+                    audio.set_volume(&settings);
+                    run(&mut audio, &receiver, &shared);
+                }
+                Err(err) => eprintln!("Failed to open audio: {}", err),
+            })
+            .map_err(|err| eprintln!("Failed to start audio thread: {}", err))
+            .ok();
+        AudioBus {
+            sender,
+            beat,
+            thread,
+        }
+    }
+
+    pub fn send(&self, command: AudioCommand) {
+        // 音频线程已经没了就算了，游戏照样能玩
+        let _ = self.sender.send(command);
+    }
+
+    // 正在放的背景音乐的节拍，没在放（或者暂停了）就是 None
+    pub fn beat(&self) -> Option<BeatClock> {
+        *self.beat.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Drop for AudioBus {
+    fn drop(&mut self) {
+        self.send(AudioCommand::Quit);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(audio: &mut Audio, receiver: &Receiver<AudioCommand>, beat: &Mutex<Option<BeatClock>>) {
+    let mut music = MusicState::default();
+    let mut next_tick = Instant::now();
+    loop {
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok(AudioCommand::Events(events)) => audio.play_events(&events),
+            Ok(AudioCommand::Music(state)) => music = state,
+            Ok(AudioCommand::Volume(settings)) => audio.set_volume(&settings),
+            Ok(AudioCommand::LoadPack(pack)) => {
+                if let Err(err) = audio.load_pack(&pack) {
+                    eprintln!("Failed to load sound pack {}: {}", pack.name, err);
+                }
+            }
+            Ok(AudioCommand::Quit) | Err(RecvTimeoutError::Disconnected) => return,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if Instant::now() >= next_tick {
+            audio.update_music(&music);
+            *beat.lock().unwrap_or_else(|err| err.into_inner()) = audio.beat();
+            next_tick += MUSIC_TICK;
+            // 落后太多（比如机器休眠过）就不补了
+            if next_tick < Instant::now() {
+                next_tick = Instant::now() + MUSIC_TICK;
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::{BackgroundAnimation, SkinAssets};
+use crate::engine::Color as SemanticColor;
+
+const STAR_COUNT: u64 = 90;
+const RAIN_DROPS: u64 = 24;
+
+// 整数哈希，让每颗星星、每个方块的位置和速度固定下来，不用存状态
+fn hash(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+// 清屏并画出皮肤的背景：背景图铺满窗口（多出来的裁掉），再叠上动画，最后用背景色压暗
+pub fn draw(canvas: &mut Canvas<Window>, skin: &SkinAssets, frame: u64) {
+    canvas.set_draw_color(skin.colors.background.0);
+    canvas.clear();
+    let viewport = canvas.viewport();
+    if skin.background.is_none() && skin.background_animation.is_none() {
+        return;
+    }
+
+    if let Some(texture) = &skin.background {
+        let query = texture.query();
+        canvas
+            .copy(texture, cover(query.width, query.height, viewport), None)
+            .unwrap();
+    }
+    match skin.background_animation {
+        Some(BackgroundAnimation::Stars) => draw_stars(canvas, viewport, frame),
+        Some(BackgroundAnimation::Rain) => draw_rain(canvas, viewport, skin, frame),
+        None => {}
+    }
+
+    let dim = skin.colors.background.0;
+    let alpha = (skin.background_dim * 255.0) as u8;
+    canvas.set_draw_color(Color::RGBA(dim.r, dim.g, dim.b, alpha));
+    canvas.fill_rect(viewport).unwrap();
+}
+
+// 保持比例铺满 `target` 时，要从图片里取的那一块
+fn cover(width: u32, height: u32, target: Rect) -> Rect {
+    let (tw, th) = (target.width() as u64, target.height() as u64);
+    let (w, h) = (width as u64, height as u64);
+    // 图片比窗口更宽就裁左右，否则裁上下
+    let (src_w, src_h) = if w * th > h * tw {
+        (h * tw / th, h)
+    } else {
+        (w, w * th / tw)
+    };
+    Rect::new(
+        ((w - src_w) / 2) as i32,
+        ((h - src_h) / 2) as i32,
+        src_w.max(1) as u32,
+        src_h.max(1) as u32,
+    )
+}
+
+fn draw_stars(canvas: &mut Canvas<Window>, viewport: Rect, frame: u64) {
+    let (width, height) = (viewport.width() as u64, viewport.height() as u64);
+    for i in 0..STAR_COUNT {
+        let seed = hash(i);
+        // 近的星星更大更亮、飘得更快
+        let depth = seed % 3 + 1;
+        let x = seed % width;
+        let y = (seed / width + frame * depth / 4) % height;
+        let size = depth as u32;
+        let brightness = 0x50 + 0x30 * depth as u8;
+        canvas.set_draw_color(Color::RGB(brightness, brightness, brightness));
+        canvas
+            .fill_rect(Rect::new(x as i32, y as i32, size, size))
+            .unwrap();
+    }
+}
+
+fn draw_rain(canvas: &mut Canvas<Window>, viewport: Rect, skin: &SkinAssets, frame: u64) {
+    let size = (viewport.width() / 40).max(4);
+    let (width, height) = (viewport.width() as u64, (viewport.height() + size) as u64);
+    for i in 0..RAIN_DROPS {
+        let seed = hash(i + STAR_COUNT);
+        let color = SemanticColor::ALL[(seed % 7) as usize];
+        let speed = seed % 3 + 1;
+        let x = (seed / 7) % width;
+        let y = ((seed / 7 / width) + frame * speed) % height;
+        canvas.set_draw_color(skin.piece_color(color));
+        canvas
+            .fill_rect(Rect::new(x as i32, y as i32 - size as i32, size, size))
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cover_crops_to_target_aspect() {
+        // 宽图放进正方形，裁掉左右
+        assert_eq!(
+            cover(1600, 900, Rect::new(0, 0, 500, 500)),
+            Rect::new(350, 0, 900, 900)
+        );
+        // 高图放进宽窗口，裁掉上下
+        assert_eq!(
+            cover(800, 1200, Rect::new(0, 0, 1600, 900)),
+            Rect::new(0, 375, 800, 450)
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use sdl2::pixels::Color as SdlColor;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::combo::ComboMeter;
+use super::effects::Effects;
+use super::i18n::tr;
+use super::layout::Layout;
+use super::pace::Pace;
+use super::settings::Settings;
+use super::skin::{SkinAssets, Theme};
+use super::{danger, garbage, nes, sidebar, text, CellDrawCtx, Clock};
+use crate::engine::bot::{BotView, Hint, Stacker};
+use crate::engine::mode::Mode;
+use crate::engine::opener::{self, Opener};
+use crate::engine::review::Recorder;
+use crate::engine::{Engine, Event};
+
+const GHOST_ALPHA: u8 = 0x50;
+const HINT_ALPHA: u8 = 0x60;
+
+// 一块棋盘：引擎，加上只属于界面的特效和计量条。
+// 玩家自己的、观战的（远程或者 AI）都用这个画
+pub struct Board {
+    pub engine: Engine,
+    pub effects: Effects,
+    pub combo_meter: ComboMeter,
+    // sprint / ultra 和最好成绩比快慢，其它模式是 None
+    pub pace: Option<Pace>,
+    // 落点提示和它是替哪一块算的（按锁定和 hold 的次数认）
+    hint: Option<((u32, u32), Hint)>,
+    openers: Vec<Opener>,
+    // 上一次照着哪个定式提示的
+    opener: Option<usize>,
+    // 记下每块放在哪，打完之后复盘用
+    pub recorder: Recorder,
+}
+
+impl Board {
+    pub fn new(mode: Mode) -> Self {
+        Self::with_engine(Engine::with_mode(mode))
+    }
+
+    pub fn with_engine(engine: Engine) -> Self {
+        Self {
+            engine,
+            effects: Effects::default(),
+            combo_meter: ComboMeter::default(),
+            pace: None,
+            hint: None,
+            openers: opener::builtin(),
+            opener: None,
+            recorder: Recorder::default(),
+        }
+    }
+
+    // 引擎走一帧，再把这一帧的事件交给特效和计量条；事件还会返回给调用方放音效
+    pub fn update(&mut self) -> Vec<Event> {
+        self.engine.tick();
+        let events = self.engine.take_events();
+        self.recorder.record(&self.engine, &events);
+        for event in &events {
+            self.effects.handle(event);
+            self.combo_meter.handle(event);
+        }
+        self.effects.update();
+        self.combo_meter.update();
+        if let Some(pace) = &mut self.pace {
+            pace.update(&self.engine.stats());
+        }
+        events
+    }
+
+    // 每来一个新方块才重新算一次，不然每帧都要把所有放法试一遍。
+    // 开局定式优先，认出一个新的定式的时候返回它的名字
+    pub fn update_hint(&mut self, placement: bool, openers: bool) -> Option<&str> {
+        if !placement && !openers {
+            self.hint = None;
+            return None;
+        }
+        let stats = self.engine.stats();
+        let piece = (stats.pieces, stats.holds);
+        if self.hint.is_some_and(|(hinted, _)| hinted == piece) {
+            return None;
+        }
+        let suggestion = BotView::of(&self.engine)
+            .filter(|_| openers)
+            .and_then(|view| opener::suggest(&self.openers, &view));
+        let previous = self.opener;
+        self.opener = suggestion.map(|(index, _)| index);
+        self.hint = match suggestion {
+            Some((_, hint)) => Some((piece, hint)),
+            None if placement => Stacker::hint(&self.engine).map(|hint| (piece, hint)),
+            None => None,
+        };
+        let index = self.opener.filter(|&index| previous != Some(index))?;
+        Some(&self.openers[index].name)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.engine.is_game_over() || self.engine.is_finished()
+    }
+
+    // 结束之后棋盘定格一会儿，再切到结算界面
+    pub fn show_results(&self) -> bool {
+        self.engine.end_freeze_done()
+    }
+
+    // 把整块棋盘连同预览、hold、分数面板画进 `ui_square`；
+    // 分屏的时候 `label` 写在 matrix 上方，区分是哪个玩家
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        ui_square: Rect,
+        label: Option<&str>,
+        settings: &Settings,
+        skin: &SkinAssets,
+        clock: Clock,
+    ) {
+        let engine = &self.engine;
+        // 震动只是把整个布局挪一下，不影响各区域的大小
+        let mut ui_square = ui_square;
+        let (dx, dy) = self.effects.shake_offset();
+        let shake = ui_square.width() as f32 * settings.shake_scale();
+        ui_square.offset((dx * shake) as i32, (dy * shake) as i32);
+        let Layout {
+            matrix,
+            up_next,
+            next_queue,
+            hold,
+            score,
+            combo,
+            garbage_meter,
+            stats,
+            ..
+        } = Layout::new(ui_square, settings.previews, engine.matrix_size());
+        canvas.set_draw_color(skin.colors.matrix.0);
+        canvas.fill_rect(matrix).unwrap();
+        if let Some(label) = label {
+            let margin = (matrix.top() - ui_square.top()).max(1) as u32;
+            let scale = (margin / 2 / text::GLYPH_HEIGHT).max(1);
+            let center = Point::new(matrix.center().x, ui_square.top() + (margin / 2) as i32);
+            text::draw_text_centered(canvas, label, center, scale, skin.colors.accent.0);
+        }
+        let mut previews = engine.preview(settings.previews);
+        if let Some(kind) = previews.next() {
+            canvas.fill_rect(up_next).unwrap();
+            super::draw_piece_preview(canvas, up_next, kind, skin);
+        }
+        // 剩下的在 next 面板里从上往下排，每个方块一格
+        let rest: Vec<_> = previews.collect();
+        if !rest.is_empty() {
+            canvas.set_draw_color(skin.colors.matrix.0);
+            canvas.fill_rect(next_queue).unwrap();
+            let slot_height = next_queue.height() / rest.len() as u32;
+            for (i, kind) in rest.into_iter().enumerate() {
+                let slot = Rect::new(
+                    next_queue.x(),
+                    next_queue.y() + (slot_height * i as u32) as i32,
+                    next_queue.width(),
+                    slot_height,
+                );
+                super::draw_piece_preview(canvas, slot, kind, skin);
+            }
+        }
+        canvas.set_draw_color(skin.colors.matrix.0);
+        canvas.fill_rect(hold).unwrap();
+        canvas.fill_rect(score).unwrap();
+
+        if let Some(frames) = engine.timer_frames() {
+            super::draw_timer(canvas, score, frames, skin);
+        }
+        // 任务写在计时器下面，计时器是这个任务还剩的时间
+        if let Some(active) = engine.mission() {
+            let text = tr(active.mission.text);
+            let scale = (text::fit_scale(&text, score.width() * 7 / 8) / 2).max(1);
+            let height = (text::GLYPH_HEIGHT * scale) as i32;
+            let center = Point::new(score.center().x, score.top() + height * 6);
+            text::draw_text_centered(canvas, &text, center, scale, skin.colors.accent.0);
+        }
+        if let Some(pace) = &self.pace {
+            pace.draw(canvas, score, &engine.stats(), skin);
+        }
+        match skin.theme() {
+            Theme::Modern => sidebar::draw_stats(canvas, stats, &engine.stats(), &skin.colors),
+            Theme::Nes => nes::draw_piece_counts(canvas, stats, &engine.stats(), skin),
+        }
+        self.combo_meter
+            .draw(canvas, combo, &skin.colors, settings.reduced_motion);
+        if let Some(kind) = engine.held() {
+            super::draw_piece_preview(canvas, hold, kind, skin);
+        }
+        garbage::draw_garbage_meter(
+            canvas,
+            garbage_meter,
+            engine.matrix().height(),
+            engine.garbage(),
+            skin,
+        );
+        if let Some(level) = danger::danger_level(engine.matrix(), settings.danger_row) {
+            danger::draw_border(canvas, matrix, level, clock.frame, &skin.colors);
+        }
+
+        let mut cell_draw_ctx = CellDrawCtx {
+            // 原点在左下角
+            origin: matrix.bottom_left(),
+            dims: matrix.size().into(),
+            cells: engine.matrix_size().into(),
+            outline: settings.high_contrast.then_some(skin.colors.text.0),
+            canvas,
+            skin,
+        };
+        super::draw_grid(&mut cell_draw_ctx, settings.grid);
+        // matrix 上已存在的 cell
+        if let Some((rows, progress)) = engine.line_clear_progress() {
+            let flash = !settings.reduced_motion;
+            super::draw_line_clear(&mut cell_draw_ctx, engine, rows, progress, flash);
+        } else {
+            for (coord, cell_color) in engine.cells() {
+                if let Some(cell_color) = cell_color {
+                    let links = super::matrix_links(engine.matrix(), coord);
+                    cell_draw_ctx.draw_locked_mino(engine, *cell_color, coord, links, 0.0);
+                }
+            }
+        }
+        if !settings.reduced_motion {
+            self.effects.draw_trails(&mut cell_draw_ctx);
+            self.effects.draw_lock_flashes(&mut cell_draw_ctx);
+        }
+        // 红白机上没有影子
+        if settings.ghost && skin.theme() != Theme::Nes {
+            if let (Some(ghost_cells), Some((_, color))) =
+                (engine.ghost_cells(), engine.cursor_info())
+            {
+                let base = skin.piece_color(color);
+                let ghost = SdlColor::RGBA(base.r, base.g, base.b, GHOST_ALPHA);
+                for coord in ghost_cells {
+                    cell_draw_ctx.draw_cell_shifted(ghost, coord, 0.0);
+                }
+            }
+        }
+        // 落点提示只描个边，和影子区分开；要先 hold 的话在 hold 框里写一下
+        if let Some((_, hint)) = self.hint.filter(|_| engine.cursor_info().is_some()) {
+            let base = skin.piece_color(hint.color);
+            let outline = SdlColor::RGBA(base.r, base.g, base.b, HINT_ALPHA);
+            for coord in hint.cells {
+                cell_draw_ctx.draw_cell_outline(outline, coord);
+            }
+            if hint.hold {
+                let label = tr("HOLD");
+                let scale = (text::fit_scale(&label, hold.width()) / 2).max(1);
+                let center = Point::new(
+                    hold.center().x,
+                    hold.bottom() - (text::GLYPH_HEIGHT * scale) as i32,
+                );
+                text::draw_text_centered(cell_draw_ctx.canvas, &label, center, scale, outline);
+            }
+        }
+        // cursor 处的 piece
+        let fall = if settings.smooth_fall {
+            engine.fall_progress(clock.alpha)
+        } else {
+            0.0
+        };
+        if let Some((cursor_cells, color)) = engine.cursor_info() {
+            for coord in cursor_cells {
+                let links = super::piece_links(&cursor_cells, coord);
+                cell_draw_ctx.draw_mino(color, coord, links, fall);
+            }
+        }
+        // 合作模式的第二个玩家，不做平滑下落
+        if let Some((cursor_cells, color)) = engine.partner_cursor_info() {
+            for coord in cursor_cells {
+                let links = super::piece_links(&cursor_cells, coord);
+                cell_draw_ctx.draw_mino(color, coord, links, 0.0);
+            }
+        }
+    }
+}
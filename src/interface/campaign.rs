@@ -0,0 +1,72 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::campaign::{Stage, STAGES};
+
+// 闯关进度：已经过了前几关
+pub const CAMPAIGN_FILE: &str = "campaign.toml";
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    cleared: usize,
+}
+
+impl CampaignProgress {
+    pub fn load(path: &Path) -> Result<CampaignProgress, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(CampaignProgress::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    // 过了前一关才能玩这一关
+    pub fn is_unlocked(&self, stage: usize) -> bool {
+        stage <= self.cleared
+    }
+
+    // 第一次过这一关返回 true，下一关跟着解锁
+    pub fn clear(&mut self, stage: usize) -> bool {
+        if stage < self.cleared {
+            return false;
+        }
+        self.cleared = (stage + 1).min(STAGES.len());
+        true
+    }
+}
+
+// 选关菜单里的名字：过了的打星号，没解锁的不显示名字
+pub fn label(index: usize, stage: &Stage, progress: &CampaignProgress) -> String {
+    if !progress.is_unlocked(index) {
+        return format!("  {} ----", index + 1);
+    }
+    let mark = if index < progress.cleared { '*' } else { ' ' };
+    format!("{} {} {}", mark, index + 1, stage.name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clearing_a_stage_unlocks_the_next() {
+        let mut progress = CampaignProgress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+        assert!(progress.clear(0));
+        assert!(progress.is_unlocked(1));
+        assert!(!progress.clear(0));
+        assert!(!progress.is_unlocked(2));
+        assert_eq!(label(2, &STAGES[2], &progress), "  3 ----");
+        assert_eq!(label(0, &STAGES[0], &progress), "* 1 FIRST STEPS");
+    }
+}
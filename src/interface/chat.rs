@@ -0,0 +1,104 @@
+use sdl2::keyboard::Keycode;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinColors;
+use super::text;
+use crate::net::chat::{ChatLog, MAX_CHAT_LEN};
+
+// 联机时按 Tab 打开的聊天框。开着的时候按键都用来打字，不会动到方块；
+// 回车发出去，再按 Tab 不发了
+#[derive(Default)]
+pub struct ChatEntry {
+    pub text: String,
+}
+
+impl ChatEntry {
+    // 能打出字的键，键码就是那个字符
+    pub fn push_key(&mut self, key: Keycode) {
+        let c = match key {
+            Keycode::Semicolon | Keycode::Colon => ':',
+            _ => match char::from_u32(key as u32) {
+                Some(c) if c.is_ascii_alphanumeric() || " ./".contains(c) => c,
+                _ => return,
+            },
+        };
+        if self.text.len() < MAX_CHAT_LEN {
+            self.text.push(c.to_ascii_uppercase());
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.text.pop();
+    }
+}
+
+// 在左上角画最近的几句，聊天框开着的话画在最下面
+pub fn draw(
+    canvas: &mut Canvas<Window>,
+    log: &ChatLog,
+    entry: Option<&ChatEntry>,
+    colors: &SkinColors,
+) {
+    let lines: Vec<String> = log
+        .lines()
+        .map(|line| format!("{}: {}", line.name, line.text))
+        .collect();
+    let prompt = entry.map(|entry| format!("> {}_", entry.text));
+    if lines.is_empty() && prompt.is_none() {
+        return;
+    }
+    let viewport = canvas.viewport();
+    let scale = (viewport.height() / 300).max(1);
+    let margin = (4 * scale) as i32;
+    let line_height = (text::GLYPH_HEIGHT * scale) as i32 + margin;
+    let count = lines.len() + prompt.iter().len();
+    let width = lines
+        .iter()
+        .chain(&prompt)
+        .map(|line| text::text_size(line, scale).0)
+        .max()
+        .unwrap_or(0);
+    let panel = Rect::new(
+        margin,
+        margin,
+        width + margin as u32 * 2,
+        (line_height * count as i32 + margin) as u32,
+    );
+    canvas.set_draw_color(colors.overlay.0);
+    canvas.fill_rect(panel).unwrap();
+    let mut y = panel.y() + margin;
+    for line in &lines {
+        let top_left = Point::new(panel.x() + margin, y);
+        text::draw_text(canvas, line, top_left, scale, colors.text.0);
+        y += line_height;
+    }
+    if let Some(prompt) = prompt {
+        let top_left = Point::new(panel.x() + margin, y);
+        text::draw_text(canvas, &prompt, top_left, scale, colors.accent.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chat_entry_types_printable_keys_only() {
+        let mut entry = ChatEntry::default();
+        for key in [
+            Keycode::G,
+            Keycode::Num1,
+            Keycode::Space,
+            Keycode::Period,
+            Keycode::Up,
+            Keycode::Return,
+        ] {
+            entry.push_key(key);
+        }
+        assert_eq!(entry.text, "G1 .");
+        entry.pop();
+        assert_eq!(entry.text, "G1 ");
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gif::{Encoder, Frame, Repeat};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::engine::Stats;
+
+// 每隔几个逻辑帧抓一张，60 / 4 = 15 fps，GIF 够看了
+const CAPTURE_EVERY: u64 = 4;
+const CLIP_SECONDS: u64 = 15;
+const MAX_FRAMES: usize = (CLIP_SECONDS * Stats::FRAMES_PER_SECOND / CAPTURE_EVERY) as usize;
+// 存下来的画面最宽这么多像素，整个窗口原样存 15 秒太占内存
+const MAX_WIDTH: u32 = 320;
+// GIF 的延迟单位是 1/100 秒
+const FRAME_DELAY: u16 = (CAPTURE_EVERY * 100 / Stats::FRAMES_PER_SECOND) as u16;
+// NeuQuant 的采样步长，1 最慢最好，30 最快
+const QUANTIZE_SPEED: i32 = 20;
+
+// 缩小后的一帧，RGB 三个字节一个像素
+struct ClipFrame {
+    width: u16,
+    height: u16,
+    rgb: Vec<u8>,
+}
+
+// 最近十几秒画面的环形缓冲，按快捷键把它导出成 GIF
+#[derive(Default)]
+pub struct ClipRecorder {
+    frames: VecDeque<ClipFrame>,
+    last_capture: Option<u64>,
+}
+
+impl ClipRecorder {
+    // 画完一帧、present 之前调用；`frame` 是逻辑帧计数，渲染再快也只按逻辑帧抓
+    pub fn capture(&mut self, canvas: &Canvas<Window>, frame: u64) {
+        if self
+            .last_capture
+            .is_some_and(|last| frame < last + CAPTURE_EVERY)
+        {
+            return;
+        }
+        self.last_capture = Some(frame);
+
+        let (width, height) = match canvas.output_size() {
+            Ok(size) => size,
+            Err(err) => {
+                eprintln!("Failed to capture frame: {}", err);
+                return;
+            }
+        };
+        let pixels = match canvas.read_pixels(None, PixelFormatEnum::RGB24) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                eprintln!("Failed to capture frame: {}", err);
+                return;
+            }
+        };
+        self.push(downscale(&pixels, width, height, MAX_WIDTH));
+    }
+
+    fn push(&mut self, frame: ClipFrame) {
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    // 在后台线程里编码，不卡游戏；返回要写的文件名
+    pub fn export(&self) -> Option<PathBuf> {
+        let last = self.frames.back()?;
+        // 窗口大小中途变过的话，只要和最后一帧一样大的
+        let (width, height) = (last.width, last.height);
+        let frames: Vec<Vec<u8>> = self
+            .frames
+            .iter()
+            .filter(|frame| (frame.width, frame.height) == (width, height))
+            .map(|frame| frame.rgb.clone())
+            .collect();
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = PathBuf::from(format!("clip-{}.gif", secs));
+        let target = path.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = write_gif(&target, width, height, &frames) {
+                eprintln!("Failed to write {}: {}", target.display(), err);
+            }
+        });
+        Some(path)
+    }
+}
+
+fn write_gif(path: &Path, width: u16, height: u16, frames: &[Vec<u8>]) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut encoder =
+        Encoder::new(BufWriter::new(file), width, height, &[]).map_err(|err| err.to_string())?;
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|err| err.to_string())?;
+    for rgb in frames {
+        let mut frame = Frame::from_rgb_speed(width, height, rgb, QUANTIZE_SPEED);
+        frame.delay = FRAME_DELAY;
+        encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+// 最近邻缩小到不超过 `max_width` 宽，保持宽高比
+fn downscale(rgb: &[u8], width: u32, height: u32, max_width: u32) -> ClipFrame {
+    let step = width.div_ceil(max_width).max(1);
+    let (out_width, out_height) = (width / step, height / step);
+    let mut out = Vec::with_capacity((out_width * out_height * 3) as usize);
+    for y in 0..out_height {
+        let row = (y * step * width * 3) as usize;
+        for x in 0..out_width {
+            let i = row + (x * step * 3) as usize;
+            out.extend_from_slice(&rgb[i..i + 3]);
+        }
+    }
+    ClipFrame {
+        width: out_width as u16,
+        height: out_height as u16,
+        rgb: out,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_last_few_seconds() {
+        let mut clip = ClipRecorder::default();
+        for i in 0..MAX_FRAMES + 10 {
+            clip.push(downscale(&[i as u8, 0, 0], 1, 1, MAX_WIDTH));
+        }
+        assert_eq!(clip.frames.len(), MAX_FRAMES);
+        assert_eq!(clip.frames[0].rgb[0], 10);
+    }
+
+    #[test]
+    fn downscale_samples_every_nth_pixel() {
+        // 4x2，每个像素的 R 是它的下标
+        let rgb: Vec<u8> = (0..8).flat_map(|i| [i, 0, 0]).collect();
+        let frame = downscale(&rgb, 4, 2, 2);
+        assert_eq!((frame.width, frame.height), (2, 1));
+        assert_eq!(frame.rgb, vec![0, 0, 0, 2, 0, 0]);
+    }
+}
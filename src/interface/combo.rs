@@ -0,0 +1,145 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::skin::{shade, SkinColors};
+use super::text;
+use crate::engine::Event;
+
+// 连击数到这么多时计量条就满了
+const FULL_METER: u32 = 10;
+const PULSE_FRAMES: u32 = 10;
+const BROKEN_FRAMES: u32 = 45;
+const FLAME_COLORS: [Color; 3] = [
+    Color::RGB(0xff, 0xd0, 0x40),
+    Color::RGB(0xff, 0x80, 0x20),
+    Color::RGB(0xe0, 0x30, 0x20),
+];
+
+// 棋盘旁边的连击计量条：连击越多越满，满到一定程度上面冒火苗，中断时慢慢褪掉
+#[derive(Default)]
+pub struct ComboMeter {
+    combo: u32,
+    // 每次连击数增加时文字放大一下
+    pulse: u32,
+    // 中断前的连击数，以及还剩多少帧褪完
+    broken: Option<(u32, u32)>,
+    frame: u32,
+}
+
+impl ComboMeter {
+    pub fn handle(&mut self, event: &Event) {
+        match event {
+            Event::LineClear { combo, .. } => {
+                self.combo = *combo;
+                if *combo > 0 {
+                    self.pulse = PULSE_FRAMES;
+                    self.broken = None;
+                }
+            }
+            Event::ComboBroken { combo } => {
+                self.combo = 0;
+                self.broken = Some((*combo, BROKEN_FRAMES));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        self.pulse = self.pulse.saturating_sub(1);
+        if let Some((_, frames_left)) = &mut self.broken {
+            *frames_left -= 1;
+            if *frames_left == 0 {
+                self.broken = None;
+            }
+        }
+    }
+
+    // `reduced_motion` 的时候不冒火苗，数字也不跳
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        area: Rect,
+        colors: &SkinColors,
+        reduced_motion: bool,
+    ) {
+        let (combo, fade) = match self.broken {
+            Some((combo, frames_left)) => (combo, frames_left as f32 / BROKEN_FRAMES as f32),
+            None if self.combo > 0 => (self.combo, 1.0),
+            None => return,
+        };
+        let alpha = (255.0 * fade) as u8;
+
+        // 左边竖着的计量条
+        let bar_width = (area.width() / 6).max(4);
+        let bar = Rect::new(area.x(), area.y(), bar_width, area.height());
+        let fill_height = bar.height() * combo.min(FULL_METER) / FULL_METER;
+        let fill = Rect::new(
+            bar.x(),
+            bar.bottom() - fill_height as i32,
+            bar_width,
+            fill_height,
+        );
+        let heat = combo.min(FULL_METER) as f32 / FULL_METER as f32;
+        let mut fill_color = shade(colors.accent.0, heat * 0.6);
+        fill_color.a = alpha;
+        canvas.set_draw_color(fill_color);
+        canvas.fill_rect(fill).unwrap();
+        let mut outline = colors.text_dim.0;
+        outline.a = alpha;
+        canvas.set_draw_color(outline);
+        canvas.draw_rect(bar).unwrap();
+
+        // 连击还在继续，而且够多的时候，计量条顶上冒火苗
+        if self.broken.is_none() && combo >= 3 && !reduced_motion {
+            self.draw_flames(canvas, fill, combo);
+        }
+
+        let scale = (area.width() / 40).max(1) + u32::from(self.pulse > 0 && !reduced_motion);
+        let mut text_color = if self.broken.is_some() {
+            colors.text_dim.0
+        } else {
+            colors.text.0
+        };
+        text_color.a = alpha;
+        let text_x = bar.right() + bar_width as i32;
+        let number = combo.to_string();
+        text::draw_text(
+            canvas,
+            &number,
+            Point::new(text_x, fill.top() - (text::GLYPH_HEIGHT * scale * 2) as i32),
+            scale * 2,
+            text_color,
+        );
+        text::draw_text(
+            canvas,
+            &tr("COMBO"),
+            Point::new(text_x, fill.top() + scale as i32),
+            scale,
+            text_color,
+        );
+    }
+
+    // 火苗是几根高度一直在跳动的小矩形，连击越多越高
+    fn draw_flames(&self, canvas: &mut Canvas<Window>, fill: Rect, combo: u32) {
+        let tongues = 4;
+        let width = (fill.width() / tongues).max(1);
+        let max_height = fill.width() * combo.min(FULL_METER) / 4;
+        for i in 0..tongues {
+            let flicker = (self.frame / 3 + i * 7) % 5;
+            let height = max_height * (5 + flicker) / 10;
+            let color = FLAME_COLORS[((self.frame / 4 + i) % 3) as usize];
+            canvas.set_draw_color(color);
+            let tongue = Rect::new(
+                fill.x() + (i * width) as i32,
+                fill.top() - height as i32,
+                width,
+                height.max(1),
+            );
+            canvas.fill_rect(tongue).unwrap();
+        }
+    }
+}
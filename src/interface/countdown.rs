@@ -0,0 +1,80 @@
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::Stats;
+
+const STEP_FRAMES: u32 = Stats::FRAMES_PER_SECOND as u32;
+const STEPS: [&str; 3] = ["3", "2", "1"];
+// GO 和游戏一起开始，只是多显示一会儿
+const GO_FRAMES: u32 = STEP_FRAMES / 2;
+
+// 开局和暂停回来时的 3-2-1-GO，倒数期间引擎不走，第一个方块已经出来但不会动
+#[derive(Default)]
+pub struct Countdown {
+    frame: u32,
+}
+
+impl Countdown {
+    pub fn update(&mut self) {
+        self.frame += 1;
+    }
+
+    // 还在数 3-2-1，游戏逻辑和玩家输入都要停着
+    pub fn is_frozen(&self) -> bool {
+        self.frame < STEP_FRAMES * STEPS.len() as u32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frame >= STEP_FRAMES * STEPS.len() as u32 + GO_FRAMES
+    }
+
+    fn label(&self) -> &'static str {
+        STEPS
+            .get((self.frame / STEP_FRAMES) as usize)
+            .copied()
+            .unwrap_or("GO")
+    }
+
+    // 在 matrix 中间画当前的数字，每一步开始时大一些再慢慢缩回去
+    pub fn draw(&self, canvas: &mut Canvas<Window>, matrix: Rect, colors: &SkinColors) {
+        let label = tr(self.label());
+        let step_progress = (self.frame % STEP_FRAMES) as f32 / STEP_FRAMES as f32;
+        let base = text::fit_scale(&tr("GO"), matrix.width() / 2);
+        let scale = (base as f32 * (1.3 - 0.3 * step_progress)) as u32;
+        let color = if self.is_frozen() {
+            colors.text.0
+        } else {
+            colors.accent.0
+        };
+        text::draw_text_centered(canvas, &label, matrix.center(), scale.max(1), color);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_down_then_says_go() {
+        let mut countdown = Countdown::default();
+        assert_eq!(countdown.label(), "3");
+        for _ in 0..STEP_FRAMES * 3 - 1 {
+            countdown.update();
+        }
+        assert_eq!(countdown.label(), "1");
+        assert!(countdown.is_frozen());
+
+        countdown.update();
+        assert_eq!(countdown.label(), "GO");
+        assert!(!countdown.is_frozen());
+        assert!(!countdown.is_done());
+        for _ in 0..GO_FRAMES {
+            countdown.update();
+        }
+        assert!(countdown.is_done());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Stats;
+
+// 每天的最好成绩，按日期记；要传到线上排行榜的话传这个文件就行
+pub const DAILY_FILE: &str = "daily.toml";
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// 从 1970-01-01 起的第几天，按 UTC 算，这样各个时区的人同一时刻拿到的是同一天的题
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / SECONDS_PER_DAY)
+}
+
+// 第几天换成 YYYY-MM-DD，算法来自 Howard Hinnant 的 civil_from_days
+pub fn date(day: u64) -> String {
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DailyEntry {
+    pub score: u32,
+    pub lines: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyResults {
+    best: BTreeMap<String, DailyEntry>,
+}
+
+impl DailyResults {
+    pub fn load(path: &Path) -> Result<DailyResults, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(DailyResults::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn best(&self, day: u64) -> Option<DailyEntry> {
+        self.best.get(&date(day)).copied()
+    }
+
+    // 比当天的最好成绩高才记下来，记下来了返回 true
+    pub fn record(&mut self, day: u64, stats: &Stats) -> bool {
+        if self.best(day).is_some_and(|best| best.score >= stats.score) {
+            return false;
+        }
+        let entry = DailyEntry {
+            score: stats.score,
+            lines: stats.lines,
+        };
+        self.best.insert(date(day), entry);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn results_keep_the_best_of_each_day() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(20_742), "2026-10-16");
+
+        let mut results = DailyResults::default();
+        let stats = |score| Stats {
+            score,
+            ..Stats::default()
+        };
+        assert!(results.record(20_742, &stats(500)));
+        assert!(!results.record(20_742, &stats(300)));
+        assert!(results.record(20_743, &stats(100)));
+        assert_eq!(results.best(20_742).map(|best| best.score), Some(500));
+        assert_eq!(results.best(20_744), None);
+    }
+}
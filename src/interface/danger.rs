@@ -0,0 +1,75 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinColors;
+use crate::engine::Matrix;
+
+// 一次呼吸（变亮再变暗）的帧数
+const PULSE_FRAMES: u64 = 60;
+const MAX_TINT_ALPHA: f32 = 48.0;
+
+// 堆超过 `danger_row` 之后的危险程度，(0, 1]，越接近顶部越大；没超过就是 None
+pub fn danger_level(matrix: &Matrix, danger_row: usize) -> Option<f32> {
+    let height = matrix.stack_height();
+    if height <= danger_row || danger_row >= matrix.height() {
+        return None;
+    }
+    Some((height - danger_row) as f32 / (matrix.height() - danger_row) as f32)
+}
+
+fn pulse(frame: u64) -> f32 {
+    let phase = (frame % PULSE_FRAMES) as f32 / PULSE_FRAMES as f32;
+    1.0 - (phase * 2.0 - 1.0).abs()
+}
+
+fn with_alpha(color: Color, alpha: f32) -> Color {
+    Color::RGBA(color.r, color.g, color.b, alpha.clamp(0.0, 255.0) as u8)
+}
+
+// 整个背景染上一层淡淡的警告色，要在各个面板之前画
+pub fn draw_tint(canvas: &mut Canvas<Window>, level: f32, frame: u64, colors: &SkinColors) {
+    let alpha = MAX_TINT_ALPHA * level * (0.5 + 0.5 * pulse(frame));
+    canvas.set_draw_color(with_alpha(colors.danger.0, alpha));
+    canvas.fill_rect(canvas.viewport()).unwrap();
+}
+
+// matrix 外面一圈闪烁的边框
+pub fn draw_border(
+    canvas: &mut Canvas<Window>,
+    matrix: Rect,
+    level: f32,
+    frame: u64,
+    colors: &SkinColors,
+) {
+    let thickness = (matrix.width() / 40).max(2);
+    let alpha = 255.0 * (0.3 + 0.7 * level) * pulse(frame);
+    canvas.set_draw_color(with_alpha(colors.danger.0, alpha));
+    for i in 1..=thickness as i32 {
+        let border = Rect::new(
+            matrix.x() - i,
+            matrix.y() - i,
+            matrix.width() + 2 * i as u32,
+            matrix.height() + 2 * i as u32,
+        );
+        canvas.draw_rect(border).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn danger_starts_above_threshold() {
+        let mut matrix = Matrix::blank();
+        assert_eq!(danger_level(&matrix, 16), None);
+
+        for y in 0..Matrix::HEIGHT {
+            matrix[cgmath::Point2::new(0, y)] = Some(crate::engine::Color::Red);
+        }
+        assert_eq!(danger_level(&matrix, 16), Some(1.0));
+        assert_eq!(danger_level(&matrix, Matrix::HEIGHT), None);
+    }
+}
@@ -0,0 +1,63 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::{DebugInfo, Kick};
+
+// 引擎内部状态，画在右上角，排查旋转、锁定和出块顺序的问题用
+pub fn draw(canvas: &mut Canvas<Window>, info: &DebugInfo, colors: &SkinColors) {
+    let lines = lines(info);
+    let viewport = canvas.viewport();
+    let scale = (viewport.height() / 400).max(1);
+    let line_height = (text::GLYPH_HEIGHT + 3) * scale;
+    let width = lines
+        .iter()
+        .map(|line| text::text_size(line, scale).0)
+        .max()
+        .unwrap_or(0);
+
+    let margin = 4 * scale;
+    let background = Rect::new(
+        viewport.right() - (width + margin * 2) as i32,
+        0,
+        width + margin * 2,
+        line_height * lines.len() as u32 + margin * 2,
+    );
+    canvas.set_draw_color(colors.overlay.0);
+    canvas.fill_rect(background).unwrap();
+    for (i, line) in lines.iter().enumerate() {
+        let top_left = Point::new(
+            background.x() + margin as i32,
+            (margin + line_height * i as u32) as i32,
+        );
+        text::draw_text(canvas, line, top_left, scale, colors.text.0);
+    }
+}
+
+fn lines(info: &DebugInfo) -> Vec<String> {
+    let (piece, position, rotation) = match info.cursor {
+        Some(cursor) => (
+            format!("{:?}", cursor.kind),
+            format!("{},{}", cursor.position.x, cursor.position.y),
+            format!("{:?}", cursor.rotation),
+        ),
+        None => ("-".to_string(), "-".to_string(), "-".to_string()),
+    };
+    let kick = match info.last_kick {
+        Some(Kick::Succeeded(index)) => index.to_string(),
+        Some(Kick::Failed) => "FAIL".to_string(),
+        None => "-".to_string(),
+    };
+    let bag: String = info.bag.iter().map(|kind| format!("{:?}", kind)).collect();
+    vec![
+        format!("PIECE  {}", piece),
+        format!("POS    {}", position),
+        format!("ROT    {}", rotation),
+        format!("KICK   {}", kick),
+        format!("LOCK   {:.0}%", info.lock_progress * 100.0),
+        format!("GRAV   {:.0}%", info.gravity_progress * 100.0),
+        format!("BAG    {}", bag),
+    ]
+}
@@ -0,0 +1,200 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::nav::Nav;
+use super::skin::SkinAssets;
+use super::text;
+use crate::engine::piece::Kind as PieceKind;
+use crate::engine::puzzle::{Goal, Puzzle};
+use crate::engine::{Color, Matrix};
+
+// 目标按这个顺序切换
+const GOALS: [Goal; 5] = [
+    Goal::ClearAll,
+    Goal::Lines(1),
+    Goal::Lines(2),
+    Goal::Lines(3),
+    Goal::Lines(4),
+];
+// 再多右边就放不下了
+const MAX_PIECES: usize = 16;
+// 右边的说明，一行一条
+const HELP: [&str; 6] = [
+    "ARROWS MOVE",
+    "SPACE PAINT",
+    "OITLJSZ ADD PIECE",
+    "DEL REMOVE PIECE",
+    "G CHANGE GOAL",
+    "ENTER SAVE",
+];
+
+// 谜题编辑器：在空棋盘上涂格子，排好方块顺序，选一个目标，存成谜题文件
+pub struct Editor {
+    pub name: String,
+    matrix: Matrix,
+    // 光标所在的格子，原点在左下角
+    cursor: (usize, usize),
+    pieces: Vec<PieceKind>,
+    goal: usize,
+}
+
+impl Editor {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            matrix: Matrix::blank(),
+            cursor: (0, 0),
+            pieces: Vec::new(),
+            goal: 0,
+        }
+    }
+
+    // 方向键移动光标，确认涂格子；返回 true 表示不存直接离开
+    pub fn navigate(&mut self, nav: Nav) -> bool {
+        let (x, y) = &mut self.cursor;
+        match nav {
+            Nav::Left => *x = x.saturating_sub(1),
+            Nav::Right => *x = (*x + 1).min(Matrix::WIDTH - 1),
+            Nav::Down => *y = y.saturating_sub(1),
+            Nav::Up => *y = (*y + 1).min(Matrix::HEIGHT - 1),
+            Nav::Confirm => self.toggle(),
+            Nav::Back => return true,
+        }
+        false
+    }
+
+    fn toggle(&mut self) {
+        let coord = self.cursor.into();
+        self.matrix[coord] = match self.matrix[coord] {
+            Some(_) => None,
+            None => Some(Color::Gray),
+        };
+    }
+
+    pub fn push_piece(&mut self, kind: PieceKind) {
+        if self.pieces.len() < MAX_PIECES {
+            self.pieces.push(kind);
+        }
+    }
+
+    pub fn pop_piece(&mut self) {
+        self.pieces.pop();
+    }
+
+    pub fn cycle_goal(&mut self) {
+        self.goal = (self.goal + 1) % GOALS.len();
+    }
+
+    // 没排方块之类的问题在这里报出来
+    pub fn to_puzzle(&self) -> Result<Puzzle, String> {
+        Puzzle::new(
+            self.name.clone(),
+            &self.matrix,
+            self.pieces.clone(),
+            GOALS[self.goal],
+        )
+    }
+
+    // 左边是棋盘，右边是名字、目标、方块顺序和按键说明
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, skin: &SkinAssets) {
+        let colors = &skin.colors;
+        let cell = area.height() / Matrix::HEIGHT as u32;
+        let board = Rect::new(
+            area.x(),
+            area.y(),
+            cell * Matrix::WIDTH as u32,
+            cell * Matrix::HEIGHT as u32,
+        );
+        canvas.set_draw_color(colors.matrix.0);
+        canvas.fill_rect(board).unwrap();
+        let cell_rect = |x: usize, y: usize| {
+            let top = board.bottom() - (cell * (y as u32 + 1)) as i32;
+            Rect::new(board.x() + (cell * x as u32) as i32, top, cell, cell)
+        };
+        canvas.set_draw_color(colors.grid.0);
+        for y in 0..Matrix::HEIGHT {
+            for x in 0..Matrix::WIDTH {
+                canvas.draw_rect(cell_rect(x, y)).unwrap();
+            }
+        }
+        canvas.set_draw_color(skin.piece_color(Color::Gray));
+        for y in 0..Matrix::HEIGHT {
+            for x in 0..Matrix::WIDTH {
+                if self.matrix.get(x as isize, y as isize).is_some() {
+                    canvas.fill_rect(cell_rect(x, y)).unwrap();
+                }
+            }
+        }
+        canvas.set_draw_color(colors.accent.0);
+        let cursor = cell_rect(self.cursor.0, self.cursor.1);
+        for i in 0..(cell / 8).max(1) {
+            let mut border = cursor;
+            border.resize(cursor.width() - 2 * i, cursor.height() - 2 * i);
+            border.center_on(cursor.center());
+            canvas.draw_rect(border).unwrap();
+        }
+
+        let panel_x = board.right() + cell as i32;
+        let panel_width = (area.right() - panel_x).max(1) as u32;
+        let scale = text::fit_scale("OITLJSZ ADD PIECE", panel_width)
+            .min(cell / 8)
+            .max(1);
+        let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+        let goal = match GOALS[self.goal] {
+            Goal::ClearAll => tr("CLEAR ALL"),
+            Goal::Lines(lines) => format!("{} {}", tr("LINES"), lines),
+            // 编辑器只能选 `GOALS` 里的
+            goal => unreachable!("{:?} is not offered by the editor", goal),
+        };
+        let pieces = self
+            .pieces
+            .iter()
+            .map(|kind| format!("{:?}", kind))
+            .collect::<String>();
+        let rows = [
+            (self.name.clone(), colors.accent.0),
+            (String::new(), colors.text.0),
+            (tr("GOAL"), colors.text_dim.0),
+            (goal, colors.text.0),
+            (tr("PIECES"), colors.text_dim.0),
+            // 后面的下划线是下一块的位置
+            (format!("{}_", pieces), colors.text.0),
+            (String::new(), colors.text.0),
+        ];
+        let help = HELP.iter().map(|line| (tr(line), colors.text_dim.0));
+        for (i, (line, color)) in rows.into_iter().chain(help).enumerate() {
+            let position = Point::new(panel_x, area.y() + line_height * i as i32);
+            text::draw_text(canvas, &line, position, scale, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn painted_board_saves_as_a_puzzle() {
+        let mut editor = Editor::new("MINE".to_string());
+        assert!(editor.to_puzzle().is_err());
+        editor.navigate(Nav::Confirm);
+        editor.navigate(Nav::Up);
+        editor.navigate(Nav::Right);
+        editor.navigate(Nav::Confirm);
+        editor.push_piece(PieceKind::T);
+        editor.push_piece(PieceKind::I);
+        editor.pop_piece();
+        editor.cycle_goal();
+
+        let puzzle = Puzzle::parse(&editor.to_puzzle().unwrap().to_toml().unwrap()).unwrap();
+        assert_eq!(puzzle.name, "MINE");
+        assert_eq!(puzzle.pieces, [PieceKind::T]);
+        assert_eq!(puzzle.goal, Goal::Lines(1));
+        let matrix = puzzle.matrix();
+        assert_eq!(matrix.get(0, 0), Some(Color::Gray));
+        assert_eq!(matrix.get(1, 1), Some(Color::Gray));
+        assert_eq!(matrix.stack_height(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+use cgmath::Point2;
+use sdl2::pixels::Color;
+
+use super::CellDrawCtx;
+use crate::engine::piece::Piece;
+use crate::engine::{Color as SemanticColor, Event, Matrix};
+
+const TRAIL_FRAMES: u32 = 12;
+const TRAIL_ALPHA: u8 = 0x90;
+const LOCK_FLASH_FRAMES: u32 = 8;
+const LOCK_FLASH_ALPHA: u8 = 0xc0;
+const SHAKE_FRAMES: u32 = 12;
+// 震动幅度，按界面正方形边长的比例算
+const HARD_DROP_SHAKE: f32 = 0.004;
+const TETRIS_SHAKE: f32 = 0.012;
+
+// 硬降留下的竖直拖影，每一列从方块最上面的格子往上延伸下落的距离
+struct Trail {
+    columns: Vec<(usize, usize)>,
+    distance: usize,
+    color: SemanticColor,
+    frames_left: u32,
+}
+
+// 方块锁定的那一刻让它的格子发白，再慢慢恢复原来的颜色
+struct LockFlash {
+    cells: [Point2<usize>; Piece::CELL_COUNT],
+    frames_left: u32,
+}
+
+// 硬降和消四行时整个画面抖一下，幅度随时间衰减
+struct Shake {
+    amplitude: f32,
+    frames_left: u32,
+}
+
+// 界面上的临时特效，不影响游戏逻辑，只根据引擎的事件来播放
+#[derive(Default)]
+pub struct Effects {
+    trails: Vec<Trail>,
+    lock_flashes: Vec<LockFlash>,
+    shake: Option<Shake>,
+}
+
+impl Effects {
+    pub fn handle(&mut self, event: &Event) {
+        match event {
+            Event::HardDrop {
+                cells,
+                distance,
+                color,
+            } => {
+                if *distance == 0 {
+                    return;
+                }
+                // 落得越远抖得越厉害
+                let amount = (*distance as f32 / Matrix::HEIGHT as f32).min(1.0);
+                self.start_shake(HARD_DROP_SHAKE * (0.5 + amount));
+                // 每一列只保留最上面的那个格子
+                let mut columns: Vec<(usize, usize)> = Vec::new();
+                for cell in cells {
+                    match columns.iter_mut().find(|(x, _)| *x == cell.x) {
+                        Some((_, top)) => *top = (*top).max(cell.y),
+                        None => columns.push((cell.x, cell.y)),
+                    }
+                }
+                self.trails.push(Trail {
+                    columns,
+                    distance: *distance,
+                    color: *color,
+                    frames_left: TRAIL_FRAMES,
+                });
+            }
+            Event::Lock { cells, .. } => self.lock_flashes.push(LockFlash {
+                cells: *cells,
+                frames_left: LOCK_FLASH_FRAMES,
+            }),
+            Event::LineClear { rows, .. } if rows.len() >= 4 => self.start_shake(TETRIS_SHAKE),
+            _ => {}
+        }
+    }
+
+    // 正在抖的时候不会被更小的震动盖掉
+    fn start_shake(&mut self, amplitude: f32) {
+        if let Some(shake) = &self.shake {
+            if shake.amplitude * shake.frames_left as f32 / SHAKE_FRAMES as f32 > amplitude {
+                return;
+            }
+        }
+        self.shake = Some(Shake {
+            amplitude,
+            frames_left: SHAKE_FRAMES,
+        });
+    }
+
+    // 这一帧画面的偏移量，单位是界面边长；由 `draw` 加到布局上
+    pub fn shake_offset(&self) -> (f32, f32) {
+        let Some(shake) = &self.shake else {
+            return (0.0, 0.0);
+        };
+        let t = shake.frames_left as f32;
+        let decay = t / SHAKE_FRAMES as f32;
+        let amplitude = shake.amplitude * decay;
+        (amplitude * (t * 2.3).sin(), amplitude * (t * 3.7).cos())
+    }
+
+    pub fn update(&mut self) {
+        if let Some(shake) = &mut self.shake {
+            shake.frames_left -= 1;
+            if shake.frames_left == 0 {
+                self.shake = None;
+            }
+        }
+        for trail in &mut self.trails {
+            trail.frames_left -= 1;
+        }
+        self.trails.retain(|trail| trail.frames_left > 0);
+        for flash in &mut self.lock_flashes {
+            flash.frames_left -= 1;
+        }
+        self.lock_flashes.retain(|flash| flash.frames_left > 0);
+    }
+
+    pub fn draw_lock_flashes(&self, ctx: &mut CellDrawCtx) {
+        for flash in &self.lock_flashes {
+            let fade = flash.frames_left as f32 / LOCK_FLASH_FRAMES as f32;
+            let color = Color::RGBA(0xff, 0xff, 0xff, (LOCK_FLASH_ALPHA as f32 * fade) as u8);
+            for cell in flash.cells {
+                ctx.draw_cell_shifted(color, cell, 0.0);
+            }
+        }
+    }
+
+    pub fn draw_trails(&self, ctx: &mut CellDrawCtx) {
+        for trail in &self.trails {
+            let fade = trail.frames_left as f32 / TRAIL_FRAMES as f32;
+            let base = ctx.skin.piece_color(trail.color);
+            for &(x, top) in &trail.columns {
+                let rows = (top + 1)..(top + 1 + trail.distance).min(ctx.cells.y);
+                for (i, y) in rows.enumerate() {
+                    // 离落点越远越淡
+                    let falloff = 1.0 - i as f32 / trail.distance as f32;
+                    let alpha = (TRAIL_ALPHA as f32 * fade * falloff) as u8;
+                    let color = Color::RGBA(base.r, base.g, base.b, alpha);
+                    ctx.draw_cell_shifted(color, Point2::new(x, y), 0.0);
+                }
+            }
+        }
+    }
+}
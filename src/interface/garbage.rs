@@ -0,0 +1,55 @@
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinAssets;
+use crate::engine::garbage::{Attack, GarbageQueue};
+use crate::engine::Color as SemanticColor;
+
+// 每次攻击之间留的空隙，方便看出是几次攻击
+const SEGMENT_GAP: u32 = 2;
+
+// matrix 左边的垃圾行计量条：一行垃圾占一个格子高，从下往上按攻击先后叠起来，
+// 颜色表示离进场还有多久
+pub fn draw_garbage_meter(
+    canvas: &mut Canvas<Window>,
+    area: Rect,
+    rows: usize,
+    garbage: &GarbageQueue,
+    skin: &SkinAssets,
+) {
+    canvas.set_draw_color(skin.colors.matrix.0);
+    canvas.fill_rect(area).unwrap();
+
+    let row_height = area.height() / rows.max(1) as u32;
+    let mut bottom = area.bottom();
+    for attack in garbage.attacks() {
+        let height = (attack.lines * row_height).saturating_sub(SEGMENT_GAP);
+        // 超出计量条的部分就不画了
+        let top = (bottom - height as i32).max(area.top());
+        if top >= bottom {
+            break;
+        }
+        canvas.set_draw_color(skin.piece_color(imminence_color(attack)));
+        canvas
+            .fill_rect(Rect::new(
+                area.x(),
+                top,
+                area.width(),
+                (bottom - top) as u32,
+            ))
+            .unwrap();
+        bottom = top - SEGMENT_GAP as i32;
+    }
+}
+
+// 可以进场了是红色，过了一半延迟是橙色，刚送来的是黄色
+fn imminence_color(attack: &Attack) -> SemanticColor {
+    if attack.is_ready() {
+        SemanticColor::Red
+    } else if attack.delay <= GarbageQueue::DELAY / 2 {
+        SemanticColor::Orange
+    } else {
+        SemanticColor::Yellow
+    }
+}
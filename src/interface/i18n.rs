@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+// 语言文件目录：每个语言一个 <代码>.toml
+pub const LANG_DIR: &str = "lang";
+
+// 一种界面语言。翻译表的键就是界面上的英文原文，没翻译的直接显示原文；
+// 位图字体只有 ASCII 大写字母，翻译也只能用这些字符。
+// 同一段英文在两个地方要翻得不一样的（"MASTER" 既是模式又是总音量），
+// 其中一个的键前面加上小写的用处和一个点，比如 "volume.MASTER"
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct Language {
+    pub name: String,
+    #[serde(default)]
+    strings: HashMap<String, String>,
+}
+
+thread_local! {
+    // 当前语言的翻译表；界面只在主线程上画，不用到处传
+    static CURRENT: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+// 翻译一段界面文字；带用处前缀的没翻译的话去掉前缀显示原文
+pub fn tr(text: &str) -> String {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .get(text)
+            .cloned()
+            .unwrap_or_else(|| without_context(text).to_string())
+    })
+}
+
+fn without_context(text: &str) -> &str {
+    match text.split_once('.') {
+        Some((context, original))
+            if !context.is_empty() && context.chars().all(|c| c.is_ascii_lowercase()) =>
+        {
+            original
+        }
+        _ => text,
+    }
+}
+
+pub fn set_language(language: &Language) {
+    CURRENT.with(|current| *current.borrow_mut() = language.strings.clone());
+}
+
+impl Language {
+    fn english() -> Self {
+        Self {
+            name: "ENGLISH".to_string(),
+            strings: HashMap::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Language, String> {
+        let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        toml::from_str(&source).map_err(|err| err.to_string())
+    }
+
+    // 内置的英文加上语言目录里能读出来的所有语言
+    pub fn load_all(dir: &Path) -> Vec<Language> {
+        let mut languages = vec![Self::english()];
+        let Ok(entries) = fs::read_dir(dir) else {
+            return languages;
+        };
+        let mut files = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect::<Vec<_>>();
+        files.sort();
+        for file in files {
+            match Language::load(&file) {
+                Ok(language) => languages.push(language),
+                Err(err) => eprintln!("Failed to load language {}: {}", file.display(), err),
+            }
+        }
+        languages
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn missing_translations_fall_back_to_english() {
+        let language: Language = toml::from_str(
+            r#"
+            name = "ESPANOL"
+
+            [strings]
+            "RESUME" = "CONTINUAR"
+            "MASTER" = "MAESTRO"
+            "volume.MASTER" = "GENERAL"
+            "#,
+        )
+        .unwrap();
+        set_language(&language);
+        assert_eq!(tr("RESUME"), "CONTINUAR");
+        assert_eq!(tr("QUIT"), "QUIT");
+        assert_eq!(tr("MASTER"), "MAESTRO");
+        assert_eq!(tr("volume.MASTER"), "GENERAL");
+
+        set_language(&Language::english());
+        assert_eq!(tr("RESUME"), "RESUME");
+        assert_eq!(tr("volume.MASTER"), "MASTER");
+        assert_eq!(tr("3.5"), "3.5");
+
+        // 自带的翻译文件要能读，键重复了整个文件都读不出来
+        assert!(Language::load(&Path::new(LANG_DIR).join("es.toml")).is_ok());
+    }
+}
@@ -0,0 +1,24 @@
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::surface::Surface;
+
+const ICON_SIZE: u32 = 32;
+const CELL: u32 = ICON_SIZE / 4;
+
+// 窗口图标：透明底上画一个 T 块，不用额外带图片文件
+pub fn window_icon() -> Result<Surface<'static>, String> {
+    let mut surface = Surface::new(ICON_SIZE, ICON_SIZE, PixelFormatEnum::RGBA8888)?;
+    surface.fill_rect(None, Color::RGBA(0, 0, 0, 0))?;
+
+    let body = Color::RGB(0xa0, 0x60, 0xd0);
+    let edge = Color::RGB(0x60, 0x30, 0x90);
+    // 下面一排三格，上面中间一格，整体垂直居中
+    let cells = [(0, 2), (1, 2), (2, 2), (1, 1)];
+    for (x, y) in cells {
+        let rect = Rect::new((x * CELL + CELL / 2) as i32, (y * CELL) as i32, CELL, CELL);
+        surface.fill_rect(rect, edge)?;
+        let inner = Rect::new(rect.x() + 1, rect.y() + 1, CELL - 2, CELL - 2);
+        surface.fill_rect(inner, body)?;
+    }
+    Ok(surface)
+}
@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+
+use sdl2::keyboard::Keycode;
+
+use super::settings::Settings;
+use crate::engine::{Engine, MoveKind};
+
+// 按住左右键的自动移动（DAS / ARR）和按住下键的连续软降，按逻辑帧计时，
+// 不依赖系统的按键重复，手感在任何机器上都一样
+#[derive(Default)]
+pub struct AutoShift {
+    // 按住的方向和按住了多少帧
+    held: Option<(MoveKind, u32)>,
+    soft_drop: bool,
+}
+
+impl AutoShift {
+    pub fn press(&mut self, kind: MoveKind) {
+        self.held = Some((kind, 0));
+    }
+
+    pub fn release(&mut self, kind: MoveKind) {
+        if matches!(self.held, Some((held, _)) if held == kind) {
+            self.held = None;
+        }
+    }
+
+    pub fn set_soft_drop(&mut self, held: bool) {
+        self.soft_drop = held;
+    }
+
+    pub fn update(&mut self, engine: &mut Engine, settings: &Settings) {
+        if self.soft_drop {
+            engine.soft_drop();
+        }
+        let Some((kind, frames)) = &mut self.held else {
+            return;
+        };
+        *frames += 1;
+        if *frames < settings.das {
+            return;
+        }
+        if settings.arr == 0 {
+            while engine.cursor.is_some() && engine.auto_shift(*kind).is_ok() {}
+        } else if (*frames - settings.das).is_multiple_of(settings.arr) {
+            let _ = engine.auto_shift(*kind);
+        }
+    }
+}
+
+// 联机时可选的固定输入延迟：游戏里的按键先排着，过几帧再生效。
+// 每一下都晚同样的帧数，网络忽快忽慢手感也不变
+#[derive(Default)]
+pub struct InputDelay {
+    // 第几帧生效，哪个键，按下还是松开
+    queue: VecDeque<(u64, Keycode, bool)>,
+}
+
+impl InputDelay {
+    pub fn push(&mut self, due: u64, key: Keycode, pressed: bool) {
+        self.queue.push_back((due, key, pressed));
+    }
+
+    // 到第 `frame` 帧该生效的，按先后顺序
+    pub fn due(&mut self, frame: u64) -> Vec<(Keycode, bool)> {
+        let count = self
+            .queue
+            .iter()
+            .take_while(|&&(due, ..)| due <= frame)
+            .count();
+        self.queue
+            .drain(..count)
+            .map(|(_, key, pressed)| (key, pressed))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delayed_keys_arrive_in_order_when_due() {
+        let mut delay = InputDelay::default();
+        delay.push(3, Keycode::Left, true);
+        delay.push(4, Keycode::Left, false);
+        delay.push(4, Keycode::Space, true);
+        assert_eq!(delay.due(2), []);
+        assert_eq!(delay.due(3), [(Keycode::Left, true)]);
+        assert_eq!(
+            delay.due(5),
+            [(Keycode::Left, false), (Keycode::Space, true)]
+        );
+        delay.push(9, Keycode::Up, true);
+        delay.clear();
+        assert_eq!(delay.due(9), []);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+
+use sdl2::keyboard::Keycode;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinColors;
+use super::text;
+
+// 最近按过的键显示几个
+const HISTORY_LEN: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Button {
+    Left,
+    Right,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+impl Button {
+    const ALL: [Self; 6] = [
+        Self::Left,
+        Self::Right,
+        Self::Rotate,
+        Self::SoftDrop,
+        Self::HardDrop,
+        Self::Hold,
+    ];
+
+    fn from_key(key: Keycode) -> Option<Self> {
+        match key {
+            Keycode::Left => Some(Self::Left),
+            Keycode::Right => Some(Self::Right),
+            Keycode::Up => Some(Self::Rotate),
+            Keycode::Down => Some(Self::SoftDrop),
+            Keycode::Space => Some(Self::HardDrop),
+            Keycode::LShift | Keycode::RShift => Some(Self::Hold),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Left => "<",
+            Self::Right => ">",
+            Self::Rotate => "^",
+            Self::SoftDrop => "V",
+            Self::HardDrop => "D",
+            Self::Hold => "H",
+        }
+    }
+
+    // 在 4 x 2 的格子里的位置：左边是方向键，右边是 hold 和硬降
+    fn slot(self) -> (i32, i32) {
+        match self {
+            Self::Rotate => (1, 0),
+            Self::Left => (0, 1),
+            Self::SoftDrop => (1, 1),
+            Self::Right => (2, 1),
+            Self::Hold => (3, 0),
+            Self::HardDrop => (3, 1),
+        }
+    }
+}
+
+// 直播用的按键显示：哪些键正按着，以及最近按键的顺序，方便看 finesse
+#[derive(Default)]
+pub struct InputDisplay {
+    pressed: Vec<Button>,
+    history: VecDeque<Button>,
+}
+
+impl InputDisplay {
+    pub fn press(&mut self, key: Keycode) {
+        let Some(button) = Button::from_key(key) else {
+            return;
+        };
+        if !self.pressed.contains(&button) {
+            self.pressed.push(button);
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(button);
+    }
+
+    pub fn release(&mut self, key: Keycode) {
+        if let Some(button) = Button::from_key(key) {
+            self.pressed.retain(|&pressed| pressed != button);
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        // 上面三分之二画按键，下面一行画历史
+        let key_size = (area.width() / 5).min(area.height() * 2 / 3 / 2).max(1);
+        let gap = (key_size / 8).max(1);
+        let keys_width = key_size * 4 + gap * 3;
+        let left = area.x() + (area.width().saturating_sub(keys_width) / 2) as i32;
+        let scale = (key_size / 2 / text::GLYPH_HEIGHT).max(1);
+
+        for button in Button::ALL {
+            let (column, row) = button.slot();
+            let rect = Rect::new(
+                left + column * (key_size + gap) as i32,
+                area.y() + row * (key_size + gap) as i32,
+                key_size,
+                key_size,
+            );
+            let pressed = self.pressed.contains(&button);
+            let ink = if pressed {
+                canvas.set_draw_color(colors.accent.0);
+                canvas.fill_rect(rect).unwrap();
+                colors.background.0
+            } else {
+                canvas.set_draw_color(colors.text_dim.0);
+                canvas.draw_rect(rect).unwrap();
+                colors.text_dim.0
+            };
+            text::draw_text_centered(canvas, button.symbol(), rect.center(), scale, ink);
+        }
+
+        let history: String = self.history.iter().map(|button| button.symbol()).collect();
+        let history_y = area.y() + (key_size * 2 + gap * 2) as i32;
+        let history_scale = text::fit_scale(&"W".repeat(HISTORY_LEN), area.width()).min(scale);
+        let center = Point::new(
+            area.center().x,
+            history_y + (text::GLYPH_HEIGHT * history_scale) as i32,
+        );
+        text::draw_text_centered(canvas, &history, center, history_scale, colors.text.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tracks_held_keys_and_recent_history() {
+        let mut display = InputDisplay::default();
+        display.press(Keycode::Left);
+        display.press(Keycode::Up);
+        display.release(Keycode::Left);
+        display.press(Keycode::P);
+        assert_eq!(display.pressed, [Button::Rotate]);
+
+        for _ in 0..HISTORY_LEN {
+            display.press(Keycode::Space);
+        }
+        assert_eq!(display.history.len(), HISTORY_LEN);
+        assert!(display.history.iter().all(|&b| b == Button::HardDrop));
+    }
+}
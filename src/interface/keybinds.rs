@@ -0,0 +1,156 @@
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+
+// 单人游戏里的按键，写在配置文件的 [keybinds] 里，值是键名："LEFT"、"SPACE"、"Z" 这样；本地对战里改过的键归左边的玩家，和右边玩家撞了的除外。
+// 界面里的逻辑还是按默认的键写的，按下的键先换成默认键（`translate`）再交给它
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybinds {
+    #[serde(with = "key_name")]
+    pub left: Keycode,
+    #[serde(with = "key_name")]
+    pub right: Keycode,
+    #[serde(with = "key_name")]
+    pub rotate: Keycode,
+    #[serde(with = "key_name")]
+    pub soft_drop: Keycode,
+    #[serde(with = "key_name")]
+    pub hard_drop: Keycode,
+    // 两个 Shift 默认都是 hold
+    #[serde(with = "key_name")]
+    pub hold: Keycode,
+    #[serde(with = "key_name")]
+    pub alt_hold: Keycode,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self {
+            left: Keycode::Left,
+            right: Keycode::Right,
+            rotate: Keycode::Up,
+            soft_drop: Keycode::Down,
+            hard_drop: Keycode::Space,
+            hold: Keycode::LShift,
+            alt_hold: Keycode::RShift,
+        }
+    }
+}
+
+impl Keybinds {
+    // 绑了动作的键换成那个动作的默认键；默认键改绑了别的以后就什么都不做，别的键原样返回
+    pub fn translate(&self, key: Keycode) -> Option<Keycode> {
+        let defaults = Keybinds::default();
+        let actions = [
+            (self.left, defaults.left),
+            (self.right, defaults.right),
+            (self.rotate, defaults.rotate),
+            (self.soft_drop, defaults.soft_drop),
+            (self.hard_drop, defaults.hard_drop),
+            (self.hold, defaults.hold),
+            (self.alt_hold, defaults.hold),
+        ];
+        if let Some(&(_, action)) = actions.iter().find(|&&(bound, _)| bound == key) {
+            return Some(action);
+        }
+        if defaults.keys().contains(&key) {
+            return None;
+        }
+        Some(key)
+    }
+
+    fn keys(&self) -> [Keycode; 7] {
+        [
+            self.left,
+            self.right,
+            self.rotate,
+            self.soft_drop,
+            self.hard_drop,
+            self.hold,
+            self.alt_hold,
+        ]
+    }
+}
+
+// 字母和数字就是那个字符，别的查表；不认得的键名读配置的时候报错
+const NAMES: [(Keycode, &str); 18] = [
+    (Keycode::Left, "LEFT"),
+    (Keycode::Right, "RIGHT"),
+    (Keycode::Up, "UP"),
+    (Keycode::Down, "DOWN"),
+    (Keycode::Space, "SPACE"),
+    (Keycode::Return, "RETURN"),
+    (Keycode::Backspace, "BACKSPACE"),
+    (Keycode::LShift, "LSHIFT"),
+    (Keycode::RShift, "RSHIFT"),
+    (Keycode::LCtrl, "LCTRL"),
+    (Keycode::RCtrl, "RCTRL"),
+    (Keycode::LAlt, "LALT"),
+    (Keycode::RAlt, "RALT"),
+    (Keycode::Comma, "COMMA"),
+    (Keycode::Period, "PERIOD"),
+    (Keycode::Slash, "SLASH"),
+    (Keycode::Semicolon, "SEMICOLON"),
+    (Keycode::Quote, "QUOTE"),
+];
+
+pub fn name(key: Keycode) -> Option<String> {
+    if let Some(&(_, name)) = NAMES.iter().find(|&&(named, _)| named == key) {
+        return Some(name.to_string());
+    }
+    char::from_u32(key as u32)
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase().to_string())
+}
+
+pub fn from_name(name: &str) -> Option<Keycode> {
+    let name = name.to_ascii_uppercase();
+    if let Some(&(key, _)) = NAMES.iter().find(|&&(_, named)| named == name) {
+        return Some(key);
+    }
+    match name.chars().collect::<Vec<_>>()[..] {
+        [c] if c.is_ascii_alphanumeric() => Keycode::from_i32(c.to_ascii_lowercase() as i32),
+        _ => None,
+    }
+}
+
+mod key_name {
+    use sdl2::keyboard::Keycode;
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &Keycode, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = super::name(*key)
+            .ok_or_else(|| ser::Error::custom(format!("key {:?} has no name", key)))?;
+        serializer.serialize_str(&name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Keycode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::from_name(&name).ok_or_else(|| de::Error::custom(format!("unknown key {:?}", name)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rebound_keys_stand_in_for_the_defaults() {
+        let keybinds = Keybinds {
+            left: Keycode::J,
+            rotate: Keycode::X,
+            ..Keybinds::default()
+        };
+        assert_eq!(keybinds.translate(Keycode::J), Some(Keycode::Left));
+        assert_eq!(keybinds.translate(Keycode::X), Some(Keycode::Up));
+        assert_eq!(keybinds.translate(Keycode::RShift), Some(Keycode::LShift));
+        // 原来的键不再动方块，别的键照旧
+        assert_eq!(keybinds.translate(Keycode::Left), None);
+        assert_eq!(keybinds.translate(Keycode::P), Some(Keycode::P));
+
+        assert_eq!(name(Keycode::Num7).as_deref(), Some("7"));
+        assert_eq!(from_name("lshift"), Some(Keycode::LShift));
+        assert_eq!(from_name("z"), Some(Keycode::Z));
+        assert_eq!(from_name("F13"), None);
+    }
+}
@@ -0,0 +1,241 @@
+use sdl2::rect::Rect;
+
+// 界面上各个区域的位置，都是从一个正方形推算出来的
+pub struct Layout {
+    pub matrix: Rect,
+    pub up_next: Rect,
+    pub next_queue: Rect,
+    pub hold: Rect,
+    pub score: Rect,
+    // 连击计量条，在分数面板的下面三分之一
+    pub combo: Rect,
+    // 实时统计，在分数面板的中间三分之一
+    pub stats: Rect,
+    // 垃圾行计量条，贴在 matrix 左边，半个格子宽
+    pub garbage_meter: Rect,
+    // 按键显示，在分数面板下面的空白里
+    pub inputs: Rect,
+}
+
+// 在窗口中间取最大的正方形，多出来的部分留空（letterbox），
+// 这样窗口不是正方形的时候，各区域的比例也不会变形
+pub fn letterbox(viewport: Rect) -> Rect {
+    let side = viewport.width().min(viewport.height());
+    let mut square = Rect::new(0, 0, side, side);
+    square.center_on(viewport.center());
+    square
+}
+
+// next 面板最多放几个方块（第一个在 up_next 里，不算在内）
+const NEXT_QUEUE_SLOTS: u32 = 5;
+
+// 窗口里放几块棋盘、怎么放
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Arrangement {
+    #[default]
+    Single,
+    // 玩家占左边三分之二，观战的棋盘在右边
+    Spectator,
+    // 本地对战，两块棋盘各占一半
+    SplitScreen,
+}
+
+// 每块棋盘的正方形，第一个是玩家自己的
+pub fn board_squares(viewport: Rect, arrangement: Arrangement) -> Vec<Rect> {
+    let first_width = match arrangement {
+        Arrangement::Single => return vec![letterbox(viewport)],
+        Arrangement::Spectator => viewport.width() * 2 / 3,
+        Arrangement::SplitScreen => viewport.width() / 2,
+    };
+    let first = Rect::new(viewport.x(), viewport.y(), first_width, viewport.height());
+    let second = Rect::new(
+        viewport.x() + first_width as i32,
+        viewport.y(),
+        viewport.width() - first_width,
+        viewport.height(),
+    );
+    vec![letterbox(first), letterbox(second)]
+}
+
+impl Layout {
+    // `previews` 是要显示几个下一个方块，next 面板按数量缩短；
+    // `cells` 是 matrix 的列数和行数，格子保持正方形
+    pub fn new(ui_square: Rect, previews: usize, cells: (usize, usize)) -> Self {
+        let (columns, rows) = (cells.0.max(1) as u32, cells.1.max(1) as u32);
+        let matrix = {
+            let mut middle_section = ui_square;
+            middle_section.set_width(middle_section.width() / 2);
+            middle_section.center_on(ui_square.center());
+
+            // 在中间区域留 1/8 的边，剩下的地方放得下的最大的格子
+            let cell = (middle_section.width() * 7 / 8 / columns)
+                .min(middle_section.height() * 7 / 8 / rows)
+                .max(1);
+            let mut matrix = middle_section;
+            matrix.resize(cell * columns, cell * rows);
+            matrix.center_on(middle_section.center());
+
+            matrix
+        };
+        let width_ui_quarter = ui_square.width() / 4;
+        let height_ui_quarter = ui_square.height() / 4;
+        let up_next = {
+            let mut outer = ui_square;
+            outer.resize(width_ui_quarter, height_ui_quarter);
+            outer.offset(3 * width_ui_quarter as i32, 0);
+
+            let mut inner = outer;
+            inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
+            inner.center_on(outer.center());
+
+            inner
+        };
+        let next_queue = {
+            let mut outer = ui_square;
+            outer.resize(width_ui_quarter, 3 * height_ui_quarter);
+            outer.offset(3 * width_ui_quarter as i32, height_ui_quarter as i32);
+
+            let mut inner = outer;
+            inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
+            inner.center_on(outer.center());
+
+            // 从上往下排，多余的部分不要
+            let slots = (previews as u32).saturating_sub(1).min(NEXT_QUEUE_SLOTS);
+            inner.set_height(inner.height() * slots / NEXT_QUEUE_SLOTS);
+
+            inner
+        };
+        let hold = {
+            let mut outer = ui_square;
+            outer.resize(width_ui_quarter, height_ui_quarter);
+
+            let mut inner = outer;
+            inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
+            inner.center_on(outer.center());
+
+            inner
+        };
+        let score = {
+            let mut outer = ui_square;
+            outer.resize(width_ui_quarter, 3 * height_ui_quarter);
+            outer.offset(0, height_ui_quarter as i32);
+
+            let mut inner = outer;
+            inner.resize(outer.width() * 7 / 8, outer.height() * 3 / 4);
+            inner.center_on(outer.center());
+
+            inner
+        };
+
+        let combo = {
+            let mut outer = score;
+            outer.set_height(score.height() / 3);
+            outer.set_y(score.bottom() - outer.height() as i32);
+
+            let mut inner = outer;
+            inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
+            inner.center_on(outer.center());
+
+            inner
+        };
+
+        let stats = {
+            let mut stats = score;
+            stats.set_height(score.height() / 3);
+            stats.center_on(score.center());
+
+            stats
+        };
+
+        let inputs = {
+            let gap = (ui_square.bottom() - score.bottom()).max(1) as u32;
+            let mut inputs = Rect::new(score.x(), score.bottom(), score.width(), gap);
+            inputs.resize(score.width(), gap * 3 / 4);
+            inputs.center_on(Rect::new(score.x(), score.bottom(), score.width(), gap).center());
+
+            inputs
+        };
+
+        let garbage_meter = {
+            let cell_width = matrix.width() / columns;
+            let mut meter = matrix;
+            meter.set_width((cell_width / 2).max(1));
+            meter.set_x(matrix.left() - (cell_width * 3 / 4) as i32);
+
+            meter
+        };
+
+        Self {
+            matrix,
+            up_next,
+            next_queue,
+            hold,
+            score,
+            combo,
+            stats,
+            garbage_meter,
+            inputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn letterbox_keeps_square_centered() {
+        let square = letterbox(Rect::new(0, 0, 1280, 720));
+        assert_eq!(square, Rect::new(280, 0, 720, 720));
+
+        let square = letterbox(Rect::new(0, 0, 600, 900));
+        assert_eq!(square, Rect::new(0, 150, 600, 600));
+    }
+
+    #[test]
+    fn split_screen_boards_do_not_overlap() {
+        let squares = board_squares(Rect::new(0, 0, 1600, 900), Arrangement::SplitScreen);
+        assert_eq!(squares.len(), 2);
+        assert_eq!(squares[0].width(), squares[1].width());
+        assert!(squares[0].right() <= squares[1].left());
+
+        let squares = board_squares(Rect::new(0, 0, 1600, 900), Arrangement::Spectator);
+        assert!(squares[0].width() > squares[1].width());
+    }
+
+    #[test]
+    fn matrix_cells_stay_square() {
+        let layout = Layout::new(letterbox(Rect::new(0, 0, 1280, 720)), 5, (10, 20));
+        assert_eq!(layout.matrix.width() * 2, layout.matrix.height());
+    }
+
+    #[test]
+    fn custom_matrix_sizes_keep_square_cells() {
+        let square = letterbox(Rect::new(0, 0, 1280, 720));
+        for (columns, rows) in [(4, 20), (10, 40), (20, 20)] {
+            let matrix = Layout::new(square, 5, (columns, rows)).matrix;
+            assert_eq!(
+                matrix.width() / columns as u32,
+                matrix.height() / rows as u32
+            );
+            assert!(matrix.width() <= square.width() / 2);
+            assert!(matrix.height() <= square.height());
+        }
+    }
+
+    #[test]
+    fn next_queue_shrinks_with_previews() {
+        let square = letterbox(Rect::new(0, 0, 1280, 720));
+        let full = Layout::new(square, 6, (10, 20)).next_queue;
+        let three = Layout::new(square, 3, (10, 20)).next_queue;
+        assert_eq!(three.top(), full.top());
+        assert_eq!(three.height(), full.height() * 2 / 5);
+    }
+
+    #[test]
+    fn garbage_meter_sits_left_of_matrix() {
+        let layout = Layout::new(letterbox(Rect::new(0, 0, 1280, 720)), 5, (10, 20));
+        assert!(layout.garbage_meter.right() < layout.matrix.left());
+        assert_eq!(layout.garbage_meter.height(), layout.matrix.height());
+    }
+}
@@ -0,0 +1,69 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::nav::{Focus, Nav};
+use super::skin::SkinColors;
+use super::text;
+
+// 一个竖排的选项列表，上下切换，确认后返回选中的那项
+// 标签画的时候才翻译，读进来的名字（比如谜题）没有翻译就原样显示
+pub struct Menu<T> {
+    entries: Vec<(String, T)>,
+    focus: Focus,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(entries: Vec<(impl Into<String>, T)>) -> Self {
+        Self {
+            focus: Focus::new(entries.len()),
+            entries: entries
+                .into_iter()
+                .map(|(label, value)| (label.into(), value))
+                .collect(),
+        }
+    }
+
+    pub fn selected(&self) -> T {
+        self.entries[self.focus.index()].1
+    }
+
+    // 返回确认了的选项；返回键由各个界面自己决定去哪
+    pub fn navigate(&mut self, nav: Nav) -> Option<T> {
+        match nav {
+            Nav::Up => self.focus.up(),
+            Nav::Down => self.focus.down(),
+            Nav::Confirm => return Some(self.selected()),
+            Nav::Left | Nav::Right | Nav::Back => {}
+        }
+        None
+    }
+
+    // 在 area 里居中画出所有选项，选中的那一项前面加上 `>`
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        let labels = self
+            .entries
+            .iter()
+            .map(|(label, _)| tr(label))
+            .collect::<Vec<_>>();
+        let longest = labels
+            .iter()
+            .map(|label| label.len() + 4)
+            .max()
+            .unwrap_or(1);
+        let scale = (area.width() / (longest as u32 * 6)).clamp(1, 6);
+        let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+        let first_y = area.center().y - line_height * (self.entries.len() as i32 - 1) / 2;
+
+        for (i, label) in labels.iter().enumerate() {
+            let center = Point::new(area.center().x, first_y + line_height * i as i32);
+            if i == self.focus.index() {
+                let label = format!("> {} <", label);
+                text::draw_text_centered(canvas, &label, center, scale, colors.accent.0);
+            } else {
+                text::draw_text_centered(canvas, label, center, scale, colors.text_dim.0);
+            }
+        }
+    }
+}
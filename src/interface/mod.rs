@@ -1,39 +1,430 @@
+use crate::engine::battle;
+use crate::engine::bot::Driver;
+use crate::engine::campaign::STAGES;
+use crate::engine::custom;
+use crate::engine::export::GameData;
+use crate::engine::finesse;
+use crate::engine::game_mode;
+use crate::engine::mode::Mode;
+use crate::engine::piece::{Kind as PieceKind, Piece, Rotation};
+use crate::engine::puzzle::Puzzle;
+use crate::engine::replay::{self, Action};
 use crate::engine::Color as SemanticColor;
-use crate::engine::{Engine, Matrix, MoveKind};
+use crate::engine::{Engine, Matrix, MoveKind, Stats};
+use crate::export::{self, EXPORT_DIR};
+use crate::net::discovery::Scanner;
+use crate::net::{self, LobbyClient, Pending, Session};
+use crate::replays::{self, Playback, ReplayRecorder, REPLAY_DIR};
+use audio_bus::{AudioBus, AudioCommand};
+use board::Board;
+use campaign::{CampaignProgress, CAMPAIGN_FILE};
 use cgmath::{Point2, Vector2};
+use chat::ChatEntry;
+use clip::ClipRecorder;
+use countdown::Countdown;
+use daily::{DailyEntry, DailyResults, DAILY_FILE};
+use editor::Editor;
+use i18n::{tr, Language, LANG_DIR};
+use input::{AutoShift, InputDelay};
+use input_display::InputDisplay;
+use keybinds::Keybinds;
+use layout::{Arrangement, Layout};
+use menu::Menu;
+use modes::MODE_DIR;
+use music::{MusicState, Track};
+use nav::Nav;
+use online::AddressEntry;
+use options::{Choices, OptionsMenu};
+use pace::{Pace, PaceRecords, PACE_FILE};
+use perf::PerfStats;
+#[cfg(feature = "discord")]
+use presence::{Activity, Presence};
+use profiles::{ProfileScreen, Profiles, PROFILES_FILE};
+use puzzles::{Progress, PROGRESS_FILE, PUZZLE_DIR};
+use replay_browser::ReplayBrowser;
+use review::{Exit, Review};
+use scores::{Entry, HighScores, Leaderboard, NameEntry, SCORES_FILE};
+use sdl2::controller::{Button, GameController};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
 use sdl2::pixels::Color as SdlColor;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use std::time::Duration;
+use sdl2::render::{BlendMode, Canvas, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext};
+use settings::{config_path, GridStyle, Settings};
+use skin::{Links, Skin, SkinAssets, SKIN_DIR};
+use sound_pack::{SoundPack, SOUND_DIR};
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use toast::Toasts;
+use tournament::{Registration, Tournament};
+use versus::Versus;
+
+mod audio;
+mod audio_bus;
+mod background;
+mod board;
+mod campaign;
+mod chat;
+mod clip;
+mod combo;
+mod countdown;
+mod daily;
+mod danger;
+mod debug;
+mod editor;
+mod effects;
+mod garbage;
+mod i18n;
+mod icon;
+mod input;
+mod input_display;
+mod keybinds;
+mod layout;
+mod menu;
+mod modes;
+mod music;
+mod nav;
+mod nes;
+mod online;
+mod options;
+mod pace;
+mod palette;
+mod perf;
+#[cfg(feature = "discord")]
+mod presence;
+mod profiles;
+mod puzzles;
+mod replay_browser;
+mod review;
+mod scores;
+mod settings;
+mod sidebar;
+mod skin;
+mod sound_pack;
+mod text;
+mod toast;
+mod tournament;
+mod versus;
 
 pub struct Interface {
-    engine: Engine,
+    board: Board,
+    // 另一块棋盘：观战的（远程玩家或者 AI），或者本地对战的二号玩家
+    second_board: Option<Board>,
+    arrangement: Arrangement,
+    screen: Screen,
+    countdown: Option<Countdown>,
+    auto_shift: AutoShift,
+    input_delay: InputDelay,
+    input_display: InputDisplay,
+    settings: Settings,
+    // 这一帧刚从配置文件重新读了设置，不用再写回去
+    settings_reloaded: bool,
+    skins: Vec<Skin>,
+    languages: Vec<Language>,
+    sound_packs: Vec<SoundPack>,
+    perf: PerfStats,
+    // F2 打开的引擎调试面板，不存进配置
+    debug_overlay: bool,
+    clip: ClipRecorder,
+    // 音频线程，窗口建好之后才启动
+    audio: Option<AudioBus>,
+    // Discord 上显示在玩什么，没配应用 ID 是 None
+    #[cfg(feature = "discord")]
+    presence: Option<Presence>,
+    toasts: Toasts,
+    high_scores: HighScores,
+    profiles: Profiles,
+    puzzles: Vec<Puzzle>,
+    puzzle_progress: Progress,
+    daily_results: DailyResults,
+    pace_records: PaceRecords,
+    campaign: CampaignProgress,
+    // 正在玩的是闯关的第几关，重来的时候还是这关
+    stage: Option<usize>,
+    // 正在玩的是第几道谜题，重来的时候还是这道
+    puzzle: Option<usize>,
+    // 正在进行的淘汰赛，每一盘打完回对阵表
+    tournament: Option<Tournament>,
+    // 本地双人对战，第二个玩家在 `second_board` 上
+    versus: Option<Versus>,
+    // 联机对战，对手在 `second_board` 上重放
+    online: Option<Session>,
+    // 上次输入的对手地址
+    peer_address: String,
+    // 开着的聊天框
+    chat_entry: Option<ChatEntry>,
+    // 正在录的这一局；谜题、闯关和对战不录
+    replay: Option<ReplayRecorder>,
+    // 主菜单上多久没按键了，久了就放电脑自己玩的演示
+    idle_frames: u64,
+    demo: Option<Driver>,
+    // 演示里上一帧的事件，下一帧交给电脑玩家
+    demo_events: Vec<crate::engine::Event>,
+    // 训练数据文件，打开设置之后第一次锁定才打开
+    samples: Option<File>,
+    // 上次输入的名字，下次上榜时直接填好
+    player_name: String,
+    // 逻辑帧计数，菜单里也在走，背景动画用
+    frame: u64,
+    // 上一个逻辑帧之后又过了多少，按一帧算，0 到 1，渲染插值用
+    alpha: f32,
+    quit: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuAction {
+    Play(Mode),
+    // 第几道谜题
+    Puzzle(usize),
+    Puzzles,
+    Editor,
+    // 闯关的第几关
+    Stage(usize),
+    Campaign,
+    Tournament,
+    Versus,
+    Online,
+    Host,
+    Join,
+    // 连大厅服务器，和在房间列表里选的
+    Lobbies,
+    QuickMatch,
+    CreateLobby,
+    JoinLobby(u32),
+    Refresh,
+    // 局域网里听到的房间
+    LocalGames,
+    JoinLocal(SocketAddr),
+    Retry,
+    Resume,
+    Settings,
+    // 重新读配置文件，改了不用重启
+    ReloadConfig,
+    HighScores,
+    Replays,
+    Profiles,
+    // 一块一块看刚打完的这一局
+    Review,
+    // 把刚打完的这一局写成 CSV 和 JSON
+    Export,
+    MainMenu,
+    Quit,
+}
+
+enum Screen {
+    MainMenu(Menu<MenuAction>),
+    Playing,
+    Paused(Menu<MenuAction>),
+    Settings(OptionsMenu),
+    NameEntry(NameEntry),
+    HighScores(Leaderboard),
+    Profiles(ProfileScreen),
+    Replays(ReplayBrowser),
+    // 放录像，按任意键回录像列表
+    Watch(Playback),
+    GameOver(Menu<MenuAction>),
+    Puzzles(Menu<MenuAction>),
+    Campaign(Menu<MenuAction>),
+    Registration(Registration),
+    Tournament,
+    Online(Menu<MenuAction>),
+    Join(AddressEntry),
+    Connecting(Pending),
+    Lobbies(LobbyClient, Menu<MenuAction>),
+    LocalGames(Scanner, Menu<MenuAction>),
+    Review(Review),
+    // 主菜单闲着的时候电脑自己玩，按任意键回主菜单
+    Demo,
+    // 编辑器里带着一整块棋盘，装箱免得所有界面都跟着变大
+    Editor(Box<Editor>),
+}
+
+impl Screen {
+    fn main_menu() -> Self {
+        let modes = game_mode::all()
+            .into_iter()
+            .filter(|game| game.selectable())
+            .map(|game| (game.name(), MenuAction::Play(game.mode())));
+        let rest = [
+            ("CAMPAIGN", MenuAction::Campaign),
+            ("TOURNAMENT", MenuAction::Tournament),
+            ("VERSUS", MenuAction::Versus),
+            ("ONLINE", MenuAction::Online),
+            ("PUZZLES", MenuAction::Puzzles),
+            ("HIGH SCORES", MenuAction::HighScores),
+            ("REPLAYS", MenuAction::Replays),
+            ("PROFILES", MenuAction::Profiles),
+            ("SETTINGS", MenuAction::Settings),
+            ("QUIT", MenuAction::Quit),
+        ];
+        Screen::MainMenu(Menu::new(modes.chain(rest).collect()))
+    }
+
+    fn paused() -> Self {
+        Screen::Paused(Menu::new(vec![
+            ("RESUME", MenuAction::Resume),
+            ("SETTINGS", MenuAction::Settings),
+            ("RELOAD CONFIG", MenuAction::ReloadConfig),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
+
+    fn game_over() -> Self {
+        Screen::GameOver(Menu::new(vec![
+            ("RETRY", MenuAction::Retry),
+            ("HIGH SCORES", MenuAction::HighScores),
+            ("REVIEW", MenuAction::Review),
+            ("EXPORT", MenuAction::Export),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
+
+    // 对战打完了可以接着再来一局
+    fn versus_over() -> Self {
+        Screen::GameOver(Menu::new(vec![
+            ("REMATCH", MenuAction::Versus),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
+
+    fn online() -> Self {
+        Screen::Online(Menu::new(vec![
+            ("HOST GAME", MenuAction::Host),
+            ("LOCAL GAMES", MenuAction::LocalGames),
+            ("JOIN GAME", MenuAction::Join),
+            ("LOBBY SERVER", MenuAction::Lobbies),
+            ("BACK", MenuAction::MainMenu),
+        ]))
+    }
+
+    // 大厅服务器上等人的房间，每个后面写几个人
+    fn lobbies(client: LobbyClient) -> Self {
+        let lobbies: Vec<_> = client
+            .lobbies()
+            .iter()
+            .map(|lobby| {
+                let label = format!("{} {}/2", lobby.name, lobby.players);
+                (label, MenuAction::JoinLobby(lobby.id))
+            })
+            .collect();
+        let entries = [
+            ("QUICK MATCH".to_string(), MenuAction::QuickMatch),
+            ("CREATE LOBBY".to_string(), MenuAction::CreateLobby),
+        ]
+        .into_iter()
+        .chain(lobbies)
+        .chain([
+            ("REFRESH".to_string(), MenuAction::Refresh),
+            ("BACK".to_string(), MenuAction::Online),
+        ])
+        .collect();
+        Screen::Lobbies(client, Menu::new(entries))
+    }
+
+    // 局域网里正在等人的房间，名字后面是地址；一个都没有的时候显示还在找
+    fn local_games(scanner: Scanner) -> Self {
+        let mut entries: Vec<_> = scanner
+            .games()
+            .iter()
+            .map(|game| {
+                let label = format!("{} {}", game.name, game.address.ip());
+                (label, MenuAction::JoinLocal(game.address))
+            })
+            .collect();
+        if entries.is_empty() {
+            entries.push(("SEARCHING".to_string(), MenuAction::Refresh));
+        }
+        entries.push(("BACK".to_string(), MenuAction::Online));
+        Screen::LocalGames(scanner, Menu::new(entries))
+    }
+
+    // 联机打完了连接就断了，要再来得重新开房
+    fn online_over() -> Self {
+        Screen::GameOver(Menu::new(vec![
+            ("ONLINE", MenuAction::Online),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
+
+    // 谜题没有排行榜，结束之后回选题
+    fn puzzle_over() -> Self {
+        Screen::GameOver(Menu::new(vec![
+            ("RETRY", MenuAction::Retry),
+            ("PUZZLES", MenuAction::Puzzles),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
+
+    fn puzzles(puzzles: &[Puzzle], progress: &Progress) -> Self {
+        let mut entries = puzzles
+            .iter()
+            .enumerate()
+            .map(|(index, puzzle)| (puzzles::label(puzzle, progress), MenuAction::Puzzle(index)))
+            .collect::<Vec<_>>();
+        entries.push(("NEW PUZZLE".to_string(), MenuAction::Editor));
+        entries.push(("BACK".to_string(), MenuAction::MainMenu));
+        Screen::Puzzles(Menu::new(entries))
+    }
+
+    fn campaign(progress: &CampaignProgress) -> Self {
+        let mut entries = STAGES
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| {
+                (
+                    campaign::label(index, stage, progress),
+                    MenuAction::Stage(index),
+                )
+            })
+            .collect::<Vec<_>>();
+        entries.push(("BACK".to_string(), MenuAction::MainMenu));
+        Screen::Campaign(Menu::new(entries))
+    }
+
+    // 闯关也不上排行榜，结束之后回选关
+    fn stage_over() -> Self {
+        Screen::GameOver(Menu::new(vec![
+            ("RETRY", MenuAction::Retry),
+            ("CAMPAIGN", MenuAction::Campaign),
+            ("BACK TO MENU", MenuAction::MainMenu),
+        ]))
+    }
 }
 
 const INIT_SIZE: Vector2<u32> = Vector2::new(1024, 1024);
-const BACKGROUND_COLOR: Color = Color::RGB(0x10, 0x10, 0x18);
-const MATRIX_COLOR: Color = Color::RGB(0x80, 0x75, 0xbf);
 const WINDOW_TITLE: &str = "Tetris";
+// 游戏逻辑固定每秒更新 60 次，和引擎里按帧计数的时间一致
+const TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / Stats::FRAMES_PER_SECOND);
+const MAX_UPDATES_PER_FRAME: u32 = 5;
+// 离拍子多近的锁定算踩上了，前后各算一点
+const BEAT_WINDOW: Duration = Duration::from_millis(80);
+// 训练数据一直往后追加，不同的几局混在一起
+const SAMPLES_FILE: &str = "samples.jsonl";
+// 主菜单闲置多久开始演示
+const DEMO_IDLE_FRAMES: u64 = 20 * Stats::FRAMES_PER_SECOND;
 
 // when drawing with the SDL2, the (0, 0) coordinates are at the top-left of a window,
 // not at the bottom-left. The same goes for all shapes.
 
 impl Interface {
-    pub fn run(mut engine: Engine) {
-
+    pub fn run() {
         let sdl_context = sdl2::init().expect("Failed to initialize SDL2");
         let video_subsystem = sdl_context.video().expect("Failed to acquire display");
 
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window(WINDOW_TITLE, INIT_SIZE.x, INIT_SIZE.y)
             .position_centered()
             .resizable()
             .build()
             .expect("Failed to create window");
+        match icon::window_icon() {
+            Ok(icon) => window.set_icon(icon),
+            Err(err) => eprintln!("Failed to create window icon: {}", err),
+        }
 
         let mut canvas = window
             .into_canvas()
@@ -41,8 +432,115 @@ impl Interface {
             .present_vsync()
             .build()
             .expect("Failed to get render canvas");
+        // 结算界面的半透明遮罩需要 alpha 混合
+        canvas.set_blend_mode(BlendMode::Blend);
+
+        // 主菜单要列出自定义模式，得在建界面之前读
+        custom::register(modes::load_all(Path::new(MODE_DIR)));
+        let mut interface = Interface {
+            board: Board::new(Mode::default()),
+            second_board: None,
+            arrangement: Arrangement::Single,
+            screen: Screen::main_menu(),
+            countdown: None,
+            auto_shift: AutoShift::default(),
+            input_delay: InputDelay::default(),
+            input_display: InputDisplay::default(),
+            settings: Settings::load_or_create(&config_path()).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", config_path().display(), err);
+                Settings::default()
+            }),
+            settings_reloaded: false,
+            skins: Skin::load_all(Path::new(SKIN_DIR)),
+            languages: Language::load_all(Path::new(LANG_DIR)),
+            sound_packs: SoundPack::load_all(Path::new(SOUND_DIR)),
+            perf: PerfStats::default(),
+            debug_overlay: false,
+            clip: ClipRecorder::default(),
+            audio: None,
+            #[cfg(feature = "discord")]
+            presence: Presence::spawn(),
+            toasts: Toasts::default(),
+            high_scores: HighScores::load(Path::new(SCORES_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", SCORES_FILE, err);
+                HighScores::default()
+            }),
+            profiles: Profiles::load(Path::new(PROFILES_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", PROFILES_FILE, err);
+                Profiles::default()
+            }),
+            puzzles: puzzles::load_all(Path::new(PUZZLE_DIR)),
+            puzzle_progress: Progress::load(Path::new(PROGRESS_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", PROGRESS_FILE, err);
+                Progress::default()
+            }),
+            daily_results: DailyResults::load(Path::new(DAILY_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", DAILY_FILE, err);
+                DailyResults::default()
+            }),
+            pace_records: PaceRecords::load(Path::new(PACE_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", PACE_FILE, err);
+                PaceRecords::default()
+            }),
+            campaign: CampaignProgress::load(Path::new(CAMPAIGN_FILE)).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", CAMPAIGN_FILE, err);
+                CampaignProgress::default()
+            }),
+            stage: None,
+            puzzle: None,
+            tournament: None,
+            versus: None,
+            online: None,
+            peer_address: String::new(),
+            chat_entry: None,
+            replay: None,
+            idle_frames: 0,
+            demo: None,
+            demo_events: Vec::new(),
+            samples: None,
+            player_name: String::new(),
+            frame: 0,
+            alpha: 0.0,
+            quit: false,
+        };
+
+        // 选了档案的话用档案自己的设置
+        if let Some(profile) = interface.profiles.current() {
+            interface.settings = profile.settings;
+            interface.player_name = profile.name.clone();
+        }
+        // 皮肤目录可能变了，保存的下标不一定还有效
+        interface.settings.skin = interface.settings.skin.min(interface.skins.len() - 1);
+        interface.settings.language = interface
+            .settings
+            .language
+            .min(interface.languages.len() - 1);
+        i18n::set_language(&interface.languages[interface.settings.language]);
+        interface.settings.sound_pack = interface
+            .settings
+            .sound_pack
+            .min(interface.sound_packs.len() - 1);
+        interface.audio = Some(AudioBus::spawn(
+            interface.sound_packs[interface.settings.sound_pack].clone(),
+            interface.settings,
+        ));
+        let texture_creator = canvas.texture_creator();
+        // 皮肤和配色相关的设置变了才需要重新加载贴图
+        let mut loaded_settings = interface.settings;
+        let mut skin_assets = interface.load_skin(&texture_creator);
+
+        // 没有手柄子系统也照样能用键盘玩
+        let controller_subsystem = sdl_context
+            .game_controller()
+            .map_err(|err| eprintln!("Failed to initialize game controllers: {}", err))
+            .ok();
+        // 打开的手柄要一直留着，drop 了就收不到它的事件
+        let mut controllers: Vec<GameController> = Vec::new();
 
         let mut event_pump = sdl_context.event_pump().expect("Failed to get event loop");
+        // 固定步长更新 + 尽量快地渲染：渲染卡顿或者显示器刷新率不同，都不影响游戏逻辑的速度
+        let mut previous = Instant::now();
+        let mut accumulator = Duration::ZERO;
         'running: loop {
             for event in event_pump.poll_iter() {
                 match event {
@@ -51,170 +549,2360 @@ impl Interface {
                         keycode: Some(Keycode::Escape),
                         ..
                     } => break 'running,
+                    // 按住不放的重复由 AutoShift 按帧处理，不用系统的按键重复
                     Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } => interface.handle_key(key),
+                    Event::KeyUp {
                         keycode: Some(key), ..
-                    } => match key {
-                        Keycode::Right => engine.move_cursor(MoveKind::Right).unwrap(),
-                        Keycode::Left => engine.move_cursor(MoveKind::Left).unwrap(),
-                        // hard_drop
-                        Keycode::Space => engine.hard_drop(),
-                        // rotate
-                        Keycode::Up => {
-                            engine.rotate_clockwise();
-                            dbg!(engine.cursor);
+                    } => interface.handle_key_up(key),
+                    // 启动时已经插着的手柄也会发这个事件
+                    Event::ControllerDeviceAdded { which, .. } => {
+                        if let Some(subsystem) = &controller_subsystem {
+                            match subsystem.open(which) {
+                                Ok(controller) => {
+                                    controllers.push(controller);
+                                    interface.toasts.push("CONTROLLER CONNECTED");
+                                }
+                                Err(err) => eprintln!("Failed to open controller: {}", err),
+                            }
                         }
-                        // soft drop
-                        Keycode::Down => {}
+                    }
+                    Event::ControllerDeviceRemoved { which, .. } => {
+                        controllers.retain(|controller| controller.instance_id() != which);
+                        interface.toasts.push("CONTROLLER DISCONNECTED");
+                    }
+                    Event::ControllerButtonDown { button, .. } => interface.handle_button(button),
+                    Event::ControllerButtonUp { button, .. } => interface.handle_button_up(button),
+                    _ => {}
+                }
+            }
+            if interface.quit {
+                break 'running;
+            }
+
+            if interface.settings.fullscreen != loaded_settings.fullscreen {
+                let fullscreen = if interface.settings.fullscreen {
+                    FullscreenType::Desktop
+                } else {
+                    FullscreenType::Off
+                };
+                if let Err(err) = canvas.window_mut().set_fullscreen(fullscreen) {
+                    eprintln!("Failed to switch fullscreen: {}", err);
+                }
+            }
+            if !interface.settings.same_skin(&loaded_settings) {
+                skin_assets = interface.load_skin(&texture_creator);
+            }
+            if interface.settings.language != loaded_settings.language {
+                i18n::set_language(&interface.languages[interface.settings.language]);
+            }
+            if interface.settings.sound_pack != loaded_settings.sound_pack {
+                if let Some(audio) = &interface.audio {
+                    let pack = &interface.sound_packs[interface.settings.sound_pack];
+                    audio.send(AudioCommand::LoadPack(pack.clone()));
+                    audio.send(AudioCommand::Volume(interface.settings));
+                }
+            }
+            if !interface.settings.same_volume(&loaded_settings) {
+                if let Some(audio) = &interface.audio {
+                    audio.send(AudioCommand::Volume(interface.settings));
+                }
+            }
+            // 设置界面、快捷键、换档案，哪里改了设置都马上存下来；
+            // 重新读的是玩家自己手改的文件，写回去会丢掉里面的注释
+            let reloaded = std::mem::take(&mut interface.settings_reloaded);
+            if interface.settings != loaded_settings && !reloaded {
+                interface.save_settings();
+            }
+            loaded_settings = interface.settings;
+
+            let now = Instant::now();
+            let frame_time = now - previous;
+            accumulator += frame_time;
+            previous = now;
+            interface.perf.end_frame(frame_time);
+            // 卡太久（比如拖动窗口）的话直接丢掉多出来的时间，不要一口气追帧
+            accumulator = accumulator.min(TIMESTEP * MAX_UPDATES_PER_FRAME);
+            while accumulator >= TIMESTEP {
+                let update_start = Instant::now();
+                interface.update();
+                interface.perf.record_update(update_start.elapsed());
+                accumulator -= TIMESTEP;
+            }
+            interface.alpha = accumulator.as_secs_f32() / TIMESTEP.as_secs_f32();
+
+            // 标题跟着游戏状态变，没变就不用去动窗口
+            let title = interface.window_title();
+            if canvas.window().title() != title {
+                canvas.window_mut().set_title(&title).unwrap();
+            }
+
+            skin_assets.set_level(interface.board.engine.stats().level);
+            let render_start = Instant::now();
+            interface.draw(&mut canvas, &skin_assets);
+            interface.perf.record_render(render_start.elapsed());
+            interface.clip.capture(&canvas, interface.frame);
+            canvas.present();
+            if accumulator < TIMESTEP / 2 {
+                // 没有垂直同步的时候别让循环空转
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    fn choices(&self) -> Choices<'_> {
+        Choices {
+            skins: &self.skins,
+            languages: &self.languages,
+            sound_packs: &self.sound_packs,
+        }
+    }
+
+    fn load_skin<'tc>(
+        &self,
+        texture_creator: &'tc TextureCreator<WindowContext>,
+    ) -> SkinAssets<'tc> {
+        let skin = &self.skins[self.settings.skin];
+        SkinAssets::load(skin, &self.settings, texture_creator)
+    }
+
+    fn handle_key(&mut self, key: Keycode) {
+        self.idle_frames = 0;
+        if let Screen::Demo = self.screen {
+            self.end_demo();
+            return;
+        }
+        if let Screen::Watch(_) = self.screen {
+            self.end_watch();
+            return;
+        }
+        // 任何界面都能用的按键
+        if key == Keycode::F2 {
+            self.debug_overlay = !self.debug_overlay;
+            return;
+        }
+        if key == Keycode::F3 {
+            self.settings.show_perf = !self.settings.show_perf;
+            return;
+        }
+        if key == Keycode::F6 {
+            self.settings.muted = !self.settings.muted;
+            self.toasts.push(if self.settings.muted {
+                "MUTED"
+            } else {
+                "SOUND ON"
+            });
+            return;
+        }
+        if key == Keycode::F4 {
+            self.settings.input_display = !self.settings.input_display;
+            return;
+        }
+        // 把最近 15 秒存成 GIF
+        if key == Keycode::F8 {
+            if let Some(path) = self.clip.export() {
+                eprintln!("Saving clip to {}", path.display());
+                self.toasts.push("CLIP SAVED");
+            }
+            return;
+        }
+        // 调试用：给自己送一次垃圾行，好看看计量条
+        #[cfg(debug_assertions)]
+        if key == Keycode::F9 {
+            self.board.engine.queue_garbage(2);
+            return;
+        }
+        // 调试用：切换一块自己往下掉的第二块棋盘，单人 -> 观战 -> 分屏
+        #[cfg(debug_assertions)]
+        if key == Keycode::F10 {
+            self.arrangement = match self.arrangement {
+                Arrangement::Single => Arrangement::Spectator,
+                Arrangement::Spectator => Arrangement::SplitScreen,
+                Arrangement::SplitScreen => Arrangement::Single,
+            };
+            self.second_board = (self.arrangement != Arrangement::Single)
+                .then(|| Board::new(self.board.engine.mode()));
+            return;
+        }
+
+        if self.handle_chat_key(key) {
+            return;
+        }
+        // 游戏里改绑过的键换成默认键
+        let key = match self.screen {
+            Screen::Playing => match self.keybinds().translate(key) {
+                Some(key) => key,
+                None => return,
+            },
+            _ => key,
+        };
+        if self.delays(key) {
+            let due = self.frame + 1 + self.settings.input_delay as u64;
+            self.input_delay.push(due, key, true);
+            return;
+        }
+        self.handle_screen_key(key);
+    }
+
+    // 本地双人对战按原来的键交给 `Versus`，改绑过的键在那边归左边的玩家
+    fn keybinds(&self) -> Keybinds {
+        match self.versus {
+            Some(_) => Keybinds::default(),
+            None => self.settings.keybinds,
+        }
+    }
+
+    // 联机时设了输入延迟的话，游戏里的按键先排着（`InputDelay`），过几帧再交给 `handle_screen_key`
+    fn delays(&self, key: Keycode) -> bool {
+        self.online.is_some()
+            && self.settings.input_delay > 0
+            && self.chat_entry.is_none()
+            && matches!(self.screen, Screen::Playing)
+            && matches!(
+                key,
+                Keycode::Right
+                    | Keycode::Left
+                    | Keycode::Space
+                    | Keycode::Up
+                    | Keycode::Down
+                    | Keycode::LShift
+                    | Keycode::RShift
+            )
+    }
+
+    fn handle_screen_key(&mut self, key: Keycode) {
+        // 倒数的时候方块不能动
+        let frozen = self.countdown.as_ref().is_some_and(Countdown::is_frozen);
+        if let (Screen::Playing, Some(versus), Some(second_board)) =
+            (&self.screen, &mut self.versus, &mut self.second_board)
+        {
+            let engines = [&mut self.board.engine, &mut second_board.engine];
+            if !frozen && versus.press(key, engines) {
+                self.input_display.press(key);
+                return;
+            }
+        }
+        let engine = &mut self.board.engine;
+        match &mut self.screen {
+            Screen::Playing => {
+                if !frozen {
+                    self.input_display.press(key);
+                }
+                match key {
+                    // 联机的时候对面不会停下来等
+                    Keycode::P if self.online.is_some() => {}
+                    Keycode::P => self.screen = Screen::paused(),
+                    Keycode::Right
+                    | Keycode::Left
+                    | Keycode::Space
+                    | Keycode::Up
+                    | Keycode::Down
+                    | Keycode::LShift
+                    | Keycode::RShift
+                        if frozen => {}
+                    Keycode::Right => {
+                        let _ = engine.move_cursor(MoveKind::Right);
+                        self.auto_shift.press(MoveKind::Right);
+                    }
+                    Keycode::Left => {
+                        let _ = engine.move_cursor(MoveKind::Left);
+                        self.auto_shift.press(MoveKind::Left);
+                    }
+                    // hard_drop
+                    Keycode::Space => engine.hard_drop(),
+                    // rotate
+                    Keycode::Up => engine.rotate_clockwise(),
+                    // soft drop
+                    Keycode::Down => {
+                        engine.soft_drop();
+                        self.auto_shift.set_soft_drop(true);
+                    }
+                    Keycode::LShift | Keycode::RShift => engine.hold(),
+                    Keycode::G => self.settings.grid = self.settings.grid.next(),
+                    Keycode::K => {
+                        self.settings.skin = (self.settings.skin + 1) % self.skins.len();
+                    }
+                    Keycode::C => self.settings.palette = self.settings.palette.next(),
+                    Keycode::V => self.settings.piece_glyphs = !self.settings.piece_glyphs,
+                    Keycode::H => self.settings.screen_shake = self.settings.screen_shake.next(),
+                    // 合作模式的第二个玩家：A D 左右移动，W 旋转，S 软降，E 硬降
+                    _ if engine.has_partner() && !frozen => engine.as_partner(|engine| match key {
+                        Keycode::A => {
+                            let _ = engine.move_cursor(MoveKind::Left);
+                        }
+                        Keycode::D => {
+                            let _ = engine.move_cursor(MoveKind::Right);
+                        }
+                        Keycode::W => engine.rotate_clockwise(),
+                        Keycode::S => engine.soft_drop(),
+                        Keycode::E => engine.hard_drop(),
                         _ => {}
+                    }),
+                    // 训练模式：数字键 1-7 按 OITLJSZ 指定下一块，U 撤销，R 清空，I 无限 hold
+                    _ if engine.mode().is_sandbox() => match key {
+                        Keycode::U => {
+                            engine.undo();
+                        }
+                        Keycode::R => engine.reset(),
+                        Keycode::I => {
+                            engine.set_infinite_hold(!engine.infinite_hold());
+                            self.toasts.push(if engine.infinite_hold() {
+                                "INFINITE HOLD ON"
+                            } else {
+                                "INFINITE HOLD OFF"
+                            });
+                        }
+                        _ => {
+                            if let Some(kind) = training_piece(key) {
+                                engine.force_next(kind);
+                            }
+                        }
                     },
                     _ => {}
                 }
             }
+            // 名字输入要认字母，不能全交给 Nav
+            Screen::NameEntry(entry) => match key {
+                Keycode::Return => self.submit_score(),
+                Keycode::Backspace => entry.pop(),
+                _ => {
+                    // 字母和数字键的名字就是那个字符
+                    let name = key.name();
+                    if let (Some(c), 1) = (name.chars().next(), name.len()) {
+                        entry.push(c);
+                    }
+                }
+            },
+            // 改名的时候要认字母；不改名的时候 R 改名，Delete 删掉
+            Screen::Replays(browser) => match (key, &mut browser.renaming) {
+                (Keycode::Return, Some(_)) => {
+                    if let Err(err) = browser.finish_rename() {
+                        eprintln!("Failed to rename replay: {}", err);
+                        self.toasts.push("REPLAY NOT SAVED");
+                    }
+                }
+                // 和档案一样，名字删光了再删就是不改了
+                (Keycode::Backspace, Some(entry)) if entry.name.is_empty() => {
+                    browser.renaming = None
+                }
+                (Keycode::Backspace, Some(entry)) => entry.pop(),
+                (_, Some(entry)) => {
+                    let name = key.name();
+                    if let (Some(c), 1) = (name.chars().next(), name.len()) {
+                        entry.push(c);
+                    }
+                }
+                (Keycode::R, None) => browser.start_rename(),
+                (Keycode::Delete, None) => {
+                    if let Err(err) = browser.delete() {
+                        eprintln!("Failed to delete replay: {}", err);
+                    }
+                }
+                (_, None) => {
+                    if let Some(nav) = Nav::from_key(key) {
+                        self.navigate(nav);
+                    }
+                }
+            },
+            // 档案名字删光了再删就回主菜单
+            Screen::Profiles(profiles) => match key {
+                Keycode::Return => self.confirm_profile(),
+                Keycode::Backspace if profiles.entry.name.is_empty() => {
+                    self.screen = Screen::main_menu()
+                }
+                Keycode::Backspace => profiles.entry.pop(),
+                Keycode::Up | Keycode::Down => {
+                    if let Some(nav) = Nav::from_key(key) {
+                        self.navigate(nav);
+                    }
+                }
+                _ => {
+                    let name = key.name();
+                    if let (Some(c), 1) = (name.chars().next(), name.len()) {
+                        profiles.entry.push(c);
+                    }
+                }
+            },
+            // 报名也要认字母
+            Screen::Registration(registration) => match key {
+                Keycode::Return => self.start_tournament(),
+                Keycode::Backspace => registration.entry.pop(),
+                _ => {
+                    let name = key.name();
+                    if let (Some(c), 1) = (name.chars().next(), name.len()) {
+                        registration.entry.push(c);
+                    }
+                }
+            },
+            // 地址删光了再删就回联机菜单
+            Screen::Join(entry) => match key {
+                Keycode::Return => self.join_game(),
+                Keycode::Backspace if entry.address.is_empty() => self.screen = Screen::online(),
+                Keycode::Backspace => entry.pop(),
+                _ => entry.push_key(key),
+            },
+            // 编辑器用字母键排方块，其它的按菜单导航走
+            Screen::Editor(editor) => match key {
+                Keycode::Return => self.save_puzzle(),
+                Keycode::Delete => editor.pop_piece(),
+                Keycode::G => editor.cycle_goal(),
+                Keycode::O => editor.push_piece(PieceKind::O),
+                Keycode::I => editor.push_piece(PieceKind::I),
+                Keycode::T => editor.push_piece(PieceKind::T),
+                Keycode::L => editor.push_piece(PieceKind::L),
+                Keycode::J => editor.push_piece(PieceKind::J),
+                Keycode::S => editor.push_piece(PieceKind::S),
+                Keycode::Z => editor.push_piece(PieceKind::Z),
+                _ => {
+                    if let Some(nav) = Nav::from_key(key) {
+                        self.navigate(nav);
+                    }
+                }
+            },
+            _ => {
+                if let Some(nav) = Nav::from_key(key) {
+                    self.navigate(nav);
+                }
+            }
+        }
+    }
+
+    // 手柄：游戏里按键位换成对应的键盘按键，菜单里翻译成 Nav
+    fn handle_button(&mut self, button: Button) {
+        self.idle_frames = 0;
+        if let Screen::Demo = self.screen {
+            self.end_demo();
+            return;
+        }
+        if matches!(self.screen, Screen::Playing) {
+            if let Some(key) = gameplay_key(button, &self.keybinds()) {
+                self.handle_key(key);
+            }
+            return;
+        }
+        if let Some(nav) = Nav::from_button(button) {
+            self.navigate(nav);
+        }
+    }
+
+    fn handle_button_up(&mut self, button: Button) {
+        if let Some(key) = gameplay_key(button, &self.keybinds()) {
+            self.handle_key_up(key);
+        }
+    }
+
+    // 所有菜单界面共用的导航，键盘和手柄都走这里
+    fn navigate(&mut self, nav: Nav) {
+        match &mut self.screen {
+            Screen::Playing | Screen::Demo | Screen::Watch(_) => {}
+            Screen::Settings(options) => {
+                let choices = Choices {
+                    skins: &self.skins,
+                    languages: &self.languages,
+                    sound_packs: &self.sound_packs,
+                };
+                if options.navigate(nav, &mut self.settings, &choices) {
+                    let from_pause = options.from_pause;
+                    self.leave_settings(from_pause);
+                }
+            }
+            Screen::NameEntry(entry) => match nav {
+                Nav::Confirm => self.submit_score(),
+                Nav::Back => entry.pop(),
+                _ => {}
+            },
+            Screen::HighScores(leaderboard) => {
+                if leaderboard.navigate(nav) {
+                    self.screen = if leaderboard.after_game() {
+                        Screen::game_over()
+                    } else {
+                        Screen::main_menu()
+                    };
+                }
+            }
+            Screen::Replays(browser) => match nav {
+                Nav::Confirm if browser.renaming.is_some() => {
+                    if let Err(err) = browser.finish_rename() {
+                        eprintln!("Failed to rename replay: {}", err);
+                        self.toasts.push("REPLAY NOT SAVED");
+                    }
+                }
+                Nav::Back if browser.renaming.is_some() => browser.renaming = None,
+                Nav::Confirm => self.watch_replay(),
+                Nav::Back => self.screen = Screen::main_menu(),
+                Nav::Up => browser.cycle(-1),
+                Nav::Down => browser.cycle(1),
+                Nav::Left | Nav::Right => {}
+            },
+            Screen::Profiles(profiles) => match nav {
+                Nav::Confirm => self.confirm_profile(),
+                Nav::Back if profiles.entry.name.is_empty() => self.screen = Screen::main_menu(),
+                Nav::Back => profiles.entry.pop(),
+                Nav::Up => profiles.cycle(-1, self.profiles.profiles().len()),
+                Nav::Down => profiles.cycle(1, self.profiles.profiles().len()),
+                Nav::Left | Nav::Right => {}
+            },
+            Screen::Registration(registration) => match nav {
+                Nav::Confirm => self.start_tournament(),
+                Nav::Back => registration.entry.pop(),
+                _ => {}
+            },
+            // 决出冠军之后按什么都回主菜单，中途退出就不打了
+            Screen::Tournament => match nav {
+                Nav::Confirm => self.next_tournament_game(),
+                Nav::Back => self.perform(MenuAction::MainMenu),
+                _ => {}
+            },
+            Screen::Review(review) => match review.navigate(nav) {
+                Some(Exit::Back) => self.screen = Screen::game_over(),
+                // 岔出去的沙盒不算闯关也不算谜题
+                Some(Exit::WhatIf(engine)) => {
+                    self.puzzle = None;
+                    self.stage = None;
+                    self.replay = None;
+                    self.board = Board::with_engine(*engine);
+                    self.auto_shift = AutoShift::default();
+                    self.start_countdown();
+                }
+                None => {}
+            },
+            Screen::Join(entry) => match nav {
+                Nav::Confirm => self.join_game(),
+                Nav::Back => entry.pop(),
+                _ => {}
+            },
+            // 不等了，回联机菜单
+            Screen::Connecting(_) | Screen::Lobbies(..) | Screen::LocalGames(..)
+                if nav == Nav::Back =>
+            {
+                self.screen = Screen::online()
+            }
+            Screen::Connecting(_) => {}
+            Screen::Paused(_) if nav == Nav::Back => self.perform(MenuAction::Resume),
+            Screen::Puzzles(_) | Screen::Campaign(_) | Screen::Online(_) if nav == Nav::Back => {
+                self.screen = Screen::main_menu()
+            }
+            Screen::Editor(editor) => {
+                if editor.navigate(nav) {
+                    self.perform(MenuAction::Puzzles);
+                }
+            }
+            Screen::MainMenu(menu)
+            | Screen::Paused(menu)
+            | Screen::GameOver(menu)
+            | Screen::Puzzles(menu)
+            | Screen::Campaign(menu)
+            | Screen::Online(menu)
+            | Screen::Lobbies(_, menu)
+            | Screen::LocalGames(_, menu) => {
+                if let Some(action) = menu.navigate(nav) {
+                    self.perform(action);
+                }
+            }
+        }
+    }
 
-            draw(&mut canvas, &engine);
-            std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+    fn handle_key_up(&mut self, key: Keycode) {
+        let Some(key) = self.keybinds().translate(key) else {
+            return;
+        };
+        if self.delays(key) {
+            let due = self.frame + 1 + self.settings.input_delay as u64;
+            self.input_delay.push(due, key, false);
+            return;
+        }
+        self.release_key(key);
+    }
+
+    fn release_key(&mut self, key: Keycode) {
+        self.input_display.release(key);
+        if let Some(versus) = &mut self.versus {
+            versus.release(key);
+            return;
+        }
+        match key {
+            Keycode::Left => self.auto_shift.release(MoveKind::Left),
+            Keycode::Right => self.auto_shift.release(MoveKind::Right),
+            Keycode::Down => self.auto_shift.set_soft_drop(false),
+            _ => {}
+        }
+    }
+
+    fn submit_score(&mut self) {
+        let Screen::NameEntry(entry) = &self.screen else {
+            return;
+        };
+        let name = if entry.name.is_empty() {
+            "PLAYER".to_string()
+        } else {
+            entry.name.clone()
+        };
+        let stats = self.board.engine.stats();
+        let mode = self.board.engine.mode();
+        let entry = Entry::new(name.clone(), &stats, self.board.engine.seed());
+        let rank = self.high_scores.insert(mode, entry);
+        if let Err(err) = self.high_scores.save(Path::new(SCORES_FILE)) {
+            eprintln!("Failed to save {}: {}", SCORES_FILE, err);
+        }
+        if rank == Some(0) {
+            self.toasts.push("NEW PERSONAL BEST");
+        }
+        self.player_name = name;
+        self.screen = Screen::HighScores(Leaderboard::with_highlight(mode, rank));
+    }
+
+    // 选了档案的话档案里的也跟着改
+    fn save_settings(&mut self) {
+        if let Err(err) = self.settings.save(&config_path()) {
+            eprintln!("Failed to save {}: {}", config_path().display(), err);
+            self.toasts.push("SETTINGS NOT SAVED");
+        }
+        if let Some(profile) = self.profiles.current_mut() {
+            profile.settings = self.settings;
+            self.save_profiles();
+        }
+    }
+
+    // 改过的设置已经存了，这里只回原来的界面
+    fn leave_settings(&mut self, from_pause: bool) {
+        self.screen = if from_pause {
+            Screen::paused()
+        } else {
+            Screen::main_menu()
+        };
+    }
+
+    // 名字打了就新建一个档案，从现在的设置开始；空着就换到选中的档案，用它的设置
+    fn confirm_profile(&mut self) {
+        let Screen::Profiles(screen) = &mut self.screen else {
+            return;
+        };
+        let name = std::mem::take(&mut screen.entry.name);
+        if !name.is_empty() {
+            match self.profiles.create(name, self.settings) {
+                Ok(index) => screen.selected = index,
+                Err(reason) => self.toasts.push(reason),
+            }
+            self.save_profiles();
+            return;
+        }
+        let Some(profile) = self.profiles.select(screen.selected) else {
+            return;
+        };
+        self.settings = profile.settings;
+        self.player_name = profile.name.clone();
+        self.save_profiles();
+        self.screen = Screen::main_menu();
+    }
+
+    fn save_profiles(&mut self) {
+        if let Err(err) = self.profiles.save(Path::new(PROFILES_FILE)) {
+            eprintln!("Failed to save {}: {}", PROFILES_FILE, err);
+        }
+    }
+
+    fn perform(&mut self, action: MenuAction) {
+        match action {
+            MenuAction::Stage(index) if !self.campaign.is_unlocked(index) => {
+                self.toasts.push("STAGE LOCKED")
+            }
+            MenuAction::Play(_)
+            | MenuAction::Puzzle(_)
+            | MenuAction::Stage(_)
+            | MenuAction::Retry => {
+                let mode = match action {
+                    MenuAction::Play(mode) => {
+                        self.puzzle = None;
+                        self.stage = None;
+                        mode
+                    }
+                    MenuAction::Puzzle(index) => {
+                        self.puzzle = Some(index);
+                        self.stage = None;
+                        Mode::Puzzle
+                    }
+                    MenuAction::Stage(index) => {
+                        self.puzzle = None;
+                        self.stage = Some(index);
+                        STAGES[index].engine().mode()
+                    }
+                    _ => self.board.engine.mode(),
+                };
+                self.board = Board::with_engine(self.new_engine(mode));
+                if pace::tracks(mode) {
+                    self.board.pace = Some(Pace::new(mode, self.pace_records.best(mode)));
+                }
+                if self.second_board.is_some() {
+                    self.second_board = Some(Board::with_engine(self.new_engine(mode)));
+                }
+                let dig_interval = self.settings.dig_interval * Stats::FRAMES_PER_SECOND as u32;
+                for board in std::iter::once(&mut self.board).chain(&mut self.second_board) {
+                    board.engine.set_dig_interval(dig_interval);
+                }
+                let replayable = self.puzzle.is_none()
+                    && self.stage.is_none()
+                    && self.second_board.is_none()
+                    && self.board.engine.mode().replayable();
+                self.replay =
+                    replayable.then(|| ReplayRecorder::new(&self.board.engine, dig_interval));
+                self.auto_shift = AutoShift::default();
+                self.start_countdown();
+            }
+            MenuAction::Resume => self.start_countdown(),
+            // 画面、音量这些在主循环里发现设置变了就跟着换
+            MenuAction::ReloadConfig => match Settings::load(&config_path()) {
+                Ok(settings) => {
+                    self.settings = settings;
+                    self.settings_reloaded = true;
+                    self.toasts.push("CONFIG RELOADED");
+                }
+                Err(err) => {
+                    eprintln!("Failed to reload {}: {}", config_path().display(), err);
+                    self.toasts.push("CONFIG ERROR");
+                }
+            },
+            MenuAction::HighScores => {
+                // 谜题没有榜，看默认模式的
+                let mode = Some(self.board.engine.mode())
+                    .filter(|mode| Mode::ranked().contains(mode))
+                    .unwrap_or_default();
+                let leaderboard = match self.screen {
+                    // 打完一局之后看的是这个模式的榜
+                    Screen::GameOver(_) => Leaderboard::with_highlight(mode, None),
+                    _ => Leaderboard::new(mode),
+                };
+                self.screen = Screen::HighScores(leaderboard);
+            }
+            MenuAction::Replays => {
+                self.screen = Screen::Replays(ReplayBrowser::new(Path::new(REPLAY_DIR)))
+            }
+            MenuAction::Profiles => {
+                self.screen = Screen::Profiles(ProfileScreen::new(&self.profiles))
+            }
+            MenuAction::Review => {
+                let moves = self.board.recorder.analysis();
+                self.screen = Screen::Review(Review::new(moves));
+            }
+            MenuAction::Export => {
+                let engine = &self.board.engine;
+                let stats = engine.stats();
+                let ledger = self.board.recorder.ledger();
+                let data = GameData {
+                    mode: engine.mode(),
+                    seed: engine.seed(),
+                    stats: &stats,
+                    ledger: &ledger,
+                };
+                let stem = export::stem(engine.mode());
+                match export::write(Path::new(EXPORT_DIR), &stem, &data) {
+                    Ok(path) => {
+                        eprintln!("Exported game to {}", path.display());
+                        self.toasts.push("EXPORTED");
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to export game: {}", err);
+                        self.toasts.push("EXPORT FAILED");
+                    }
+                }
+            }
+            MenuAction::Settings => {
+                let from_pause = matches!(self.screen, Screen::Paused(_));
+                self.screen = Screen::Settings(OptionsMenu::new(from_pause));
+            }
+            MenuAction::Puzzles => {
+                self.screen = Screen::puzzles(&self.puzzles, &self.puzzle_progress)
+            }
+            MenuAction::Campaign => self.screen = Screen::campaign(&self.campaign),
+            MenuAction::Tournament => self.screen = Screen::Registration(Registration::new()),
+            MenuAction::Versus => self.start_versus(),
+            MenuAction::Online => self.screen = Screen::online(),
+            MenuAction::Host => self.host_game(),
+            MenuAction::Join => {
+                self.screen = Screen::Join(AddressEntry::new(&self.peer_address, false))
+            }
+            MenuAction::Lobbies => {
+                self.screen = Screen::Join(AddressEntry::new(&self.peer_address, true))
+            }
+            MenuAction::QuickMatch | MenuAction::CreateLobby | MenuAction::JoinLobby(_) => {
+                self.enter_lobby(action)
+            }
+            MenuAction::LocalGames => match Scanner::bind() {
+                Ok(scanner) => self.screen = Screen::local_games(scanner),
+                Err(err) => {
+                    eprintln!("Failed to listen for local games: {}", err);
+                    self.toasts.push("CANNOT SEARCH");
+                }
+            },
+            MenuAction::JoinLocal(address) => match Pending::join(&address.to_string()) {
+                Ok(pending) => self.screen = Screen::Connecting(pending),
+                Err(err) => {
+                    eprintln!("Failed to connect to {}: {}", address, err);
+                    self.toasts.push("CANNOT CONNECT");
+                }
+            },
+            MenuAction::Refresh => {
+                if let Screen::Lobbies(client, _) = &mut self.screen {
+                    if let Err(err) = client.refresh() {
+                        eprintln!("Lost connection to the lobby server: {}", err);
+                        self.toasts.push("CONNECTION LOST");
+                        self.screen = Screen::online();
+                    }
+                }
+            }
+            MenuAction::Editor => {
+                self.screen =
+                    Screen::Editor(Box::new(Editor::new(puzzles::custom_name(&self.puzzles))))
+            }
+            MenuAction::MainMenu => {
+                self.tournament = None;
+                self.replay = None;
+                let versus = self.versus.take();
+                let online = self.online.take();
+                if versus.is_some() || online.is_some() {
+                    self.arrangement = Arrangement::Single;
+                    self.second_board = None;
+                }
+                self.screen = Screen::main_menu();
+            }
+            MenuAction::Quit => self.quit = true,
+        }
+    }
+
+    fn new_engine(&self, mode: Mode) -> Engine {
+        if let Some(stage) = self.stage.and_then(|index| STAGES.get(index)) {
+            return stage.engine();
+        }
+        match self.puzzle.and_then(|index| self.puzzles.get(index)) {
+            Some(puzzle) if mode == Mode::Puzzle => Engine::with_puzzle(puzzle),
+            _ if mode == Mode::Daily => Engine::with_seed(mode, daily::today()),
+            _ => Engine::with_mode(mode),
+        }
+    }
+
+    // 谜题做出来了就记下来
+    fn finish_puzzle(&mut self) {
+        let Some(puzzle) = self.puzzle.and_then(|index| self.puzzles.get(index)) else {
+            return;
+        };
+        if !self.board.engine.is_finished() || !self.puzzle_progress.complete(puzzle) {
+            return;
+        }
+        self.toasts.push("PUZZLE SOLVED");
+        if let Err(err) = self.puzzle_progress.save(Path::new(PROGRESS_FILE)) {
+            eprintln!("Failed to save {}: {}", PROGRESS_FILE, err);
+            self.toasts.push("PROGRESS NOT SAVED");
+        }
+    }
+
+    // 过了这关就解锁下一关
+    fn finish_stage(&mut self) {
+        let Some(stage) = self.stage else {
+            return;
+        };
+        if !self.board.engine.is_finished() || !self.campaign.clear(stage) {
+            return;
+        }
+        self.toasts.push("STAGE CLEAR");
+        if let Err(err) = self.campaign.save(Path::new(CAMPAIGN_FILE)) {
+            eprintln!("Failed to save {}: {}", CAMPAIGN_FILE, err);
+            self.toasts.push("PROGRESS NOT SAVED");
+        }
+    }
+
+    // 报名界面按回车：报上一个名字，名字空着而且人够了就开赛
+    fn start_tournament(&mut self) {
+        let Screen::Registration(registration) = &mut self.screen else {
+            return;
+        };
+        if let Some(tournament) = registration.confirm() {
+            self.tournament = Some(tournament);
+            self.screen = Screen::Tournament;
+        }
+    }
+
+    // 对阵表上按确认：轮到的人上场，冠军出来了就结束回主菜单
+    fn next_tournament_game(&mut self) {
+        let next = self.tournament.as_ref().and_then(Tournament::next_player);
+        let Some(next) = next else {
+            self.perform(MenuAction::MainMenu);
+            return;
+        };
+        self.toasts.push(next);
+        self.perform(MenuAction::Play(tournament::MODE));
+    }
+
+    // 编辑器里按回车：存成谜题文件，重新读一遍谜题目录再回选题
+    fn save_puzzle(&mut self) {
+        let Screen::Editor(editor) = &self.screen else {
+            return;
+        };
+        let puzzle = match editor.to_puzzle() {
+            Ok(puzzle) => puzzle,
+            Err(err) => {
+                eprintln!("Invalid puzzle: {}", err);
+                self.toasts.push("ADD SOME PIECES");
+                return;
+            }
+        };
+        match puzzles::save(Path::new(PUZZLE_DIR), &puzzle) {
+            Ok(path) => {
+                eprintln!("Saved puzzle to {}", path.display());
+                self.toasts.push("PUZZLE SAVED");
+            }
+            Err(err) => {
+                eprintln!("Failed to save puzzle: {}", err);
+                self.toasts.push("PUZZLE NOT SAVED");
+                return;
+            }
+        }
+        self.puzzles = puzzles::load_all(Path::new(PUZZLE_DIR));
+        self.perform(MenuAction::Puzzles);
+    }
+
+    // 每日挑战不上排行榜，只记当天的最好成绩
+    fn finish_daily(&mut self) {
+        if !self
+            .daily_results
+            .record(daily::today(), &self.board.engine.stats())
+        {
+            return;
+        }
+        self.toasts.push("NEW DAILY BEST");
+        if let Err(err) = self.daily_results.save(Path::new(DAILY_FILE)) {
+            eprintln!("Failed to save {}: {}", DAILY_FILE, err);
+            self.toasts.push("RESULT NOT SAVED");
+        }
+    }
+
+    // 跑完的 sprint / ultra 比记录好的话，把这一局的进度存下来，下次拿来比
+    fn finish_pace(&mut self) {
+        let Some(pace) = &self.board.pace else {
+            return;
+        };
+        let mode = self.board.engine.mode();
+        if !self.board.engine.is_finished() || !self.pace_records.submit(mode, pace.current.clone())
+        {
+            return;
+        }
+        if let Err(err) = self.pace_records.save(Path::new(PACE_FILE)) {
+            eprintln!("Failed to save {}: {}", PACE_FILE, err);
+        }
+    }
+
+    // 演示用一局新的 marathon，结束或者有人按键就回主菜单。
+    // 电脑玩家按设置里的难度来，顺便给玩家看看这个难度有多强
+    fn start_demo(&mut self) {
+        self.board = Board::new(Mode::Marathon);
+        self.board.engine.spawn_first();
+        self.demo = Some(self.settings.bot_difficulty.driver());
+        self.demo_events.clear();
+        self.screen = Screen::Demo;
+    }
+
+    fn end_demo(&mut self) {
+        self.demo = None;
+        self.idle_frames = 0;
+        self.board = Board::new(Mode::default());
+        self.screen = Screen::main_menu();
+    }
+
+    fn watch_replay(&mut self) {
+        let Screen::Replays(browser) = &self.screen else {
+            return;
+        };
+        let Some(replay) = browser.selected() else {
+            return;
+        };
+        let Some(engine) = replay.engine() else {
+            self.toasts.push("UNKNOWN MODE");
+            return;
+        };
+        self.board = Board::with_engine(engine);
+        self.screen = Screen::Watch(Playback::new(replay.clone()));
+    }
+
+    fn end_watch(&mut self) {
+        self.board = Board::new(Mode::default());
+        self.screen = Screen::Replays(ReplayBrowser::new(Path::new(REPLAY_DIR)));
+    }
+
+    fn update_watch(&mut self) {
+        let Screen::Watch(playback) = &mut self.screen else {
+            return;
+        };
+        let Some((actions, beat)) = playback.next() else {
+            self.end_watch();
+            return;
+        };
+        for action in actions {
+            action.apply(&mut self.board.engine);
+        }
+        let events = self.board.update();
+        if beat {
+            self.board.engine.award_beat_bonus();
+        }
+        if let Some(audio) = &self.audio {
+            audio.send(AudioCommand::Events(events));
+        }
+    }
+
+    fn update_demo(&mut self) {
+        let Some(demo) = &mut self.demo else {
+            return;
+        };
+        self.demo_events = demo_step(demo, &mut self.board, &self.demo_events);
+        self.export_samples();
+        if self.board.show_results() {
+            self.end_demo();
+        }
+    }
+
+    // 写不进去就把设置关掉，免得每帧都报错
+    fn export_samples(&mut self) {
+        if !self.settings.export_samples {
+            return;
+        }
+        let file = match self.samples.take() {
+            Some(file) => Ok(file),
+            None => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(SAMPLES_FILE),
+        };
+        let result = file.and_then(|mut file| {
+            self.board.recorder.export(&mut file)?;
+            self.samples = Some(file);
+            Ok(())
+        });
+        if let Err(err) = result {
+            eprintln!("Failed to write {}: {}", SAMPLES_FILE, err);
+            self.settings.export_samples = false;
+        }
+    }
+
+    // 两边同一个种子，方块顺序一样
+    fn start_versus(&mut self) {
+        self.puzzle = None;
+        self.stage = None;
+        self.tournament = None;
+        let seed = rand::random();
+        self.board = Board::with_engine(Engine::with_seed(versus::MODE, seed));
+        let mut second_board = Board::with_engine(Engine::with_seed(versus::MODE, seed));
+        second_board.engine.spawn_first();
+        self.second_board = Some(second_board);
+        self.arrangement = Arrangement::SplitScreen;
+        self.versus
+            .get_or_insert_with(Versus::default)
+            .new_game(&self.settings.keybinds);
+        self.auto_shift = AutoShift::default();
+        self.start_countdown();
+    }
+
+    // 有人顶满了，这一局就结束，两边一起停下
+    fn finish_versus(&mut self) -> bool {
+        let (Some(versus), Some(second_board)) = (&mut self.versus, &self.second_board) else {
+            return false;
+        };
+        let engines = [&self.board.engine, &second_board.engine];
+        if !engines.iter().any(|engine| engine.is_game_over()) {
+            return false;
+        }
+        let winner = battle::winner(engines);
+        versus.record(winner);
+        self.toasts.push(match winner {
+            Some(0) => "P1 WINS",
+            Some(_) => "P2 WINS",
+            None => "DRAW",
+        });
+        self.toasts
+            .push(format!("{} - {}", versus.wins[0], versus.wins[1]));
+        self.screen = Screen::versus_over();
+        true
+    }
+
+    // 开房：在默认端口等对手连进来
+    fn host_game(&mut self) {
+        match Pending::host(net::DEFAULT_PORT, &self.player_name) {
+            Ok(pending) => self.screen = Screen::Connecting(pending),
+            Err(err) => {
+                eprintln!("Failed to host on port {}: {}", net::DEFAULT_PORT, err);
+                self.toasts.push("CANNOT HOST");
+            }
+        }
+    }
+
+    // 输完地址按回车：直连对手，或者连大厅服务器看房间列表
+    fn join_game(&mut self) {
+        let Screen::Join(entry) = &self.screen else {
+            return;
+        };
+        let address = entry.address.to_ascii_lowercase();
+        let screen = if entry.server {
+            LobbyClient::connect(&address).map(Screen::lobbies)
+        } else {
+            Pending::join(&address).map(Screen::Connecting)
+        };
+        match screen {
+            Ok(screen) => {
+                self.peer_address = entry.address.clone();
+                self.screen = screen;
+            }
+            Err(err) => {
+                eprintln!("Failed to connect to {}: {}", address, err);
+                self.toasts.push("CANNOT CONNECT");
+            }
+        }
+    }
+
+    // 房间列表每帧看一眼服务器回了没有
+    fn update_lobbies(&mut self) {
+        let Screen::Lobbies(client, _) = &mut self.screen else {
+            return;
+        };
+        match client.poll() {
+            Ok(false) => {}
+            Ok(true) => {
+                let screen = std::mem::replace(&mut self.screen, Screen::online());
+                if let Screen::Lobbies(client, _) = screen {
+                    self.screen = Screen::lobbies(client);
+                }
+            }
+            Err(err) => {
+                eprintln!("Lost connection to the lobby server: {}", err);
+                self.toasts.push("CONNECTION LOST");
+                self.screen = Screen::online();
+            }
+        }
+    }
+
+    // 大厅的房间列表，和联机对局里、打完之后，都能聊天
+    fn chat_log(&self) -> Option<&net::chat::ChatLog> {
+        match &self.screen {
+            Screen::Lobbies(client, _) => Some(client.chat()),
+            Screen::Playing | Screen::GameOver(_) => self.online.as_ref().map(Session::chat),
+            _ => None,
+        }
+    }
+
+    // Tab 打开聊天框；开着的时候按键都归它，返回 true
+    fn handle_chat_key(&mut self, key: Keycode) -> bool {
+        if self.chat_log().is_none() {
+            self.chat_entry = None;
+            return false;
+        }
+        let Some(entry) = &mut self.chat_entry else {
+            if key == Keycode::Tab {
+                self.chat_entry = Some(ChatEntry::default());
+                return true;
+            }
+            return false;
+        };
+        match key {
+            Keycode::Tab => self.chat_entry = None,
+            Keycode::Return => self.send_chat(),
+            Keycode::Backspace => entry.pop(),
+            _ => entry.push_key(key),
+        }
+        true
+    }
+
+    // 发不出去先不管，断线的话收的时候会发现
+    fn send_chat(&mut self) {
+        let Some(entry) = self.chat_entry.take() else {
+            return;
+        };
+        let name = &self.player_name;
+        let sent = match &mut self.screen {
+            Screen::Lobbies(client, _) => client.say(name, &entry.text),
+            _ => match &mut self.online {
+                Some(online) => online.say(name, &entry.text),
+                None => Ok(()),
+            },
+        };
+        if let Err(err) = sent {
+            eprintln!("Failed to send chat: {}", err);
+        }
+    }
+
+    // 局域网的房间列表有变化就重新排菜单
+    fn update_local_games(&mut self) {
+        let Screen::LocalGames(scanner, _) = &mut self.screen else {
+            return;
+        };
+        match scanner.poll() {
+            Ok(false) => {}
+            Ok(true) => {
+                let screen = std::mem::replace(&mut self.screen, Screen::online());
+                if let Screen::LocalGames(scanner, _) = screen {
+                    self.screen = Screen::local_games(scanner);
+                }
+            }
+            Err(err) => {
+                eprintln!("Failed to listen for local games: {}", err);
+                self.toasts.push("CANNOT SEARCH");
+                self.screen = Screen::online();
+            }
+        }
+    }
+
+    // 在房间列表里选了开房、进房或者快速匹配，之后和直连一样等种子
+    fn enter_lobby(&mut self, action: MenuAction) {
+        let screen = std::mem::replace(&mut self.screen, Screen::online());
+        let Screen::Lobbies(client, _) = screen else {
+            return;
+        };
+        let name = &self.player_name;
+        let pending = match action {
+            MenuAction::JoinLobby(id) => client.join(id, name),
+            MenuAction::CreateLobby => client.create(name),
+            _ => client.quick_match(name),
+        };
+        match pending {
+            Ok(pending) => self.screen = Screen::Connecting(pending),
+            Err(err) => {
+                eprintln!("Lost connection to the lobby server: {}", err);
+                self.toasts.push("CONNECTION LOST");
+            }
+        }
+    }
+
+    // 等对手的界面每帧看一眼连上了没有
+    fn update_connecting(&mut self) {
+        let Screen::Connecting(pending) = &mut self.screen else {
+            return;
+        };
+        match pending.poll() {
+            Ok(Some(session)) => self.start_online(session),
+            Ok(None) => {}
+            // 大厅服务器不让进，或者两边的版本、模式对不上，把原因显示出来
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::ConnectionRefused | ErrorKind::Unsupported
+                ) =>
+            {
+                self.toasts.push(err.to_string());
+                self.screen = Screen::online();
+            }
+            Err(err) => {
+                eprintln!("Failed to start online game: {}", err);
+                self.toasts.push("CANNOT CONNECT");
+                self.screen = Screen::online();
+            }
+        }
+    }
+
+    // 两边同一个种子；对手的棋盘只靠收到的帧往前走
+    fn start_online(&mut self, session: Session) {
+        self.puzzle = None;
+        self.stage = None;
+        self.tournament = None;
+        self.versus = None;
+        self.board = Board::with_engine(Engine::with_seed(session.mode(), session.seed()));
+        let mut second_board =
+            Board::with_engine(Engine::with_seed(session.mode(), session.seed()));
+        second_board.engine.spawn_first();
+        self.second_board = Some(second_board);
+        self.arrangement = Arrangement::SplitScreen;
+        self.online = Some(session);
+        self.auto_shift = AutoShift::default();
+        self.input_delay.clear();
+        self.start_countdown();
+    }
+
+    // 把自己这一帧发出去，重放收到的对手的帧。分出胜负或者断线了就结束，返回 true。
+    // 自己先顶满了也要接着发帧，对面走到同一帧才算分出来
+    fn update_online(&mut self, actions: Vec<Action>) -> bool {
+        let (Some(online), Some(second_board)) = (&mut self.online, &mut self.second_board) else {
+            return false;
+        };
+        let was_reconnecting = online.is_reconnecting();
+        let sent = online.send_frame(&mut self.board.engine, actions);
+        let received = online.receive().map(|frames| {
+            for frame in frames {
+                frame.apply(&mut second_board.engine);
+                second_board.update();
+                online.replayed(&frame, &mut second_board.engine);
+            }
+        });
+        // 大厅服务器上断了线先接着打，重连上了把断线这段时间的帧补上
+        match (was_reconnecting, online.is_reconnecting()) {
+            (false, true) => self.toasts.push("RECONNECTING"),
+            (true, false) => self.toasts.push("RECONNECTED"),
+            _ => {}
+        }
+        if online.is_decided() {
+            // 连的是大厅服务器的话它要记结果；直连的对手不管
+            if let Err(err) = online.report() {
+                eprintln!("Failed to report the result: {}", err);
+            }
+            self.toasts.push(match online.winner() {
+                Some(0) => "YOU WIN",
+                Some(_) => "YOU LOSE",
+                None => "DRAW",
+            });
+        } else if let Err(err) = sent.and(received) {
+            eprintln!("Lost connection: {}", err);
+            match err.kind() {
+                // 对手走了，或者回来的时候这一局已经完了
+                ErrorKind::ConnectionRefused => self.toasts.push(err.to_string()),
+                _ => self.toasts.push("CONNECTION LOST"),
+            }
+        } else {
+            return false;
+        }
+        self.screen = Screen::online_over();
+        true
+    }
+
+    fn start_countdown(&mut self) {
+        self.board.engine.spawn_first();
+        self.countdown = Some(Countdown::default());
+        self.screen = Screen::Playing;
+    }
+
+    fn update(&mut self) {
+        self.frame += 1;
+        self.toasts.update();
+        self.update_music();
+        #[cfg(feature = "discord")]
+        self.update_presence();
+        match self.screen {
+            Screen::MainMenu(_) => {
+                self.idle_frames += 1;
+                if self.idle_frames >= DEMO_IDLE_FRAMES {
+                    self.start_demo();
+                }
+            }
+            Screen::Demo => self.update_demo(),
+            Screen::Watch(_) => self.update_watch(),
+            Screen::Connecting(_) => self.update_connecting(),
+            Screen::Lobbies(..) => self.update_lobbies(),
+            Screen::LocalGames(..) => self.update_local_games(),
+            // 联机打完了帧不用再重放，只收聊天；对面走了就收不到了
+            Screen::GameOver(_) => {
+                self.idle_frames = 0;
+                if let Some(online) = &mut self.online {
+                    let _ = online.receive();
+                }
+            }
+            _ => self.idle_frames = 0,
+        }
+        if let Screen::Playing = self.screen {
+            for (key, pressed) in self.input_delay.due(self.frame) {
+                if pressed {
+                    self.handle_screen_key(key);
+                } else {
+                    self.release_key(key);
+                }
+            }
+            if let Some(countdown) = &mut self.countdown {
+                countdown.update();
+                let frozen = countdown.is_frozen();
+                if countdown.is_done() {
+                    self.countdown = None;
+                }
+                if frozen {
+                    return;
+                }
+            }
+            match (&mut self.versus, &mut self.second_board) {
+                (Some(versus), Some(second_board)) => versus.update(
+                    [&mut self.board.engine, &mut second_board.engine],
+                    &self.settings,
+                ),
+                _ => self
+                    .auto_shift
+                    .update(&mut self.board.engine, &self.settings),
+            }
+            let events = self.board.update();
+            let actions = replay::actions(&events);
+            let opener = self
+                .board
+                .update_hint(self.settings.placement_hint, self.settings.opener_trainer);
+            if let Some(name) = opener {
+                self.toasts.push(name);
+            }
+            self.export_samples();
+            for event in &events {
+                match event {
+                    crate::engine::Event::TSpin { lines } => self.toasts.push(t_spin_name(*lines)),
+                    crate::engine::Event::MissionComplete => self.toasts.push("MISSION COMPLETE"),
+                    crate::engine::Event::Mystery(effect) => self.toasts.push(effect.name()),
+                    crate::engine::Event::FinesseFault { optimal }
+                        if self.settings.finesse_hints =>
+                    {
+                        self.toasts.push(finesse_hint(optimal))
+                    }
+                    _ => {}
+                }
+            }
+            let mut beat = false;
+            if let Some(audio) = &self.audio {
+                let locked = events
+                    .iter()
+                    .any(|event| matches!(event, crate::engine::Event::Lock { .. }));
+                let on_beat = audio
+                    .beat()
+                    .is_some_and(|clock| clock.on_beat(Instant::now(), BEAT_WINDOW));
+                if locked && on_beat {
+                    self.board.engine.award_beat_bonus();
+                    beat = true;
+                }
+                audio.send(AudioCommand::Events(events));
+            }
+            if let Some(recorder) = &mut self.replay {
+                recorder.record(actions.clone(), beat);
+            }
+            match &mut self.second_board {
+                Some(second_board) if self.online.is_none() => {
+                    second_board.update();
+                    if let Some(versus) = &mut self.versus {
+                        versus.exchange([&mut self.board.engine, &mut second_board.engine]);
+                    }
+                }
+                _ => {}
+            }
+            if self.finish_versus() || self.update_online(actions) {
+                return;
+            }
+            if self.board.show_results() && self.online.is_none() {
+                self.finish_pace();
+                let (mode, stats) = (self.board.engine.mode(), self.board.engine.stats());
+                if let Some(recorder) = self.replay.take() {
+                    let replay = recorder.finish(&stats);
+                    if let Err(err) = replays::save(Path::new(REPLAY_DIR), &replay) {
+                        eprintln!("Failed to save replay: {}", err);
+                    }
+                }
+                // 淘汰赛是几个人轮流打，不记到档案上
+                if self.tournament.is_none() && self.profiles.current().is_some() {
+                    self.profiles.record(&stats);
+                    self.save_profiles();
+                }
+                self.screen = if let Some(tournament) = &mut self.tournament {
+                    tournament.record(stats.score);
+                    Screen::Tournament
+                } else if self.stage.is_some() {
+                    self.finish_stage();
+                    Screen::stage_over()
+                } else if mode == Mode::Puzzle {
+                    self.finish_puzzle();
+                    Screen::puzzle_over()
+                } else if mode == Mode::Daily {
+                    self.finish_daily();
+                    Screen::game_over()
+                } else if self
+                    .high_scores
+                    .qualifies(mode, &stats, self.board.engine.is_finished())
+                {
+                    Screen::NameEntry(NameEntry::new(&self.player_name))
+                } else {
+                    Screen::game_over()
+                };
+            }
+        }
+    }
+
+    // 游戏里显示模式、分数和打了多久，别的时候就是在菜单里
+    #[cfg(feature = "discord")]
+    fn update_presence(&mut self) {
+        let Some(presence) = &mut self.presence else {
+            return;
+        };
+        let stats = self.board.engine.stats();
+        let activity = match self.screen {
+            Screen::Playing | Screen::Paused(_) => {
+                let mode = scores::mode_name(self.board.engine.mode());
+                let details = if self.online.is_some() {
+                    format!("{} (online)", mode)
+                } else if self.versus.is_some() {
+                    format!("{} (versus)", mode)
+                } else {
+                    mode.to_string()
+                };
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+                let elapsed = stats.frames / Stats::FRAMES_PER_SECOND;
+                Activity {
+                    details,
+                    state: format!("Score {}", stats.score),
+                    start: Some(now.saturating_sub(elapsed)),
+                }
+            }
+            Screen::Watch(_) => Activity {
+                details: "Watching a replay".to_string(),
+                state: String::new(),
+                start: None,
+            },
+            _ => Activity {
+                details: "In the menus".to_string(),
+                state: String::new(),
+                start: None,
+            },
+        };
+        presence.update(Some(activity));
+    }
+
+    // 游戏中放背景音乐，暂停（包括从暂停菜单进的设置）的时候停在原处，其它界面不放
+    fn update_music(&self) {
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        let paused = match &self.screen {
+            Screen::Playing => false,
+            Screen::Paused(_) => true,
+            Screen::Settings(options) if options.from_pause => true,
+            _ => {
+                audio.send(AudioCommand::Music(MusicState::default()));
+                return;
+            }
+        };
+        let engine = &self.board.engine;
+        let stats = engine.stats();
+        let in_danger = danger::danger_level(engine.matrix(), self.settings.danger_row).is_some();
+        audio.send(AudioCommand::Music(MusicState {
+            track: Some(Track::for_game(stats.level, in_danger)),
+            paused,
+            combo: stats.combo,
+            height: engine.matrix().stack_height(),
+        }));
+    }
+
+    fn window_title(&self) -> String {
+        let engine = &self.board.engine;
+        match &self.screen {
+            Screen::Playing => game_title(engine.mode(), &engine.stats(), None),
+            Screen::Paused(_) => game_title(engine.mode(), &engine.stats(), Some(&tr("PAUSED"))),
+            Screen::GameOver(_) | Screen::NameEntry(_) => {
+                game_title(engine.mode(), &engine.stats(), Some(&tr("GAME OVER")))
+            }
+            Screen::MainMenu(_)
+            | Screen::Settings(_)
+            | Screen::HighScores(_)
+            | Screen::Profiles(_)
+            | Screen::Replays(_)
+            | Screen::Watch(_)
+            | Screen::Puzzles(_)
+            | Screen::Campaign(_)
+            | Screen::Registration(_)
+            | Screen::Tournament
+            | Screen::Online(_)
+            | Screen::Join(_)
+            | Screen::Connecting(_)
+            | Screen::Lobbies(..)
+            | Screen::LocalGames(..)
+            | Screen::Review(_)
+            | Screen::Demo
+            | Screen::Editor(_) => WINDOW_TITLE.to_string(),
+        }
+    }
+
+    fn player_square(&self, canvas: &Canvas<Window>) -> Rect {
+        layout::board_squares(canvas.viewport(), self.arrangement)[0]
+    }
+
+    // 引擎这一帧在不在走：暂停、结算和开局倒数的时候都不走，画面也不该插值
+    fn is_running(&self) -> bool {
+        matches!(self.screen, Screen::Playing)
+            && !self.countdown.as_ref().is_some_and(Countdown::is_frozen)
+    }
+
+    fn draw_game(&self, canvas: &mut Canvas<Window>, skin: &SkinAssets) {
+        draw(
+            canvas,
+            &self.board,
+            self.second_board.as_ref(),
+            self.arrangement,
+            &self.settings,
+            skin,
+            Clock {
+                frame: self.frame,
+                alpha: if self.is_running() { self.alpha } else { 0.0 },
+            },
+        );
+    }
+
+    fn draw(&self, canvas: &mut Canvas<Window>, skin: &SkinAssets) {
+        match &self.screen {
+            Screen::MainMenu(menu)
+            | Screen::Puzzles(menu)
+            | Screen::Campaign(menu)
+            | Screen::Online(menu)
+            | Screen::Lobbies(_, menu)
+            | Screen::LocalGames(_, menu) => draw_main_menu(
+                canvas,
+                menu,
+                skin,
+                background_frame(&self.settings, self.frame),
+            ),
+            Screen::Playing => {
+                self.draw_game(canvas, skin);
+                let cells = self.board.engine.matrix_size();
+                if self.settings.input_display {
+                    let area = Layout::new(self.player_square(canvas), 0, cells).inputs;
+                    self.input_display.draw(canvas, area, &skin.colors);
+                }
+                if let Some(countdown) = &self.countdown {
+                    let matrix = Layout::new(self.player_square(canvas), 0, cells).matrix;
+                    countdown.draw(canvas, matrix, &skin.colors);
+                }
+                if let Some(online) = &self.online {
+                    online::draw_latency(canvas, online.latency(), &skin.colors);
+                }
+            }
+            Screen::Paused(menu) => {
+                self.draw_game(canvas, skin);
+                draw_paused(canvas, menu, skin);
+            }
+            Screen::Demo => {
+                self.draw_game(canvas, skin);
+                draw_demo_banner(canvas, skin, "DEMO");
+            }
+            Screen::Watch(_) => {
+                self.draw_game(canvas, skin);
+                draw_demo_banner(canvas, skin, "REPLAY");
+            }
+            Screen::NameEntry(entry) => {
+                self.draw_game(canvas, skin);
+                let viewport = canvas.viewport();
+                canvas.set_draw_color(skin.colors.overlay.0);
+                canvas.fill_rect(viewport).unwrap();
+                let mut area = layout::letterbox(viewport);
+                area.resize(area.width() * 2 / 3, area.height() / 2);
+                area.center_on(viewport.center());
+                entry.draw(canvas, area, &skin.colors);
+            }
+            Screen::HighScores(leaderboard) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 3 / 4, ui_square.height() * 3 / 4);
+                area.center_on(ui_square.center());
+                leaderboard.draw(canvas, area, &self.high_scores, &skin.colors);
+            }
+            Screen::Replays(browser) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 3 / 4, ui_square.height() * 3 / 4);
+                area.center_on(ui_square.center());
+                browser.draw(canvas, area, &skin.colors);
+            }
+            Screen::Profiles(profiles) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 3 / 4, ui_square.height() * 3 / 4);
+                area.center_on(ui_square.center());
+                profiles.draw(canvas, area, &self.profiles, &skin.colors);
+            }
+            Screen::Settings(options) => {
+                draw_settings(canvas, options, &self.settings, &self.choices(), skin)
+            }
+            Screen::Registration(registration) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 2 / 3, ui_square.height() * 3 / 4);
+                area.center_on(ui_square.center());
+                registration.draw(canvas, area, &skin.colors);
+            }
+            Screen::Join(entry) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 2 / 3, ui_square.height() / 2);
+                area.center_on(ui_square.center());
+                entry.draw(canvas, area, &skin.colors);
+            }
+            Screen::Connecting(pending) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 2 / 3, ui_square.height() / 2);
+                area.center_on(ui_square.center());
+                online::draw_waiting(canvas, area, pending, &skin.colors);
+            }
+            Screen::Tournament => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 7 / 8, ui_square.height() * 7 / 8);
+                area.center_on(ui_square.center());
+                if let Some(tournament) = &self.tournament {
+                    tournament.draw(canvas, area, &skin.colors);
+                }
+            }
+            Screen::Review(review) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 7 / 8, ui_square.height() * 7 / 8);
+                area.center_on(ui_square.center());
+                review.draw(canvas, area, skin);
+            }
+            Screen::GameOver(menu) => {
+                self.draw_game(canvas, skin);
+                let daily_best = (self.board.engine.mode() == Mode::Daily)
+                    .then(|| self.daily_results.best(daily::today()))
+                    .flatten();
+                draw_results(canvas, &self.board.engine, daily_best, menu, skin);
+            }
+            Screen::Editor(editor) => {
+                canvas.set_draw_color(skin.colors.background.0);
+                canvas.clear();
+                let ui_square = layout::letterbox(canvas.viewport());
+                let mut area = ui_square;
+                area.resize(ui_square.width() * 7 / 8, ui_square.height() * 7 / 8);
+                area.center_on(ui_square.center());
+                editor.draw(canvas, area, skin);
+            }
+        }
+        if let Some(log) = self.chat_log() {
+            chat::draw(canvas, log, self.chat_entry.as_ref(), &skin.colors);
+        }
+        self.toasts.draw(canvas, &skin.colors);
+        if self.settings.show_perf {
+            self.perf.draw(canvas, &skin.colors);
+        }
+        if self.debug_overlay {
+            debug::draw(canvas, &self.board.engine.debug_info(), &skin.colors);
         }
     }
 }
 
-fn draw(canvas: &mut Canvas<Window>, engine: &Engine) {
-    canvas.set_draw_color(BACKGROUND_COLOR);
+// 标准按法写成按键符号，比如 "< < ^"；什么都不用按的就是直接落下
+fn finesse_hint(optimal: &[finesse::Key]) -> String {
+    if optimal.is_empty() {
+        return tr("JUST DROP");
+    }
+    let keys = optimal
+        .iter()
+        .map(|key| match key {
+            finesse::Key::Left => "<",
+            finesse::Key::Right => ">",
+            finesse::Key::Rotate => "^",
+        })
+        .collect::<Vec<_>>();
+    format!("{} {}", tr("FINESSE"), keys.join(" "))
+}
+
+fn t_spin_name(lines: usize) -> &'static str {
+    match lines {
+        0 => "T-SPIN",
+        1 => "T-SPIN SINGLE",
+        2 => "T-SPIN DOUBLE",
+        _ => "T-SPIN TRIPLE",
+    }
+}
+
+// 手柄在游戏里的键位，换成键盘上绑的按键，DAS 之类的逻辑就不用再写一遍
+fn gameplay_key(button: Button, keybinds: &Keybinds) -> Option<Keycode> {
+    match button {
+        Button::DPadLeft => Some(keybinds.left),
+        Button::DPadRight => Some(keybinds.right),
+        Button::DPadDown => Some(keybinds.soft_drop),
+        Button::DPadUp => Some(keybinds.hard_drop),
+        Button::A | Button::B => Some(keybinds.rotate),
+        Button::LeftShoulder | Button::RightShoulder => Some(keybinds.hold),
+        Button::Start => Some(Keycode::P),
+        _ => None,
+    }
+}
+
+// 减少动效的时候背景动画停在第一帧
+fn background_frame(settings: &Settings, frame: u64) -> u64 {
+    if settings.reduced_motion {
+        0
+    } else {
+        frame
+    }
+}
+
+fn draw_main_menu(
+    canvas: &mut Canvas<Window>,
+    menu: &Menu<MenuAction>,
+    skin: &SkinAssets,
+    frame: u64,
+) {
+    background::draw(canvas, skin, frame);
+    let ui_square = layout::letterbox(canvas.viewport());
+    let (width, height) = (ui_square.width(), ui_square.height());
+
+    let title_center = Point::new(ui_square.center().x, ui_square.y() + (height / 4) as i32);
+    let title_scale = text::fit_scale(WINDOW_TITLE, width / 2);
+    text::draw_text_centered(
+        canvas,
+        WINDOW_TITLE,
+        title_center,
+        title_scale,
+        skin.colors.accent.0,
+    );
+
+    let menu_area = Rect::new(
+        ui_square.x(),
+        ui_square.y() + (height / 2) as i32,
+        width,
+        height / 3,
+    );
+    menu.draw(canvas, menu_area, &skin.colors);
+}
+
+fn draw_settings(
+    canvas: &mut Canvas<Window>,
+    options: &OptionsMenu,
+    settings: &Settings,
+    choices: &Choices,
+    skin: &SkinAssets,
+) {
+    canvas.set_draw_color(skin.colors.background.0);
     canvas.clear();
-    let ui_square = canvas.viewport();
-    let matrix = {
-        let mut middle_section = ui_square;
-        middle_section.set_width(middle_section.width() / 2);
-        middle_section.center_on(ui_square.center());
-
-        let mut matrix = middle_section;
-        matrix.resize(
-            (matrix.width() as f32 * (7.0 / 8.0)) as _,
-            (matrix.height() as f32 * (7.0 / 8.0)) as _,
-        );
-        matrix.center_on(middle_section.center());
+    let ui_square = layout::letterbox(canvas.viewport());
+    let mut area = ui_square;
+    area.resize(ui_square.width() * 3 / 4, ui_square.height() * 7 / 8);
+    area.center_on(ui_square.center());
+    options.draw(canvas, area, settings, choices, &skin.colors);
+}
+
+fn draw_paused(canvas: &mut Canvas<Window>, menu: &Menu<MenuAction>, skin: &SkinAssets) {
+    let viewport = canvas.viewport();
+    canvas.set_draw_color(skin.colors.overlay.0);
+    canvas.fill_rect(viewport).unwrap();
+
+    let ui_square = layout::letterbox(viewport);
+    let (width, height) = (ui_square.width(), ui_square.height());
+    let title_center = Point::new(ui_square.center().x, ui_square.y() + (height / 3) as i32);
+    let title = tr("PAUSED");
+    let title_scale = text::fit_scale(&title, width / 2);
+    text::draw_text_centered(
+        canvas,
+        &title,
+        title_center,
+        title_scale,
+        skin.colors.text.0,
+    );
+
+    let menu_area = Rect::new(
+        ui_square.x(),
+        ui_square.y() + (height / 2) as i32,
+        width,
+        height / 4,
+    );
+    menu.draw(canvas, menu_area, &skin.colors);
+}
+
+// 演示走一帧：电脑玩家先看上一帧的事件再操作，和 `battle::Battle::step` 一样；
+// 棋盘这一帧的事件返回出去留给下一帧
+fn demo_step(
+    driver: &mut Driver,
+    board: &mut Board,
+    events: &[crate::engine::Event],
+) -> Vec<crate::engine::Event> {
+    driver.update(&mut board.engine, events);
+    board.update()
+}
+
+// 演示和放录像的时候在上方提示一下，不挡棋盘
+fn draw_demo_banner(canvas: &mut Canvas<Window>, skin: &SkinAssets, label: &str) {
+    let ui_square = layout::letterbox(canvas.viewport());
+    let title = format!("{} - {}", tr(label), tr("PRESS ANY KEY"));
+    let scale = text::fit_scale(&title, ui_square.width() / 2);
+    let center = Point::new(
+        ui_square.center().x,
+        ui_square.y() + ui_square.height() as i32 / 24,
+    );
+    text::draw_text_centered(canvas, &title, center, scale, skin.colors.accent.0);
+}
+
+// 在游戏画面上盖一层半透明遮罩，显示本局成绩和后续选项
+fn draw_results(
+    canvas: &mut Canvas<Window>,
+    engine: &Engine,
+    // 每日挑战当天的最好成绩
+    daily_best: Option<DailyEntry>,
+    menu: &Menu<MenuAction>,
+    skin: &SkinAssets,
+) {
+    let viewport = canvas.viewport();
+    canvas.set_draw_color(skin.colors.overlay.0);
+    canvas.fill_rect(viewport).unwrap();
+
+    let ui_square = layout::letterbox(viewport);
+    let mut panel = ui_square;
+    panel.resize(ui_square.width() / 2, ui_square.height() * 2 / 3);
+    panel.center_on(ui_square.center());
+
+    let stats = engine.stats();
+    let title = tr(match engine.mode() {
+        _ if !engine.is_finished() => "GAME OVER",
+        Mode::Ultra | Mode::Daily => "TIME UP",
+        Mode::Puzzle => "SOLVED",
+        _ => "FINISH",
+    });
+    let mut lines = vec![
+        format!("{} {}", tr("SCORE"), stats.score),
+        format!("{} {}", tr("LINES"), stats.lines),
+        format!("{} {}", tr("LEVEL"), stats.level),
+        format!("{}  {}", tr("TIME"), format_time(stats.seconds())),
+    ];
+    for (label, value) in engine.mode().game_mode().results(&stats) {
+        lines.push(format!("{} {}", tr(label), value));
+    }
+    if let Some(best) = daily_best {
+        lines.push(format!("{} {}", tr("TODAY'S BEST"), best.score));
+    }
+    let title_scale = text::fit_scale(&title, panel.width());
+    let scale = (title_scale / 2).max(1);
+    let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+
+    let title_center = Point::new(panel.center().x, panel.top() + line_height);
+    let text_color = skin.colors.text.0;
+    text::draw_text_centered(canvas, &title, title_center, title_scale, text_color);
+
+    let mut y = title_center.y + line_height * 2;
+    for line in &lines {
+        let center = Point::new(panel.center().x, y);
+        text::draw_text_centered(canvas, line, center, scale, text_color);
+        y += line_height;
+    }
+
+    let menu_height = (panel.bottom() - y).max(1) as u32;
+    let menu_area = Rect::new(panel.x(), y, panel.width(), menu_height);
+    menu.draw(canvas, menu_area, &skin.colors);
+}
 
-        matrix
+// sprint / ultra 的计时器，画在分数面板的最上面
+fn draw_timer(canvas: &mut Canvas<Window>, panel: Rect, frames: u64, skin: &SkinAssets) {
+    let time = format_precise_time(frames);
+    let scale = text::fit_scale(&time, panel.width() * 7 / 8);
+    let (_, height) = text::text_size(&time, scale);
+    let center = Point::new(panel.center().x, panel.top() + height as i32);
+    text::draw_text_centered(canvas, &time, center, scale, skin.colors.text.0);
+}
+
+// mm:ss.mmm，直接用帧数换算，不用浮点数，回放时显示的时间才会一模一样
+fn format_precise_time(frames: u64) -> String {
+    let millis = frames * 1000 / Stats::FRAMES_PER_SECOND;
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn training_piece(key: Keycode) -> Option<PieceKind> {
+    let index = match key {
+        Keycode::Num1 => 0,
+        Keycode::Num2 => 1,
+        Keycode::Num3 => 2,
+        Keycode::Num4 => 3,
+        Keycode::Num5 => 4,
+        Keycode::Num6 => 5,
+        Keycode::Num7 => 6,
+        _ => return None,
     };
-    let width_ui_quarter = ui_square.width() / 4;
-    let height_ui_quarter = ui_square.height() / 4;
-    let up_next = {
-        let mut outer = ui_square;
-        outer.resize(width_ui_quarter, height_ui_quarter);
-        outer.offset(3 * width_ui_quarter as i32, 0);
-
-        let mut inner = outer;
-        inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
-        inner.center_on(outer.center());
-
-        inner
+    PieceKind::ALL.get(index).copied()
+}
+
+// 比如 "Tetris - Sprint - 01:23 - 4200"，计时模式显示计时器，其它显示已玩时间
+fn game_title(mode: Mode, stats: &Stats, state: Option<&str>) -> String {
+    let time = match mode.timer_frames(stats) {
+        Some(frames) => format_time(frames as f64 / Stats::FRAMES_PER_SECOND as f64),
+        None => format_time(stats.seconds()),
     };
-    let next_queue = {
-        let mut outer = ui_square;
-        outer.resize(width_ui_quarter, 3 * height_ui_quarter);
-        outer.offset(3 * width_ui_quarter as i32, height_ui_quarter as i32);
+    // "SPRINT" -> "Sprint"
+    let name = mode.game_mode().name();
+    let mode = name[..1].to_string() + &name[1..].to_lowercase();
+    let mut title = format!("{} - {} - {} - {}", WINDOW_TITLE, mode, time, stats.score);
+    if let Some(state) = state {
+        title.push_str(&format!(" ({})", state));
+    }
+    title
+}
 
-        let mut inner = outer;
-        inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
-        inner.center_on(outer.center());
+fn format_time(seconds: f64) -> String {
+    let total = seconds as u64;
+    format!("{:02}:{:02}", total / 60, total % 60)
+}
 
-        inner
+// 游戏画面：背景，玩家的棋盘，还有按 `arrangement` 摆放的第二块棋盘
+fn draw(
+    canvas: &mut Canvas<Window>,
+    board: &Board,
+    second_board: Option<&Board>,
+    arrangement: Arrangement,
+    settings: &Settings,
+    skin: &SkinAssets,
+    clock: Clock,
+) {
+    background::draw(canvas, skin, background_frame(settings, clock.frame));
+    if let Some(level) = danger::danger_level(board.engine.matrix(), settings.danger_row) {
+        danger::draw_tint(canvas, level, clock.frame, &skin.colors);
+    }
+    let squares = layout::board_squares(canvas.viewport(), arrangement);
+    let labels = match arrangement {
+        Arrangement::SplitScreen => [Some("P1"), Some("P2")],
+        _ => [None, None],
     };
-    let hold = {
-        let mut outer = ui_square;
-        outer.resize(width_ui_quarter, height_ui_quarter);
-
-        let mut inner = outer;
-        inner.resize(outer.width() * 3 / 4, outer.height() * 3 / 4);
-        inner.center_on(outer.center());
+    board.draw(canvas, squares[0], labels[0], settings, skin, clock);
+    if let (Some(second_board), Some(&square)) = (second_board, squares.get(1)) {
+        second_board.draw(canvas, square, labels[1], settings, skin, clock);
+    }
+}
 
-        inner
+// 在 hold / next 这样的小面板里居中画一个方块
+fn draw_piece_preview(canvas: &mut Canvas<Window>, area: Rect, kind: PieceKind, skin: &SkinAssets) {
+    let piece = Piece {
+        kind,
+        position: Vector2::new(0, 0),
+        rotation: Rotation::N,
     };
-    let score = {
-        let mut outer = ui_square;
-        outer.resize(width_ui_quarter, 3 * height_ui_quarter);
-        outer.offset(0, height_ui_quarter as i32);
+    let cells = piece.cells().unwrap();
+    let min_x = cells.iter().map(|cell| cell.x).min().unwrap();
+    let max_x = cells.iter().map(|cell| cell.x).max().unwrap();
+    let min_y = cells.iter().map(|cell| cell.y).min().unwrap();
+    let max_y = cells.iter().map(|cell| cell.y).max().unwrap();
+    let (columns, rows) = ((max_x - min_x + 1) as u32, (max_y - min_y + 1) as u32);
 
-        let mut inner = outer;
-        inner.resize(outer.width() * 7 / 8, outer.height() * 3 / 4);
-        inner.center_on(outer.center());
+    // 最宽的 I 块也要放得下
+    let size = (area.width() / 4).min(area.height() / 2);
+    let left = area.center().x - (size * columns / 2) as i32;
+    let bottom = area.center().y + (size * rows / 2) as i32;
+    for coord in cells {
+        let rect = Rect::new(
+            left + ((coord.x - min_x) as u32 * size) as i32,
+            bottom - ((coord.y - min_y + 1) as u32 * size) as i32,
+            size,
+            size,
+        );
+        skin.draw_mino(canvas, rect, kind.color(), piece_links(&cells, coord));
+    }
+}
 
-        inner
-    };
-    canvas.set_draw_color(MATRIX_COLOR);
-    // canvas.draw_rect(ui_square).unwrap();
-    canvas.fill_rect(matrix).unwrap();
-    canvas.fill_rect(up_next).unwrap();
-    canvas.fill_rect(next_queue).unwrap();
-    canvas.fill_rect(hold).unwrap();
-    canvas.fill_rect(score).unwrap();
-
-    let mut cell_draw_ctx = CellDrawCtx {
-        // 原点在左下角
-        origin: matrix.bottom_left(),
-        dims: matrix.size().into(),
-        canvas,
+// 在 matrix 上画出格子线或者棋盘格，方便数空着的列
+fn draw_grid(ctx: &mut CellDrawCtx, style: GridStyle) {
+    ctx.canvas.set_draw_color(ctx.skin.colors.grid.0);
+    match style {
+        GridStyle::Off => {}
+        GridStyle::Lines => {
+            let top = ctx.origin.y - ctx.dims.y as i32;
+            for x in 1..ctx.cells.x {
+                let line_x = ctx.cell_rect(Point2::new(x, 0)).left();
+                ctx.canvas
+                    .draw_line((line_x, top), (line_x, ctx.origin.y))
+                    .unwrap();
+            }
+            let right = ctx.origin.x + ctx.dims.x as i32;
+            for y in 1..ctx.cells.y {
+                let line_y = ctx.cell_rect(Point2::new(0, y)).bottom();
+                ctx.canvas
+                    .draw_line((ctx.origin.x, line_y), (right, line_y))
+                    .unwrap();
+            }
+        }
+        GridStyle::Checkerboard => {
+            let (columns, rows) = (ctx.cells.x, ctx.cells.y);
+            let cells = (0..rows)
+                .flat_map(|y| (0..columns).map(move |x| Point2::new(x, y)))
+                .filter(|coord| (coord.x + coord.y) % 2 == 1)
+                .map(|coord| ctx.cell_rect(coord))
+                .collect::<Vec<_>>();
+            ctx.canvas.fill_rects(&cells).unwrap();
+        }
+    }
+}
+
+// 消行动画：前半段被消除的行闪烁，后半段这些行消失，上面的行逐渐落下来
+// 被消的行按引擎给的行号整行画成高亮色，和白色交替闪烁，之后在原地淡出
+const LINE_CLEAR_FLASH_PHASE: f32 = 0.5;
+
+// `flash` 为 false（减少动效）时不闪，只显示高亮再淡出
+fn draw_line_clear(
+    ctx: &mut CellDrawCtx,
+    engine: &Engine,
+    rows: &[usize],
+    progress: f32,
+    flash: bool,
+) {
+    const FLASH_PHASE: f32 = LINE_CLEAR_FLASH_PHASE;
+    const FLASH_COUNT: f32 = 3.0;
+
+    let highlight = ctx.skin.colors.line_clear_highlight.0;
+    let flash_on = flash
+        && progress < FLASH_PHASE
+        && ((progress / FLASH_PHASE * FLASH_COUNT * 2.0) as u32).is_multiple_of(2);
+    let row_color = if flash_on {
+        ctx.skin.colors.line_clear_flash.0
+    } else {
+        let alpha = highlight.a as f32 * line_clear_highlight_fade(progress);
+        SdlColor::RGBA(highlight.r, highlight.g, highlight.b, alpha as u8)
     };
-    // matrix 上已存在的 cell
-    for (coord, cell_color) in engine.cells() {
-        cell_draw_ctx.draw_cell(*cell_color, coord);
+    for &y in rows {
+        for x in 0..ctx.cells.x {
+            ctx.draw_cell_shifted(row_color, Point2::new(x, y), 0.0);
+        }
     }
-    // cursor 处的 piece
-    if let Some((cursor_cells, color)) = engine.cursor_info() {
-        for coord in cursor_cells {
-            cell_draw_ctx.draw_cell(Some(color), coord);
+
+    // 其余的行先停在原地，闪完之后往下落填上空出来的行
+    for (coord, cell_color) in engine.cells() {
+        let Some(cell_color) = cell_color else {
+            continue;
+        };
+        if rows.contains(&coord.y) {
+            continue;
         }
+        let links = matrix_links(engine.matrix(), coord);
+        let shift = if progress < FLASH_PHASE {
+            0.0
+        } else {
+            let collapse = (progress - FLASH_PHASE) / (1.0 - FLASH_PHASE);
+            let rows_below = rows.iter().filter(|&&y| y < coord.y).count();
+            rows_below as f32 * collapse
+        };
+        ctx.draw_locked_mino(engine, *cell_color, coord, links, shift);
+    }
+}
+
+// 高亮的不透明度：闪烁阶段保持不变，上面的行往下落的时候淡出
+fn line_clear_highlight_fade(progress: f32) -> f32 {
+    if progress < LINE_CLEAR_FLASH_PHASE {
+        1.0
+    } else {
+        (1.0 - (progress - LINE_CLEAR_FLASH_PHASE) / (1.0 - LINE_CLEAR_FLASH_PHASE)).clamp(0.0, 1.0)
+    }
+}
+
+// 上下左右哪些邻居和自己是同一种颜色
+fn matrix_links(matrix: &Matrix, coord: Point2<usize>) -> Links {
+    let coord = coord.cast::<isize>().unwrap();
+    let color = matrix.get(coord.x, coord.y);
+    let same = |dx, dy| color.is_some() && matrix.get(coord.x + dx, coord.y + dy) == color;
+    Links {
+        up: same(0, 1),
+        down: same(0, -1),
+        left: same(-1, 0),
+        right: same(1, 0),
+    }
+}
+
+fn piece_links(cells: &[Point2<usize>], coord: Point2<usize>) -> Links {
+    let has = |x: usize, y: usize| cells.contains(&Point2::new(x, y));
+    Links {
+        up: has(coord.x, coord.y + 1),
+        down: coord.y > 0 && has(coord.x, coord.y - 1),
+        left: coord.x > 0 && has(coord.x - 1, coord.y),
+        right: has(coord.x + 1, coord.y),
     }
-    canvas.present();
+}
+
+// 画面用的时间：第几个逻辑帧，加上距离下一帧走了多少（0 到 1）
+#[derive(Clone, Copy, Default, Debug)]
+struct Clock {
+    frame: u64,
+    alpha: f32,
 }
 
 struct CellDrawCtx<'a> {
     origin: Point,
+    // matrix 的像素大小
     dims: Vector2<u32>,
+    // matrix 有几列几行
+    cells: Vector2<usize>,
+    // 高对比度模式下给方块描的边
+    outline: Option<SdlColor>,
     canvas: &'a mut Canvas<Window>,
+    skin: &'a SkinAssets<'a>,
 }
 
 impl CellDrawCtx<'_> {
-    fn draw_cell(&mut self, cell_color: Option<SemanticColor>, coord: Point2<usize>) {
-        if let Some(cell_color) = cell_color {
-            let matrix_width = self.dims.x;
-            let matrix_height = self.dims.y;
-            let coord = coord.cast::<i32>().unwrap();
-            let this_x = (coord.x + 0) * matrix_width as i32 / Matrix::WIDTH as i32;
-            let next_x = (coord.x + 1) * matrix_width as i32 / Matrix::WIDTH as i32;
-            // y 轴需要额外偏移一个 matrix_height
-            let this_y = (coord.y + 1) * matrix_height as i32 / Matrix::HEIGHT as i32;
-            // 因为我们想要的坐标系是，原点在左下角，y 轴从下往上递增
-            // 但实际 sdl2 的坐标系是，原点在左上角，y 轴是从上往下递增
-            // 所以这里的 next_y 的坐标应该是比 this_y 要小
-            let next_y = (coord.y + 0) * matrix_height as i32 / Matrix::HEIGHT as i32;
-            let cell_rect = Rect::new(
-                self.origin.x + this_x,
-                self.origin.y - this_y,
-                (next_x - this_x) as u32,
-                (this_y - next_y) as u32,
-            );
-
-            self.canvas.set_draw_color(cell_color.screen_color());
-            // canvas.draw_rect(cell_rect).unwrap();
-            self.canvas.fill_rect(cell_rect).unwrap();
-        }
-    }
-}
-
-trait ScreenColor {
-    fn screen_color(&self) -> SdlColor;
-}
-
-impl ScreenColor for SemanticColor {
-    fn screen_color(&self) -> SdlColor {
-        match self {
-            SemanticColor::Yellow => SdlColor::RGB(0xed, 0xd4, 0x00),
-            SemanticColor::Cyan => SdlColor::RGB(0x72, 0x9f, 0xcf),
-            SemanticColor::Purple => SdlColor::RGB(0x75, 0x50, 0x7b),
-            SemanticColor::Orange => SdlColor::RGB(0xf5, 0x79, 0x00),
-            SemanticColor::Blue => SdlColor::RGB(0x34, 0x65, 0xa4),
-            SemanticColor::Green => SdlColor::RGB(0x73, 0xd2, 0x16),
-            SemanticColor::Red => SdlColor::RGB(0xef, 0x29, 0x29),
+    #[allow(clippy::identity_op)]
+    fn cell_rect(&self, coord: Point2<usize>) -> Rect {
+        let matrix_width = self.dims.x;
+        let matrix_height = self.dims.y;
+        let coord = coord.cast::<i32>().unwrap();
+        let (columns, rows) = (self.cells.x as i32, self.cells.y as i32);
+        let this_x = (coord.x + 0) * matrix_width as i32 / columns;
+        let next_x = (coord.x + 1) * matrix_width as i32 / columns;
+        // y 轴需要额外偏移一个 matrix_height
+        let this_y = (coord.y + 1) * matrix_height as i32 / rows;
+        // 因为我们想要的坐标系是，原点在左下角，y 轴从下往上递增
+        // 但实际 sdl2 的坐标系是，原点在左上角，y 轴是从上往下递增
+        // 所以这里的 next_y 的坐标应该是比 this_y 要小
+        let next_y = (coord.y + 0) * matrix_height as i32 / rows;
+        Rect::new(
+            self.origin.x + this_x,
+            self.origin.y - this_y,
+            (next_x - this_x) as u32,
+            (this_y - next_y) as u32,
+        )
+    }
+
+    // 刚出生的 I 竖起来会伸出 matrix 顶上，那一格不画
+    fn is_hidden(&self, coord: Point2<usize>) -> bool {
+        coord.y >= self.cells.y
+    }
+
+    // 往下偏移 `rows` 个格子（可以是小数），消行下落动画用
+    fn shifted_cell_rect(&self, coord: Point2<usize>, rows: f32) -> Rect {
+        let mut cell_rect = self.cell_rect(coord);
+        let shift = rows * self.dims.y as f32 / self.cells.y as f32;
+        cell_rect.offset(0, shift as i32);
+        cell_rect
+    }
+
+    // 按当前皮肤画一个方块格子
+    fn draw_mino(&mut self, color: SemanticColor, coord: Point2<usize>, links: Links, rows: f32) {
+        if self.is_hidden(coord) {
+            return;
+        }
+        let cell_rect = self.shifted_cell_rect(coord, rows);
+        self.skin.draw_mino(self.canvas, cell_rect, color, links);
+        if let Some(outline) = self.outline {
+            self.canvas.set_draw_color(outline);
+            let thickness = (cell_rect.width() / 12).max(1);
+            for i in 0..thickness {
+                let mut border = cell_rect;
+                border.resize(cell_rect.width() - 2 * i, cell_rect.height() - 2 * i);
+                border.center_on(cell_rect.center());
+                self.canvas.draw_rect(border).unwrap();
+            }
+        }
+    }
+
+    // matrix 里已经锁定的格子，invisible 模式按可见度盖上一层底色淡出
+    fn draw_locked_mino(
+        &mut self,
+        engine: &Engine,
+        color: SemanticColor,
+        coord: Point2<usize>,
+        links: Links,
+        rows: f32,
+    ) {
+        let visibility = engine.cell_visibility(coord);
+        if visibility <= 0.0 {
+            return;
         }
+        self.draw_mino(color, coord, links, rows);
+        if visibility < 1.0 {
+            let base = self.skin.colors.matrix.0;
+            let alpha = ((1.0 - visibility) * 255.0) as u8;
+            self.draw_cell_shifted(SdlColor::RGBA(base.r, base.g, base.b, alpha), coord, rows);
+        }
+    }
+
+    // 只描边，落点提示用
+    fn draw_cell_outline(&mut self, color: SdlColor, coord: Point2<usize>) {
+        if self.is_hidden(coord) {
+            return;
+        }
+        let cell_rect = self.cell_rect(coord);
+        let thickness = (cell_rect.width() / 12).max(1);
+        self.canvas.set_draw_color(color);
+        for i in 0..thickness {
+            let mut border = cell_rect;
+            border.resize(cell_rect.width() - 2 * i, cell_rect.height() - 2 * i);
+            border.center_on(cell_rect.center());
+            self.canvas.draw_rect(border).unwrap();
+        }
+    }
+
+    // 纯色填充，特效的半透明叠加层用
+    fn draw_cell_shifted(&mut self, color: SdlColor, coord: Point2<usize>, rows: f32) {
+        if self.is_hidden(coord) {
+            return;
+        }
+        let cell_rect = self.shifted_cell_rect(coord, rows);
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(cell_rect).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::engine::bot::{Bot, BotView, Decision, Input};
+    use crate::engine::Event;
+
+    #[test]
+    fn demo_bots_observe_the_previous_frames_events() {
+        // 一出块就硬降，记下有没有看到过锁定
+        struct Dropper(Rc<Cell<bool>>);
+        impl Bot for Dropper {
+            fn name(&self) -> &str {
+                "DROPPER"
+            }
+
+            fn observe(&mut self, events: &[Event]) {
+                if events
+                    .iter()
+                    .any(|event| matches!(event, Event::Lock { .. }))
+                {
+                    self.0.set(true);
+                }
+            }
+
+            fn decide(&mut self, _view: &BotView) -> Decision {
+                Decision::Inputs(vec![Input::HardDrop])
+            }
+        }
+
+        let saw_lock = Rc::new(Cell::new(false));
+        let mut driver = Driver::new(Box::new(Dropper(saw_lock.clone())), 0);
+        let mut board = Board::new(Mode::Marathon);
+        board.engine.spawn_first();
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            events = demo_step(&mut driver, &mut board, &events);
+        }
+        assert!(saw_lock.get());
+    }
+
+    #[test]
+    fn line_clear_highlight_fades_after_flashing() {
+        assert_eq!(line_clear_highlight_fade(0.0), 1.0);
+        assert_eq!(line_clear_highlight_fade(0.49), 1.0);
+        assert_eq!(line_clear_highlight_fade(0.75), 0.5);
+        assert_eq!(line_clear_highlight_fade(1.0), 0.0);
+    }
+
+    #[test]
+    fn precise_time_from_frames() {
+        assert_eq!(format_precise_time(0), "00:00.000");
+        assert_eq!(format_precise_time(61), "00:01.016");
+        assert_eq!(format_precise_time(Mode::ULTRA_FRAMES), "02:00.000");
+    }
+
+    #[test]
+    fn title_shows_mode_time_and_score() {
+        let stats = Stats {
+            score: 4200,
+            frames: 83 * Stats::FRAMES_PER_SECOND,
+            ..Stats::default()
+        };
+        assert_eq!(
+            game_title(Mode::Sprint, &stats, None),
+            "Tetris - Sprint - 01:23 - 4200"
+        );
+        assert_eq!(
+            game_title(Mode::Ultra, &stats, Some("PAUSED")),
+            "Tetris - Ultra - 00:37 - 4200 (PAUSED)"
+        );
     }
 }
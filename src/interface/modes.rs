@@ -0,0 +1,28 @@
+use std::fs;
+use std::path::Path;
+
+use crate::engine::custom::ModeFile;
+
+// 自定义模式的目录，每个模式一个 .toml，按文件名排序，格式见 `ModeFile`
+pub const MODE_DIR: &str = "modes";
+
+pub fn load_all(dir: &Path) -> Vec<ModeFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| {
+            let file = fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| ModeFile::parse(&source));
+            file.map_err(|err| eprintln!("Failed to load mode {}: {}", path.display(), err))
+                .ok()
+        })
+        .collect()
+}
@@ -0,0 +1,443 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use sdl2::mixer::{Channel, Chunk};
+use serde::Deserialize;
+
+use super::audio::synthesized_chunk;
+
+// 每首曲子最多几轨，每轨占一个保留通道
+const MAX_LAYERS: usize = 4;
+pub const MUSIC_CHANNELS: usize = MAX_LAYERS * 2;
+// 换曲子的交叉淡入淡出，和每一轨淡入淡出的帧数
+const CROSSFADE_FRAMES: f32 = 60.0;
+const LAYER_FADE_FRAMES: f32 = 90.0;
+// 到了这一级就换成快的那首
+const FAST_LEVEL: u32 = 10;
+// 俄罗斯方块的经典旋律 Korobeiniki，(音符, 几个八分音符长)，0 是休止
+const MELODY: [(f32, u32); 38] = [
+    (659.3, 2),
+    (493.9, 1),
+    (523.3, 1),
+    (587.3, 2),
+    (523.3, 1),
+    (493.9, 1),
+    (440.0, 2),
+    (440.0, 1),
+    (523.3, 1),
+    (659.3, 2),
+    (587.3, 1),
+    (523.3, 1),
+    (493.9, 3),
+    (523.3, 1),
+    (587.3, 2),
+    (659.3, 2),
+    (523.3, 2),
+    (440.0, 2),
+    (440.0, 4),
+    (0.0, 1),
+    (587.3, 2),
+    (698.5, 1),
+    (880.0, 2),
+    (784.0, 1),
+    (698.5, 1),
+    (659.3, 3),
+    (523.3, 1),
+    (659.3, 2),
+    (587.3, 1),
+    (523.3, 1),
+    (493.9, 2),
+    (493.9, 1),
+    (523.3, 1),
+    (587.3, 2),
+    (659.3, 2),
+    (523.3, 2),
+    (440.0, 2),
+    (440.0, 4),
+];
+// 低音每小节一个根音，八分音符高低八度交替，和旋律一样长
+const BASS_ROOTS: [f32; 8] = [82.4, 110.0, 82.4, 110.0, 146.8, 130.8, 82.4, 110.0];
+
+// 背景音乐：平时放正常的，堆得太高或者等级够高就换成快的
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Track {
+    Normal,
+    Fast,
+}
+
+impl Track {
+    const ALL: [Track; 2] = [Track::Normal, Track::Fast];
+
+    pub fn for_game(level: u32, in_danger: bool) -> Track {
+        if in_danger || level >= FAST_LEVEL {
+            Track::Fast
+        } else {
+            Track::Normal
+        }
+    }
+
+    // 合成的曲子一拍是两个八分音符
+    fn beat(self) -> Duration {
+        Duration::from_millis(u64::from(self.eighth_millis()) * 2)
+    }
+
+    // 八分音符多少毫秒
+    fn eighth_millis(self) -> u32 {
+        match self {
+            Track::Normal => 200,
+            Track::Fast => 130,
+        }
+    }
+
+    fn melody(self) -> Vec<(f32, u32)> {
+        MELODY
+            .iter()
+            .map(|&(pitch, eighths)| (pitch, eighths * self.eighth_millis()))
+            .collect()
+    }
+
+    fn bass(self) -> Vec<(f32, u32)> {
+        BASS_ROOTS
+            .iter()
+            .flat_map(|&root| (0..8).map(move |i| if i % 2 == 0 { root } else { root * 2.0 }))
+            .map(|pitch| (pitch, self.eighth_millis()))
+            .collect()
+    }
+
+    // 这首曲子第 `layer` 轨用的通道
+    fn channel(self, layer: usize) -> Channel {
+        Channel((self as usize * MAX_LAYERS + layer) as i32)
+    }
+}
+
+// 一轨什么时候响：连击数或者堆的高度到了其中一个就淡入，两个都没写就一直响
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct LayerConfig {
+    pub file: PathBuf,
+    #[serde(default)]
+    pub combo: Option<u32>,
+    #[serde(default)]
+    pub height: Option<usize>,
+}
+
+impl LayerConfig {
+    fn is_active(&self, state: &MusicState) -> bool {
+        if self.combo.is_none() && self.height.is_none() {
+            return true;
+        }
+        self.combo.is_some_and(|combo| state.combo >= combo)
+            || self.height.is_some_and(|height| state.height >= height)
+    }
+}
+
+// 音效包 pack.toml 里的 [soundtrack]：两首曲子各自的分轨，从下往上叠
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct SoundtrackConfig {
+    #[serde(default)]
+    pub normal: Vec<LayerConfig>,
+    #[serde(default)]
+    pub fast: Vec<LayerConfig>,
+    // 分轨文件的速度，没写就当和合成的一样
+    #[serde(default)]
+    pub normal_bpm: Option<f32>,
+    #[serde(default)]
+    pub fast_bpm: Option<f32>,
+}
+
+impl SoundtrackConfig {
+    // 文件名是相对于音效包目录写的
+    pub fn relative_to(mut self, dir: &Path) -> SoundtrackConfig {
+        for layer in self.normal.iter_mut().chain(&mut self.fast) {
+            layer.file = dir.join(&layer.file);
+        }
+        self
+    }
+
+    fn layers(&self, track: Track) -> &[LayerConfig] {
+        match track {
+            Track::Normal => &self.normal,
+            Track::Fast => &self.fast,
+        }
+    }
+
+    fn beat(&self, track: Track) -> Duration {
+        let bpm = match track {
+            Track::Normal => self.normal_bpm,
+            Track::Fast => self.fast_bpm,
+        };
+        bpm.filter(|&bpm| bpm > 0.0)
+            .map_or(track.beat(), |bpm| Duration::from_secs_f32(60.0 / bpm))
+    }
+}
+
+// 正在放的曲子的节拍：从 `start` 开始每 `period` 一拍。暂停过的话 `start` 往后挪，
+// 所以总是和听到的对得上
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BeatClock {
+    pub start: Instant,
+    pub period: Duration,
+}
+
+impl BeatClock {
+    // 离最近的一拍差多少，早一点晚一点都算
+    pub fn offset(&self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f64();
+        let period = self.period.as_secs_f64();
+        let phase = elapsed % period;
+        Duration::from_secs_f64(phase.min(period - phase))
+    }
+
+    pub fn on_beat(&self, now: Instant, window: Duration) -> bool {
+        self.offset(now) <= window
+    }
+}
+
+// 游戏这一帧的状态，音乐跟着它变
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MusicState {
+    // None 是不放
+    pub track: Option<Track>,
+    pub paused: bool,
+    pub combo: u32,
+    pub height: usize,
+}
+
+// 往 `target` 走一步，`frames` 帧从 0 走到 1
+fn approach(value: f32, target: f32, frames: f32) -> f32 {
+    if value < target {
+        (value + 1.0 / frames).min(target)
+    } else {
+        (value - 1.0 / frames).max(target)
+    }
+}
+
+struct Layer {
+    chunk: Chunk,
+    config: LayerConfig,
+    level: f32,
+}
+
+#[derive(Default)]
+struct TrackPlayer {
+    layers: Vec<Layer>,
+    beat: Duration,
+    // 开始放了才有
+    clock: Option<BeatClock>,
+    gain: f32,
+}
+
+// 分轨的背景音乐：同一首的所有轨一起开始循环，保持对齐，只靠音量决定哪几轨听得见
+pub struct MusicPlayer {
+    tracks: Vec<TrackPlayer>,
+    volume: i32,
+    // 现在要放的曲子和暂停的时刻
+    current: Option<Track>,
+    paused_at: Option<Instant>,
+}
+
+impl MusicPlayer {
+    pub fn load(config: &SoundtrackConfig, spec: (i32, i32)) -> Result<MusicPlayer, String> {
+        Ok(MusicPlayer {
+            tracks: Self::load_tracks(config, spec)?,
+            volume: 0,
+            current: None,
+            paused_at: None,
+        })
+    }
+
+    // 换音效包：停掉正在放的，音量保持不变
+    pub fn reload(&mut self, config: &SoundtrackConfig, spec: (i32, i32)) -> Result<(), String> {
+        for channel in 0..MUSIC_CHANNELS {
+            Channel(channel as i32).halt();
+        }
+        self.tracks = Self::load_tracks(config, spec)?;
+        Ok(())
+    }
+
+    // 包里没写分轨的曲子用合成的：旋律一直响，低音在连击或者堆高的时候进来
+    fn load_tracks(
+        config: &SoundtrackConfig,
+        spec: (i32, i32),
+    ) -> Result<Vec<TrackPlayer>, String> {
+        let mut tracks = Vec::new();
+        for track in Track::ALL {
+            let mut layers = Vec::new();
+            for config in config.layers(track).iter().take(MAX_LAYERS) {
+                // 文件读不了也没法合成，这一轨就不要了
+                match Chunk::from_file(&config.file) {
+                    Ok(chunk) => layers.push(Layer {
+                        chunk,
+                        config: config.clone(),
+                        level: 0.0,
+                    }),
+                    Err(err) => eprintln!("Failed to load {}: {}", config.file.display(), err),
+                }
+            }
+            if layers.is_empty() {
+                layers = Self::synthesized(track, spec)?;
+            }
+            tracks.push(TrackPlayer {
+                layers,
+                beat: config.beat(track),
+                ..TrackPlayer::default()
+            });
+        }
+        Ok(tracks)
+    }
+
+    fn synthesized(track: Track, spec: (i32, i32)) -> Result<Vec<Layer>, String> {
+        let melody = LayerConfig::default();
+        let bass = LayerConfig {
+            combo: Some(2),
+            height: Some(10),
+            ..LayerConfig::default()
+        };
+        Ok(vec![
+            Layer {
+                chunk: synthesized_chunk(&track.melody(), spec)?,
+                config: melody,
+                level: 0.0,
+            },
+            Layer {
+                chunk: synthesized_chunk(&track.bass(), spec)?,
+                config: bass,
+                level: 0.0,
+            },
+        ])
+    }
+
+    // chunk 要在关掉设备之前释放
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    pub fn set_volume(&mut self, volume: i32) {
+        self.volume = volume;
+    }
+
+    // 暂停的时候没有节拍
+    pub fn beat(&self) -> Option<BeatClock> {
+        let track = self.current.filter(|_| self.paused_at.is_none())?;
+        self.tracks.get(track as usize)?.clock
+    }
+
+    // 每帧调一次：曲子之间交叉淡入淡出，同一首里按状态淡入淡出各轨
+    pub fn update(&mut self, state: &MusicState) {
+        let now = Instant::now();
+        self.current = state.track;
+        match (state.paused, self.paused_at) {
+            (true, None) => self.paused_at = Some(now),
+            (false, Some(paused_at)) => {
+                for clock in self
+                    .tracks
+                    .iter_mut()
+                    .filter_map(|player| player.clock.as_mut())
+                {
+                    clock.start += now - paused_at;
+                }
+                self.paused_at = None;
+            }
+            _ => {}
+        }
+        for (&track, player) in Track::ALL.iter().zip(&mut self.tracks) {
+            let wanted = state.track == Some(track);
+            player.gain = approach(player.gain, f32::from(u8::from(wanted)), CROSSFADE_FRAMES);
+            if wanted && player.clock.is_none() {
+                for (i, layer) in player.layers.iter_mut().enumerate() {
+                    // 一开始就该响的轨直接满音量，跟着整首一起淡入
+                    layer.level = f32::from(u8::from(layer.config.is_active(state)));
+                    let channel = track.channel(i);
+                    channel.set_volume(0);
+                    if let Err(err) = channel.play(&layer.chunk, -1) {
+                        eprintln!("Failed to play music: {}", err);
+                    }
+                }
+                player.clock = Some(BeatClock {
+                    start: now,
+                    period: player.beat,
+                });
+            } else if !wanted && player.gain == 0.0 && player.clock.is_some() {
+                for i in 0..player.layers.len() {
+                    track.channel(i).halt();
+                }
+                player.clock = None;
+            }
+            for (i, layer) in player.layers.iter_mut().enumerate() {
+                let target = f32::from(u8::from(layer.config.is_active(state)));
+                layer.level = approach(layer.level, target, LAYER_FADE_FRAMES);
+                let channel = track.channel(i);
+                channel.set_volume((self.volume as f32 * player.gain * layer.level) as i32);
+                if state.paused && !channel.is_paused() {
+                    channel.pause();
+                } else if !state.paused && channel.is_paused() {
+                    channel.resume();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn music_speeds_up_when_in_danger_or_fast() {
+        assert_eq!(Track::for_game(1, false), Track::Normal);
+        assert_eq!(Track::for_game(1, true), Track::Fast);
+        assert_eq!(Track::for_game(FAST_LEVEL, false), Track::Fast);
+        let length = |notes: Vec<(f32, u32)>| notes.iter().map(|&(_, ms)| ms).sum::<u32>();
+        assert!(length(Track::Fast.melody()) < length(Track::Normal.melody()));
+        // 分轨要一样长，循环起来才对得上
+        assert_eq!(length(Track::Normal.melody()), length(Track::Normal.bass()));
+    }
+
+    #[test]
+    fn beats_count_from_the_start_of_the_loop() {
+        let start = Instant::now();
+        let clock = BeatClock {
+            start,
+            period: Track::Normal.beat(),
+        };
+        let window = Duration::from_millis(50);
+        assert!(clock.on_beat(start + Duration::from_millis(820), window));
+        assert!(clock.on_beat(start + Duration::from_millis(1180), window));
+        assert!(!clock.on_beat(start + Duration::from_millis(1000), window));
+        assert_eq!(
+            clock.offset(start + Duration::from_millis(1000)),
+            Duration::from_millis(200)
+        );
+
+        let config: SoundtrackConfig = toml::from_str("normal_bpm = 120").unwrap();
+        assert_eq!(config.beat(Track::Normal), Duration::from_millis(500));
+        assert_eq!(config.beat(Track::Fast), Track::Fast.beat());
+    }
+
+    #[test]
+    fn layers_fade_in_with_combo_or_height() {
+        let config: SoundtrackConfig = toml::from_str(
+            r#"
+            [[normal]]
+            file = "melody.ogg"
+
+            [[normal]]
+            file = "drums.ogg"
+            combo = 3
+            height = 12
+            "#,
+        )
+        .unwrap();
+        let [melody, drums] = &config.normal[..] else {
+            panic!("expected two layers");
+        };
+        let calm = MusicState::default();
+        assert!(melody.is_active(&calm));
+        assert!(!drums.is_active(&calm));
+        let combo = MusicState { combo: 3, ..calm };
+        let high = MusicState { height: 12, ..calm };
+        assert!(drums.is_active(&combo) && drums.is_active(&high));
+
+        assert_eq!(approach(0.0, 1.0, 4.0), 0.25);
+        assert_eq!(approach(0.1, 0.0, 4.0), 0.0);
+    }
+}
@@ -0,0 +1,182 @@
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::text;
+
+// 菜单里用到的输入，键盘和手柄都先翻译成这个，菜单只认它
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Nav {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+impl Nav {
+    pub fn from_key(key: Keycode) -> Option<Self> {
+        match key {
+            Keycode::Up => Some(Nav::Up),
+            Keycode::Down => Some(Nav::Down),
+            Keycode::Left => Some(Nav::Left),
+            Keycode::Right => Some(Nav::Right),
+            Keycode::Return | Keycode::Space => Some(Nav::Confirm),
+            Keycode::Backspace => Some(Nav::Back),
+            _ => None,
+        }
+    }
+
+    // 按 Xbox 手柄的习惯：A 确认，B 返回
+    pub fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::DPadUp => Some(Nav::Up),
+            Button::DPadDown => Some(Nav::Down),
+            Button::DPadLeft => Some(Nav::Left),
+            Button::DPadRight => Some(Nav::Right),
+            Button::A | Button::Start => Some(Nav::Confirm),
+            Button::B | Button::Back => Some(Nav::Back),
+            _ => None,
+        }
+    }
+}
+
+// 竖排列表里当前选中的是第几项，上下移动到头了会绕回去
+#[derive(Clone, Copy, Debug)]
+pub struct Focus {
+    index: usize,
+    len: usize,
+}
+
+impl Focus {
+    pub fn new(len: usize) -> Self {
+        debug_assert!(len > 0);
+        Self { index: 0, len }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.index == self.len - 1
+    }
+
+    pub fn up(&mut self) {
+        self.index = (self.index + self.len - 1) % self.len;
+    }
+
+    pub fn down(&mut self) {
+        self.index = (self.index + 1) % self.len;
+    }
+}
+
+// 设置项右边显示的控件，左右键改值
+#[derive(Clone, PartialEq, Debug)]
+pub enum Widget {
+    Toggle(bool),
+    // 从 0 到 max 的数值，`unit` 写在数字后面
+    Slider {
+        value: u32,
+        max: u32,
+        unit: &'static str,
+    },
+    // 在几个选项里轮换
+    Choice(String),
+}
+
+impl Widget {
+    fn label(&self) -> String {
+        match self {
+            Widget::Toggle(true) => tr("ON"),
+            Widget::Toggle(false) => tr("OFF"),
+            Widget::Slider { value, unit, .. } => format!("{}{}", value, unit),
+            Widget::Choice(choice) => choice.clone(),
+        }
+    }
+
+    // 滑块填满的比例
+    fn fill(&self) -> Option<f32> {
+        match self {
+            Widget::Slider { value, max, .. } => Some(*value as f32 / (*max).max(1) as f32),
+            _ => None,
+        }
+    }
+
+    // 右对齐画在 `right` 左边，返回占了多宽
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        right: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+        selected: bool,
+    ) -> u32 {
+        let label = if selected {
+            format!("< {} >", self.label())
+        } else {
+            self.label()
+        };
+        let (width, height) = text::text_size(&label, scale);
+        text::draw_text(
+            canvas,
+            &label,
+            Point::new(right - width as i32, y),
+            scale,
+            color,
+        );
+        let Some(fill) = self.fill() else {
+            return width;
+        };
+        // 数值左边再画一条进度条
+        let gap = text::ADVANCE * scale;
+        let bar_width = gap * 8;
+        let bar = Rect::new(
+            right - (width + gap + bar_width) as i32,
+            y,
+            bar_width,
+            height,
+        );
+        canvas.set_draw_color(color);
+        canvas.draw_rect(bar).unwrap();
+        let filled = (bar_width as f32 * fill) as u32;
+        if filled > 0 {
+            canvas
+                .fill_rect(Rect::new(bar.x(), bar.y(), filled, height))
+                .unwrap();
+        }
+        width + gap + bar_width
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn focus_wraps_around() {
+        let mut focus = Focus::new(3);
+        focus.up();
+        assert_eq!(focus.index(), 2);
+        assert!(focus.is_last());
+        focus.down();
+        assert_eq!(focus.index(), 0);
+    }
+
+    #[test]
+    fn keyboard_and_gamepad_share_actions() {
+        assert_eq!(Nav::from_key(Keycode::Return), Nav::from_button(Button::A));
+        assert_eq!(
+            Nav::from_key(Keycode::Backspace),
+            Nav::from_button(Button::B)
+        );
+        assert_eq!(Nav::from_key(Keycode::Up), Nav::from_button(Button::DPadUp));
+        assert_eq!(Nav::from_key(Keycode::A), None);
+    }
+}
@@ -0,0 +1,167 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::{HexColor, PieceColors, SkinAssets, SkinColors};
+use super::text;
+use crate::engine::piece::Kind as PieceKind;
+use crate::engine::{Color as SemanticColor, Stats};
+
+const WHITE: Color = Color::RGB(0xfc, 0xfc, 0xfc);
+
+// 红白机俄罗斯方块每一级两种颜色，十级一轮
+const PALETTES: [(Color, Color); 10] = [
+    (Color::RGB(0x00, 0x58, 0xf8), Color::RGB(0x3c, 0xbc, 0xfc)),
+    (Color::RGB(0x00, 0xa8, 0x00), Color::RGB(0xb8, 0xf8, 0x18)),
+    (Color::RGB(0xd8, 0x00, 0xcc), Color::RGB(0xf8, 0x78, 0xf8)),
+    (Color::RGB(0x00, 0x58, 0xf8), Color::RGB(0x58, 0xd8, 0x54)),
+    (Color::RGB(0xe4, 0x00, 0x58), Color::RGB(0x58, 0xf8, 0x98)),
+    (Color::RGB(0x58, 0xf8, 0x98), Color::RGB(0x68, 0x88, 0xfc)),
+    (Color::RGB(0xf8, 0x38, 0x00), Color::RGB(0x7c, 0x7c, 0x7c)),
+    (Color::RGB(0x68, 0x44, 0xfc), Color::RGB(0xa8, 0x00, 0x20)),
+    (Color::RGB(0x00, 0x58, 0xf8), Color::RGB(0xf8, 0x38, 0x00)),
+    (Color::RGB(0xf8, 0x38, 0x00), Color::RGB(0xfc, 0xa0, 0x44)),
+];
+
+// 界面配色：黑底、灰框，和原版一样朴素
+pub fn skin_colors() -> SkinColors {
+    SkinColors {
+        background: HexColor(Color::RGB(0x74, 0x74, 0x74)),
+        matrix: HexColor(Color::RGB(0x00, 0x00, 0x00)),
+        grid: HexColor(Color::RGBA(0x00, 0x00, 0x00, 0x00)),
+        accent: HexColor(WHITE),
+        pieces: piece_colors(1),
+        ..SkinColors::default()
+    }
+}
+
+// 原版只有三种格子：T、O、I 是白心带框的，J、S 用第一种颜色，L、Z 用第二种
+pub fn piece_colors(level: u32) -> PieceColors {
+    let (primary, secondary) = PALETTES[(level.max(1) - 1) as usize % PALETTES.len()];
+    PieceColors {
+        yellow: HexColor(primary),
+        cyan: HexColor(primary),
+        purple: HexColor(primary),
+        orange: HexColor(secondary),
+        blue: HexColor(primary),
+        green: HexColor(primary),
+        red: HexColor(secondary),
+        gray: HexColor(secondary),
+    }
+}
+
+fn is_hollow(color: SemanticColor) -> bool {
+    matches!(
+        color,
+        SemanticColor::Yellow | SemanticColor::Cyan | SemanticColor::Purple
+    )
+}
+
+// 一格画成 8x8 像素的样子：四周留一圈黑缝，左上角一个白色高光
+pub fn draw_cell(canvas: &mut Canvas<Window>, rect: Rect, color: SemanticColor, base: Color) {
+    let pixel = (rect.width().min(rect.height()) / 8).max(1);
+    let body = Rect::new(
+        rect.x(),
+        rect.y(),
+        rect.width() - pixel,
+        rect.height() - pixel,
+    );
+    canvas.set_draw_color(base);
+    canvas.fill_rect(body).unwrap();
+    canvas.set_draw_color(WHITE);
+    if is_hollow(color) {
+        let mut inner = body;
+        inner.resize(body.width() - 2 * pixel, body.height() - 2 * pixel);
+        inner.center_on(body.center());
+        canvas.fill_rect(inner).unwrap();
+        canvas
+            .fill_rect(Rect::new(body.x(), body.y(), pixel, pixel))
+            .unwrap();
+    } else {
+        canvas
+            .fill_rect(Rect::new(body.x(), body.y(), pixel, pixel))
+            .unwrap();
+        canvas
+            .fill_rect(Rect::new(
+                body.x() + pixel as i32,
+                body.y() + pixel as i32,
+                2 * pixel,
+                pixel,
+            ))
+            .unwrap();
+        canvas
+            .fill_rect(Rect::new(
+                body.x() + pixel as i32,
+                body.y() + 2 * pixel as i32,
+                pixel,
+                pixel,
+            ))
+            .unwrap();
+    }
+}
+
+// 左边的统计栏：每种方块出现了几次，顺序和原版一样是 T J Z O S L I
+pub fn draw_piece_counts(
+    canvas: &mut Canvas<Window>,
+    area: Rect,
+    stats: &Stats,
+    skin: &SkinAssets,
+) {
+    const ORDER: [PieceKind; 7] = [
+        PieceKind::T,
+        PieceKind::J,
+        PieceKind::Z,
+        PieceKind::O,
+        PieceKind::S,
+        PieceKind::L,
+        PieceKind::I,
+    ];
+    let row_height = area.height() / ORDER.len() as u32;
+    let preview_width = area.width() / 2;
+    let scale = (row_height * 2 / 3 / text::GLYPH_HEIGHT)
+        .min(area.width() / 2 / (3 * text::ADVANCE))
+        .max(1);
+    for (i, kind) in ORDER.into_iter().enumerate() {
+        let row = Rect::new(
+            area.x(),
+            area.y() + (row_height * i as u32) as i32,
+            area.width(),
+            row_height,
+        );
+        super::draw_piece_preview(
+            canvas,
+            Rect::new(row.x(), row.y(), preview_width, row_height),
+            kind,
+            skin,
+        );
+        let count = format!("{:03}", stats.piece_counts[kind as usize]);
+        let (width, height) = text::text_size(&count, scale);
+        text::draw_text(
+            canvas,
+            &count,
+            Point::new(
+                row.right() - width as i32,
+                row.center().y - height as i32 / 2,
+            ),
+            scale,
+            skin.colors.text.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn palette_cycles_every_ten_levels() {
+        assert_eq!(piece_colors(1), piece_colors(11));
+        assert_ne!(piece_colors(1), piece_colors(2));
+        // 同一级里 J 和 S 同色，L 和 Z 同色
+        let colors = piece_colors(3);
+        assert_eq!(colors.blue, colors.green);
+        assert_eq!(colors.orange, colors.red);
+        assert_ne!(colors.blue, colors.red);
+    }
+}
@@ -0,0 +1,153 @@
+use sdl2::keyboard::Keycode;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::skin::SkinColors;
+use super::text;
+use crate::net::latency::Latency;
+use crate::net::Pending;
+
+// 联机对战开始之前的两个界面：输对手的地址，和等对手连上来。
+// 连上之后和本地对战一样分屏，右边是在本地重放的对手
+const MAX_ADDRESS_LEN: usize = "255.255.255.255:65535".len();
+
+pub struct AddressEntry {
+    pub address: String,
+    // 填的是大厅服务器，不是对手
+    pub server: bool,
+}
+
+impl AddressEntry {
+    pub fn new(last_address: &str, server: bool) -> Self {
+        Self {
+            address: last_address.to_string(),
+            server,
+        }
+    }
+
+    // 数字、字母、点和冒号；美式键盘上冒号要按 Shift，分号键也当冒号。
+    // 能打出字的键，键码就是那个字符
+    pub fn push_key(&mut self, key: Keycode) {
+        let c = match key {
+            Keycode::Semicolon | Keycode::Colon => ':',
+            _ => match char::from_u32(key as u32) {
+                Some(c) if c.is_ascii_alphanumeric() || c == '.' => c,
+                _ => return,
+            },
+        };
+        if self.address.len() < MAX_ADDRESS_LEN {
+            self.address.push(c.to_ascii_uppercase());
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.address.pop();
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        let address = format!("{:_<width$}", self.address, width = MAX_ADDRESS_LEN);
+        let scale = text::fit_scale(&address, area.width());
+        let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+        let center = area.center();
+        let title = if self.server {
+            "LOBBY SERVER"
+        } else {
+            "JOIN GAME"
+        };
+        text::draw_text_centered(
+            canvas,
+            &tr(title),
+            Point::new(center.x, center.y - line_height * 2),
+            scale,
+            colors.accent.0,
+        );
+        text::draw_text_centered(
+            canvas,
+            &tr("ENTER ADDRESS"),
+            Point::new(center.x, center.y - line_height),
+            scale,
+            colors.text_dim.0,
+        );
+        text::draw_text_centered(
+            canvas,
+            &address,
+            Point::new(center.x, center.y + line_height / 2),
+            scale,
+            colors.text.0,
+        );
+    }
+}
+
+// 开房的显示在哪个端口等，大厅服务器上的显示在几号房间等，加入的显示正在连
+pub fn draw_waiting(
+    canvas: &mut Canvas<Window>,
+    area: Rect,
+    pending: &Pending,
+    colors: &SkinColors,
+) {
+    let detail = match (pending.port(), pending.lobby()) {
+        (Some(port), _) => Some(format!("{} {}", tr("PORT"), port)),
+        (None, Some(lobby)) => Some(format!("{} {}", tr("LOBBY"), lobby)),
+        (None, None) => None,
+    };
+    let title = match detail {
+        Some(_) => tr("WAITING FOR OPPONENT"),
+        None => tr("CONNECTING"),
+    };
+    let scale = text::fit_scale(&title, area.width());
+    let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+    let center = area.center();
+    text::draw_text_centered(canvas, &title, center, scale, colors.accent.0);
+    if let Some(detail) = detail {
+        text::draw_text_centered(
+            canvas,
+            &detail,
+            Point::new(center.x, center.y + line_height),
+            scale,
+            colors.text_dim.0,
+        );
+    }
+}
+
+// 对局里在右上角显示延迟和抖动，还没量出来就不显示
+pub fn draw_latency(canvas: &mut Canvas<Window>, latency: &Latency, colors: &SkinColors) {
+    let Some(ping) = latency.ping_time() else {
+        return;
+    };
+    let mut line = format!("{} {}MS", tr("PING"), ping.as_millis());
+    if let Some(jitter) = latency.jitter() {
+        line += &format!("  {} {}MS", tr("JITTER"), jitter.as_millis());
+    }
+    let viewport = canvas.viewport();
+    let scale = (viewport.height() / 400).max(1);
+    let margin = (4 * scale) as i32;
+    let (width, _) = text::text_size(&line, scale);
+    let top_left = Point::new(viewport.width() as i32 - width as i32 - margin, margin);
+    text::draw_text(canvas, &line, top_left, scale, colors.text_dim.0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn addresses_take_digits_dots_and_colons() {
+        let mut entry = AddressEntry::new("", false);
+        for key in [
+            Keycode::Num1,
+            Keycode::Period,
+            Keycode::Num2,
+            Keycode::Semicolon,
+            Keycode::Num7,
+            Keycode::Space,
+            Keycode::L,
+        ] {
+            entry.push_key(key);
+        }
+        assert_eq!(entry.address, "1.2:7L");
+        entry.pop();
+        assert_eq!(entry.address, "1.2:7");
+    }
+}
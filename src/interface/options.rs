@@ -0,0 +1,363 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::{tr, Language};
+use super::nav::{Focus, Nav, Widget};
+use super::palette::Palette;
+use super::settings::{GridStyle, Settings, ShakeLevel};
+use super::skin::{Skin, SkinColors};
+use super::sound_pack::SoundPack;
+use super::text;
+use crate::engine::Engine;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Item {
+    Language,
+    Fullscreen,
+    Skin,
+    Palette,
+    Glyphs,
+    Grid,
+    Shake,
+    ReducedMotion,
+    HighContrast,
+    Perf,
+    InputDisplay,
+    SmoothFall,
+    Mute,
+    SoundPack,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Das,
+    Arr,
+    InputDelay,
+    Ghost,
+    FinesseHints,
+    PlacementHint,
+    OpenerTrainer,
+    BotLevel,
+    ExportSamples,
+    Previews,
+    DangerRow,
+    DigInterval,
+}
+
+const SECTIONS: [(&str, &[Item]); 5] = [
+    ("GENERAL", &[Item::Language]),
+    (
+        "VIDEO",
+        &[
+            Item::Fullscreen,
+            Item::Skin,
+            Item::Palette,
+            Item::Glyphs,
+            Item::Grid,
+            Item::Shake,
+            Item::ReducedMotion,
+            Item::HighContrast,
+            Item::Perf,
+            Item::InputDisplay,
+            Item::SmoothFall,
+        ],
+    ),
+    (
+        "AUDIO",
+        &[
+            Item::Mute,
+            Item::SoundPack,
+            Item::MasterVolume,
+            Item::MusicVolume,
+            Item::SfxVolume,
+        ],
+    ),
+    ("HANDLING", &[Item::Das, Item::Arr, Item::InputDelay]),
+    (
+        "GAMEPLAY",
+        &[
+            Item::Ghost,
+            Item::FinesseHints,
+            Item::PlacementHint,
+            Item::OpenerTrainer,
+            Item::BotLevel,
+            Item::ExportSamples,
+            Item::Previews,
+            Item::DangerRow,
+            Item::DigInterval,
+        ],
+    ),
+];
+const VOLUME_STEP: i32 = 10;
+const MAX_DAS: u32 = 30;
+const MAX_ARR: u32 = 10;
+const MAX_INPUT_DELAY: u32 = 6;
+const MAX_DIG_INTERVAL: u32 = 15;
+
+fn items() -> impl Iterator<Item = Item> {
+    SECTIONS.iter().flat_map(|(_, items)| items.iter().copied())
+}
+
+fn label(item: Item) -> &'static str {
+    match item {
+        Item::Language => "LANGUAGE",
+        Item::Fullscreen => "FULLSCREEN",
+        Item::Skin => "SKIN",
+        Item::Palette => "PALETTE",
+        Item::Glyphs => "PIECE LETTERS",
+        Item::Grid => "GRID",
+        Item::Shake => "SCREEN SHAKE",
+        Item::ReducedMotion => "REDUCED MOTION",
+        Item::HighContrast => "HIGH CONTRAST",
+        Item::Perf => "FPS COUNTER",
+        Item::InputDisplay => "INPUT DISPLAY",
+        Item::SmoothFall => "SMOOTH FALL",
+        Item::Mute => "MUTE",
+        Item::SoundPack => "SOUND PACK",
+        // 模式里也有个 "MASTER"，翻译要分开
+        Item::MasterVolume => "volume.MASTER",
+        Item::MusicVolume => "MUSIC",
+        Item::SfxVolume => "EFFECTS",
+        Item::Das => "DAS",
+        Item::Arr => "ARR",
+        Item::InputDelay => "INPUT DELAY",
+        Item::Ghost => "GHOST PIECE",
+        Item::FinesseHints => "FINESSE HINTS",
+        Item::PlacementHint => "PLACEMENT HINT",
+        Item::OpenerTrainer => "OPENER TRAINER",
+        Item::BotLevel => "CPU LEVEL",
+        Item::ExportSamples => "TRAINING DATA",
+        Item::Previews => "NEXT PIECES",
+        Item::DangerRow => "DANGER ROW",
+        Item::DigInterval => "DIG INTERVAL",
+    }
+}
+
+fn choice(name: &str) -> Widget {
+    Widget::Choice(tr(name))
+}
+
+fn slider(value: u32, max: u32, unit: &'static str) -> Widget {
+    Widget::Slider { value, max, unit }
+}
+
+// 设置里那些要在几个加载出来的东西里选一个的选项
+pub struct Choices<'a> {
+    pub skins: &'a [Skin],
+    pub languages: &'a [Language],
+    pub sound_packs: &'a [SoundPack],
+}
+
+// 在 `count` 个里往前或往后选，两头循环
+fn cycle(index: usize, delta: i32, count: usize) -> usize {
+    (index as i32 + delta).rem_euclid(count as i32) as usize
+}
+
+fn widget(item: Item, settings: &Settings, choices: &Choices) -> Widget {
+    match item {
+        Item::Language => choice(&choices.languages[settings.language].name),
+        Item::SoundPack => choice(&choices.sound_packs[settings.sound_pack].name),
+        Item::Fullscreen => Widget::Toggle(settings.fullscreen),
+        Item::Skin => choice(&choices.skins[settings.skin].name),
+        Item::Palette => choice(match settings.palette {
+            Palette::Skin => "SKIN",
+            Palette::OkabeIto => "OKABE-ITO",
+            Palette::TolBright => "TOL BRIGHT",
+        }),
+        Item::Glyphs => Widget::Toggle(settings.piece_glyphs),
+        Item::Grid => choice(match settings.grid {
+            GridStyle::Off => "OFF",
+            GridStyle::Lines => "LINES",
+            GridStyle::Checkerboard => "CHECKER",
+        }),
+        Item::Shake => choice(match settings.screen_shake {
+            ShakeLevel::Off => "OFF",
+            ShakeLevel::Subtle => "SUBTLE",
+            ShakeLevel::Strong => "STRONG",
+        }),
+        Item::ReducedMotion => Widget::Toggle(settings.reduced_motion),
+        Item::HighContrast => Widget::Toggle(settings.high_contrast),
+        Item::Perf => Widget::Toggle(settings.show_perf),
+        Item::InputDisplay => Widget::Toggle(settings.input_display),
+        Item::SmoothFall => Widget::Toggle(settings.smooth_fall),
+        Item::Mute => Widget::Toggle(settings.muted),
+        Item::MasterVolume => slider(settings.master_volume as u32, 100, "%"),
+        Item::MusicVolume => slider(settings.music_volume as u32, 100, "%"),
+        Item::SfxVolume => slider(settings.sfx_volume as u32, 100, "%"),
+        Item::Das => slider(settings.das, MAX_DAS, "F"),
+        Item::Arr => slider(settings.arr, MAX_ARR, "F"),
+        Item::InputDelay => slider(settings.input_delay, MAX_INPUT_DELAY, "F"),
+        Item::Ghost => Widget::Toggle(settings.ghost),
+        Item::FinesseHints => Widget::Toggle(settings.finesse_hints),
+        Item::PlacementHint => Widget::Toggle(settings.placement_hint),
+        Item::OpenerTrainer => Widget::Toggle(settings.opener_trainer),
+        Item::BotLevel => choice(settings.bot_difficulty.name()),
+        Item::ExportSamples => Widget::Toggle(settings.export_samples),
+        Item::Previews => slider(settings.previews as u32, Engine::MAX_PREVIEW as u32, ""),
+        Item::DangerRow => slider(settings.danger_row as u32, 19, ""),
+        Item::DigInterval => slider(settings.dig_interval, MAX_DIG_INTERVAL, "S"),
+    }
+}
+
+fn step_volume(volume: &mut u8, delta: i32) {
+    *volume = (*volume as i32 + delta * VOLUME_STEP).clamp(0, 100) as u8;
+}
+
+fn step(value: u32, delta: i32, max: u32) -> u32 {
+    (value as i32 + delta).clamp(0, max as i32) as u32
+}
+
+// 设置界面：上下选，左右改，改动马上生效，离开的时候再写进配置文件
+pub struct OptionsMenu {
+    focus: Focus,
+    // 从暂停菜单进来的，返回时回到暂停菜单
+    pub from_pause: bool,
+}
+
+impl OptionsMenu {
+    pub fn new(from_pause: bool) -> Self {
+        Self {
+            focus: Focus::new(Self::len()),
+            from_pause,
+        }
+    }
+
+    // 最后多一项 BACK
+    fn len() -> usize {
+        items().count() + 1
+    }
+
+    pub fn is_back_selected(&self) -> bool {
+        self.focus.is_last()
+    }
+
+    // 返回 true 表示要离开设置界面
+    pub fn navigate(&mut self, nav: Nav, settings: &mut Settings, choices: &Choices) -> bool {
+        match nav {
+            Nav::Up => self.focus.up(),
+            Nav::Down => self.focus.down(),
+            Nav::Left => self.adjust(settings, -1, choices),
+            Nav::Right => self.adjust(settings, 1, choices),
+            Nav::Confirm if !self.is_back_selected() => self.adjust(settings, 1, choices),
+            Nav::Confirm | Nav::Back => return true,
+        }
+        false
+    }
+
+    pub fn adjust(&self, settings: &mut Settings, delta: i32, choices: &Choices) {
+        let Some(item) = items().nth(self.focus.index()) else {
+            return;
+        };
+        match item {
+            Item::Language => {
+                settings.language = cycle(settings.language, delta, choices.languages.len())
+            }
+            Item::SoundPack => {
+                settings.sound_pack = cycle(settings.sound_pack, delta, choices.sound_packs.len())
+            }
+            Item::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Item::Skin => settings.skin = cycle(settings.skin, delta, choices.skins.len()),
+            Item::Palette => settings.palette = settings.palette.next(),
+            Item::Glyphs => settings.piece_glyphs = !settings.piece_glyphs,
+            Item::Grid => settings.grid = settings.grid.next(),
+            Item::Shake => settings.screen_shake = settings.screen_shake.next(),
+            Item::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            Item::HighContrast => settings.high_contrast = !settings.high_contrast,
+            Item::Perf => settings.show_perf = !settings.show_perf,
+            Item::InputDisplay => settings.input_display = !settings.input_display,
+            Item::SmoothFall => settings.smooth_fall = !settings.smooth_fall,
+            Item::Mute => settings.muted = !settings.muted,
+            Item::MasterVolume => step_volume(&mut settings.master_volume, delta),
+            Item::MusicVolume => step_volume(&mut settings.music_volume, delta),
+            Item::SfxVolume => step_volume(&mut settings.sfx_volume, delta),
+            Item::Das => settings.das = step(settings.das, delta, MAX_DAS),
+            Item::Arr => settings.arr = step(settings.arr, delta, MAX_ARR),
+            Item::InputDelay => {
+                settings.input_delay = step(settings.input_delay, delta, MAX_INPUT_DELAY)
+            }
+            Item::Ghost => settings.ghost = !settings.ghost,
+            Item::FinesseHints => settings.finesse_hints = !settings.finesse_hints,
+            Item::PlacementHint => settings.placement_hint = !settings.placement_hint,
+            Item::OpenerTrainer => settings.opener_trainer = !settings.opener_trainer,
+            Item::BotLevel => settings.bot_difficulty = settings.bot_difficulty.next(),
+            Item::ExportSamples => settings.export_samples = !settings.export_samples,
+            Item::Previews => {
+                settings.previews =
+                    step(settings.previews as u32, delta, Engine::MAX_PREVIEW as u32) as usize
+            }
+            Item::DangerRow => {
+                settings.danger_row = step(settings.danger_row as u32, delta, 19).max(1) as usize
+            }
+            Item::DigInterval => {
+                settings.dig_interval = step(settings.dig_interval, delta, MAX_DIG_INTERVAL).max(1)
+            }
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        area: Rect,
+        settings: &Settings,
+        choices: &Choices,
+        colors: &SkinColors,
+    ) {
+        // 每个分组一行标题，再加上最后的 BACK
+        let rows = Self::len() + SECTIONS.len() + 1;
+        let line_height = area.height() / rows as u32;
+        let scale = text::fit_scale("SCREEN SHAKE  > TOL BRIGHT <", area.width())
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+        let mut y = area.y();
+        let mut index = 0;
+        for (section, items) in SECTIONS {
+            text::draw_text(
+                canvas,
+                &tr(section),
+                Point::new(area.x(), y),
+                scale,
+                colors.accent.0,
+            );
+            y += line_height as i32;
+            for &item in items {
+                let selected = index == self.focus.index();
+                let color = if selected {
+                    colors.text.0
+                } else {
+                    colors.text_dim.0
+                };
+                let indent = (text::ADVANCE * scale * 2) as i32;
+                text::draw_text(
+                    canvas,
+                    &tr(label(item)),
+                    Point::new(area.x() + indent, y),
+                    scale,
+                    color,
+                );
+                widget(item, settings, choices).draw(
+                    canvas,
+                    area.right(),
+                    y,
+                    scale,
+                    color,
+                    selected,
+                );
+                y += line_height as i32;
+                index += 1;
+            }
+        }
+
+        y += line_height as i32;
+        let back = if self.is_back_selected() {
+            format!("> {} <", tr("BACK"))
+        } else {
+            tr("BACK")
+        };
+        let color = if self.is_back_selected() {
+            colors.accent.0
+        } else {
+            colors.text_dim.0
+        };
+        let center = Point::new(area.center().x, y + (line_height / 2) as i32);
+        text::draw_text_centered(canvas, &back, center, scale, color);
+    }
+}
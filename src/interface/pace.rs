@@ -0,0 +1,204 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use serde::{Deserialize, Serialize};
+
+use super::skin::SkinAssets;
+use super::text;
+use crate::engine::mode::Mode;
+use crate::engine::{Color as SemanticColor, Stats};
+
+// sprint 和 ultra 的最好成绩，连同那一局每秒的进度，用来和正在打的这一局比快慢
+pub const PACE_FILE: &str = "pace.toml";
+
+// 每秒记一次进度
+const SAMPLE_FRAMES: u64 = Stats::FRAMES_PER_SECOND;
+
+// 只有这两个模式比快慢有意义
+pub fn tracks(mode: Mode) -> bool {
+    matches!(mode, Mode::Sprint | Mode::Ultra)
+}
+
+// sprint 比消了几行，ultra 比分数
+fn progress(mode: Mode, stats: &Stats) -> u32 {
+    match mode {
+        Mode::Sprint => stats.lines,
+        _ => stats.score,
+    }
+}
+
+// 一局的成绩和进度曲线，`samples[i]` 是第 i 秒时的进度
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Run {
+    pub frames: u64,
+    pub score: u32,
+    samples: Vec<u32>,
+}
+
+impl Run {
+    // 每帧调用，到了整秒就记一个点
+    pub fn record(&mut self, mode: Mode, stats: &Stats) {
+        while self.samples.len() as u64 * SAMPLE_FRAMES <= stats.frames {
+            self.samples.push(progress(mode, stats));
+        }
+        self.frames = stats.frames;
+        self.score = stats.score;
+    }
+
+    // 打到同一时间的时候，进度比这一局多多少；这一局没打到这么久就是 None
+    fn lead(&self, mode: Mode, stats: &Stats) -> Option<i64> {
+        let sample = *self.samples.get((stats.frames / SAMPLE_FRAMES) as usize)?;
+        Some(i64::from(progress(mode, stats)) - i64::from(sample))
+    }
+
+    fn beats(&self, mode: Mode, other: &Run) -> bool {
+        match mode {
+            Mode::Sprint => self.frames < other.frames,
+            _ => self.score > other.score,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaceRecords {
+    sprint: Option<Run>,
+    ultra: Option<Run>,
+}
+
+impl PaceRecords {
+    pub fn load(path: &Path) -> Result<PaceRecords, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(PaceRecords::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn best(&self, mode: Mode) -> Option<Run> {
+        match mode {
+            Mode::Sprint => self.sprint.clone(),
+            Mode::Ultra => self.ultra.clone(),
+            _ => None,
+        }
+    }
+
+    fn slot(&mut self, mode: Mode) -> Option<&mut Option<Run>> {
+        match mode {
+            Mode::Sprint => Some(&mut self.sprint),
+            Mode::Ultra => Some(&mut self.ultra),
+            _ => None,
+        }
+    }
+
+    // 跑完的一局比记录好就换掉，换了返回 true
+    pub fn submit(&mut self, mode: Mode, run: Run) -> bool {
+        let Some(slot) = self.slot(mode) else {
+            return false;
+        };
+        if slot.as_ref().is_some_and(|best| !run.beats(mode, best)) {
+            return false;
+        }
+        *slot = Some(run);
+        true
+    }
+}
+
+// 正在打的这一局，和最好的那一局
+pub struct Pace {
+    mode: Mode,
+    best: Option<Run>,
+    pub current: Run,
+}
+
+impl Pace {
+    pub fn new(mode: Mode, best: Option<Run>) -> Self {
+        Self {
+            mode,
+            best,
+            current: Run::default(),
+        }
+    }
+
+    pub fn update(&mut self, stats: &Stats) {
+        self.current.record(self.mode, stats);
+    }
+
+    // 计时器下面写领先（绿）或者落后（红）多少
+    pub fn draw(&self, canvas: &mut Canvas<Window>, panel: Rect, stats: &Stats, skin: &SkinAssets) {
+        let Some(lead) = self
+            .best
+            .as_ref()
+            .and_then(|best| best.lead(self.mode, stats))
+        else {
+            return;
+        };
+        let (label, color) = match lead {
+            0 => ("PB =0".to_string(), skin.colors.text_dim.0),
+            lead if lead > 0 => (
+                format!("PB +{}", lead),
+                skin.piece_color(SemanticColor::Green),
+            ),
+            lead => (format!("PB {}", lead), skin.piece_color(SemanticColor::Red)),
+        };
+        // 和计时器一样按 "00:00.000" 的宽度定大小，小一半
+        let scale = (text::fit_scale("00:00.000", panel.width() * 7 / 8) / 2).max(1);
+        let height = (text::GLYPH_HEIGHT * scale) as i32;
+        let center = Point::new(panel.center().x, panel.top() + height * 5);
+        text::draw_text_centered(canvas, &label, center, scale, color);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(frames: u64, lines: u32) -> Stats {
+        Stats {
+            frames,
+            lines,
+            ..Stats::default()
+        }
+    }
+
+    #[test]
+    fn pace_compares_against_the_best_run() {
+        let mut best = Run::default();
+        for second in 0..10 {
+            best.record(Mode::Sprint, &stats(second * SAMPLE_FRAMES, second as u32));
+        }
+        assert_eq!(
+            best.lead(Mode::Sprint, &stats(5 * SAMPLE_FRAMES, 7)),
+            Some(2)
+        );
+        assert_eq!(
+            best.lead(Mode::Sprint, &stats(3 * SAMPLE_FRAMES + 10, 1)),
+            Some(-2)
+        );
+        assert_eq!(
+            best.lead(Mode::Sprint, &stats(60 * SAMPLE_FRAMES, 40)),
+            None
+        );
+
+        let mut records = PaceRecords::default();
+        assert!(records.submit(Mode::Sprint, best.clone()));
+        let slower = Run {
+            frames: best.frames + 1,
+            ..Run::default()
+        };
+        assert!(!records.submit(Mode::Sprint, slower));
+        assert!(!records.submit(Mode::Marathon, best));
+        assert!(records.best(Mode::Sprint).is_some());
+        assert!(records.best(Mode::Ultra).is_none());
+    }
+}
@@ -0,0 +1,82 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use serde::{Deserialize, Serialize};
+
+use super::skin::{HexColor, PieceColors};
+use super::text;
+use crate::engine::Color as SemanticColor;
+
+// 方块配色：默认跟着皮肤走，也可以换成对色盲友好的配色
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Skin,
+    // Okabe & Ito 的八色方案，红绿色盲也能区分
+    OkabeIto,
+    // Paul Tol 的 bright 方案
+    TolBright,
+}
+
+impl Palette {
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Skin => Palette::OkabeIto,
+            Palette::OkabeIto => Palette::TolBright,
+            Palette::TolBright => Palette::Skin,
+        }
+    }
+
+    // 返回 None 表示沿用皮肤自己的配色
+    pub fn piece_colors(self) -> Option<PieceColors> {
+        let rgb = |r, g, b| HexColor(Color::RGB(r, g, b));
+        match self {
+            Palette::Skin => None,
+            Palette::OkabeIto => Some(PieceColors {
+                yellow: rgb(0xf0, 0xe4, 0x42),
+                cyan: rgb(0x56, 0xb4, 0xe9),
+                purple: rgb(0xcc, 0x79, 0xa7),
+                orange: rgb(0xe6, 0x9f, 0x00),
+                blue: rgb(0x00, 0x72, 0xb2),
+                green: rgb(0x00, 0x9e, 0x73),
+                red: rgb(0xd5, 0x5e, 0x00),
+                gray: rgb(0x99, 0x99, 0x99),
+            }),
+            Palette::TolBright => Some(PieceColors {
+                yellow: rgb(0xcc, 0xbb, 0x44),
+                cyan: rgb(0x66, 0xcc, 0xee),
+                purple: rgb(0xaa, 0x33, 0x77),
+                orange: rgb(0xbb, 0xbb, 0xbb),
+                blue: rgb(0x44, 0x77, 0xaa),
+                green: rgb(0x22, 0x88, 0x33),
+                red: rgb(0xee, 0x66, 0x77),
+                gray: rgb(0x77, 0x77, 0x77),
+            }),
+        }
+    }
+}
+
+// 每种颜色对应一种方块，在格子中间画上方块的字母，不用靠颜色也能分辨
+fn glyph_for(color: SemanticColor) -> &'static str {
+    match color {
+        SemanticColor::Yellow => "O",
+        SemanticColor::Cyan => "I",
+        SemanticColor::Purple => "T",
+        SemanticColor::Orange => "L",
+        SemanticColor::Blue => "J",
+        SemanticColor::Green => "S",
+        SemanticColor::Red => "Z",
+        SemanticColor::Gray => "X",
+    }
+}
+
+pub fn draw_glyph(canvas: &mut Canvas<Window>, rect: Rect, color: SemanticColor, ink: Color) {
+    let scale = rect.height() * 3 / 5 / text::GLYPH_HEIGHT;
+    if scale == 0 {
+        return;
+    }
+    text::draw_text_centered(canvas, glyph_for(color), rect.center(), scale, ink);
+}
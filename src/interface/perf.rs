@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::skin::SkinColors;
+use super::text;
+
+// 统计最近这么多帧
+const WINDOW: usize = 120;
+
+#[derive(Clone, Copy, Default)]
+struct Sample {
+    frame: Duration,
+    update: Duration,
+    updates: u32,
+    render: Duration,
+}
+
+// 帧率和每帧耗时统计，用来排查卡顿
+#[derive(Default)]
+pub struct PerfStats {
+    samples: VecDeque<Sample>,
+    current: Sample,
+}
+
+impl PerfStats {
+    pub fn record_update(&mut self, elapsed: Duration) {
+        self.current.update += elapsed;
+        self.current.updates += 1;
+    }
+
+    pub fn record_render(&mut self, elapsed: Duration) {
+        self.current.render += elapsed;
+    }
+
+    // 一帧结束，frame 是整个循环一圈的时间（包括等垂直同步）
+    pub fn end_frame(&mut self, frame: Duration) {
+        self.current.frame = frame;
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(std::mem::take(&mut self.current));
+    }
+
+    fn average(&self, f: impl Fn(&Sample) -> Duration) -> Duration {
+        let total: Duration = self.samples.iter().map(f).sum();
+        total / self.samples.len().max(1) as u32
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let frame = self.average(|sample| sample.frame);
+        let worst = self
+            .samples
+            .iter()
+            .map(|sample| sample.frame)
+            .max()
+            .unwrap_or_default();
+        let fps = if frame.is_zero() {
+            0.0
+        } else {
+            1.0 / frame.as_secs_f64()
+        };
+        let updates: u32 = self.samples.iter().map(|sample| sample.updates).sum();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        vec![
+            format!("FPS    {:.0}", fps),
+            format!("FRAME  {:.2}MS", ms(frame)),
+            format!("WORST  {:.2}MS", ms(worst)),
+            format!("UPDATE {:.2}MS", ms(self.average(|sample| sample.update))),
+            format!("RENDER {:.2}MS", ms(self.average(|sample| sample.render))),
+            format!(
+                "TICKS  {:.2}/FRAME",
+                updates as f64 / self.samples.len().max(1) as f64
+            ),
+        ]
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, colors: &SkinColors) {
+        let viewport = canvas.viewport();
+        let scale = (viewport.height() / 400).max(1);
+        let line_height = (text::GLYPH_HEIGHT + 3) * scale;
+        let lines = self.lines();
+        let width = lines
+            .iter()
+            .map(|line| text::text_size(line, scale).0)
+            .max()
+            .unwrap_or(0);
+
+        let margin = 4 * scale;
+        let background = Rect::new(
+            0,
+            0,
+            width + margin * 2,
+            line_height * lines.len() as u32 + margin * 2,
+        );
+        canvas.set_draw_color(colors.overlay.0);
+        canvas.fill_rect(background).unwrap();
+        for (i, line) in lines.iter().enumerate() {
+            let top_left = Point::new(margin as i32, (margin + line_height * i as u32) as i32);
+            text::draw_text(canvas, line, top_left, scale, colors.text.0);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::env;
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 在 Discord 的个人资料上显示正在玩什么（Rich Presence）。本机开着的 Discord 客户端
+// 有一个 IPC 管道，连上去握手，之后发 SET_ACTIVITY。没开 Discord 也不影响玩
+//
+// 应用 ID 要在 Discord 的开发者后台建一个应用拿到，放在这个环境变量里，没有就不连
+const CLIENT_ID_VAR: &str = "TETRIS_DISCORD_CLIENT_ID";
+// Discord 限制 20 秒最多改 5 次，分数又一直在变，发一次歇一会儿，攒下来的只发最新的
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+// 连不上（Discord 没开）的话隔一阵再试
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const OP_CLOSE: u32 = 2;
+
+#[cfg(unix)]
+type Pipe = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type Pipe = std::fs::File;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Activity {
+    // 第一行，比如在玩哪个模式
+    pub details: String,
+    // 第二行，比如分数
+    pub state: String,
+    // 这一局开始的 Unix 时间（秒），Discord 照着显示打了多久；菜单里是 None
+    pub start: Option<u64>,
+}
+
+// 连 Discord 和发消息都在自己的线程里，卡住了也不影响游戏。主线程只管报现在在干什么
+pub struct Presence {
+    sender: Sender<Option<Activity>>,
+    // 上次报的，一样的就不再发
+    last: Option<Option<Activity>>,
+}
+
+impl Presence {
+    // 没配应用 ID 是 None；线程在 `Presence` 丢掉之后自己结束
+    pub fn spawn() -> Option<Presence> {
+        let client_id = env::var(CLIENT_ID_VAR).ok().filter(|id| !id.is_empty())?;
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("discord".to_string())
+            .spawn(move || run(&client_id, &receiver))
+            .map_err(|err| eprintln!("Failed to start Discord thread: {}", err))
+            .ok()?;
+        Some(Presence { sender, last: None })
+    }
+
+    // `None` 是清掉显示的内容
+    pub fn update(&mut self, mut activity: Option<Activity>) {
+        // 开始时间是拿现在减去打了多久算的，每帧会差一两秒，差得不多就还用原来的
+        if let (Some(new), Some(Some(old))) = (&mut activity, &self.last) {
+            if let (Some(start), Some(previous)) = (new.start, old.start) {
+                if start.abs_diff(previous) <= 2 {
+                    new.start = Some(previous);
+                }
+            }
+        }
+        if self.last.as_ref() == Some(&activity) {
+            return;
+        }
+        self.last = Some(activity.clone());
+        // 线程已经没了就算了
+        let _ = self.sender.send(activity);
+    }
+}
+
+fn run(client_id: &str, receiver: &Receiver<Option<Activity>>) {
+    let mut pipe: Option<Pipe> = None;
+    let mut last_try: Option<Instant> = None;
+    let mut nonce = 0;
+    while let Ok(mut activity) = receiver.recv() {
+        while let Ok(newer) = receiver.try_recv() {
+            activity = newer;
+        }
+        if pipe.is_none() && last_try.is_none_or(|last| last.elapsed() >= RETRY_INTERVAL) {
+            last_try = Some(Instant::now());
+            pipe = connect(client_id)
+                .map_err(|err| eprintln!("Failed to connect to Discord: {}", err))
+                .ok();
+        }
+        if let Some(stream) = &mut pipe {
+            nonce += 1;
+            let payload = set_activity(activity.as_ref(), std::process::id(), nonce);
+            let sent = send(stream, OP_FRAME, &payload).and_then(|_| receive(stream));
+            if let Err(err) = sent {
+                eprintln!("Lost connection to Discord: {}", err);
+                pipe = None;
+            }
+        }
+        thread::sleep(MIN_INTERVAL);
+    }
+}
+
+// Discord 开了好几个的话管道编号往后排，挨个试
+fn connect(client_id: &str) -> io::Result<Pipe> {
+    let mut last_err = io::Error::new(ErrorKind::NotFound, "Discord is not running");
+    for index in 0..10 {
+        match open_pipe(index) {
+            Ok(mut stream) => {
+                let handshake = format!("{{\"v\":1,\"client_id\":{}}}", quote(client_id));
+                send(&mut stream, OP_HANDSHAKE, &handshake)?;
+                receive(&mut stream)?;
+                return Ok(stream);
+            }
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+#[cfg(unix)]
+fn open_pipe(index: u32) -> io::Result<Pipe> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(|name| env::var_os(name).filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "/tmp".into());
+    Pipe::connect(std::path::Path::new(&dir).join(format!("discord-ipc-{}", index)))
+}
+
+#[cfg(windows)]
+fn open_pipe(index: u32) -> io::Result<Pipe> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\pipe\discord-ipc-{}", index))
+}
+
+// 一帧是操作码和长度（都是小端的 u32）加上 JSON
+fn frame(op: u32, payload: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + payload.len());
+    bytes.extend_from_slice(&op.to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(payload.as_bytes());
+    bytes
+}
+
+fn send(stream: &mut Pipe, op: u32, payload: &str) -> io::Result<()> {
+    stream.write_all(&frame(op, payload))
+}
+
+// 每发一条 Discord 都回一条，读掉就行；它要断开的话把原因当错误返回
+fn receive(stream: &mut Pipe) -> io::Result<()> {
+    let mut header = [0; 8];
+    stream.read_exact(&mut header)?;
+    let op = u32::from_le_bytes(header[..4].try_into().expect("4 bytes"));
+    let len = u32::from_le_bytes(header[4..].try_into().expect("4 bytes"));
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    if op == OP_CLOSE {
+        let reason = String::from_utf8_lossy(&payload).into_owned();
+        return Err(io::Error::new(ErrorKind::ConnectionAborted, reason));
+    }
+    Ok(())
+}
+
+fn set_activity(activity: Option<&Activity>, pid: u32, nonce: u64) -> String {
+    let activity = match activity {
+        Some(activity) => {
+            let mut fields = vec![format!("\"details\":{}", quote(&activity.details))];
+            if !activity.state.is_empty() {
+                fields.push(format!("\"state\":{}", quote(&activity.state)));
+            }
+            if let Some(start) = activity.start {
+                fields.push(format!("\"timestamps\":{{\"start\":{}}}", start));
+            }
+            format!("{{{}}}", fields.join(","))
+        }
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"cmd\":\"SET_ACTIVITY\",\"args\":{{\"pid\":{},\"activity\":{}}},\"nonce\":\"{}\"}}",
+        pid, activity, nonce
+    )
+}
+
+// JSON 字符串
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn activities_are_framed_as_discord_ipc_json() {
+        let activity = Activity {
+            details: "Playing \"SPRINT\"".to_string(),
+            state: "Score 1200".to_string(),
+            start: Some(1_700_000_000),
+        };
+        assert_eq!(
+            set_activity(Some(&activity), 42, 7),
+            "{\"cmd\":\"SET_ACTIVITY\",\"args\":{\"pid\":42,\"activity\":\
+             {\"details\":\"Playing \\\"SPRINT\\\"\",\"state\":\"Score 1200\",\
+             \"timestamps\":{\"start\":1700000000}}},\"nonce\":\"7\"}"
+        );
+        assert!(set_activity(None, 42, 8).contains("\"activity\":null"));
+        assert_eq!(quote("a\\b\n"), "\"a\\\\b\\u000a\"");
+
+        let bytes = frame(OP_FRAME, "{}");
+        assert_eq!(bytes, [1, 0, 0, 0, 2, 0, 0, 0, b'{', b'}']);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use serde::{Deserialize, Serialize};
+
+use super::i18n::tr;
+use super::scores::{NameEntry, MAX_NAME_LEN};
+use super::settings::Settings;
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::Stats;
+
+pub const PROFILES_FILE: &str = "profiles.toml";
+pub const MAX_PROFILES: usize = 8;
+// 放得太少的一局 PPS 不准，不算最好成绩
+const MIN_PIECES_FOR_PPS: u32 = 20;
+
+// 一个档案打过的所有单人局加起来
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lifetime {
+    pub games: u32,
+    pub lines: u64,
+    pub tetrises: u64,
+    pub best_pps: f64,
+    pub frames: u64,
+}
+
+impl Lifetime {
+    pub fn record(&mut self, stats: &Stats) {
+        self.games += 1;
+        self.lines += stats.lines as u64;
+        self.tetrises += stats.tetrises as u64;
+        self.frames += stats.frames;
+        if stats.pieces >= MIN_PIECES_FOR_PPS {
+            self.best_pps = self.best_pps.max(stats.pps());
+        }
+    }
+
+    // 消的行里有多少是四消消掉的
+    pub fn tetris_rate(&self) -> f64 {
+        if self.lines == 0 {
+            return 0.0;
+        }
+        (self.tetrises * 4) as f64 / self.lines as f64
+    }
+}
+
+// 每个档案有自己的设置，选了哪个档案就用哪个的
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub lifetime: Lifetime,
+    #[serde(default)]
+    pub settings: Settings,
+}
+
+// 本地的所有档案；没选档案的时候成绩不记，设置用配置文件里的
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    current: Option<usize>,
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn load(path: &Path) -> Result<Profiles, String> {
+        let mut profiles: Profiles = match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string())?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Profiles::default(),
+            Err(err) => return Err(err.to_string()),
+        };
+        profiles.current = profiles
+            .current
+            .filter(|&index| index < profiles.profiles.len());
+        Ok(profiles)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub fn current(&self) -> Option<&Profile> {
+        self.current.map(|index| &self.profiles[index])
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Profile> {
+        self.current.map(|index| &mut self.profiles[index])
+    }
+
+    // 新档案从现在的设置开始；返回它的下标
+    pub fn create(&mut self, name: String, settings: Settings) -> Result<usize, &'static str> {
+        if self.profiles.iter().any(|profile| profile.name == name) {
+            return Err("NAME TAKEN");
+        }
+        if self.profiles.len() >= MAX_PROFILES {
+            return Err("TOO MANY PROFILES");
+        }
+        self.profiles.push(Profile {
+            name,
+            lifetime: Lifetime::default(),
+            settings,
+        });
+        Ok(self.profiles.len() - 1)
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<&Profile> {
+        self.profiles.get(index)?;
+        self.current = Some(index);
+        self.current()
+    }
+
+    // 打完一局记到当前档案上
+    pub fn record(&mut self, stats: &Stats) {
+        if let Some(profile) = self.current_mut() {
+            profile.lifetime.record(stats);
+        }
+    }
+}
+
+// 档案界面：上下选档案，回车换过去；先打名字再回车是新建一个
+pub struct ProfileScreen {
+    pub selected: usize,
+    pub entry: NameEntry,
+}
+
+impl ProfileScreen {
+    pub fn new(profiles: &Profiles) -> Self {
+        Self {
+            selected: profiles.current.unwrap_or(0),
+            entry: NameEntry::new(""),
+        }
+    }
+
+    pub fn cycle(&mut self, delta: isize, count: usize) {
+        if count > 0 {
+            self.selected = (self.selected as isize + delta).rem_euclid(count as isize) as usize;
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        area: Rect,
+        profiles: &Profiles,
+        colors: &SkinColors,
+    ) {
+        // 标题、空一行、档案、新名字、空一行、五项统计
+        let rows = (MAX_PROFILES + 9) as u32;
+        let line_height = area.height() / rows;
+        let scale = text::fit_scale("* WWWWWWWW  TETRIS RATE  100%", area.width())
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+        let row_y = |row: usize| area.y() + (line_height * row as u32) as i32;
+
+        let center = Point::new(area.center().x, row_y(0) + (line_height / 2) as i32);
+        text::draw_text_centered(canvas, &tr("PROFILES"), center, scale, colors.accent.0);
+        for (row, profile) in profiles.profiles.iter().enumerate() {
+            let marker = if profiles.current == Some(row) {
+                '*'
+            } else {
+                ' '
+            };
+            let line = format!("{} {}", marker, profile.name);
+            let color = if row == self.selected {
+                colors.accent.0
+            } else {
+                colors.text.0
+            };
+            text::draw_text(
+                canvas,
+                &line,
+                Point::new(area.x(), row_y(row + 2)),
+                scale,
+                color,
+            );
+        }
+        if profiles.profiles.len() < MAX_PROFILES {
+            let line = format!("+ {:_<width$}", self.entry.name, width = MAX_NAME_LEN);
+            let position = Point::new(area.x(), row_y(profiles.profiles.len() + 2));
+            text::draw_text(canvas, &line, position, scale, colors.text_dim.0);
+        }
+
+        let Some(profile) = profiles.profiles.get(self.selected) else {
+            return;
+        };
+        let lifetime = &profile.lifetime;
+        let seconds = lifetime.frames / Stats::FRAMES_PER_SECOND;
+        let stats = [
+            ("GAMES", lifetime.games.to_string()),
+            ("LINES", lifetime.lines.to_string()),
+            (
+                "TETRIS RATE",
+                format!("{:.0}%", lifetime.tetris_rate() * 100.0),
+            ),
+            ("BEST PPS", format!("{:.2}", lifetime.best_pps)),
+            (
+                "PLAY TIME",
+                format!(
+                    "{}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                ),
+            ),
+        ];
+        for (row, (label, value)) in stats.iter().enumerate() {
+            let y = row_y(MAX_PROFILES + 4 + row);
+            text::draw_text(
+                canvas,
+                &tr(label),
+                Point::new(area.x(), y),
+                scale,
+                colors.text_dim.0,
+            );
+            let (width, _) = text::text_size(value, scale);
+            let position = Point::new(area.right() - width as i32, y);
+            text::draw_text(canvas, value, position, scale, colors.text.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profiles_collect_lifetime_stats_for_the_current_one() {
+        let mut profiles = Profiles::default();
+        let game = Stats {
+            lines: 40,
+            tetrises: 5,
+            pieces: 100,
+            frames: 50 * Stats::FRAMES_PER_SECOND,
+            ..Stats::default()
+        };
+        // 没选档案的时候不记
+        profiles.record(&game);
+        let ann = profiles
+            .create("ANN".to_string(), Settings::default())
+            .unwrap();
+        assert_eq!(
+            profiles.create("ANN".to_string(), Settings::default()),
+            Err("NAME TAKEN")
+        );
+        profiles.select(ann).unwrap();
+        profiles.record(&game);
+        // 只放了几块的一局不算 PPS
+        profiles.record(&Stats {
+            pieces: 3,
+            frames: 1,
+            ..Stats::default()
+        });
+
+        let lifetime = profiles.current().unwrap().lifetime;
+        assert_eq!(lifetime.games, 2);
+        assert_eq!(lifetime.lines, 40);
+        assert_eq!(lifetime.tetris_rate(), 0.5);
+        assert_eq!(lifetime.best_pps, 2.0);
+
+        let source = toml::to_string(&profiles).unwrap();
+        let loaded: Profiles = toml::from_str(&source).unwrap();
+        assert_eq!(loaded.current(), profiles.current());
+        assert!(profiles.select(5).is_none());
+    }
+}
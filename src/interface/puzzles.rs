@@ -0,0 +1,114 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::puzzle::Puzzle;
+
+// 谜题的目录，每道题一个 .toml，按文件名排序
+pub const PUZZLE_DIR: &str = "puzzles";
+// 做完了哪些题，按题目名字记
+pub const PROGRESS_FILE: &str = "puzzle_progress.toml";
+
+pub fn load_all(dir: &Path) -> Vec<Puzzle> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| {
+            let puzzle = fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| Puzzle::parse(&source));
+            puzzle
+                .map_err(|err| eprintln!("Failed to load puzzle {}: {}", path.display(), err))
+                .ok()
+        })
+        .collect()
+}
+
+// 编辑器存的题放进谜题目录，文件名从题目名字来
+pub fn save(dir: &Path, puzzle: &Puzzle) -> Result<PathBuf, String> {
+    let source = puzzle.to_toml()?;
+    let stem = puzzle.name.to_lowercase().replace(' ', "-");
+    let path = dir.join(stem).with_extension("toml");
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    fs::write(&path, source).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+// 编辑器新建的题默认叫 CUSTOM 1、CUSTOM 2……，跳过已经有的名字
+pub fn custom_name(puzzles: &[Puzzle]) -> String {
+    (1..)
+        .map(|n| format!("CUSTOM {}", n))
+        .find(|name| puzzles.iter().all(|puzzle| &puzzle.name != name))
+        .unwrap()
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    completed: BTreeSet<String>,
+}
+
+impl Progress {
+    pub fn load(path: &Path) -> Result<Progress, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Progress::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn is_completed(&self, puzzle: &Puzzle) -> bool {
+        self.completed.contains(&puzzle.name)
+    }
+
+    // 第一次做完返回 true
+    pub fn complete(&mut self, puzzle: &Puzzle) -> bool {
+        self.completed.insert(puzzle.name.clone())
+    }
+}
+
+// 选题菜单里的名字，做完的前面打个星号
+pub fn label(puzzle: &Puzzle, progress: &Progress) -> String {
+    let mark = if progress.is_completed(puzzle) {
+        '*'
+    } else {
+        ' '
+    };
+    format!("{} {}", mark, puzzle.name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress_remembers_completed_puzzles() {
+        let puzzle =
+            Puzzle::parse("name = \"SQUARE\"\npieces = [\"O\"]\ngoal = { lines = 2 }\nboard = []")
+                .unwrap();
+        let mut progress = Progress::default();
+        assert_eq!(label(&puzzle, &progress), "  SQUARE");
+        assert!(progress.complete(&puzzle));
+        assert!(!progress.complete(&puzzle));
+
+        let source = toml::to_string(&progress).unwrap();
+        let progress: Progress = toml::from_str(&source).unwrap();
+        assert!(progress.is_completed(&puzzle));
+        assert_eq!(label(&puzzle, &progress), "* SQUARE");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::daily::{date, SECONDS_PER_DAY};
+use super::i18n::tr;
+use super::scores::{NameEntry, MAX_NAME_LEN};
+use super::skin::SkinColors;
+use super::text;
+use crate::replays::{load_all, Replay};
+
+// 列表一屏显示几个
+const VISIBLE_ROWS: usize = 10;
+
+// 录像列表：上下选，回车看，R 改名，Delete 删掉
+pub struct ReplayBrowser {
+    replays: Vec<(PathBuf, Replay)>,
+    selected: usize,
+    // 正在改名的话是新名字
+    pub renaming: Option<NameEntry>,
+}
+
+impl ReplayBrowser {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            replays: load_all(dir),
+            selected: 0,
+            renaming: None,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Replay> {
+        self.replays.get(self.selected).map(|(_, replay)| replay)
+    }
+
+    pub fn cycle(&mut self, delta: isize) {
+        let count = self.replays.len() as isize;
+        if count > 0 {
+            self.selected = (self.selected as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+
+    pub fn start_rename(&mut self) {
+        if let Some(replay) = self.selected() {
+            let mut entry = NameEntry::new("");
+            replay.title.chars().for_each(|c| entry.push(c));
+            self.renaming = Some(entry);
+        }
+    }
+
+    // 新名字写回文件；空着就不改
+    pub fn finish_rename(&mut self) -> Result<(), String> {
+        let Some(entry) = self.renaming.take() else {
+            return Ok(());
+        };
+        let Some((path, replay)) = self.replays.get_mut(self.selected) else {
+            return Ok(());
+        };
+        if entry.name.is_empty() {
+            return Ok(());
+        }
+        replay.title = entry.name;
+        let source = toml::to_string(replay).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn delete(&mut self) -> Result<(), String> {
+        if self.selected >= self.replays.len() {
+            return Ok(());
+        }
+        fs::remove_file(&self.replays[self.selected].0).map_err(|err| err.to_string())?;
+        self.replays.remove(self.selected);
+        self.selected = self.selected.min(self.replays.len().saturating_sub(1));
+        Ok(())
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        // 标题、空一行、列表、空一行、提示
+        let rows = VISIBLE_ROWS as u32 + 4;
+        let line_height = area.height() / rows;
+        let scale = text::fit_scale("WWWWWWWW MARATHON  9999999  0000-00-00", area.width())
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+        let row_y = |row: usize| area.y() + (line_height * row as u32) as i32;
+        let center_x = area.center().x;
+        let middle = |row: usize| row_y(row) + (line_height / 2) as i32;
+
+        let title = tr("REPLAYS");
+        text::draw_text_centered(
+            canvas,
+            &title,
+            Point::new(center_x, middle(0)),
+            scale,
+            colors.accent.0,
+        );
+        if self.replays.is_empty() {
+            let empty = tr("NO REPLAYS");
+            text::draw_text_centered(
+                canvas,
+                &empty,
+                Point::new(center_x, middle(2)),
+                scale,
+                colors.text_dim.0,
+            );
+            return;
+        }
+        let first = self
+            .selected
+            .saturating_sub(VISIBLE_ROWS / 2)
+            .min(self.replays.len().saturating_sub(VISIBLE_ROWS));
+        let visible = self
+            .replays
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_ROWS);
+        for (row, (index, (_, replay))) in visible.enumerate() {
+            let y = row_y(row + 2);
+            let selected = index == self.selected;
+            let color = if selected {
+                colors.accent.0
+            } else {
+                colors.text.0
+            };
+            let name = match &self.renaming {
+                Some(entry) if selected => format!("{:_<width$}", entry.name, width = MAX_NAME_LEN),
+                _ => replay.title.clone(),
+            };
+            let left = format!(
+                "{:<width$} {}",
+                name,
+                tr(&replay.config.mode),
+                width = MAX_NAME_LEN
+            );
+            text::draw_text(canvas, &left, Point::new(area.x(), y), scale, color);
+            let right = format!(
+                "{}  {}",
+                replay.score,
+                date(replay.timestamp / SECONDS_PER_DAY)
+            );
+            let (width, _) = text::text_size(&right, scale);
+            let position = Point::new(area.right() - width as i32, y);
+            text::draw_text(canvas, &right, position, scale, color);
+        }
+        let hint = tr("R RENAME  DEL DELETE");
+        text::draw_text_centered(
+            canvas,
+            &hint,
+            Point::new(center_x, middle(VISIBLE_ROWS + 3)),
+            scale,
+            colors.text_dim.0,
+        );
+    }
+}
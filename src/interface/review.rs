@@ -0,0 +1,155 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::nav::Nav;
+use super::skin::SkinAssets;
+use super::text;
+use crate::engine::review::Misdrop;
+use crate::engine::Engine;
+
+// 一局打完之后一块一块地看，左右翻，从掉分最多的那块开始。
+// 左边是放之前的棋盘，实心的是实际放的；打开分析的话描边的是电脑觉得更好的放法。
+// 从哪块都可以岔出去，在沙盒里试试换个放法会怎样
+pub struct Review {
+    moves: Vec<Misdrop>,
+    index: usize,
+    analysis: bool,
+}
+
+pub enum Exit {
+    Back,
+    // 引擎带着整块棋盘，装箱
+    WhatIf(Box<Engine>),
+}
+
+impl Review {
+    pub fn new(moves: Vec<Misdrop>) -> Self {
+        let index = (0..moves.len())
+            .max_by(|&a, &b| moves[a].loss.total_cmp(&moves[b].loss))
+            .unwrap_or(0);
+        Self {
+            moves,
+            index,
+            analysis: true,
+        }
+    }
+
+    // 上下开关分析，确认从这块岔出去
+    pub fn navigate(&mut self, nav: Nav) -> Option<Exit> {
+        let count = self.moves.len().max(1);
+        match nav {
+            Nav::Left => self.index = (self.index + count - 1) % count,
+            Nav::Right => self.index = (self.index + 1) % count,
+            Nav::Up | Nav::Down => self.analysis = !self.analysis,
+            Nav::Confirm => {
+                return Some(match self.moves.get(self.index) {
+                    Some(position) => Exit::WhatIf(Box::new(position.sandbox())),
+                    None => Exit::Back,
+                })
+            }
+            Nav::Back => return Some(Exit::Back),
+        }
+        None
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, skin: &SkinAssets) {
+        let colors = &skin.colors;
+        let Some(misdrop) = self.moves.get(self.index) else {
+            let message = tr("NOTHING TO REVIEW");
+            let scale = text::fit_scale(&message, area.width() / 2);
+            text::draw_text_centered(canvas, &message, area.center(), scale, colors.text.0);
+            return;
+        };
+
+        let matrix = &misdrop.position.matrix;
+        let (width, height) = (matrix.width(), matrix.height());
+        let cell = area.height() / height as u32;
+        let board = Rect::new(
+            area.x(),
+            area.y(),
+            cell * width as u32,
+            cell * height as u32,
+        );
+        canvas.set_draw_color(colors.matrix.0);
+        canvas.fill_rect(board).unwrap();
+        let cell_rect = |x: usize, y: usize| {
+            let top = board.bottom() - (cell * (y as u32 + 1)) as i32;
+            Rect::new(board.x() + (cell * x as u32) as i32, top, cell, cell)
+        };
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(color) = matrix.get(x as isize, y as isize) {
+                    canvas.set_draw_color(skin.piece_color(color));
+                    canvas.fill_rect(cell_rect(x, y)).unwrap();
+                }
+            }
+        }
+        canvas.set_draw_color(skin.piece_color(misdrop.actual.color));
+        for coord in misdrop.actual.cells {
+            canvas.fill_rect(cell_rect(coord.x, coord.y)).unwrap();
+        }
+        canvas.set_draw_color(colors.accent.0);
+        let best = if self.analysis {
+            &misdrop.best.cells[..]
+        } else {
+            &[]
+        };
+        for &coord in best {
+            let rect = cell_rect(coord.x, coord.y);
+            for i in 0..(cell / 8).max(1) {
+                let mut border = rect;
+                border.resize(rect.width() - 2 * i, rect.height() - 2 * i);
+                border.center_on(rect.center());
+                canvas.draw_rect(border).unwrap();
+            }
+        }
+
+        let panel_x = board.right() + cell as i32;
+        let panel_width = (area.right() - panel_x).max(1) as u32;
+        let scale = text::fit_scale("PIECE 100/100", panel_width)
+            .min(cell / 8)
+            .max(1);
+        let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+        let hold = |hold: bool| if hold { tr("HOLD") } else { String::new() };
+        let mut lines = vec![(
+            format!("{} {}/{}", tr("PIECE"), misdrop.piece, self.moves.len()),
+            colors.accent.0,
+        )];
+        if self.analysis {
+            let verdict = if misdrop.is_misdrop() {
+                tr("MISDROP")
+            } else {
+                String::new()
+            };
+            lines.push((verdict, colors.accent.0));
+            let loss = misdrop.loss.max(0.0);
+            lines.push((format!("{} {:.1}", tr("LOSS"), loss), colors.text.0));
+        }
+        lines.push((String::new(), colors.text.0));
+        lines.push((
+            format!("{} {}", tr("YOURS"), hold(misdrop.actual.hold)),
+            skin.piece_color(misdrop.actual.color),
+        ));
+        if self.analysis {
+            lines.push((
+                format!("{} {}", tr("BETTER"), hold(misdrop.best.hold)),
+                colors.accent.0,
+            ));
+        }
+        lines.push((String::new(), colors.text.0));
+        lines.push(("< >".to_string(), colors.text_dim.0));
+        let toggle = if self.analysis {
+            colors.accent.0
+        } else {
+            colors.text_dim.0
+        };
+        lines.push((format!("^ {}", tr("ANALYSIS")), toggle));
+        lines.push((tr("ENTER WHAT IF"), colors.text_dim.0));
+        for (row, (line, color)) in lines.iter().enumerate() {
+            let position = Point::new(panel_x, area.y() + line_height * row as i32);
+            text::draw_text(canvas, line, position, scale, *color);
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use serde::{Deserialize, Serialize};
+
+use super::daily::{date, SECONDS_PER_DAY};
+use super::i18n::tr;
+use super::nav::Nav;
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::mode::Mode;
+use crate::engine::Stats;
+use crate::net::protocol::as_text;
+
+pub const SCORES_FILE: &str = "scores.json";
+// 每个模式保留的名次
+pub const TOP_N: usize = 10;
+pub const MAX_NAME_LEN: usize = 8;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub frames: u64,
+    // 这一局的种子，拿它能打到一样的方块
+    #[serde(with = "as_text")]
+    pub seed: u64,
+    // 什么时候打的，Unix 时间（秒）
+    pub timestamp: u64,
+}
+
+impl Entry {
+    pub fn new(name: String, stats: &Stats, seed: u64) -> Self {
+        Self {
+            name,
+            score: stats.score,
+            lines: stats.lines,
+            frames: stats.frames,
+            seed,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+        }
+    }
+}
+
+// 本地排行榜，每个模式一张表，按名次排好。存成 JSON，按模式的名字分表
+#[derive(Default)]
+pub struct HighScores {
+    tables: HashMap<Mode, Vec<Entry>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ScoresFile {
+    tables: BTreeMap<String, Vec<Entry>>,
+}
+
+// sprint 和 attack race 比谁用的时间短，survival 比谁撑得久，其它模式比分数
+fn ranks_above(mode: Mode, entry: &Entry, other: &Entry) -> bool {
+    match mode {
+        Mode::Sprint | Mode::AttackRace => entry.frames < other.frames,
+        Mode::Survival => entry.frames > other.frames,
+        _ => entry.score > other.score,
+    }
+}
+
+impl HighScores {
+    // 没有文件就是空的榜；认不得的模式丢掉
+    pub fn load(path: &Path) -> Result<HighScores, String> {
+        let file: ScoresFile = match fs::read_to_string(path) {
+            Ok(source) => serde_json::from_str(&source).map_err(|err| err.to_string())?,
+            Err(err) if err.kind() == ErrorKind::NotFound => ScoresFile::default(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let mut scores = HighScores::default();
+        for (name, mut table) in file.tables {
+            let Some(mode) = Mode::ranked()
+                .into_iter()
+                .find(|&mode| mode_name(mode) == name)
+            else {
+                continue;
+            };
+            table.truncate(TOP_N);
+            scores.tables.insert(mode, table);
+        }
+        Ok(scores)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = ScoresFile {
+            tables: self
+                .tables
+                .iter()
+                .map(|(&mode, table)| (mode_name(mode).to_string(), table.clone()))
+                .collect(),
+        };
+        let source = serde_json::to_string_pretty(&file).map_err(|err| err.to_string())?;
+        fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn table(&self, mode: Mode) -> &[Entry] {
+        self.tables.get(&mode).map_or(&[], Vec::as_slice)
+    }
+
+    // 这一局能不能上榜；sprint 和 attack race 没跑完的不算，没有榜的模式（谜题）也不算
+    pub fn qualifies(&self, mode: Mode, stats: &Stats, finished: bool) -> bool {
+        if !Mode::ranked().contains(&mode) {
+            return false;
+        }
+        if matches!(mode, Mode::Sprint | Mode::AttackRace) && !finished {
+            return false;
+        }
+        if mode == Mode::Survival && stats.frames == 0 {
+            return false;
+        }
+        if !matches!(mode, Mode::Sprint | Mode::AttackRace | Mode::Survival) && stats.score == 0 {
+            return false;
+        }
+        let table = self.table(mode);
+        let entry = Entry::new(String::new(), stats, 0);
+        table.len() < TOP_N || table.iter().any(|other| ranks_above(mode, &entry, other))
+    }
+
+    // 插入之后的名次（从 0 开始），挤出榜的话返回 None
+    pub fn insert(&mut self, mode: Mode, entry: Entry) -> Option<usize> {
+        let table = self.tables.entry(mode).or_default();
+        // 成绩一样的话先上榜的排前面
+        let rank = table
+            .iter()
+            .position(|other| ranks_above(mode, &entry, other))
+            .unwrap_or(table.len());
+        table.insert(rank, entry);
+        table.truncate(TOP_N);
+        (rank < TOP_N).then_some(rank)
+    }
+}
+
+pub fn mode_name(mode: Mode) -> &'static str {
+    mode.game_mode().name()
+}
+
+// 排行榜界面，左右键切换模式
+pub struct Leaderboard {
+    pub mode: Mode,
+    // 刚刚上榜的模式和名次，高亮显示；有的话说明是打完一局进来的
+    highlight: Option<(Mode, usize)>,
+}
+
+impl Leaderboard {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            highlight: None,
+        }
+    }
+
+    pub fn with_highlight(mode: Mode, rank: Option<usize>) -> Self {
+        Self {
+            mode,
+            highlight: Some((mode, rank.unwrap_or(TOP_N))),
+        }
+    }
+
+    pub fn after_game(&self) -> bool {
+        self.highlight.is_some()
+    }
+
+    pub fn cycle(&mut self, delta: isize) {
+        let ranked = Mode::ranked();
+        let count = ranked.len() as isize;
+        let index = ranked.iter().position(|&mode| mode == self.mode).unwrap() as isize;
+        self.mode = ranked[(index + delta).rem_euclid(count) as usize];
+    }
+
+    // 左右切换模式；返回 true 表示要离开
+    pub fn navigate(&mut self, nav: Nav) -> bool {
+        match nav {
+            Nav::Left => self.cycle(-1),
+            Nav::Right => self.cycle(1),
+            Nav::Confirm | Nav::Back => return true,
+            Nav::Up | Nav::Down => {}
+        }
+        false
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        area: Rect,
+        scores: &HighScores,
+        colors: &SkinColors,
+    ) {
+        // 标题、空一行、TOP_N 行、空一行、提示
+        let rows = TOP_N as u32 + 4;
+        let line_height = area.height() / rows;
+        let scale = text::fit_scale("10 WWWWWWWW  0000-00-00  00:00.000", area.width())
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+
+        let title = format!("< {} >", tr(mode_name(self.mode)));
+        let center = Point::new(area.center().x, area.y() + (line_height / 2) as i32);
+        text::draw_text_centered(canvas, &title, center, scale, colors.accent.0);
+
+        let table = scores.table(self.mode);
+        for rank in 0..TOP_N {
+            let y = area.y() + (line_height * (rank as u32 + 2)) as i32;
+            let color = if self.highlight == Some((self.mode, rank)) {
+                colors.accent.0
+            } else {
+                colors.text.0
+            };
+            let (name, result) = match table.get(rank) {
+                Some(entry) => (entry.name.as_str(), result(self.mode, entry)),
+                None => ("---", String::new()),
+            };
+            let left = format!("{:>2} {}", rank + 1, name);
+            text::draw_text(canvas, &left, Point::new(area.x(), y), scale, color);
+            // 日期画在中间，淡一点
+            if let Some(entry) = table.get(rank) {
+                let day = date(entry.timestamp / SECONDS_PER_DAY);
+                let center =
+                    Point::new(area.center().x, y + (text::GLYPH_HEIGHT * scale / 2) as i32);
+                text::draw_text_centered(canvas, &day, center, scale, colors.text_dim.0);
+            }
+            let (width, _) = text::text_size(&result, scale);
+            let position = Point::new(area.right() - width as i32, y);
+            text::draw_text(canvas, &result, position, scale, color);
+        }
+    }
+}
+
+fn result(mode: Mode, entry: &Entry) -> String {
+    match mode {
+        Mode::Sprint | Mode::AttackRace | Mode::Survival => {
+            super::format_precise_time(entry.frames)
+        }
+        _ => entry.score.to_string(),
+    }
+}
+
+// 上榜之后输入名字，只收字母和数字
+pub struct NameEntry {
+    pub name: String,
+}
+
+impl NameEntry {
+    pub fn new(last_name: &str) -> Self {
+        Self {
+            name: last_name.to_string(),
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        if self.name.len() < MAX_NAME_LEN && c.is_ascii_alphanumeric() {
+            self.name.push(c.to_ascii_uppercase());
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.name.pop();
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        let prompt = tr("ENTER YOUR NAME");
+        let scale = text::fit_scale(&prompt, area.width());
+        let line_height = (text::GLYPH_HEIGHT * scale * 2) as i32;
+        let center = area.center();
+        text::draw_text_centered(
+            canvas,
+            &tr("NEW HIGH SCORE"),
+            Point::new(center.x, center.y - line_height * 2),
+            scale,
+            colors.accent.0,
+        );
+        text::draw_text_centered(
+            canvas,
+            &prompt,
+            Point::new(center.x, center.y - line_height),
+            scale,
+            colors.text_dim.0,
+        );
+        // 名字后面画一个下划线当光标
+        let name = format!("{:_<width$}", self.name, width = MAX_NAME_LEN);
+        text::draw_text_centered(
+            canvas,
+            &name,
+            Point::new(center.x, center.y + line_height / 2),
+            scale,
+            colors.text.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(score: u32, frames: u64) -> Entry {
+        Entry {
+            name: "AAA".to_string(),
+            score,
+            lines: 0,
+            frames,
+            seed: u64::MAX,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn tables_keep_top_n_in_order() {
+        let mut scores = HighScores::default();
+        for score in 1..=TOP_N as u32 {
+            scores.insert(Mode::Marathon, entry(score * 100, 0));
+        }
+        let low = Stats {
+            score: 50,
+            ..Stats::default()
+        };
+        assert!(!scores.qualifies(Mode::Marathon, &low, false));
+        assert_eq!(scores.insert(Mode::Marathon, entry(550, 0)), Some(5));
+        assert_eq!(scores.table(Mode::Marathon).len(), TOP_N);
+        assert_eq!(scores.table(Mode::Marathon)[0].score, 1000);
+    }
+
+    #[test]
+    fn sprint_ranks_by_time() {
+        let mut scores = HighScores::default();
+        scores.insert(Mode::Sprint, entry(0, 3000));
+        assert_eq!(scores.insert(Mode::Sprint, entry(0, 2000)), Some(0));
+        let stats = Stats {
+            frames: 1000,
+            ..Stats::default()
+        };
+        assert!(!scores.qualifies(Mode::Sprint, &stats, false));
+        assert!(scores.qualifies(Mode::Sprint, &stats, true));
+    }
+
+    #[test]
+    fn tables_survive_a_save_and_load() {
+        let mut scores = HighScores::default();
+        scores.insert(Mode::Marathon, entry(1200, 0));
+        scores.insert(Mode::Sprint, entry(0, 3000));
+        let path = std::env::temp_dir().join(format!("scores-{}.json", std::process::id()));
+        scores.save(&path).unwrap();
+        let loaded = HighScores::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.table(Mode::Marathon), scores.table(Mode::Marathon));
+        assert_eq!(loaded.table(Mode::Sprint)[0].seed, u64::MAX);
+        assert!(loaded.table(Mode::Ultra).is_empty());
+        assert!(HighScores::load(&path)
+            .unwrap()
+            .table(Mode::Sprint)
+            .is_empty());
+    }
+}
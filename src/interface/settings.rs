@@ -0,0 +1,302 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::keybinds::Keybinds;
+use super::palette::Palette;
+use crate::engine::bot::Difficulty;
+
+// 设置保存在系统的配置目录下（见 `config_path`）；找不到配置目录就放当前目录。
+// 以前的版本放在当前目录，第一次运行的时候搬过去
+pub const CONFIG_FILE: &str = "config.toml";
+const APP_DIR: &str = "tetris-rs";
+
+// 玩家可以调整的界面选项
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub grid: GridStyle,
+    // `Interface::skins` 里的下标
+    pub skin: usize,
+    // `Interface::languages` 里的下标，0 是内置的英文
+    pub language: usize,
+    pub palette: Palette,
+    // 在格子上画方块字母，不靠颜色也能分辨方块
+    pub piece_glyphs: bool,
+    // 左上角显示帧率和每帧耗时
+    pub show_perf: bool,
+    // 直播模式：在角落显示按键
+    pub input_display: bool,
+    // 堆到这一行（从 1 开始数）以上就开始警告
+    pub danger_row: usize,
+    pub screen_shake: ShakeLevel,
+    // 关掉震动、拖影、闪白这些动来动去的特效
+    pub reduced_motion: bool,
+    // 每个格子描一圈亮边，颜色相近的方块也分得清
+    pub high_contrast: bool,
+    pub fullscreen: bool,
+    // 音量都是 0 到 100
+    pub master_volume: u8,
+    pub music_volume: u8,
+    pub sfx_volume: u8,
+    // 静音不改音量，取消静音后还是原来的大小
+    pub muted: bool,
+    // `Interface::sound_packs` 里的下标，0 是内置的合成音效
+    pub sound_pack: usize,
+    // 按住左右键多少帧之后开始自动移动（DAS），之后每隔多少帧移一格（ARR），0 表示直接到底
+    pub das: u32,
+    pub arr: u32,
+    // 联机时游戏里的按键晚几帧生效，0 是不延迟
+    pub input_delay: u32,
+    // 显示硬降的落点
+    pub ghost: bool,
+    // 按键比标准按法多的时候提示标准按法
+    pub finesse_hints: bool,
+    // 用自带的 AI 提示当前方块放哪最好
+    pub placement_hint: bool,
+    // 开局的时候认出这一包能搭哪个定式（PCO、TKI 这些），提示每块放哪
+    pub opener_trainer: bool,
+    // 电脑玩家的难度
+    pub bot_difficulty: Difficulty,
+    // 每锁一块往 samples.jsonl 里记一条局面、放法和收益，演示里电脑自己玩的也记
+    pub export_samples: bool,
+    // 在两次下落之间插值，让方块看起来是平滑往下走的，只影响画面
+    pub smooth_fall: bool,
+    // 显示接下来几个方块，0 到 Engine::MAX_PREVIEW
+    pub previews: usize,
+    // dig 模式隔几秒涨一行垃圾
+    pub dig_interval: u32,
+    pub keybinds: Keybinds,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            grid: GridStyle::default(),
+            skin: 0,
+            language: 0,
+            palette: Palette::default(),
+            piece_glyphs: false,
+            show_perf: false,
+            input_display: false,
+            danger_row: 16,
+            screen_shake: ShakeLevel::default(),
+            reduced_motion: false,
+            high_contrast: false,
+            fullscreen: false,
+            master_volume: 80,
+            music_volume: 60,
+            sfx_volume: 80,
+            muted: false,
+            sound_pack: 0,
+            das: 10,
+            arr: 2,
+            input_delay: 0,
+            ghost: true,
+            finesse_hints: false,
+            placement_hint: false,
+            opener_trainer: false,
+            bot_difficulty: Difficulty::default(),
+            export_samples: false,
+            smooth_fall: false,
+            previews: 5,
+            dig_interval: 5,
+            keybinds: Keybinds::default(),
+        }
+    }
+}
+
+impl Settings {
+    // 会影响皮肤贴图的那些设置是否一样
+    pub fn same_skin(&self, other: &Settings) -> bool {
+        self.skin == other.skin
+            && self.palette == other.palette
+            && self.piece_glyphs == other.piece_glyphs
+    }
+
+    // 会影响混音音量的那些设置是否一样
+    pub fn same_volume(&self, other: &Settings) -> bool {
+        self.master_volume == other.master_volume
+            && self.music_volume == other.music_volume
+            && self.sfx_volume == other.sfx_volume
+            && self.muted == other.muted
+    }
+
+    // 实际的震动幅度；减少动效的时候不管震动档位都不抖
+    pub fn shake_scale(&self) -> f32 {
+        if self.reduced_motion {
+            0.0
+        } else {
+            self.screen_shake.scale()
+        }
+    }
+
+    // 文件不存在就用默认设置，文件里没写的字段也用默认值
+    pub fn load(path: &Path) -> Result<Settings, String> {
+        match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    // 第一次运行还没有配置文件，写一份默认的（或者当前目录下老的）出来，方便照着改
+    pub fn load_or_create(path: &Path) -> Result<Settings, String> {
+        if path.exists() {
+            return Settings::load(path);
+        }
+        let legacy = Path::new(CONFIG_FILE);
+        // 老配置读不出来也不耽误写新的，用默认设置
+        let settings = if path != legacy {
+            Settings::load(legacy).unwrap_or_else(|err| {
+                eprintln!("Failed to load {}: {}", legacy.display(), err);
+                Settings::default()
+            })
+        } else {
+            Settings::default()
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        settings.save(path)?;
+        Ok(settings)
+    }
+
+    // 先写到旁边的临时文件再换过去，写到一半退出了也不会留下半个配置文件
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let source = toml::to_string(self).map_err(|err| err.to_string())?;
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, source).map_err(|err| err.to_string())?;
+        fs::rename(&temporary, path).map_err(|err| err.to_string())
+    }
+}
+
+// Linux 是 $XDG_CONFIG_HOME 或者 ~/.config，macOS 是 ~/Library/Application Support，
+// Windows 是 %APPDATA%，下面的 tetris-rs/config.toml
+pub fn config_path() -> PathBuf {
+    let var = |name| {
+        env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let dir = if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    };
+    match dir {
+        Some(dir) => dir.join(APP_DIR).join(CONFIG_FILE),
+        None => PathBuf::from(CONFIG_FILE),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridStyle {
+    Off,
+    #[default]
+    Lines,
+    Checkerboard,
+}
+
+impl GridStyle {
+    pub fn next(self) -> Self {
+        match self {
+            GridStyle::Off => GridStyle::Lines,
+            GridStyle::Lines => GridStyle::Checkerboard,
+            GridStyle::Checkerboard => GridStyle::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShakeLevel {
+    Off,
+    #[default]
+    Subtle,
+    Strong,
+}
+
+impl ShakeLevel {
+    pub fn next(self) -> Self {
+        match self {
+            ShakeLevel::Off => ShakeLevel::Subtle,
+            ShakeLevel::Subtle => ShakeLevel::Strong,
+            ShakeLevel::Strong => ShakeLevel::Off,
+        }
+    }
+
+    pub fn scale(self) -> f32 {
+        match self {
+            ShakeLevel::Off => 0.0,
+            ShakeLevel::Subtle => 1.0,
+            ShakeLevel::Strong => 2.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sdl2::keyboard::Keycode;
+
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_through_toml() {
+        let settings = Settings {
+            grid: GridStyle::Checkerboard,
+            palette: Palette::OkabeIto,
+            ghost: false,
+            das: 7,
+            keybinds: Keybinds {
+                hard_drop: Keycode::Z,
+                ..Keybinds::default()
+            },
+            ..Settings::default()
+        };
+        let source = toml::to_string(&settings).unwrap();
+        assert!(source.contains("hard_drop = \"Z\""));
+        assert_eq!(toml::from_str::<Settings>(&source).unwrap(), settings);
+        assert!(toml::from_str::<Settings>("[keybinds]\nleft = \"F13\"").is_err());
+
+        // 老版本的配置文件缺字段也能读
+        let partial: Settings = toml::from_str("arr = 0").unwrap();
+        assert_eq!(partial.arr, 0);
+        assert_eq!(partial.das, Settings::default().das);
+
+        // 存盘换掉旧文件，不留临时文件
+        let path = env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        Settings::default().save(&path).unwrap();
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path).unwrap(), settings);
+        assert!(!path.with_extension("toml.tmp").exists());
+        fs::remove_file(&path).unwrap();
+
+        // 第一次运行，没有老配置也照样写出一份默认的
+        let dir = env::temp_dir().join(format!("config-dir-{}", std::process::id()));
+        let path = dir.join(CONFIG_FILE);
+        assert_eq!(
+            Settings::load_or_create(&path).unwrap(),
+            Settings::default()
+        );
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reduced_motion_overrides_screen_shake() {
+        let mut settings = Settings {
+            screen_shake: ShakeLevel::Strong,
+            ..Settings::default()
+        };
+        assert!(settings.shake_scale() > 0.0);
+        settings.reduced_motion = true;
+        assert_eq!(settings.shake_scale(), 0.0);
+    }
+}
@@ -0,0 +1,47 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::Stats;
+
+// 分数面板里的实时统计，每帧直接从引擎的 Stats 算出来
+pub fn draw_stats(canvas: &mut Canvas<Window>, area: Rect, stats: &Stats, colors: &SkinColors) {
+    let rows = [
+        (tr("PPS"), format!("{:.2}", stats.pps())),
+        (tr("APM"), format!("{:.1}", stats.apm())),
+        (tr("SENT"), stats.attack.to_string()),
+        (tr("HOLD"), stats.holds.to_string()),
+        (tr("FAULT"), stats.finesse_faults.to_string()),
+    ];
+    // 标签左对齐、数值右对齐，按最长的一行来定字号
+    let widest = rows
+        .iter()
+        .map(|(label, value)| label.len() + value.len() + 1)
+        .max()
+        .unwrap_or(1);
+    let scale = (area.width() / (widest as u32 * 6)).max(1);
+    let line_height = area.height() / rows.len() as u32;
+    let scale = scale.min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+
+    for (i, (label, value)) in rows.iter().enumerate() {
+        let y = area.y() + (line_height * i as u32) as i32;
+        text::draw_text(
+            canvas,
+            label,
+            Point::new(area.x(), y),
+            scale,
+            colors.text_dim.0,
+        );
+        let (width, _) = text::text_size(value, scale);
+        text::draw_text(
+            canvas,
+            value,
+            Point::new(area.right() - width as i32, y),
+            scale,
+            colors.text.0,
+        );
+    }
+}
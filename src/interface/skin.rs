@@ -0,0 +1,504 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::image::LoadTexture;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+use serde::Deserialize;
+
+use super::settings::Settings;
+use super::{nes, palette};
+use crate::engine::Color as SemanticColor;
+
+// 皮肤目录：每个子目录放一个 skin.toml，贴图路径相对于这个子目录
+pub const SKIN_DIR: &str = "skins";
+const SKIN_FILE: &str = "skin.toml";
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellStyle {
+    Flat,
+    // 上左边亮、下右边暗，看起来像凸起的方块
+    Beveled,
+    // 同色相邻的格子之间不画边框，连成一整块
+    Connected,
+}
+
+// 整体的画风。Nes 模仿红白机：配色随等级变、方块只有三种画法、没有影子
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Modern,
+    Nes,
+}
+
+// 皮肤文件里的颜色写成 "#rrggbb" 或者 "#rrggbbaa"
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub Color);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        let channel = |i: usize| {
+            digits
+                .get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| format!("invalid color {:?}", hex))
+        };
+        let alpha = match digits.len() {
+            6 => 0xff,
+            8 => channel(6)?,
+            _ => return Err(format!("invalid color {:?}", hex)),
+        };
+        Ok(HexColor(Color::RGBA(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            alpha,
+        )))
+    }
+}
+
+const fn hex(r: u8, g: u8, b: u8, a: u8) -> HexColor {
+    HexColor(Color::RGBA(r, g, b, a))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct PieceColors {
+    pub yellow: HexColor,
+    pub cyan: HexColor,
+    pub purple: HexColor,
+    pub orange: HexColor,
+    pub blue: HexColor,
+    pub green: HexColor,
+    pub red: HexColor,
+    // 垃圾行
+    pub gray: HexColor,
+}
+
+impl Default for PieceColors {
+    fn default() -> Self {
+        Self {
+            yellow: hex(0xed, 0xd4, 0x00, 0xff),
+            cyan: hex(0x72, 0x9f, 0xcf, 0xff),
+            purple: hex(0x75, 0x50, 0x7b, 0xff),
+            orange: hex(0xf5, 0x79, 0x00, 0xff),
+            blue: hex(0x34, 0x65, 0xa4, 0xff),
+            green: hex(0x73, 0xd2, 0x16, 0xff),
+            red: hex(0xef, 0x29, 0x29, 0xff),
+            gray: hex(0x88, 0x8a, 0x85, 0xff),
+        }
+    }
+}
+
+impl PieceColors {
+    pub fn get(&self, color: SemanticColor) -> Color {
+        match color {
+            SemanticColor::Yellow => self.yellow.0,
+            SemanticColor::Cyan => self.cyan.0,
+            SemanticColor::Purple => self.purple.0,
+            SemanticColor::Orange => self.orange.0,
+            SemanticColor::Blue => self.blue.0,
+            SemanticColor::Green => self.green.0,
+            SemanticColor::Red => self.red.0,
+            SemanticColor::Gray => self.gray.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct SkinColors {
+    pub background: HexColor,
+    pub matrix: HexColor,
+    pub grid: HexColor,
+    pub overlay: HexColor,
+    pub text: HexColor,
+    // 菜单里没选中的选项
+    pub text_dim: HexColor,
+    // 标题、选中的菜单项之类需要突出的地方
+    pub accent: HexColor,
+    pub line_clear_flash: HexColor,
+    // 正在被消掉的行，闪烁的间隙和之后淡出都用这个颜色
+    pub line_clear_highlight: HexColor,
+    // 堆得太高时的警告边框和背景色
+    pub danger: HexColor,
+    pub pieces: PieceColors,
+}
+
+impl Default for SkinColors {
+    fn default() -> Self {
+        Self {
+            background: hex(0x10, 0x10, 0x18, 0xff),
+            matrix: hex(0x80, 0x75, 0xbf, 0xff),
+            grid: hex(0x00, 0x00, 0x00, 0x30),
+            overlay: hex(0x00, 0x00, 0x00, 0xc0),
+            text: hex(0xff, 0xff, 0xff, 0xff),
+            text_dim: hex(0xa0, 0xa0, 0xb0, 0xff),
+            accent: hex(0x80, 0x75, 0xbf, 0xff),
+            line_clear_flash: hex(0xff, 0xff, 0xff, 0xff),
+            line_clear_highlight: hex(0xff, 0xd8, 0x60, 0xff),
+            danger: hex(0xe0, 0x20, 0x20, 0xff),
+            pieces: PieceColors::default(),
+        }
+    }
+}
+
+// 背景上的程序动画，没有背景图的时候也能用
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundAnimation {
+    // 缓缓往下飘的星星
+    Stars,
+    // 各种颜色的方块从上往下掉
+    Rain,
+}
+
+// skin.toml 里的 [background]，图片路径相对于皮肤目录
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct Background {
+    pub image: Option<PathBuf>,
+    pub animation: Option<BackgroundAnimation>,
+    // 盖在背景上的背景色的不透明度，0 到 1，越大越暗，保证方块看得清
+    pub dim: f32,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self {
+            image: None,
+            animation: None,
+            dim: 0.6,
+        }
+    }
+}
+
+// skin.toml 的内容，没写的字段用默认皮肤的值
+#[derive(Deserialize)]
+#[serde(default)]
+struct SkinFile {
+    name: Option<String>,
+    theme: Theme,
+    cell_style: CellStyle,
+    mino_texture: Option<PathBuf>,
+    background: Background,
+    colors: SkinColors,
+}
+
+impl Default for SkinFile {
+    fn default() -> Self {
+        Self {
+            name: None,
+            theme: Theme::Modern,
+            cell_style: CellStyle::Beveled,
+            mino_texture: None,
+            background: Background::default(),
+            colors: SkinColors::default(),
+        }
+    }
+}
+
+// 一套皮肤：方块的画法、可选的方块贴图，以及整个界面的配色
+pub struct Skin {
+    pub name: String,
+    pub theme: Theme,
+    pub cell_style: CellStyle,
+    // 单个格子的灰度贴图，画的时候按方块颜色染色
+    pub mino_texture: Option<PathBuf>,
+    pub background: Background,
+    pub colors: SkinColors,
+}
+
+impl Skin {
+    pub fn builtin() -> Vec<Skin> {
+        let mut skins: Vec<Skin> = [
+            ("FLAT", CellStyle::Flat),
+            ("BEVELED", CellStyle::Beveled),
+            ("CONNECTED", CellStyle::Connected),
+        ]
+        .into_iter()
+        .map(|(name, cell_style)| Skin {
+            name: name.to_string(),
+            theme: Theme::Modern,
+            cell_style,
+            mino_texture: None,
+            background: Background::default(),
+            colors: SkinColors::default(),
+        })
+        .collect();
+        skins.push(Skin {
+            name: "NES".to_string(),
+            theme: Theme::Nes,
+            cell_style: CellStyle::Flat,
+            mino_texture: None,
+            background: Background {
+                dim: 0.0,
+                ..Background::default()
+            },
+            colors: nes::skin_colors(),
+        });
+        skins
+    }
+
+    pub fn load(dir: &Path) -> Result<Skin, String> {
+        let path = dir.join(SKIN_FILE);
+        let source = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let file: SkinFile = toml::from_str(&source).map_err(|err| err.to_string())?;
+        let fallback_name = dir.file_name().unwrap_or_default().to_string_lossy();
+        Ok(Skin {
+            name: file
+                .name
+                .unwrap_or_else(|| fallback_name.to_string())
+                .to_uppercase(),
+            theme: file.theme,
+            cell_style: file.cell_style,
+            mino_texture: file.mino_texture.map(|texture| dir.join(texture)),
+            background: Background {
+                image: file.background.image.map(|image| dir.join(image)),
+                ..file.background
+            },
+            colors: file.colors,
+        })
+    }
+
+    // 内置皮肤加上皮肤目录里能读出来的所有皮肤
+    pub fn load_all(dir: &Path) -> Vec<Skin> {
+        let mut skins = Self::builtin();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return skins;
+        };
+        let mut dirs = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join(SKIN_FILE).is_file())
+            .collect::<Vec<_>>();
+        dirs.sort();
+        for skin_dir in dirs {
+            match Skin::load(&skin_dir) {
+                Ok(skin) => skins.push(skin),
+                Err(err) => eprintln!("Failed to load skin {}: {}", skin_dir.display(), err),
+            }
+        }
+        skins
+    }
+}
+
+// 格子四周哪些方向连着同一种颜色，Connected 风格用
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Links {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+// 皮肤加载到显卡上的资源，贴图的生命周期跟着 TextureCreator
+pub struct SkinAssets<'tc> {
+    pub colors: SkinColors,
+    theme: Theme,
+    cell_style: CellStyle,
+    piece_glyphs: bool,
+    // 每种颜色一张已经染好色的贴图，按 `SemanticColor as usize` 索引
+    minos: Vec<Texture<'tc>>,
+    pub background: Option<Texture<'tc>>,
+    pub background_animation: Option<BackgroundAnimation>,
+    pub background_dim: f32,
+}
+
+impl<'tc> SkinAssets<'tc> {
+    pub fn load(
+        skin: &Skin,
+        settings: &Settings,
+        texture_creator: &'tc TextureCreator<WindowContext>,
+    ) -> Self {
+        let mut colors = skin.colors;
+        // 红白机画风的方块颜色由等级决定，不用配色方案
+        if let (Theme::Modern, Some(pieces)) = (skin.theme, settings.palette.piece_colors()) {
+            colors.pieces = pieces;
+        }
+        let minos = match &skin.mino_texture {
+            Some(path) => SemanticColor::ALL
+                .iter()
+                .map(|color| {
+                    let mut texture = texture_creator.load_texture(path)?;
+                    let tint = colors.pieces.get(*color);
+                    texture.set_color_mod(tint.r, tint.g, tint.b);
+                    Ok(texture)
+                })
+                .collect::<Result<Vec<_>, String>>()
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to load mino texture {}: {}", path.display(), err);
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        let background = skin.background.image.as_ref().and_then(|path| {
+            texture_creator
+                .load_texture(path)
+                .map_err(|err| eprintln!("Failed to load background {}: {}", path.display(), err))
+                .ok()
+        });
+        Self {
+            colors,
+            theme: skin.theme,
+            cell_style: skin.cell_style,
+            piece_glyphs: settings.piece_glyphs,
+            minos,
+            background,
+            background_animation: skin.background.animation,
+            background_dim: skin.background.dim.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    // 每帧画之前告诉皮肤当前等级，红白机画风靠它换配色
+    pub fn set_level(&mut self, level: u32) {
+        if self.theme == Theme::Nes {
+            self.colors.pieces = nes::piece_colors(level);
+        }
+    }
+
+    pub fn piece_color(&self, color: SemanticColor) -> Color {
+        self.colors.pieces.get(color)
+    }
+
+    pub fn draw_mino(
+        &self,
+        canvas: &mut Canvas<Window>,
+        rect: Rect,
+        color: SemanticColor,
+        links: Links,
+    ) {
+        let base = self.piece_color(color);
+        if let Some(texture) = self.minos.get(color as usize) {
+            canvas.copy(texture, None, rect).unwrap();
+        } else if self.theme == Theme::Nes {
+            nes::draw_cell(canvas, rect, color, base);
+        } else {
+            self.draw_styled(canvas, rect, base, links);
+        }
+        if self.piece_glyphs {
+            palette::draw_glyph(canvas, rect, color, shade(base, -0.6));
+        }
+    }
+
+    fn draw_styled(&self, canvas: &mut Canvas<Window>, rect: Rect, base: Color, links: Links) {
+        let edge = (rect.width().min(rect.height()) / 8).max(1);
+        canvas.set_draw_color(base);
+        canvas.fill_rect(rect).unwrap();
+        match self.cell_style {
+            CellStyle::Flat => {}
+            CellStyle::Beveled => {
+                canvas.set_draw_color(shade(base, 0.4));
+                canvas.fill_rect(top_edge(rect, edge)).unwrap();
+                canvas.fill_rect(left_edge(rect, edge)).unwrap();
+                canvas.set_draw_color(shade(base, -0.4));
+                canvas.fill_rect(bottom_edge(rect, edge)).unwrap();
+                canvas.fill_rect(right_edge(rect, edge)).unwrap();
+            }
+            CellStyle::Connected => {
+                canvas.set_draw_color(shade(base, -0.5));
+                let edges = [
+                    (links.up, top_edge(rect, edge)),
+                    (links.down, bottom_edge(rect, edge)),
+                    (links.left, left_edge(rect, edge)),
+                    (links.right, right_edge(rect, edge)),
+                ];
+                for (linked, edge_rect) in edges {
+                    if !linked {
+                        canvas.fill_rect(edge_rect).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// amount 为正往白色靠，为负往黑色靠
+pub fn shade(color: Color, amount: f32) -> Color {
+    let target = if amount > 0.0 { 255.0 } else { 0.0 };
+    let amount = amount.abs();
+    let mix = |c: u8| (c as f32 + (target - c as f32) * amount) as u8;
+    Color::RGBA(mix(color.r), mix(color.g), mix(color.b), color.a)
+}
+
+fn top_edge(rect: Rect, edge: u32) -> Rect {
+    Rect::new(rect.x(), rect.y(), rect.width(), edge)
+}
+
+fn bottom_edge(rect: Rect, edge: u32) -> Rect {
+    Rect::new(rect.x(), rect.bottom() - edge as i32, rect.width(), edge)
+}
+
+fn left_edge(rect: Rect, edge: u32) -> Rect {
+    Rect::new(rect.x(), rect.y(), edge, rect.height())
+}
+
+fn right_edge(rect: Rect, edge: u32) -> Rect {
+    Rect::new(rect.right() - edge as i32, rect.y(), edge, rect.height())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_color() {
+        assert_eq!(
+            HexColor::try_from("#102030".to_string()),
+            Ok(HexColor(Color::RGBA(0x10, 0x20, 0x30, 0xff)))
+        );
+        assert_eq!(
+            HexColor::try_from("10203040".to_string()),
+            Ok(HexColor(Color::RGBA(0x10, 0x20, 0x30, 0x40)))
+        );
+        assert!(HexColor::try_from("#12345".to_string()).is_err());
+        assert!(HexColor::try_from("#zz0000".to_string()).is_err());
+    }
+
+    #[test]
+    fn partial_skin_file_uses_defaults() {
+        let file: SkinFile = toml::from_str(
+            r##"
+            cell_style = "flat"
+
+            [colors.pieces]
+            red = "#ff0000"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(file.cell_style, CellStyle::Flat);
+        assert_eq!(file.theme, Theme::Modern);
+        assert_eq!(file.colors.pieces.red.0, Color::RGB(0xff, 0, 0));
+        assert_eq!(file.colors.pieces.cyan, PieceColors::default().cyan);
+        assert_eq!(file.colors.background, SkinColors::default().background);
+        assert_eq!(file.background, Background::default());
+    }
+
+    #[test]
+    fn background_section() {
+        let file: SkinFile = toml::from_str(
+            r##"
+            [background]
+            image = "space.png"
+            animation = "stars"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(file.background.image, Some(PathBuf::from("space.png")));
+        assert_eq!(file.background.animation, Some(BackgroundAnimation::Stars));
+        assert_eq!(file.background.dim, Background::default().dim);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::audio::Sound;
+use super::music::SoundtrackConfig;
+
+// 音效包的目录，每个包一个子目录，里面放 pack.toml 和声音文件
+pub const SOUND_DIR: &str = "sounds";
+const PACK_FILE: &str = "pack.toml";
+
+// pack.toml 的内容：
+//
+//     name = "retro"
+//     [sounds]
+//     lock = "thud.wav"
+//     [soundtrack]
+//     normal_bpm = 120
+//     [[soundtrack.normal]]
+//     file = "melody.ogg"
+//
+// [sounds] 里没写的音效找目录里的默认文件名（比如 lock.wav），再没有就用合成的
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PackFile {
+    name: Option<String>,
+    sounds: HashMap<Sound, PathBuf>,
+    soundtrack: SoundtrackConfig,
+}
+
+// 一套音效和背景音乐，路径都已经是相对于当前目录的
+#[derive(Clone)]
+pub struct SoundPack {
+    pub name: String,
+    sounds: HashMap<Sound, PathBuf>,
+    pub soundtrack: SoundtrackConfig,
+}
+
+impl SoundPack {
+    // 内置的音效包，全部用合成的声音
+    pub fn builtin() -> SoundPack {
+        SoundPack {
+            name: "CLASSIC".to_string(),
+            sounds: HashMap::new(),
+            soundtrack: SoundtrackConfig::default(),
+        }
+    }
+
+    pub fn load(dir: &Path) -> Result<SoundPack, String> {
+        let path = dir.join(PACK_FILE);
+        let source = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let file: PackFile = toml::from_str(&source).map_err(|err| err.to_string())?;
+        let fallback_name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let mut sounds = HashMap::new();
+        for sound in Sound::ALL {
+            let path = match file.sounds.get(&sound) {
+                Some(path) => dir.join(path),
+                None => dir.join(sound.file_name()),
+            };
+            if path.exists() {
+                sounds.insert(sound, path);
+            }
+        }
+        Ok(SoundPack {
+            name: file
+                .name
+                .unwrap_or_else(|| fallback_name.to_string())
+                .to_uppercase(),
+            sounds,
+            soundtrack: file.soundtrack.relative_to(dir),
+        })
+    }
+
+    // 内置的音效包加上目录里能读出来的所有音效包
+    pub fn load_all(dir: &Path) -> Vec<SoundPack> {
+        let mut packs = vec![Self::builtin()];
+        let Ok(entries) = fs::read_dir(dir) else {
+            return packs;
+        };
+        let mut dirs = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join(PACK_FILE).is_file())
+            .collect::<Vec<_>>();
+        dirs.sort();
+        for pack_dir in dirs {
+            match SoundPack::load(&pack_dir) {
+                Ok(pack) => packs.push(pack),
+                Err(err) => eprintln!("Failed to load sound pack {}: {}", pack_dir.display(), err),
+            }
+        }
+        packs
+    }
+
+    // None 的话用合成的
+    pub fn sound(&self, sound: Sound) -> Option<&Path> {
+        self.sounds.get(&sound).map(PathBuf::as_path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manifest_maps_sounds_and_soundtrack() {
+        let file: PackFile = toml::from_str(
+            r#"
+            name = "retro"
+
+            [sounds]
+            level_up = "fanfare.wav"
+
+            [[soundtrack.fast]]
+            file = "fast.ogg"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            file.sounds.get(&Sound::LevelUp),
+            Some(&PathBuf::from("fanfare.wav"))
+        );
+        let soundtrack = file.soundtrack.relative_to(Path::new("sounds/retro"));
+        assert!(soundtrack.normal.is_empty());
+        assert_eq!(soundtrack.fast[0].file, Path::new("sounds/retro/fast.ogg"));
+    }
+}
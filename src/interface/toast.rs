@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::skin::SkinColors;
+use super::text;
+
+// 每条通知显示 3 秒，最后半秒淡出
+const TOAST_FRAMES: u32 = 180;
+const FADE_FRAMES: u32 = 30;
+// 同时最多摞这么多条，多出来的排队
+const MAX_VISIBLE: usize = 3;
+
+struct Toast {
+    text: String,
+    frames_left: u32,
+}
+
+// 屏幕底部的通知，比如破纪录、录像保存好了；哪个模块都可以往里面塞
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    // 文字在画的时候才翻译，所以这里直接传英文
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.queue.push_back(Toast {
+            text: text.into(),
+            frames_left: TOAST_FRAMES,
+        });
+    }
+
+    // 只有显示出来的才开始计时
+    pub fn update(&mut self) {
+        for toast in self.queue.iter_mut().take(MAX_VISIBLE) {
+            toast.frames_left = toast.frames_left.saturating_sub(1);
+        }
+        self.queue.retain(|toast| toast.frames_left > 0);
+    }
+
+    fn visible(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.queue.iter().take(MAX_VISIBLE).map(|toast| {
+            let fade = (toast.frames_left as f32 / FADE_FRAMES as f32).min(1.0);
+            (toast.text.as_str(), fade)
+        })
+    }
+
+    // 从屏幕底部往上摞，最早的在最下面
+    pub fn draw(&self, canvas: &mut Canvas<Window>, colors: &SkinColors) {
+        let viewport = canvas.viewport();
+        let scale = (viewport.height() / 300).max(1);
+        let margin = (4 * scale) as i32;
+        let line_height = (text::GLYPH_HEIGHT * scale) as i32 + margin * 2;
+        let mut bottom = viewport.bottom() - line_height;
+        for (message, fade) in self.visible() {
+            let message = tr(message);
+            let (width, _) = text::text_size(&message, scale);
+            let panel = Rect::new(
+                viewport.center().x - width as i32 / 2 - margin,
+                bottom - line_height,
+                width + margin as u32 * 2,
+                line_height as u32,
+            );
+            canvas.set_draw_color(faded(colors.overlay.0, fade));
+            canvas.fill_rect(panel).unwrap();
+            let center = Point::new(panel.center().x, panel.center().y);
+            text::draw_text_centered(canvas, &message, center, scale, faded(colors.text.0, fade));
+            bottom -= line_height + margin;
+        }
+    }
+}
+
+fn faded(color: Color, fade: f32) -> Color {
+    Color::RGBA(color.r, color.g, color.b, (color.a as f32 * fade) as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn toasts_fade_out_and_queue_behind_visible_ones() {
+        let mut toasts = Toasts::default();
+        for i in 0..=MAX_VISIBLE {
+            toasts.push(format!("TOAST {}", i));
+        }
+        for _ in 0..TOAST_FRAMES - FADE_FRAMES / 2 {
+            toasts.update();
+        }
+        let visible = toasts.visible().collect::<Vec<_>>();
+        assert_eq!(visible.len(), MAX_VISIBLE);
+        assert_eq!(visible[0], ("TOAST 0", 0.5));
+
+        // 前面几条消失之后，排队的那条才开始计时
+        for _ in 0..FADE_FRAMES / 2 {
+            toasts.update();
+        }
+        assert_eq!(
+            toasts.visible().collect::<Vec<_>>(),
+            vec![(format!("TOAST {}", MAX_VISIBLE).as_str(), 1.0)]
+        );
+    }
+}
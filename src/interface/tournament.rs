@@ -0,0 +1,322 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::i18n::tr;
+use super::scores::NameEntry;
+use super::skin::SkinColors;
+use super::text;
+use crate::engine::mode::Mode;
+
+// 本地轮流上机的淘汰赛：每一局两个人先后各玩一盘 ultra，分数高的赢这一局，
+// 先赢够 BEST_OF 局的一半以上晋级
+pub const MODE: Mode = Mode::Ultra;
+const BEST_OF: u32 = 3;
+const MAX_PLAYERS: usize = 8;
+
+// 一场对阵；人数是单数的话最后一个人轮空，直接晋级
+#[derive(Clone, Debug)]
+struct Match {
+    players: [usize; 2],
+    bye: bool,
+    wins: [u32; 2],
+    // 这一局先上的人的分数，等后上的人打完再比
+    first_score: Option<u32>,
+}
+
+impl Match {
+    fn winner(&self, best_of: u32) -> Option<usize> {
+        if self.bye {
+            return Some(self.players[0]);
+        }
+        let needed = best_of / 2 + 1;
+        (0..2)
+            .find(|&side| self.wins[side] >= needed)
+            .map(|side| self.players[side])
+    }
+}
+
+pub struct Tournament {
+    names: Vec<String>,
+    // 每一轮的对阵，最后一轮是正在打的
+    rounds: Vec<Vec<Match>>,
+    best_of: u32,
+}
+
+impl Tournament {
+    fn new(names: Vec<String>, best_of: u32) -> Self {
+        let entrants = (0..names.len()).collect();
+        Self {
+            names,
+            rounds: vec![Self::pair(entrants)],
+            best_of,
+        }
+    }
+
+    // 按顺序两两配对
+    fn pair(entrants: Vec<usize>) -> Vec<Match> {
+        entrants
+            .chunks(2)
+            .map(|pair| Match {
+                players: [pair[0], *pair.get(1).unwrap_or(&pair[0])],
+                bye: pair.len() == 1,
+                wins: [0, 0],
+                first_score: None,
+            })
+            .collect()
+    }
+
+    fn current(&mut self) -> Option<&mut Match> {
+        let best_of = self.best_of;
+        self.rounds
+            .last_mut()?
+            .iter_mut()
+            .find(|game| game.winner(best_of).is_none())
+    }
+
+    // 下一盘轮到谁上，决出冠军之后是 None
+    pub fn next_player(&self) -> Option<&str> {
+        let game = self
+            .rounds
+            .last()?
+            .iter()
+            .find(|game| game.winner(self.best_of).is_none())?;
+        let side = game.first_score.map_or(0, |_| 1);
+        Some(&self.names[game.players[side]])
+    }
+
+    // 记下刚打完的这一盘；平局这一局重打。一轮打完了就排下一轮
+    pub fn record(&mut self, score: u32) {
+        let Some(game) = self.current() else {
+            return;
+        };
+        match game.first_score.take() {
+            None => game.first_score = Some(score),
+            Some(first) if first > score => game.wins[0] += 1,
+            Some(first) if first < score => game.wins[1] += 1,
+            Some(_) => {}
+        }
+        let best_of = self.best_of;
+        let round = self.rounds.last().expect("there is always a round");
+        let winners: Option<Vec<usize>> = round.iter().map(|game| game.winner(best_of)).collect();
+        if let Some(winners) = winners.filter(|winners| winners.len() > 1) {
+            self.rounds.push(Self::pair(winners));
+        }
+    }
+
+    pub fn champion(&self) -> Option<&str> {
+        match self.rounds.last()?.as_slice() {
+            [game] => game
+                .winner(self.best_of)
+                .map(|winner| self.names[winner].as_str()),
+            _ => None,
+        }
+    }
+
+    // 排名：赢的场数多的在前，一样的比赢的局数
+    fn standings(&self) -> Vec<(&str, u32, u32)> {
+        let mut standings: Vec<(&str, u32, u32)> = self
+            .names
+            .iter()
+            .map(|name| (name.as_str(), 0, 0))
+            .collect();
+        for game in self.rounds.iter().flatten().filter(|game| !game.bye) {
+            for side in 0..2 {
+                standings[game.players[side]].2 += game.wins[side];
+            }
+            if let Some(winner) = game.winner(self.best_of) {
+                standings[winner].1 += 1;
+            }
+        }
+        standings.sort_by_key(|&(_, matches, games)| std::cmp::Reverse((matches, games)));
+        standings
+    }
+
+    // 对阵表：每一轮一行标题，下面每场一行
+    fn bracket(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (round, games) in self.rounds.iter().enumerate() {
+            lines.push(format!("{} {}", tr("ROUND"), round + 1));
+            for game in games {
+                let [a, b] = game.players.map(|player| self.names[player].as_str());
+                lines.push(if game.bye {
+                    format!("  {} {}", a, tr("BYE"))
+                } else {
+                    format!("  {} {}-{} {}", a, game.wins[0], game.wins[1], b)
+                });
+            }
+        }
+        lines
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        let status = match (self.champion(), self.next_player()) {
+            (Some(champion), _) => format!("{} {}", tr("CHAMPION"), champion),
+            (None, Some(next)) => format!("{} {}", tr("NEXT"), next),
+            (None, None) => String::new(),
+        };
+        let bracket = self.bracket();
+        let standings = self.standings();
+        // 状态、空一行、对阵表、空一行、排名标题和排名
+        let rows = (bracket.len() + standings.len() + 4) as u32;
+        let line_height = area.height() / rows.max(1);
+        let scale = text::fit_scale("  WWWWWWWW 0-0 WWWWWWWW", area.width() / 2)
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+
+        let center_x = area.center().x;
+        let row_y = |row: usize| area.y() + (line_height * row as u32) as i32;
+        text::draw_text_centered(
+            canvas,
+            &status,
+            Point::new(center_x, row_y(0) + (line_height / 2) as i32),
+            scale,
+            colors.accent.0,
+        );
+        let left = area.x() + area.width() as i32 / 4;
+        for (row, line) in bracket.iter().enumerate() {
+            let position = Point::new(left, row_y(row + 2));
+            text::draw_text(canvas, line, position, scale, colors.text.0);
+        }
+        let top = bracket.len() + 3;
+        let title = tr("STANDINGS");
+        let position = Point::new(left, row_y(top));
+        text::draw_text(canvas, &title, position, scale, colors.text_dim.0);
+        for (row, (name, matches, games)) in standings.iter().enumerate() {
+            let line = format!("  {:<8} {} {}", name, matches, games);
+            let position = Point::new(left, row_y(top + row + 1));
+            text::draw_text(canvas, &line, position, scale, colors.text.0);
+        }
+    }
+}
+
+// 报名：一个一个输名字，名字空着按回车就开赛
+pub struct Registration {
+    names: Vec<String>,
+    pub entry: NameEntry,
+}
+
+impl Default for Registration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registration {
+    pub fn new() -> Self {
+        Self {
+            names: Vec::new(),
+            entry: NameEntry::new(""),
+        }
+    }
+
+    // 有名字就报上，重名和满员的不收；空着的话人够了就开赛
+    pub fn confirm(&mut self) -> Option<Tournament> {
+        let name = std::mem::take(&mut self.entry.name);
+        if name.is_empty() {
+            return (self.names.len() >= 2).then(|| Tournament::new(self.names.clone(), BEST_OF));
+        }
+        if self.names.len() < MAX_PLAYERS && !self.names.contains(&name) {
+            self.names.push(name);
+        }
+        None
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, colors: &SkinColors) {
+        let prompt = tr("ENTER PLAYER NAMES");
+        // 提示、已报名的、正在输的、空一行、开赛提示
+        let rows = (MAX_PLAYERS + 4) as u32;
+        let line_height = area.height() / rows;
+        let scale = text::fit_scale(&prompt, area.width())
+            .min((line_height * 2 / 3 / text::GLYPH_HEIGHT).max(1));
+        let center_x = area.center().x;
+        let row_y = |row: usize| area.y() + (line_height * row as u32 + line_height / 2) as i32;
+
+        text::draw_text_centered(
+            canvas,
+            &prompt,
+            Point::new(center_x, row_y(0)),
+            scale,
+            colors.accent.0,
+        );
+        for (row, name) in self.names.iter().enumerate() {
+            let line = format!("{} {:<8}", row + 1, name);
+            text::draw_text_centered(
+                canvas,
+                &line,
+                Point::new(center_x, row_y(row + 1)),
+                scale,
+                colors.text.0,
+            );
+        }
+        if self.names.len() < MAX_PLAYERS {
+            let line = format!("{} {:_<8}", self.names.len() + 1, self.entry.name);
+            text::draw_text_centered(
+                canvas,
+                &line,
+                Point::new(center_x, row_y(self.names.len() + 1)),
+                scale,
+                colors.text.0,
+            );
+        }
+        if self.names.len() >= 2 {
+            text::draw_text_centered(
+                canvas,
+                &tr("EMPTY NAME TO START"),
+                Point::new(center_x, row_y(MAX_PLAYERS + 3)),
+                scale,
+                colors.text_dim.0,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn register(names: &[&str]) -> Tournament {
+        let mut registration = Registration::new();
+        for name in names {
+            registration.entry.name = name.to_string();
+            assert!(registration.confirm().is_none());
+        }
+        registration.confirm().unwrap()
+    }
+
+    #[test]
+    fn brackets_run_best_of_matches_to_a_champion() {
+        let mut registration = Registration::new();
+        registration.entry.name = "ANN".to_string();
+        assert!(registration.confirm().is_none());
+        // 一个人开不了赛
+        assert!(registration.confirm().is_none());
+
+        // 三个人：ANN 对 BOB，CAT 轮空
+        let mut tournament = register(&["ANN", "BOB", "CAT"]);
+        assert_eq!(tournament.next_player(), Some("ANN"));
+        tournament.record(100);
+        assert_eq!(tournament.next_player(), Some("BOB"));
+        tournament.record(200);
+        // 平局重打
+        tournament.record(50);
+        tournament.record(50);
+        tournament.record(300);
+        tournament.record(100);
+        assert_eq!(tournament.next_player(), Some("ANN"));
+        tournament.record(10);
+        tournament.record(20);
+        assert_eq!(tournament.rounds.len(), 2);
+
+        // 决赛 BOB 对 CAT，CAT 连赢两局
+        assert_eq!(tournament.next_player(), Some("BOB"));
+        for _ in 0..2 {
+            tournament.record(100);
+            tournament.record(500);
+        }
+        assert_eq!(tournament.champion(), Some("CAT"));
+        assert_eq!(tournament.next_player(), None);
+        let standings = tournament.standings();
+        assert_eq!(standings[2], ("ANN", 0, 1));
+        assert_eq!(tournament.bracket()[2], "  CAT BYE");
+    }
+}
@@ -0,0 +1,219 @@
+use sdl2::keyboard::Keycode;
+
+use super::input::AutoShift;
+use super::keybinds::Keybinds;
+use super::settings::Settings;
+use crate::engine::battle;
+use crate::engine::mode::Mode;
+use crate::engine::{Engine, MoveKind};
+
+// 本地双人对战：两块棋盘分屏一起走，各用一套按键，先顶满的输。
+// 消行的攻击和电脑对打一样，先抵消自己排着的垃圾再送给对面（见 `battle::exchange`）。
+// 连着打的话记着两边各赢了几局
+pub const MODE: Mode = Mode::Marathon;
+
+// 一个玩家的按键
+#[derive(Clone, Copy)]
+pub struct Bindings {
+    left: Keycode,
+    right: Keycode,
+    rotate: Keycode,
+    soft_drop: Keycode,
+    hard_drop: Keycode,
+    hold: Keycode,
+    // 配置里另外绑了第二个 hold 键的话也算
+    alt_hold: Option<Keycode>,
+}
+
+// 左边的玩家用 WASD、空格和左 Shift，右边的用方向键、回车和右 Shift。
+// 配置里改绑过的单人键归左边的玩家，没改的动作、和右边玩家撞了的键还是用这里的
+pub const BINDINGS: [Bindings; 2] = [
+    Bindings {
+        left: Keycode::A,
+        right: Keycode::D,
+        rotate: Keycode::W,
+        soft_drop: Keycode::S,
+        hard_drop: Keycode::Space,
+        hold: Keycode::LShift,
+        alt_hold: None,
+    },
+    Bindings {
+        left: Keycode::Left,
+        right: Keycode::Right,
+        rotate: Keycode::Up,
+        soft_drop: Keycode::Down,
+        hard_drop: Keycode::Return,
+        hold: Keycode::RShift,
+        alt_hold: None,
+    },
+];
+
+impl Bindings {
+    // 左边玩家的键：[keybinds] 里改过的动作用改过的键，改成右边玩家的键的不算
+    fn player_one(keybinds: &Keybinds) -> Bindings {
+        let defaults = Keybinds::default();
+        let taken = BINDINGS[1].keys();
+        let rebound = |bound: Keycode, default: Keycode| {
+            (bound != default && !taken.contains(&bound)).then_some(bound)
+        };
+        let pick = |bound: Keycode, default: Keycode, fixed: Keycode| {
+            rebound(bound, default).unwrap_or(fixed)
+        };
+        let fixed = BINDINGS[0];
+        Bindings {
+            left: pick(keybinds.left, defaults.left, fixed.left),
+            right: pick(keybinds.right, defaults.right, fixed.right),
+            rotate: pick(keybinds.rotate, defaults.rotate, fixed.rotate),
+            soft_drop: pick(keybinds.soft_drop, defaults.soft_drop, fixed.soft_drop),
+            hard_drop: pick(keybinds.hard_drop, defaults.hard_drop, fixed.hard_drop),
+            hold: pick(keybinds.hold, defaults.hold, fixed.hold),
+            alt_hold: rebound(keybinds.alt_hold, defaults.alt_hold),
+        }
+    }
+
+    fn keys(&self) -> [Keycode; 6] {
+        [
+            self.left,
+            self.right,
+            self.rotate,
+            self.soft_drop,
+            self.hard_drop,
+            self.hold,
+        ]
+    }
+}
+
+pub struct Versus {
+    bindings: [Bindings; 2],
+    // 每个玩家自己按住的方向和软降
+    auto_shift: [AutoShift; 2],
+    // 这一局两边已经打出去了多少攻击
+    sent: [u32; 2],
+    pub wins: [u32; 2],
+}
+
+impl Default for Versus {
+    fn default() -> Self {
+        Self {
+            bindings: BINDINGS,
+            auto_shift: Default::default(),
+            sent: [0, 0],
+            wins: [0, 0],
+        }
+    }
+}
+
+impl Versus {
+    // 是谁的键就交给谁的引擎；不是对战用的键返回 false
+    pub fn press(&mut self, key: Keycode, engines: [&mut Engine; 2]) -> bool {
+        for ((bindings, auto_shift), engine) in
+            self.bindings.iter().zip(&mut self.auto_shift).zip(engines)
+        {
+            match key {
+                _ if key == bindings.left => {
+                    let _ = engine.move_cursor(MoveKind::Left);
+                    auto_shift.press(MoveKind::Left);
+                }
+                _ if key == bindings.right => {
+                    let _ = engine.move_cursor(MoveKind::Right);
+                    auto_shift.press(MoveKind::Right);
+                }
+                _ if key == bindings.rotate => engine.rotate_clockwise(),
+                _ if key == bindings.soft_drop => {
+                    engine.soft_drop();
+                    auto_shift.set_soft_drop(true);
+                }
+                _ if key == bindings.hard_drop => engine.hard_drop(),
+                _ if key == bindings.hold || Some(key) == bindings.alt_hold => engine.hold(),
+                _ => continue,
+            }
+            return true;
+        }
+        false
+    }
+
+    pub fn release(&mut self, key: Keycode) {
+        for (bindings, auto_shift) in self.bindings.iter().zip(&mut self.auto_shift) {
+            match key {
+                _ if key == bindings.left => auto_shift.release(MoveKind::Left),
+                _ if key == bindings.right => auto_shift.release(MoveKind::Right),
+                _ if key == bindings.soft_drop => auto_shift.set_soft_drop(false),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self, engines: [&mut Engine; 2], settings: &Settings) {
+        for (auto_shift, engine) in self.auto_shift.iter_mut().zip(engines) {
+            auto_shift.update(engine, settings);
+        }
+    }
+
+    // 两块棋盘都走完这一帧之后调，交换这一帧的攻击
+    pub fn exchange(&mut self, engines: [&mut Engine; 2]) {
+        battle::exchange(engines, &mut self.sent);
+    }
+
+    // 一局开始的时候松开所有按着的键，攻击从零算；左边玩家的键按当前配置重新取
+    pub fn new_game(&mut self, keybinds: &Keybinds) {
+        self.bindings[0] = Bindings::player_one(keybinds);
+        self.auto_shift = Default::default();
+        self.sent = [0, 0];
+    }
+
+    // 记下这一局谁赢了，平局是 None
+    pub fn record(&mut self, winner: Option<usize>) {
+        if let Some(winner) = winner {
+            self.wins[winner] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn each_player_drives_their_own_engine() {
+        let mut versus = Versus::default();
+        let mut left = Engine::with_seed(MODE, 3);
+        let mut right = Engine::with_seed(MODE, 3);
+        left.spawn_first();
+        right.spawn_first();
+
+        assert!(versus.press(Keycode::Space, [&mut left, &mut right]));
+        assert_eq!(left.stats().pieces, 1);
+        assert_eq!(right.stats().pieces, 0);
+        assert!(versus.press(Keycode::Return, [&mut left, &mut right]));
+        assert_eq!(right.stats().pieces, 1);
+        assert!(!versus.press(Keycode::P, [&mut left, &mut right]));
+
+        // 改绑的键归左边的玩家，没改的动作照旧
+        versus.new_game(&Keybinds {
+            hard_drop: Keycode::J,
+            ..Keybinds::default()
+        });
+        assert!(versus.press(Keycode::J, [&mut left, &mut right]));
+        assert_eq!(left.stats().pieces, 2);
+        assert!(!versus.press(Keycode::Space, [&mut left, &mut right]));
+        assert!(versus.press(Keycode::A, [&mut left, &mut right]));
+
+        // 第二个 hold 键也归左边；改成右边玩家的键的动作还用原来的键
+        versus.new_game(&Keybinds {
+            hard_drop: Keycode::Return,
+            alt_hold: Keycode::C,
+            ..Keybinds::default()
+        });
+        assert!(versus.press(Keycode::C, [&mut left, &mut right]));
+        assert_eq!(left.stats().holds, 1);
+        assert!(versus.press(Keycode::Return, [&mut left, &mut right]));
+        assert_eq!(left.stats().pieces, 2);
+        assert_eq!(right.stats().pieces, 2);
+        assert!(versus.press(Keycode::Space, [&mut left, &mut right]));
+        assert_eq!(left.stats().pieces, 3);
+
+        versus.record(Some(1));
+        versus.record(None);
+        assert_eq!(versus.wins, [0, 1]);
+    }
+}
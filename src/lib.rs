@@ -0,0 +1,5 @@
+// 引擎不依赖任何前端，桌面版和网页版都用它
+pub mod engine;
+pub mod net;
+#[cfg(feature = "web")]
+pub mod web;
@@ -1,17 +1,44 @@
 #![allow(dead_code)]
-use engine::{Engine, Matrix, Color, piece::Kind as PieceKind};
-use interface::Interface;
+// 前端里都是按 `crate::engine` 引用的；只编网页版的时候没有本地前端用它
+#[allow(unused_imports)]
+use tetris_rs::{engine, net};
 
-mod engine;
+mod export;
+mod frontend;
+#[cfg(feature = "sdl")]
 mod interface;
+mod replays;
+mod simulate;
+#[cfg(feature = "terminal")]
+mod terminal;
 
 fn main() {
-    // let engine = Engine::new();
-    let mut matrix = Matrix::blank();
-
-    matrix[(1,1).into()] = Some(Color::Green);
-    let mut engine = Engine::with_matrix(matrix);
-    engine.debug_test_cursor(PieceKind::T, (5,5).into());
-
-    Interface::run(engine);
+    let args: Vec<String> = std::env::args().collect();
+    // 对打模拟不需要前端，命令行跑完就退出
+    if let Some(options) = simulate::parse(&args) {
+        match options {
+            Ok(options) => simulate::run(&options),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // 录像导出成表格也一样，重放一遍写完文件就退出
+    if let Some(path) = export::parse(&args) {
+        match export::run(std::path::Path::new(path)) {
+            Ok(path) => println!("Exported {}", path.display()),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let result = frontend::from_args(&args).and_then(|mut frontend| frontend.run());
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 }
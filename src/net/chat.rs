@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::player_name;
+
+// 大厅里和一局前后的聊天。消息是一行字，只留像素字体里画得出来的字符
+pub const MAX_CHAT_LEN: usize = 40;
+// 只留最近几句，画得下就行
+const HISTORY: usize = 6;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+}
+
+impl ChatLine {
+    pub fn new(name: &str, text: &str) -> Self {
+        Self {
+            name: player_name(name),
+            text: clean(text),
+        }
+    }
+}
+
+// 大写，去掉字体里没有的字符，截到最长
+pub fn clean(text: &str) -> String {
+    let text: String = text
+        .to_ascii_uppercase()
+        .chars()
+        .filter(|&c| c.is_ascii_alphanumeric() || " .:/".contains(c))
+        .take(MAX_CHAT_LEN)
+        .collect();
+    text.trim().to_string()
+}
+
+#[derive(Default)]
+pub struct ChatLog {
+    lines: VecDeque<ChatLine>,
+}
+
+impl ChatLog {
+    // 收到的也再洗一遍，对面不一定是我们的客户端
+    pub fn push(&mut self, line: ChatLine) {
+        let line = ChatLine::new(&line.name, &line.text);
+        if line.text.is_empty() {
+            return;
+        }
+        if self.lines.len() == HISTORY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    // 从旧到新
+    pub fn lines(&self) -> impl Iterator<Item = &ChatLine> {
+        self.lines.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chat_keeps_the_latest_printable_lines() {
+        let line = ChatLine::new("ann", "  gg, wp! rematch? ");
+        assert_eq!(line.name, "ANN");
+        assert_eq!(line.text, "GG WP REMATCH");
+        assert_eq!(clean(&"A".repeat(100)).len(), MAX_CHAT_LEN);
+
+        let mut log = ChatLog::default();
+        log.push(ChatLine::new("ann", "!!!"));
+        assert!(log.is_empty());
+        for i in 0..HISTORY + 2 {
+            log.push(ChatLine::new("ann", &i.to_string()));
+        }
+        let texts: Vec<&str> = log.lines().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["2", "3", "4", "5", "6", "7"]);
+    }
+}
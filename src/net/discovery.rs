@@ -0,0 +1,149 @@
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+use super::player_name;
+
+// 局域网里找房：开房的每秒往广播地址喊一声自己的名字和端口，
+// 找房的在固定端口听，听到的就列出来，一会儿没声了就当房间没了
+pub const DISCOVERY_PORT: u16 = 7877;
+const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+const EXPIRY: Duration = Duration::from_secs(3);
+// 不是我们发的包不认
+const MAGIC: &str = "TETRIS-RS";
+
+// 局域网里听到的一个房间
+#[derive(Clone, PartialEq, Debug)]
+pub struct LocalGame {
+    pub name: String,
+    pub address: SocketAddr,
+    seen: Instant,
+}
+
+pub struct Beacon {
+    socket: UdpSocket,
+    target: SocketAddr,
+    message: String,
+    last_sent: Option<Instant>,
+}
+
+impl Beacon {
+    pub fn new(name: &str, port: u16) -> io::Result<Self> {
+        let target = SocketAddrV4::new(Ipv4Addr::BROADCAST, DISCOVERY_PORT);
+        Self::with_target(name, port, target.into())
+    }
+
+    fn with_target(name: &str, port: u16, target: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            target,
+            message: format!("{} {} {}", MAGIC, player_name(name), port),
+            last_sent: None,
+        })
+    }
+
+    // 每帧调，到时间了就喊一声；发不出去也不要紧，下一秒再喊
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        if matches!(self.last_sent, Some(last) if now - last < BEACON_INTERVAL) {
+            return;
+        }
+        self.last_sent = Some(now);
+        let _ = self.socket.send_to(self.message.as_bytes(), self.target);
+    }
+}
+
+pub struct Scanner {
+    socket: UdpSocket,
+    games: Vec<LocalGame>,
+}
+
+impl Scanner {
+    pub fn bind() -> io::Result<Self> {
+        Self::bind_port(DISCOVERY_PORT)
+    }
+
+    fn bind_port(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            games: Vec::new(),
+        })
+    }
+
+    pub fn games(&self) -> &[LocalGame] {
+        &self.games
+    }
+
+    // 每帧调一次，列表变了（来了新房间或者有房间没声了）返回 true
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let now = Instant::now();
+        let mut changed = false;
+        let mut packet = [0; 256];
+        loop {
+            let (read, sender) = match self.socket.recv_from(&mut packet) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            };
+            let text = String::from_utf8_lossy(&packet[..read]);
+            let Some((name, port)) = parse(&text) else {
+                continue;
+            };
+            let address = SocketAddr::new(sender.ip(), port);
+            match self.games.iter_mut().find(|game| game.address == address) {
+                Some(game) => {
+                    changed |= game.name != name;
+                    game.name = name;
+                    game.seen = now;
+                }
+                None => {
+                    self.games.push(LocalGame {
+                        name,
+                        address,
+                        seen: now,
+                    });
+                    changed = true;
+                }
+            }
+        }
+        let count = self.games.len();
+        self.games.retain(|game| now - game.seen < EXPIRY);
+        Ok(changed || self.games.len() != count)
+    }
+}
+
+fn parse(text: &str) -> Option<(String, u16)> {
+    match text.split(' ').collect::<Vec<_>>()[..] {
+        [MAGIC, name, port] => Some((name.to_string(), port.parse().ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hosted_games_are_heard_on_the_local_network() {
+        let mut scanner = Scanner::bind_port(0).unwrap();
+        let port = scanner.socket.local_addr().unwrap().port();
+        let target = SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
+        let mut beacon = Beacon::with_target("ann", 7878, target.into()).unwrap();
+        beacon.update();
+        // 一秒之内不会再喊
+        beacon.update();
+        scanner.socket.send_to(b"HELLO 1", target).unwrap();
+        while !scanner.poll().unwrap() {}
+        assert_eq!(scanner.games().len(), 1);
+        let game = &scanner.games()[0];
+        assert_eq!(game.name, "ANN");
+        assert_eq!(game.address.port(), 7878);
+        assert!(game.address.ip().is_loopback());
+        assert!(!scanner.poll().unwrap());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// 联机时每秒 ping 一下对面（连的是大厅服务器就 ping 服务器），记最近几次的往返时间。
+// 延迟是这几次的平均，抖动是相邻两次差多少的平均
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const SAMPLES: usize = 8;
+
+#[derive(Default)]
+pub struct Latency {
+    next_id: u32,
+    // 同时只等一个，下一次 ping 的时候还没回来就当丢了
+    waiting: Option<(u32, Instant)>,
+    last_ping: Option<Instant>,
+    samples: VecDeque<Duration>,
+}
+
+impl Latency {
+    // 到时间了返回这次 ping 的编号
+    pub fn ping(&mut self, now: Instant) -> Option<u32> {
+        if self
+            .last_ping
+            .is_some_and(|last| now - last < PING_INTERVAL)
+        {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.last_ping = Some(now);
+        self.waiting = Some((id, now));
+        Some(id)
+    }
+
+    // 对面回了；不是在等的那个就不要了
+    pub fn pong(&mut self, id: u32, now: Instant) {
+        let Some((waiting, sent)) = self.waiting else {
+            return;
+        };
+        if waiting != id {
+            return;
+        }
+        self.waiting = None;
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(now - sent);
+    }
+
+    pub fn ping_time(&self) -> Option<Duration> {
+        let count = self.samples.len() as u32;
+        (count > 0).then(|| self.samples.iter().sum::<Duration>() / count)
+    }
+
+    pub fn jitter(&self) -> Option<Duration> {
+        let count = self
+            .samples
+            .len()
+            .checked_sub(1)
+            .filter(|&count| count > 0)?;
+        let total: Duration = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(&a, &b)| a.abs_diff(b))
+            .sum();
+        Some(total / count as u32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pings_measure_round_trips_and_jitter() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut latency = Latency::default();
+        assert_eq!(latency.ping_time(), None);
+        let first = latency.ping(start).unwrap();
+        // 一秒之内不再 ping
+        assert_eq!(latency.ping(start + ms(500)), None);
+        latency.pong(first, start + ms(40));
+        assert_eq!(latency.ping_time(), Some(ms(40)));
+        assert_eq!(latency.jitter(), None);
+
+        // 回晚了的、重复的不算
+        let second = latency.ping(start + ms(1000)).unwrap();
+        latency.pong(first, start + ms(1010));
+        latency.pong(second, start + ms(1060));
+        latency.pong(second, start + ms(1090));
+        assert_eq!(latency.ping_time(), Some(ms(50)));
+        assert_eq!(latency.jitter(), Some(ms(20)));
+    }
+}
@@ -0,0 +1,702 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::engine::mode::Mode;
+use crate::engine::replay::{self, Action};
+use crate::engine::Engine;
+use chat::{ChatLine, ChatLog};
+use discovery::Beacon;
+use latency::Latency;
+use protocol::{Config, Frame, Lobby, Message};
+
+// 联机对战：两边用同一个种子各玩各的，每走一帧就把这一帧的操作和打出去的攻击发给对面。
+// 对面拿同一个种子的引擎照着重放，在本地画出你的棋盘；攻击排进对面自己的垃圾队列。
+// 开房的一方定种子和模式，消息的格式见 `protocol`。也可以都连到大厅服务器（`server`），
+// 由它配对、转发，再照着帧重放两边核对（`referee`）。局域网里开的房会广播出去（`discovery`），
+// 打的时候量着延迟（`latency`）
+pub mod chat;
+pub mod discovery;
+pub mod latency;
+pub mod protocol;
+pub mod referee;
+pub mod server;
+
+pub const MODE: Mode = Mode::Marathon;
+pub const DEFAULT_PORT: u16 = 7878;
+pub const SERVER_PORT: u16 = 7879;
+pub const UNSUPPORTED_MODE: &str = "UNSUPPORTED MODE";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// 对局中在大厅服务器上断了线，这么久之内重连上来接着打，过了就算走了
+pub const GRACE: Duration = Duration::from_secs(20);
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+// 名字只留字母数字，画得出来
+const MAX_NAME_LEN: usize = 12;
+
+// 名字只留字母数字，大写，没有就叫 PLAYER
+pub fn player_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(MAX_NAME_LEN)
+        .collect();
+    if name.is_empty() {
+        "PLAYER".to_string()
+    } else {
+        name.to_ascii_uppercase()
+    }
+}
+
+// 不阻塞的 TCP 连接，每帧把收到的完整消息取出来
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    // 对面关了连接；关之前发来的还没取完
+    closed: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            closed: false,
+        })
+    }
+
+    // 地址不写端口就用默认的
+    pub fn connect(address: &str, default_port: u16) -> io::Result<Self> {
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{}:{}", address, default_port)
+        };
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no address to connect to"))?;
+        Self::new(TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?)
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let bytes = message.encode();
+        let mut bytes = &bytes[..];
+        // 发送缓冲满了就等一下，一帧的消息很短，一般不会等
+        while !bytes.is_empty() {
+            match self.stream.write(bytes) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => bytes = &bytes[written..],
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(1))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    // 不再发了，对面把已经发过去的取完就会读到连接关了；这边还能接着收
+    fn finish(&self) {
+        let _ = self.stream.shutdown(Shutdown::Write);
+    }
+
+    // 到现在为止收到的完整消息；对面关了连接而且都取完了是错误。
+    // 对面的协议版本不一样的话回一句 `Refused` 就不收了
+    pub fn receive(&mut self) -> io::Result<Vec<Message>> {
+        if self.closed && self.buffer.is_empty() {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let mut chunk = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let mut messages = Vec::new();
+        loop {
+            match Message::decode(&self.buffer) {
+                Ok(Some((message, used))) => {
+                    self.buffer.drain(..used);
+                    messages.push(message);
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    if err.kind() == ErrorKind::Unsupported {
+                        let reason = protocol::INCOMPATIBLE.to_string();
+                        let _ = self.send(&Message::Refused { reason });
+                    }
+                    self.closed = true;
+                    self.buffer.clear();
+                    return Err(err);
+                }
+            }
+        }
+        if self.closed {
+            // 最后半条不要了
+            self.buffer.clear();
+        }
+        Ok(messages)
+    }
+}
+
+// 关之前把对面发来没读的先读掉，不然会直接发 RST，对面还没读的最后几帧就丢了
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.finish();
+        let mut chunk = [0; 4096];
+        while matches!(self.stream.read(&mut chunk), Ok(read) if read > 0) {}
+    }
+}
+
+// 连着大厅服务器还没进房间：看有哪些房间，开一个、进一个，或者让服务器配一个。
+// 进了房间就和直连加入一样，等服务器发种子
+pub struct LobbyClient {
+    address: String,
+    connection: Connection,
+    lobbies: Vec<Lobby>,
+    chat: ChatLog,
+}
+
+impl LobbyClient {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let mut connection = Connection::connect(address, SERVER_PORT)?;
+        connection.send(&Message::List)?;
+        Ok(Self {
+            address: address.to_string(),
+            connection,
+            lobbies: Vec::new(),
+            chat: ChatLog::default(),
+        })
+    }
+
+    pub fn lobbies(&self) -> &[Lobby] {
+        &self.lobbies
+    }
+
+    pub fn chat(&self) -> &ChatLog {
+        &self.chat
+    }
+
+    // 自己说的服务器不会发回来，直接记上
+    pub fn say(&mut self, name: &str, text: &str) -> io::Result<()> {
+        let line = ChatLine::new(name, text);
+        self.chat.push(line.clone());
+        self.connection.send(&Message::Chat(line))
+    }
+
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.connection.send(&Message::List)
+    }
+
+    // 每帧调一次，房间列表更新了返回 true；聊天记进 `chat`
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let mut updated = false;
+        for message in self.connection.receive()? {
+            match message {
+                Message::Lobbies(lobbies) => {
+                    self.lobbies = lobbies;
+                    updated = true;
+                }
+                Message::Chat(line) => self.chat.push(line),
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "expected lobbies")),
+            }
+        }
+        Ok(updated)
+    }
+
+    pub fn create(self, name: &str) -> io::Result<Pending> {
+        let name = player_name(name);
+        self.enter(Message::Create { name })
+    }
+
+    pub fn join(self, id: u32, name: &str) -> io::Result<Pending> {
+        let name = player_name(name);
+        self.enter(Message::Join { id, name })
+    }
+
+    pub fn quick_match(self, name: &str) -> io::Result<Pending> {
+        let name = player_name(name);
+        self.enter(Message::Match { name })
+    }
+
+    fn enter(mut self, message: Message) -> io::Result<Pending> {
+        self.connection.send(&message)?;
+        Ok(Pending {
+            listener: None,
+            beacon: None,
+            connection: Some(self.connection),
+            server: Some(self.address),
+            lobby: None,
+        })
+    }
+}
+
+// 还没开始的一局：开房的等人连进来，加入的等房主（或者大厅服务器）发种子
+pub struct Pending {
+    listener: Option<TcpListener>,
+    // 开房的一边等一边在局域网里广播
+    beacon: Option<Beacon>,
+    connection: Option<Connection>,
+    // 连的大厅服务器，进了哪个房间，座位的凭证
+    server: Option<String>,
+    lobby: Option<(u32, u64)>,
+}
+
+impl Pending {
+    // 广播不出去（比如没有网卡）也照样开房，只是局域网里找不到，得输地址
+    pub fn host(port: u16, name: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        Ok(Self {
+            listener: Some(listener),
+            beacon: Beacon::new(name, port).ok(),
+            connection: None,
+            server: None,
+            lobby: None,
+        })
+    }
+
+    pub fn join(address: &str) -> io::Result<Self> {
+        Ok(Self {
+            listener: None,
+            beacon: None,
+            connection: Some(Connection::connect(address, DEFAULT_PORT)?),
+            server: None,
+            lobby: None,
+        })
+    }
+
+    // 开房的话在哪个端口等
+    pub fn port(&self) -> Option<u16> {
+        let address = self.listener.as_ref()?.local_addr().ok()?;
+        Some(address.port())
+    }
+
+    pub fn lobby(&self) -> Option<u32> {
+        self.lobby.map(|(id, _)| id)
+    }
+
+    // 每帧调一次，连上了就开始
+    pub fn poll(&mut self) -> io::Result<Option<Session>> {
+        if let Some(listener) = &self.listener {
+            if let Some(beacon) = &mut self.beacon {
+                beacon.update();
+            }
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(err) => return Err(err),
+            };
+            self.listener = None;
+            self.beacon = None;
+            let mut connection = Connection::new(stream)?;
+            let seed = rand::random();
+            connection.send(&Message::Start(Config::new(seed, MODE)))?;
+            return Ok(Some(Session::new(connection, MODE, seed, VecDeque::new())));
+        }
+        let Some(connection) = &mut self.connection else {
+            return Ok(None);
+        };
+        let mut messages = connection.receive()?.into_iter();
+        // 大厅服务器先回进了哪个房间，人齐了才发开局的设置；服务器不让进的原因当错误交出去
+        let config = loop {
+            match messages.next() {
+                None => return Ok(None),
+                Some(Message::Start(config)) => break config,
+                Some(Message::Joined { id, token }) => self.lobby = Some((id, token)),
+                // 等人的时候没有地方显示聊天，不要了
+                Some(Message::Lobbies(_) | Message::Chat(_)) => {}
+                Some(Message::Refused { reason }) => {
+                    return Err(io::Error::new(ErrorKind::ConnectionRefused, reason))
+                }
+                Some(_) => return Err(io::Error::new(ErrorKind::InvalidData, "expected a start")),
+            }
+        };
+        let Some(mode) = config.mode() else {
+            let reason = UNSUPPORTED_MODE.to_string();
+            let _ = connection.send(&Message::Refused { reason });
+            return Err(io::Error::new(ErrorKind::Unsupported, UNSUPPORTED_MODE));
+        };
+        // 房主可能已经开始发帧了，留着之后重放
+        let inbox: VecDeque<Frame> = messages
+            .filter_map(|message| match message {
+                Message::Frame(frame) => Some(frame),
+                _ => None,
+            })
+            .collect();
+        let connection = self.connection.take().expect("checked above");
+        let mut session = Session::new(connection, mode, config.seed, inbox);
+        if let (Some(address), Some((room, token))) = (self.server.take(), self.lobby) {
+            session.rejoin = Some(Rejoin {
+                address,
+                room,
+                token,
+                lost: None,
+                last_try: None,
+                resuming: false,
+            });
+        }
+        Ok(Some(session))
+    }
+}
+
+// 连的是大厅服务器的话，断了线还能回去
+struct Rejoin {
+    address: String,
+    room: u32,
+    token: u64,
+    // 什么时候断的，上一次试着重连是什么时候
+    lost: Option<Instant>,
+    last_try: Option<Instant>,
+    // 重连上了，在等服务器说它收到了自己的几帧
+    resuming: bool,
+}
+
+// 正在打的一局。0 是自己，1 是对手
+pub struct Session {
+    connection: Connection,
+    mode: Mode,
+    seed: u64,
+    rejoin: Option<Rejoin>,
+    // 能重连的话留着自己发过的帧，服务器没收到的重连之后补发
+    history: Vec<Frame>,
+    // 收到了还没交出去重放的对手的帧，一共收到了几帧
+    inbox: VecDeque<Frame>,
+    received: u64,
+    // 对手打过来的攻击，下一帧排进自己的垃圾队列
+    incoming: Vec<u32>,
+    // 两边各走了几帧，一共打出去多少攻击（抵消之前的），第几帧顶满的
+    frames: [u64; 2],
+    sent: [u32; 2],
+    topped_out: [Option<u64>; 2],
+    chat: ChatLog,
+    latency: Latency,
+}
+
+impl Session {
+    fn new(connection: Connection, mode: Mode, seed: u64, inbox: VecDeque<Frame>) -> Self {
+        Self {
+            connection,
+            mode,
+            seed,
+            rejoin: None,
+            history: Vec::new(),
+            received: inbox.len() as u64,
+            inbox,
+            incoming: Vec::new(),
+            frames: [0, 0],
+            sent: [0, 0],
+            topped_out: [None, None],
+            chat: ChatLog::default(),
+            latency: Latency::default(),
+        }
+    }
+
+    pub fn chat(&self) -> &ChatLog {
+        &self.chat
+    }
+
+    pub fn say(&mut self, name: &str, text: &str) -> io::Result<()> {
+        let line = ChatLine::new(name, text);
+        self.chat.push(line.clone());
+        self.connection.send(&Message::Chat(line))
+    }
+
+    pub fn latency(&self) -> &Latency {
+        &self.latency
+    }
+
+    // 两边的引擎都用这个模式和种子
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 自己这一帧走完之后调：新打出的攻击先抵消排着的垃圾，再收下对手打过来的，
+    // 连同这一帧的操作一起发给对手
+    pub fn send_frame(&mut self, engine: &mut Engine, actions: Vec<Action>) -> io::Result<()> {
+        let attack = self.counter(0, engine);
+        let garbage = std::mem::take(&mut self.incoming);
+        for &lines in &garbage {
+            engine.queue_garbage(lines);
+        }
+        self.advance(0, engine);
+        let frame = Frame {
+            actions,
+            garbage,
+            attack,
+            hash: replay::state_hash(engine),
+        };
+        if self.rejoin.is_some() {
+            self.history.push(frame.clone());
+        }
+        if self.is_reconnecting() {
+            return Ok(());
+        }
+        match self.connection.send(&Message::Frame(frame)) {
+            Err(_) if self.can_rejoin() => {
+                self.lose();
+                Ok(())
+            }
+            sent => sent,
+        }
+    }
+
+    // 收到的对手的帧，按顺序重放；对面断开了而且都取完了是错误。
+    // 连的是大厅服务器的话自己断了先重连，宽限期过了还没连上才是错误
+    pub fn receive(&mut self) -> io::Result<Vec<Frame>> {
+        let mut frames: Vec<Frame> = self.inbox.drain(..).collect();
+        if self
+            .rejoin
+            .as_ref()
+            .is_some_and(|rejoin| rejoin.lost.is_some())
+        {
+            self.reconnect()?;
+            return Ok(frames);
+        }
+        // ping 发不出去的话下面收的时候就知道断了
+        if let Some(id) = self.latency.ping(Instant::now()) {
+            let _ = self.connection.send(&Message::Ping { id });
+        }
+        let messages = match self.connection.receive() {
+            Ok(messages) => messages,
+            Err(err) if self.can_rejoin() && err.kind() != ErrorKind::Unsupported => {
+                self.lose();
+                return Ok(frames);
+            }
+            Err(err) if frames.is_empty() => return Err(err),
+            Err(_) => Vec::new(),
+        };
+        for message in messages {
+            match message {
+                Message::Frame(frame) => {
+                    self.received += 1;
+                    frames.push(frame);
+                }
+                Message::Resumed { received } => self.resend(received),
+                Message::Ping { id } => {
+                    let _ = self.connection.send(&Message::Pong { id });
+                }
+                Message::Pong { id } => self.latency.pong(id, Instant::now()),
+                Message::Result { .. } => {}
+                Message::Chat(line) => self.chat.push(line),
+                Message::Refused { reason } => {
+                    return Err(io::Error::new(ErrorKind::ConnectionRefused, reason))
+                }
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "expected a frame")),
+            }
+        }
+        Ok(frames)
+    }
+
+    // 对手的一帧在本地重放完（`Frame::apply`，再走一帧）之后调：
+    // 和对面一样抵消、收垃圾，它打出来的攻击留着下一帧给自己
+    pub fn replayed(&mut self, frame: &Frame, engine: &mut Engine) {
+        self.counter(1, engine);
+        for &lines in &frame.garbage {
+            engine.queue_garbage(lines);
+        }
+        if frame.attack > 0 {
+            self.incoming.push(frame.attack);
+        }
+        self.advance(1, engine);
+    }
+
+    // 先顶满的输。一边在第 n 帧顶满了，另一边也走到第 n 帧才算分出来，两边算的一样
+    pub fn is_decided(&self) -> bool {
+        (0..2).any(|side| self.decided_at(side).is_some())
+    }
+
+    // 分出来了谁赢，同一帧一起顶满的是平局
+    pub fn winner(&self) -> Option<usize> {
+        match [0, 1].map(|side| self.decided_at(side)) {
+            [Some(own), Some(other)] if own == other => None,
+            [Some(own), Some(other)] => Some(if own < other { 1 } else { 0 }),
+            [Some(_), None] => Some(1),
+            [None, Some(_)] => Some(0),
+            [None, None] => None,
+        }
+    }
+
+    // 分出来之后告诉大厅服务器谁赢了，服务器对两边报的结果
+    pub fn report(&mut self) -> io::Result<()> {
+        let winner = self.winner();
+        self.connection.send(&Message::Result { winner })
+    }
+
+    // 断了线在等重连，这期间的帧先留着
+    pub fn is_reconnecting(&self) -> bool {
+        self.rejoin
+            .as_ref()
+            .is_some_and(|rejoin| rejoin.lost.is_some() || rejoin.resuming)
+    }
+
+    // 分出来之后就不用回去了
+    fn can_rejoin(&self) -> bool {
+        self.rejoin.is_some() && !self.is_decided()
+    }
+
+    fn lose(&mut self) {
+        if let Some(rejoin) = &mut self.rejoin {
+            rejoin.lost = Some(Instant::now());
+            rejoin.last_try = None;
+            rejoin.resuming = false;
+        }
+    }
+
+    // 断了之后每秒试一次，连上了报上房间、凭证和收到了对手的几帧（`Message::Resume`）
+    fn reconnect(&mut self) -> io::Result<()> {
+        let rejoin = self
+            .rejoin
+            .as_mut()
+            .expect("only resumable sessions reconnect");
+        let lost = rejoin.lost.expect("only lost connections reconnect");
+        if lost.elapsed() >= GRACE {
+            return Err(io::Error::new(ErrorKind::TimedOut, "could not reconnect"));
+        }
+        if rejoin
+            .last_try
+            .is_some_and(|last| last.elapsed() < RETRY_INTERVAL)
+        {
+            return Ok(());
+        }
+        rejoin.last_try = Some(Instant::now());
+        let resume = Message::Resume {
+            room: rejoin.room,
+            token: rejoin.token,
+            received: self.received,
+        };
+        // 连不上下一秒再试
+        let Ok(mut connection) = Connection::connect(&rejoin.address, SERVER_PORT) else {
+            return Ok(());
+        };
+        if connection.send(&resume).is_ok() {
+            rejoin.lost = None;
+            rejoin.resuming = true;
+            self.connection = connection;
+        }
+        Ok(())
+    }
+
+    // 服务器收到了自己的前几帧，后面的补发；补发的时候又断了就再重连
+    fn resend(&mut self, received: u64) {
+        if let Some(rejoin) = &mut self.rejoin {
+            rejoin.resuming = false;
+        }
+        let start = (received as usize).min(self.history.len());
+        let sent = self.history[start..]
+            .iter()
+            .try_for_each(|frame| self.connection.send(&Message::Frame(frame.clone())));
+        if sent.is_err() {
+            self.lose();
+        }
+    }
+
+    fn decided_at(&self, side: usize) -> Option<u64> {
+        self.topped_out[side].filter(|&frame| self.frames[1 - side] >= frame)
+    }
+
+    // 这一帧新打出的攻击抵消完排着的垃圾之后还剩多少
+    fn counter(&mut self, side: usize, engine: &mut Engine) -> u32 {
+        let attack = engine.stats().attack;
+        let fresh = attack - self.sent[side];
+        self.sent[side] = attack;
+        engine.counter_garbage(fresh)
+    }
+
+    fn advance(&mut self, side: usize, engine: &Engine) {
+        self.frames[side] += 1;
+        if engine.is_game_over() && self.topped_out[side].is_none() {
+            self.topped_out[side] = Some(self.frames[side]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Matrix;
+
+    #[test]
+    fn opponents_are_replayed_and_attacks_exchanged() {
+        let mut host = Pending::host(0, "ann").unwrap();
+        let address = format!("127.0.0.1:{}", host.port().unwrap());
+        let mut join = Pending::join(&address).unwrap();
+        let mut host = loop {
+            if let Some(session) = host.poll().unwrap() {
+                break session;
+            }
+        };
+        let mut join = loop {
+            if let Some(session) = join.poll().unwrap() {
+                break session;
+            }
+        };
+        assert_eq!(host.seed(), join.seed());
+
+        // 加入的一方在本地照着房主的操作重放房主的棋盘
+        let mut engine = Engine::with_seed(MODE, host.seed());
+        let mut replica = Engine::with_seed(MODE, join.seed());
+        let mut own = Engine::with_seed(MODE, join.seed());
+        for engine in [&mut engine, &mut replica, &mut own] {
+            engine.spawn_first();
+        }
+        engine.hard_drop();
+        engine.tick();
+        let actions = replay::actions(&engine.take_events());
+        host.send_frame(&mut engine, actions).unwrap();
+        let frames = loop {
+            let frames = join.receive().unwrap();
+            if !frames.is_empty() {
+                break frames;
+            }
+        };
+        assert_eq!(frames.len(), 1);
+        frames[0].apply(&mut replica);
+        replica.tick();
+        join.replayed(&frames[0], &mut replica);
+        assert!(replica.cells().eq(engine.cells()));
+        join.send_frame(&mut own, Vec::new()).unwrap();
+        while host.receive().unwrap().is_empty() {}
+
+        // 房主在第二帧顶满了，加入的一方也走到第二帧才算赢
+        engine.insert_garbage(Matrix::HEIGHT as u32, 0);
+        host.send_frame(&mut engine, Vec::new()).unwrap();
+        assert!(!host.is_decided());
+        drop(host);
+        let mut frames = Vec::new();
+        while let Ok(received) = join.receive() {
+            frames.extend(received);
+        }
+        frames[0].apply(&mut replica);
+        replica.tick();
+        replica.insert_garbage(Matrix::HEIGHT as u32, 0);
+        join.replayed(&frames[0], &mut replica);
+        assert!(!join.is_decided());
+        let _ = join.send_frame(&mut own, Vec::new());
+        assert!(join.is_decided());
+        assert_eq!(join.winner(), Some(0));
+
+        // 对手打过来的攻击下一帧才排进自己的垃圾队列，也跟着这一帧发回去
+        let attack = Frame {
+            attack: 3,
+            ..Frame::default()
+        };
+        join.replayed(&attack, &mut replica);
+        assert_eq!(join.incoming, [3]);
+        let _ = join.send_frame(&mut own, Vec::new());
+        assert_eq!(own.garbage().total(), 3);
+        assert!(join.incoming.is_empty());
+    }
+}
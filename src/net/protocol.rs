@@ -0,0 +1,288 @@
+use std::io::{self, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use super::chat::ChatLine;
+use crate::engine::mode::Mode;
+use crate::engine::replay::Action;
+use crate::engine::Engine;
+
+// 客户端和大厅服务器共用的网络协议。每条消息连同协议版本序列化成 TOML，前面一行写字节数。
+// 改了消息的格式就把 `VERSION` 加一：版本不一样的两边收到第一条消息（握手）就知道，
+// 回一句 `Refused` 再断开，不会把看不懂的消息当成坏数据
+pub const VERSION: u32 = 3;
+pub const INCOMPATIBLE: &str = "INCOMPATIBLE VERSION";
+// 一条消息最长多少字节，再长就是坏数据
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    message: Message,
+}
+
+// 先只读版本，别的字段看不懂也不要紧
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+// 开局的设置：开房的一方（或者大厅服务器）定，加入的一方认得这个模式才开打
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(with = "as_text")]
+    pub seed: u64,
+    pub mode: String,
+}
+
+impl Config {
+    pub fn new(seed: u64, mode: Mode) -> Self {
+        Self {
+            seed,
+            mode: mode.game_mode().name().to_string(),
+        }
+    }
+
+    // 模式按名字找；不认得的是 None
+    pub fn mode(&self) -> Option<Mode> {
+        Mode::ranked()
+            .into_iter()
+            .find(|mode| mode.game_mode().name() == self.mode)
+    }
+}
+
+// 一边的一帧：按顺序做了哪些操作，这一帧收下了哪些垃圾，抵消完还打出去多少，
+// 走完之后的局面指纹（`replay::state_hash`），大厅服务器拿来核对
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Frame {
+    #[serde(with = "symbols")]
+    pub actions: Vec<Action>,
+    pub garbage: Vec<u32>,
+    pub attack: u32,
+    #[serde(with = "as_text")]
+    pub hash: u64,
+}
+
+impl Frame {
+    // 重放对手这一帧的操作；之后引擎走一帧，再交给 `Session::replayed`
+    pub fn apply(&self, engine: &mut Engine) {
+        for action in &self.actions {
+            action.apply(engine);
+        }
+    }
+}
+
+// 大厅服务器上等人的一个房间，用开房的人的名字
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Lobby {
+    pub id: u32,
+    pub name: String,
+    pub players: usize,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Message {
+    Start(Config),
+    Frame(Frame),
+    // 一局分出来之后报结果：0 是自己赢，1 是对手赢，`None` 平局。直连的对手不管它
+    Result {
+        winner: Option<usize>,
+    },
+    // 对局里发给对手；在大厅服务器上发给同一个房间的人，没进房间的话发给所有在看房间列表的人
+    Chat(ChatLine),
+    // 量延迟：收到 `Ping` 马上原样回一个 `Pong`。对局里 ping 对手，连着大厅服务器就是服务器回
+    Ping {
+        id: u32,
+    },
+    Pong {
+        id: u32,
+    },
+    // 下面的只在和大厅服务器之间用
+    List,
+    Lobbies(Vec<Lobby>),
+    Create {
+        name: String,
+    },
+    Join {
+        id: u32,
+        name: String,
+    },
+    // 有人在等就进去，没有就开一个
+    Match {
+        name: String,
+    },
+    // 进了房间；`token` 是这个座位的凭证，断了之后凭它回来
+    Joined {
+        id: u32,
+        #[serde(with = "as_text")]
+        token: u64,
+    },
+    Refused {
+        reason: String,
+    },
+    // 对局中断了线，重连上来接着打：报上房间、凭证和已经收到了对手的几帧
+    Resume {
+        room: u32,
+        #[serde(with = "as_text")]
+        token: u64,
+        received: u64,
+    },
+    // 服务器回它收到了自己的几帧，之后的自己补发；对手这期间的帧服务器接着发过来
+    Resumed {
+        received: u64,
+    },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let envelope = Envelope {
+            version: VERSION,
+            message: self.clone(),
+        };
+        let body = toml::to_string(&envelope).expect("messages always serialize");
+        format!("{}\n{}", body.len(), body).into_bytes()
+    }
+
+    // 从收到的字节开头取一条完整的消息，返回它和用掉了几个字节；还没收全是 `Ok(None)`。
+    // 版本不对是 `ErrorKind::Unsupported`
+    pub fn decode(bytes: &[u8]) -> io::Result<Option<(Message, usize)>> {
+        let invalid = |reason: String| io::Error::new(ErrorKind::InvalidData, reason);
+        let Some(end) = bytes.iter().position(|&byte| byte == b'\n') else {
+            return match bytes.len() {
+                0..=8 => Ok(None),
+                _ => Err(invalid("missing message length".to_string())),
+            };
+        };
+        let length: usize = std::str::from_utf8(&bytes[..end])
+            .ok()
+            .and_then(|length| length.parse().ok())
+            .filter(|&length| length <= MAX_MESSAGE_LEN)
+            .ok_or_else(|| invalid("bad message length".to_string()))?;
+        let Some(body) = bytes.get(end + 1..end + 1 + length) else {
+            return Ok(None);
+        };
+        let body = std::str::from_utf8(body).map_err(|err| invalid(err.to_string()))?;
+        let header: Header = toml::from_str(body).map_err(|err| invalid(err.to_string()))?;
+        if header.version != VERSION {
+            return Err(io::Error::new(ErrorKind::Unsupported, INCOMPATIBLE));
+        }
+        let envelope: Envelope = toml::from_str(body).map_err(|err| invalid(err.to_string()))?;
+        if let Message::Result {
+            winner: Some(2..), ..
+        } = envelope.message
+        {
+            return Err(invalid("bad winner".to_string()));
+        }
+        Ok(Some((envelope.message, end + 1 + length)))
+    }
+}
+
+// 操作一个字符一个，写成一串
+pub mod symbols {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::engine::replay::Action;
+
+    pub fn serialize<S: Serializer>(actions: &[Action], serializer: S) -> Result<S::Ok, S::Error> {
+        let symbols: String = actions.iter().map(|action| action.symbol()).collect();
+        serializer.serialize_str(&symbols)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Action>, D::Error> {
+        let symbols = String::deserialize(deserializer)?;
+        symbols
+            .chars()
+            .map(|symbol| {
+                Action::from_symbol(symbol)
+                    .ok_or_else(|| de::Error::custom(format!("unknown action {:?}", symbol)))
+            })
+            .collect()
+    }
+}
+
+// TOML 的整数只到 i64，种子和指纹写成字符串
+pub mod as_text {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::MoveKind;
+
+    #[test]
+    fn messages_round_trip_and_other_versions_are_rejected() {
+        let messages = [
+            Message::Start(Config::new(u64::MAX, Mode::Marathon)),
+            Message::Frame(Frame {
+                actions: vec![Action::Shift(MoveKind::Left), Action::HardDrop],
+                garbage: vec![2, 1],
+                attack: 4,
+                hash: u64::MAX - 1,
+            }),
+            Message::Frame(Frame::default()),
+            Message::Result { winner: None },
+            Message::Result { winner: Some(1) },
+            Message::Chat(ChatLine::new("ann", "good game")),
+            Message::Ping { id: 7 },
+            Message::Pong { id: 7 },
+            Message::List,
+            Message::Lobbies(Vec::new()),
+            Message::Lobbies(vec![Lobby {
+                id: 3,
+                name: "ANN".to_string(),
+                players: 1,
+            }]),
+            Message::Join {
+                id: 3,
+                name: "BOB".to_string(),
+            },
+            Message::Refused {
+                reason: "LOBBY IS FULL".to_string(),
+            },
+            Message::Joined {
+                id: 3,
+                token: u64::MAX,
+            },
+            Message::Resume {
+                room: 3,
+                token: u64::MAX,
+                received: 120,
+            },
+            Message::Resumed { received: 118 },
+        ];
+        let bytes: Vec<u8> = messages.iter().flat_map(Message::encode).collect();
+        let mut rest = &bytes[..];
+        for message in &messages {
+            // 只收到一半的时候等着
+            assert!(Message::decode(&rest[..4]).unwrap().is_none());
+            let (decoded, used) = Message::decode(rest).unwrap().unwrap();
+            assert_eq!(&decoded, message);
+            rest = &rest[used..];
+        }
+        assert!(rest.is_empty());
+        assert_eq!(Config::new(1, Mode::Sprint).mode(), Some(Mode::Sprint));
+
+        let other = format!("version = {}\n[message]\nSomethingNew = 1\n", VERSION + 1);
+        let other = format!("{}\n{}", other.len(), other);
+        let err = Message::decode(other.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let bad = format!("version = {}\n[message.Result]\nwinner = 7\n", VERSION);
+        let bad = format!("{}\n{}", bad.len(), bad);
+        let err = Message::decode(bad.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(Message::decode(b"HELLO 123\n").is_err());
+    }
+}
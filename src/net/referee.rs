@@ -0,0 +1,139 @@
+use super::protocol::Frame;
+use crate::engine::mode::Mode;
+use crate::engine::replay;
+use crate::engine::Engine;
+
+// 服务器这边对一个玩家的重放：用同一个种子照着他发来的帧走，和 `Session::send_frame` 一样
+// 抵消、收垃圾，再看他说打出去的攻击和局面指纹对不对得上。对不上就是客户端动过手脚
+pub struct Referee {
+    engine: Engine,
+    sent: u32,
+    frames: u64,
+    topped_out: Option<u64>,
+    forged: bool,
+}
+
+impl Referee {
+    pub fn new(mode: Mode, seed: u64) -> Self {
+        let mut engine = Engine::with_seed(mode, seed);
+        engine.spawn_first();
+        Self {
+            engine,
+            sent: 0,
+            frames: 0,
+            topped_out: None,
+            forged: false,
+        }
+    }
+
+    // 重放一帧，对不上返回 false；对不上过一次之后的帧就不看了
+    pub fn check(&mut self, frame: &Frame) -> bool {
+        if self.forged {
+            return false;
+        }
+        frame.apply(&mut self.engine);
+        self.engine.tick();
+        let attack = self.engine.stats().attack;
+        let fresh = attack - self.sent;
+        self.sent = attack;
+        let attack = self.engine.counter_garbage(fresh);
+        for &lines in &frame.garbage {
+            self.engine.queue_garbage(lines);
+        }
+        self.frames += 1;
+        if self.engine.is_game_over() && self.topped_out.is_none() {
+            self.topped_out = Some(self.frames);
+        }
+        self.forged = attack != frame.attack || replay::state_hash(&self.engine) != frame.hash;
+        !self.forged
+    }
+
+    pub fn is_forged(&self) -> bool {
+        self.forged
+    }
+}
+
+// 照重放的结果谁赢，和 `Session::winner` 的算法一样：一边在第 n 帧顶满，
+// 另一边也走到第 n 帧才算分出来。作弊的一边直接输，两边都作弊或者还没分出来是 None
+pub fn verdict(referees: [&Referee; 2]) -> Option<usize> {
+    match referees.map(Referee::is_forged) {
+        [true, false] => return Some(1),
+        [false, true] => return Some(0),
+        [true, true] => return None,
+        [false, false] => {}
+    }
+    let decided_at = |side: usize| {
+        referees[side]
+            .topped_out
+            .filter(|&frame| referees[1 - side].frames >= frame)
+    };
+    match [decided_at(0), decided_at(1)] {
+        [Some(own), Some(other)] if own == other => None,
+        [Some(own), Some(other)] => Some(if own < other { 1 } else { 0 }),
+        [Some(_), None] => Some(1),
+        [None, Some(_)] => Some(0),
+        [None, None] => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Matrix;
+    use crate::net::MODE;
+
+    // 和 `Session::send_frame` 一样出一帧，只是没有对手打过来的垃圾
+    fn play(engine: &mut Engine, sent: &mut u32) -> Frame {
+        engine.hard_drop();
+        engine.tick();
+        let actions = replay::actions(&engine.take_events());
+        let fresh = engine.stats().attack - *sent;
+        *sent += fresh;
+        let attack = engine.counter_garbage(fresh);
+        Frame {
+            actions,
+            garbage: Vec::new(),
+            attack,
+            hash: replay::state_hash(engine),
+        }
+    }
+
+    #[test]
+    fn replays_that_do_not_check_out_lose() {
+        let mut engine = Engine::with_seed(MODE, 5);
+        engine.spawn_first();
+        let mut still = Engine::with_seed(MODE, 5);
+        still.spawn_first();
+        let (mut honest, mut idle) = (Referee::new(MODE, 5), Referee::new(MODE, 5));
+        let mut sent = 0;
+        while !engine.is_game_over() {
+            assert!(honest.check(&play(&mut engine, &mut sent)));
+            still.tick();
+            let hash = replay::state_hash(&still);
+            assert!(idle.check(&Frame {
+                hash,
+                ..Frame::default()
+            }));
+        }
+        // 一直叠着硬降的先顶满，什么都不做的赢
+        assert_eq!(verdict([&honest, &idle]), Some(1));
+
+        // 多报了攻击，或者局面是改过的，都对不上
+        let mut engine = Engine::with_seed(MODE, 5);
+        engine.spawn_first();
+        let mut sent = 0;
+        let mut inflated = play(&mut engine, &mut sent);
+        inflated.attack += 4;
+        let mut referee = Referee::new(MODE, 5);
+        assert!(!referee.check(&inflated));
+        assert!(!referee.check(&Frame::default()));
+        assert_eq!(verdict([&referee, &idle]), Some(1));
+
+        let mut tampered = Engine::with_seed(MODE, 5);
+        tampered.spawn_first();
+        tampered.insert_garbage(Matrix::HEIGHT as u32, 0);
+        let mut referee = Referee::new(MODE, 5);
+        assert!(!referee.check(&play(&mut tampered, &mut 0)));
+        assert!(referee.is_forged());
+    }
+}
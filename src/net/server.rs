@@ -0,0 +1,646 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use super::protocol::{Config, Frame, Lobby, Message};
+use super::referee::{self, Referee};
+use super::{player_name, Connection, GRACE, MODE};
+
+// 大厅服务器：客户端连上来先看房间列表，开房、进房或者快速匹配。
+// 房间满两个人就发同一个种子开打，之后把两边的帧转给对方，自己也照着重放一遍核对；
+// 谁赢按重放的算，客户端报上来的结果只用来看两边说的一不一样。
+// 打到一半断了线的，座位留到宽限期过了为止，这期间凭进房时给的凭证回来接着打
+pub struct Server {
+    listener: TcpListener,
+    clients: BTreeMap<u32, Client>,
+    rooms: Vec<Room>,
+    next_id: u32,
+    grace: Duration,
+}
+
+struct Client {
+    connection: Connection,
+    room: Option<u32>,
+}
+
+struct Room {
+    id: u32,
+    seats: Vec<Seat>,
+    started: bool,
+}
+
+struct Seat {
+    client: u32,
+    name: String,
+    token: u64,
+    // 这个人报的结果，0 是他自己赢
+    result: Option<Option<usize>>,
+    // 开打之后照着他的帧重放，帧都留着，断了线回来的对手从哪一帧没收到就从哪一帧补
+    referee: Option<Referee>,
+    frames: Vec<Frame>,
+    // 断开了也留着座位，等另一边把结果报完
+    connected: bool,
+    // 打到一半断了线的时间，宽限期内还能回来
+    lost: Option<Instant>,
+}
+
+// 打完的一局，0 号座位是开房的。`winner` 是服务器重放出来的，没分出来是 None
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub room: u32,
+    pub players: [String; 2],
+    pub winner: Option<usize>,
+    // 重放对不上的一边，判输
+    pub forged: [bool; 2],
+    // 两边报上来的结果都和重放的一样
+    pub agreed: bool,
+    // 没打完就走了（宽限期内没回来）的一边，重放没分出来的话判输
+    pub left: [bool; 2],
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [first, second] = &self.players;
+        match self.winner {
+            Some(winner) => write!(
+                f,
+                "lobby {}: {} beat {}",
+                self.room,
+                self.players[winner],
+                self.players[1 - winner]
+            )?,
+            None => write!(f, "lobby {}: {} drew with {}", self.room, first, second)?,
+        }
+        for (name, _) in self
+            .players
+            .iter()
+            .zip(self.forged)
+            .filter(|&(_, forged)| forged)
+        {
+            write!(f, ", {}'s replay did not check out", name)?;
+        }
+        for (name, _) in self.players.iter().zip(self.left).filter(|&(_, left)| left) {
+            write!(f, ", {} left", name)?;
+        }
+        if !self.agreed {
+            write!(f, ", reported results differ")?;
+        }
+        Ok(())
+    }
+}
+
+impl Server {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: BTreeMap::new(),
+            rooms: Vec::new(),
+            next_id: 1,
+            grace: GRACE,
+        })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    // 跑一轮：收下新连上来的，处理每个客户端发来的消息。返回这一轮报完结果的比赛
+    pub fn step(&mut self) -> io::Result<Vec<Report>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    // 单个连接出了问题不影响别人
+                    if let Ok(connection) = Connection::new(stream) {
+                        let id = self.next_id();
+                        let client = Client {
+                            connection,
+                            room: None,
+                        };
+                        self.clients.insert(id, client);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut reports = Vec::new();
+        let ids: Vec<u32> = self.clients.keys().copied().collect();
+        for id in ids {
+            let Some(client) = self.clients.get_mut(&id) else {
+                continue;
+            };
+            let handled = client.connection.receive().and_then(|messages| {
+                messages
+                    .into_iter()
+                    .try_for_each(|message| self.handle(id, message, &mut reports))
+            });
+            if handled.is_err() {
+                self.disconnect(id, &mut reports);
+            }
+        }
+        self.expire(&mut reports);
+        Ok(reports)
+    }
+
+    // 一个客户端发来的一条消息；返回错误就断开它
+    fn handle(&mut self, id: u32, message: Message, reports: &mut Vec<Report>) -> io::Result<()> {
+        let room = self.clients[&id].room;
+        match message {
+            Message::List => {
+                let lobbies = self
+                    .rooms
+                    .iter()
+                    .filter(|room| !room.started)
+                    .map(|room| Lobby {
+                        id: room.id,
+                        name: room.seats[0].name.clone(),
+                        players: room.seats.len(),
+                    })
+                    .collect();
+                self.send(id, &Message::Lobbies(lobbies))
+            }
+            Message::Create { .. }
+            | Message::Join { .. }
+            | Message::Match { .. }
+            | Message::Resume { .. }
+                if room.is_some() =>
+            {
+                self.refuse(id, "ALREADY IN A LOBBY")
+            }
+            Message::Create { name } => self.create(id, &name),
+            Message::Join { id: room, name } => {
+                match self.rooms.iter().position(|other| other.id == room) {
+                    Some(index) if self.rooms[index].started => self.refuse(id, "LOBBY IS FULL"),
+                    Some(index) => self.join(index, id, &name),
+                    None => self.refuse(id, "NO SUCH LOBBY"),
+                }
+            }
+            Message::Match { name } => match self.rooms.iter().position(|room| !room.started) {
+                Some(index) => self.join(index, id, &name),
+                None => self.create(id, &name),
+            },
+            Message::Resume {
+                room,
+                token,
+                received,
+            } => self.resume(id, room, token, received),
+            // 服务器自己回，不转给对手
+            Message::Ping { id: ping } => self.send(id, &Message::Pong { id: ping }),
+            // 先重放核对、记下来，再转给同一个房间的另一个人；还没开打或者已经报完结果的帧不要了
+            Message::Frame(frame) => {
+                if let Some(index) = self.room_of(id) {
+                    let seats = &mut self.rooms[index].seats;
+                    let seat = seats.iter_mut().find(|seat| seat.client == id);
+                    if let Some(seat) = seat.filter(|seat| seat.referee.is_some()) {
+                        seat.referee.as_mut().expect("checked").check(&frame);
+                        seat.frames.push(frame.clone());
+                    }
+                }
+                if let Some(other) = self.opponent(id) {
+                    // 对面发不过去是对面的事，它自己收的时候会断
+                    let _ = self.send(other, &Message::Frame(frame));
+                }
+                Ok(())
+            }
+            Message::Result { winner } => {
+                let Some(index) = self.room_of(id) else {
+                    return Ok(());
+                };
+                let room = &mut self.rooms[index];
+                if let Some(seat) = room.seats.iter_mut().find(|seat| seat.client == id) {
+                    seat.result = Some(winner);
+                }
+                self.settle(index, reports);
+                Ok(())
+            }
+            // 房间里的发给另一个人，没进房间的发给别的没进房间的
+            Message::Chat(line) => {
+                let others: Vec<u32> = match room {
+                    Some(_) => self.roommate(id).into_iter().collect(),
+                    None => self
+                        .clients
+                        .iter()
+                        .filter(|(&other, client)| other != id && client.room.is_none())
+                        .map(|(&other, _)| other)
+                        .collect(),
+                };
+                for other in others {
+                    let _ = self.send(other, &Message::Chat(line.clone()));
+                }
+                Ok(())
+            }
+            Message::Start(_)
+            | Message::Lobbies(_)
+            | Message::Joined { .. }
+            | Message::Refused { .. }
+            | Message::Resumed { .. }
+            | Message::Pong { .. } => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "unexpected message from a client",
+            )),
+        }
+    }
+
+    fn create(&mut self, client: u32, name: &str) -> io::Result<()> {
+        let id = self.next_id();
+        let seat = Seat::new(client, name);
+        let token = seat.token;
+        self.rooms.push(Room {
+            id,
+            seats: vec![seat],
+            started: false,
+        });
+        self.clients.get_mut(&client).expect("client exists").room = Some(id);
+        self.send(client, &Message::Joined { id, token })
+    }
+
+    // 进了就满了，给两边发同样的开局设置
+    fn join(&mut self, index: usize, client: u32, name: &str) -> io::Result<()> {
+        let room = &mut self.rooms[index];
+        let seat = Seat::new(client, name);
+        let token = seat.token;
+        room.seats.push(seat);
+        room.started = true;
+        let id = room.id;
+        let host = room.seats[0].client;
+        self.clients.get_mut(&client).expect("client exists").room = Some(id);
+        self.send(client, &Message::Joined { id, token })?;
+        let seed = rand::random();
+        for seat in &mut self.rooms[index].seats {
+            seat.referee = Some(Referee::new(MODE, seed));
+        }
+        let _ = self.send(host, &Message::Start(Config::new(seed, MODE)));
+        self.send(client, &Message::Start(Config::new(seed, MODE)))
+    }
+
+    // 凭证对上了就坐回原来的座位。服务器还没发现旧的连接断了的话，旧的直接不要了。
+    // 先告诉他服务器收到了他的几帧，再把对手这期间的帧补给他
+    fn resume(&mut self, client: u32, room: u32, token: u64, received: u64) -> io::Result<()> {
+        let index = self.rooms.iter().position(|other| other.id == room);
+        let Some(index) = index.filter(|&index| self.rooms[index].started) else {
+            return self.refuse(client, "MATCH IS OVER");
+        };
+        let seats = &mut self.rooms[index].seats;
+        let Some(side) = seats.iter().position(|seat| seat.token == token) else {
+            return self.refuse(client, "MATCH IS OVER");
+        };
+        let seat = &mut seats[side];
+        if seat.result.is_some() || (!seat.connected && seat.lost.is_none()) {
+            return self.refuse(client, "MATCH IS OVER");
+        }
+        let old = std::mem::replace(&mut seat.client, client);
+        seat.connected = true;
+        seat.lost = None;
+        let own = seat.frames.len() as u64;
+        let missed = seats[1 - side].frames.get(received as usize..);
+        let missed = missed.unwrap_or_default().to_vec();
+        if old != client {
+            self.clients.remove(&old);
+        }
+        self.clients.get_mut(&client).expect("client exists").room = Some(room);
+        self.send(client, &Message::Resumed { received: own })?;
+        missed
+            .into_iter()
+            .try_for_each(|frame| self.send(client, &Message::Frame(frame)))
+    }
+
+    fn refuse(&mut self, client: u32, reason: &str) -> io::Result<()> {
+        let reason = reason.to_string();
+        self.send(client, &Message::Refused { reason })
+    }
+
+    fn send(&mut self, client: u32, message: &Message) -> io::Result<()> {
+        match self.clients.get_mut(&client) {
+            Some(client) => client.connection.send(message),
+            None => Err(ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn room_of(&self, client: u32) -> Option<usize> {
+        let id = self.clients.get(&client)?.room?;
+        self.rooms.iter().position(|room| room.id == id)
+    }
+
+    // 开打了的房间里的另一个人，还连着的话
+    fn opponent(&self, client: u32) -> Option<u32> {
+        let room = &self.rooms[self.room_of(client)?];
+        self.roommate(client).filter(|_| room.started)
+    }
+
+    fn roommate(&self, client: u32) -> Option<u32> {
+        let room = &self.rooms[self.room_of(client)?];
+        room.seats
+            .iter()
+            .find(|seat| seat.client != client && seat.connected)
+            .map(|seat| seat.client)
+    }
+
+    // 打到一半断了的，座位留着等他回来，对面接着打；
+    // 别的时候走了就是走了，没开打的房间直接关掉
+    fn disconnect(&mut self, client: u32, reports: &mut Vec<Report>) {
+        if let Some(index) = self.room_of(client) {
+            let room = &mut self.rooms[index];
+            let started = room.started;
+            for seat in &mut room.seats {
+                if seat.client == client {
+                    seat.connected = false;
+                    if started && seat.result.is_none() {
+                        seat.lost = Some(Instant::now());
+                    }
+                }
+            }
+            if started {
+                self.leave(index);
+            }
+            self.settle(index, reports);
+        }
+        self.clients.remove(&client);
+    }
+
+    // 宽限期过了还没回来的算走了
+    fn expire(&mut self, reports: &mut Vec<Report>) {
+        let grace = self.grace;
+        for index in (0..self.rooms.len()).rev() {
+            let mut expired = false;
+            for seat in &mut self.rooms[index].seats {
+                if seat.lost.is_some_and(|lost| lost.elapsed() >= grace) {
+                    seat.lost = None;
+                    expired = true;
+                }
+            }
+            if expired {
+                self.leave(index);
+                self.settle(index, reports);
+            }
+        }
+    }
+
+    // 有人走了（宽限期内的不算），告诉还连着的人不用等了，他把转过去的帧取完就会知道
+    fn leave(&mut self, index: usize) {
+        let seats = &self.rooms[index].seats;
+        if !seats.iter().any(Seat::has_left) {
+            return;
+        }
+        let staying: Vec<u32> = seats
+            .iter()
+            .filter(|seat| seat.connected)
+            .map(|seat| seat.client)
+            .collect();
+        for client in staying {
+            if let Some(client) = self.clients.get_mut(&client) {
+                let reason = "OPPONENT LEFT".to_string();
+                let _ = client.connection.send(&Message::Refused { reason });
+                client.connection.finish();
+            }
+        }
+    }
+
+    // 开打了的房间两个人都报了结果或者有人走了，没开打的房间人都走了，就关掉。
+    // 开打了的按重放判，重放没分出来的话中途走了的输
+    fn settle(&mut self, index: usize, reports: &mut Vec<Report>) {
+        let room = &self.rooms[index];
+        let done = |seat: &Seat| seat.result.is_some() || seat.has_left();
+        let left = |seat: &Seat| seat.has_left() && seat.result.is_none();
+        if !room.seats.iter().all(done) && !room.seats.iter().any(left) {
+            return;
+        }
+        if let [first, second] = &room.seats[..] {
+            let referees = [first, second].map(|seat| seat.referee.as_ref().expect("started"));
+            let left = [first, second].map(left);
+            let winner = match (referee::verdict(referees), left) {
+                (Some(winner), _) => Some(winner),
+                (None, [true, false]) => Some(1),
+                (None, [false, true]) => Some(0),
+                (None, _) => None,
+            };
+            // 两边说的都是自己这边，换到 0 号座位看
+            let claims = [
+                first.result,
+                second.result.map(|result| result.map(|w| 1 - w)),
+            ];
+            reports.push(Report {
+                room: room.id,
+                players: [first.name.clone(), second.name.clone()],
+                winner,
+                forged: referees.map(Referee::is_forged),
+                agreed: claims.into_iter().flatten().all(|claim| claim == winner),
+                left,
+            });
+        }
+        let room = self.rooms.remove(index);
+        for seat in room.seats {
+            if let Some(client) = self.clients.get_mut(&seat.client) {
+                client.room = None;
+            }
+        }
+    }
+
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+}
+
+impl Seat {
+    fn new(client: u32, name: &str) -> Self {
+        Self {
+            client,
+            name: player_name(name),
+            token: rand::random(),
+            result: None,
+            referee: None,
+            frames: Vec::new(),
+            connected: true,
+            lost: None,
+        }
+    }
+
+    // 断开了，而且不是在宽限期里
+    fn has_left(&self) -> bool {
+        !self.connected && self.lost.is_none()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Shutdown;
+
+    use crate::engine::{Engine, Matrix};
+    use crate::net::{LobbyClient, Pending, Session};
+
+    fn start(server: &mut Server, pending: &mut Pending) -> Session {
+        loop {
+            server.step().unwrap();
+            if let Some(session) = pending.poll().unwrap() {
+                break session;
+            }
+        }
+    }
+
+    #[test]
+    fn lobbies_pair_players_and_relay_their_games() {
+        let mut server = Server::bind(0).unwrap();
+        let address = format!("127.0.0.1:{}", server.port().unwrap());
+        let host = LobbyClient::connect(&address).unwrap();
+        let mut host = host.create("ann").unwrap();
+        let mut guest = LobbyClient::connect(&address).unwrap();
+        // 第一次的列表可能还没有房间，刷新到看见为止
+        let lobby = loop {
+            server.step().unwrap();
+            if guest.poll().unwrap() {
+                match guest.lobbies().first() {
+                    Some(lobby) => break lobby.clone(),
+                    None => guest.refresh().unwrap(),
+                }
+            }
+        };
+        assert_eq!(lobby.name, "ANN");
+        assert_eq!(lobby.players, 1);
+        let late = LobbyClient::connect(&address).unwrap();
+        let mut guest = guest.join(lobby.id, "bob").unwrap();
+        let mut guest = start(&mut server, &mut guest);
+        let mut host = start(&mut server, &mut host);
+        assert_eq!(host.seed(), guest.seed());
+
+        // 满了的房间进不去
+        let mut late = late.join(lobby.id, "cy").unwrap();
+        let refused = loop {
+            server.step().unwrap();
+            match late.poll() {
+                Ok(_) => {}
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(refused.to_string(), "LOBBY IS FULL");
+
+        // 帧原样转给对面，服务器自己也重放一遍
+        let mut engine = Engine::with_seed(MODE, host.seed());
+        engine.spawn_first();
+        engine.tick();
+        host.send_frame(&mut engine, Vec::new()).unwrap();
+        let frames = loop {
+            server.step().unwrap();
+            let frames = guest.receive().unwrap();
+            if !frames.is_empty() {
+                break frames;
+            }
+        };
+        assert_eq!(frames.len(), 1);
+        // 聊天只发给同一个房间的人
+        host.say("ann", "gl hf").unwrap();
+        while guest.chat().is_empty() {
+            server.step().unwrap();
+            guest.receive().unwrap();
+        }
+        let line = guest.chat().lines().next().unwrap();
+        assert_eq!((line.name.as_str(), line.text.as_str()), ("ANN", "GL HF"));
+
+        // 房主改了自己的局面说自己顶满了，重放对不上，判他输；两边报的都是没分出来
+        engine.insert_garbage(Matrix::HEIGHT as u32, 0);
+        engine.tick();
+        host.send_frame(&mut engine, Vec::new()).unwrap();
+        host.report().unwrap();
+        guest.report().unwrap();
+        let reports = loop {
+            let reports = server.step().unwrap();
+            if !reports.is_empty() {
+                break reports;
+            }
+        };
+        assert_eq!(reports[0].winner, Some(1));
+        assert_eq!(reports[0].forged, [true, false]);
+        assert!(!reports[0].agreed);
+        assert_eq!(
+            reports[0].to_string(),
+            format!(
+                "lobby {}: BOB beat ANN, ANN's replay did not check out, reported results differ",
+                lobby.id
+            )
+        );
+        assert!(server.rooms.is_empty());
+    }
+
+    #[test]
+    fn dropped_players_resume_within_the_grace_window() {
+        let mut server = Server::bind(0).unwrap();
+        let address = format!("127.0.0.1:{}", server.port().unwrap());
+        let mut host = LobbyClient::connect(&address)
+            .unwrap()
+            .create("ann")
+            .unwrap();
+        let mut guest = LobbyClient::connect(&address)
+            .unwrap()
+            .quick_match("bob")
+            .unwrap();
+        let mut guest = start(&mut server, &mut guest);
+        let mut host = start(&mut server, &mut host);
+        let mut engines = [host.seed(), guest.seed()].map(|seed| {
+            let mut engine = Engine::with_seed(MODE, seed);
+            engine.spawn_first();
+            engine
+        });
+        engines[0].tick();
+        host.send_frame(&mut engines[0], Vec::new()).unwrap();
+        while guest.receive().unwrap().is_empty() {
+            server.step().unwrap();
+        }
+
+        // 加入的一方断了线，服务器留着座位，房主接着打
+        guest.connection.stream.shutdown(Shutdown::Both).unwrap();
+        while !guest.is_reconnecting() {
+            guest.receive().unwrap();
+        }
+        while server.rooms[0].seats[1].connected {
+            server.step().unwrap();
+        }
+        engines[0].tick();
+        host.send_frame(&mut engines[0], Vec::new()).unwrap();
+        engines[1].tick();
+        guest.send_frame(&mut engines[1], Vec::new()).unwrap();
+
+        // 重连上来，补上断线时房主的帧，自己没发出去的也补发给房主
+        let frames = loop {
+            server.step().unwrap();
+            let frames = guest.receive().unwrap();
+            if !frames.is_empty() {
+                break frames;
+            }
+        };
+        assert_eq!(frames.len(), 1);
+        assert!(!guest.is_reconnecting());
+        while host.receive().unwrap().is_empty() {
+            server.step().unwrap();
+        }
+        let seats = &server.rooms[0].seats;
+        let counts: Vec<usize> = seats.iter().map(|seat| seat.frames.len()).collect();
+        assert_eq!(counts, [2, 1]);
+        assert!(seats
+            .iter()
+            .all(|seat| !seat.referee.as_ref().unwrap().is_forged()));
+
+        // 宽限期过了还没回来算走了，重放没分出来就判他输
+        server.grace = Duration::ZERO;
+        drop(host);
+        let reports = loop {
+            let reports = server.step().unwrap();
+            if !reports.is_empty() {
+                break reports;
+            }
+        };
+        assert_eq!(reports[0].winner, Some(1));
+        assert_eq!(reports[0].left, [true, false]);
+        let err = loop {
+            match guest.receive() {
+                Ok(_) => {}
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.to_string(), "OPPONENT LEFT");
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::replay::Action;
+use crate::engine::{Engine, Stats};
+use crate::net::protocol::{symbols, Config};
+
+// 打完的单人局自动存到这里，一局一个文件
+pub const REPLAY_DIR: &str = "replays";
+
+// 一局的录像：开局的模式、种子和挖洞间隔，加上每一帧做了哪些操作。
+// 用同样的设置开一局，一帧一帧照着做就是原来那一局
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    // 列表里显示的名字，可以改
+    pub title: String,
+    pub config: Config,
+    pub dig_interval: u32,
+    pub score: u32,
+    pub lines: u32,
+    pub frames: u64,
+    // 什么时候打的，Unix 时间（秒）
+    pub timestamp: u64,
+    // 一共录了几帧
+    pub length: u64,
+    // 踩上拍子拿了奖励分的帧
+    #[serde(default)]
+    pub beats: Vec<u64>,
+    // 只记有操作的帧
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Step {
+    pub frame: u64,
+    #[serde(with = "symbols")]
+    pub actions: Vec<Action>,
+}
+
+impl Replay {
+    // 认不得的模式（比如删掉了的自定义模式）放不了
+    pub fn engine(&self) -> Option<Engine> {
+        let mut engine = Engine::with_seed(self.config.mode()?, self.config.seed);
+        engine.set_dig_interval(self.dig_interval);
+        engine.spawn_first();
+        Some(engine)
+    }
+}
+
+// 一边打一边录，引擎每走一帧记一次
+pub struct ReplayRecorder {
+    replay: Replay,
+}
+
+impl ReplayRecorder {
+    pub fn new(engine: &Engine, dig_interval: u32) -> Self {
+        let mode = engine.mode();
+        Self {
+            replay: Replay {
+                title: mode.game_mode().name().to_string(),
+                config: Config::new(engine.seed(), mode),
+                dig_interval,
+                score: 0,
+                lines: 0,
+                frames: 0,
+                timestamp: 0,
+                length: 0,
+                beats: Vec::new(),
+                steps: Vec::new(),
+            },
+        }
+    }
+
+    pub fn record(&mut self, actions: Vec<Action>, beat: bool) {
+        let frame = self.replay.length;
+        if !actions.is_empty() {
+            self.replay.steps.push(Step { frame, actions });
+        }
+        if beat {
+            self.replay.beats.push(frame);
+        }
+        self.replay.length += 1;
+    }
+
+    pub fn finish(self, stats: &Stats) -> Replay {
+        Replay {
+            score: stats.score,
+            lines: stats.lines,
+            frames: stats.frames,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            ..self.replay
+        }
+    }
+}
+
+// 放录像：每帧先把录下来的操作做了，引擎走完一帧再看要不要加踩拍子的分
+pub struct Playback {
+    replay: Replay,
+    frame: u64,
+    step: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            frame: 0,
+            step: 0,
+        }
+    }
+
+    // 下一帧的操作和这一帧有没有拍子奖励；放完了是 None
+    pub fn next(&mut self) -> Option<(&[Action], bool)> {
+        if self.frame >= self.replay.length {
+            return None;
+        }
+        let frame = self.frame;
+        self.frame += 1;
+        let beat = self.replay.beats.contains(&frame);
+        match self.replay.steps.get(self.step) {
+            Some(step) if step.frame == frame => {
+                self.step += 1;
+                Some((&self.replay.steps[self.step - 1].actions, beat))
+            }
+            _ => Some((&[], beat)),
+        }
+    }
+}
+
+// 文件名是时间加模式名；自定义模式的名字什么字符都可能有，只留小写字母、数字和 `-`。
+// 导出表格的文件也这样起名
+pub fn file_stem(timestamp: u64, mode: &str) -> String {
+    let mode: String = mode
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' => c,
+            _ => '-',
+        })
+        .collect();
+    format!("{}-{}", timestamp, mode)
+}
+
+pub fn save(dir: &Path, replay: &Replay) -> Result<PathBuf, String> {
+    let source = toml::to_string(replay).map_err(|err| err.to_string())?;
+    let stem = file_stem(replay.timestamp, &replay.config.mode);
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    // 同一秒里打完两局的话文件名后面加个序号，不盖掉前一局
+    for count in 1.. {
+        let name = match count {
+            1 => format!("{}.toml", stem),
+            _ => format!("{}-{}.toml", stem, count),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(source.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    unreachable!()
+}
+
+pub fn load(path: &Path) -> Result<Replay, String> {
+    let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    toml::from_str(&source).map_err(|err| err.to_string())
+}
+
+// 新的在前
+pub fn load_all(dir: &Path) -> Vec<(PathBuf, Replay)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut replays: Vec<(PathBuf, Replay)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| match load(&path) {
+            Ok(replay) => Some((path, replay)),
+            Err(err) => {
+                eprintln!("Failed to load replay {}: {}", path.display(), err);
+                None
+            }
+        })
+        .collect();
+    replays.sort_by_key(|(_, replay)| std::cmp::Reverse(replay.timestamp));
+    replays
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::mode::Mode;
+    use crate::engine::replay::{self, state_hash};
+
+    #[test]
+    fn playback_repeats_the_recorded_game() {
+        let mut engine = Engine::with_seed(Mode::Mission, 9);
+        engine.set_dig_interval(300);
+        engine.spawn_first();
+        let mut recorder = ReplayRecorder::new(&engine, 300);
+        for frame in 0..600 {
+            match frame % 45 {
+                0 => engine.rotate_clockwise(),
+                20 => engine.hard_drop(),
+                _ => {}
+            }
+            engine.tick();
+            let actions = replay::actions(&engine.take_events());
+            recorder.record(actions, frame == 100);
+        }
+        let recorded = recorder.finish(&engine.stats());
+        assert_eq!(recorded.title, "MISSIONS");
+        assert_eq!(recorded.length, 600);
+
+        let source = toml::to_string(&recorded).unwrap();
+        let loaded: Replay = toml::from_str(&source).unwrap();
+        assert_eq!(loaded, recorded);
+
+        assert_eq!(file_stem(7, "A/B: C\\D"), "7-a-b--c-d");
+
+        // 同一秒存两次是两个文件
+        let dir = std::env::temp_dir().join(format!("replays-{}", std::process::id()));
+        let first = save(&dir, &recorded).unwrap();
+        let second = save(&dir, &recorded).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(load_all(&dir).len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut replayed = loaded.engine().unwrap();
+        let mut playback = Playback::new(loaded);
+        let mut beats = Vec::new();
+        while let Some((actions, beat)) = playback.next() {
+            for action in actions {
+                action.apply(&mut replayed);
+            }
+            replayed.tick();
+            beats.push(beat);
+        }
+        assert_eq!(state_hash(&replayed), state_hash(&engine));
+        assert_eq!(beats.iter().position(|&beat| beat), Some(100));
+    }
+}
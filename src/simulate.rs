@@ -0,0 +1,163 @@
+use crate::engine::battle::{Battle, Outcome};
+use crate::engine::bot::Difficulty;
+use crate::engine::Stats;
+
+// `--simulate N` 不开窗口，让两个电脑玩家对打 N 局，打完印胜负和平均数据。
+// `--seed S` 是第一局的种子，之后每局加一；`--players LEFT,RIGHT` 是两边的难度
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    games: u64,
+    seed: u64,
+    players: [Difficulty; 2],
+}
+
+// 没有 `--simulate` 是 None
+pub fn parse(args: &[String]) -> Option<Result<Options, String>> {
+    let value = |flag: &str| {
+        args.iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg.strip_prefix(flag) {
+                Some("") => Some(args.get(i + 1).map_or("", String::as_str)),
+                Some(rest) => rest.strip_prefix('='),
+                None => None,
+            })
+    };
+    let games = value("--simulate")?;
+    Some(options(games, value("--seed"), value("--players")))
+}
+
+fn options(games: &str, seed: Option<&str>, players: Option<&str>) -> Result<Options, String> {
+    let games = games
+        .parse()
+        .map_err(|_| format!("invalid number of games {:?}", games))?;
+    let seed = match seed {
+        Some(seed) => seed
+            .parse()
+            .map_err(|_| format!("invalid seed {:?}", seed))?,
+        None => 0,
+    };
+    let difficulty = |name: &str| {
+        Difficulty::ALL
+            .into_iter()
+            .find(|tier| tier.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown difficulty {:?}", name))
+    };
+    let players = match players.map(|players| players.split_once(',')) {
+        None => [Difficulty::Expert; 2],
+        Some(Some((left, right))) => [difficulty(left)?, difficulty(right)?],
+        Some(None) => return Err("--players takes LEFT,RIGHT".to_string()),
+    };
+    Ok(Options {
+        games,
+        seed,
+        players,
+    })
+}
+
+pub fn run(options: &Options) {
+    let outcomes: Vec<Outcome> = (0..options.games)
+        .map(|game| {
+            let seed = options.seed + game;
+            // 两边的失误用不一样的种子，免得一模一样
+            let drivers = [0, 1].map(|side: usize| {
+                options.players[side]
+                    .driver_with_seed(seed.wrapping_mul(2).wrapping_add(side as u64))
+            });
+            Battle::new(seed, drivers).run(seed)
+        })
+        .collect();
+    for line in report(options, &outcomes) {
+        println!("{}", line);
+    }
+}
+
+fn report(options: &Options, outcomes: &[Outcome]) -> Vec<String> {
+    let games = outcomes.len().max(1) as f64;
+    let draws = outcomes
+        .iter()
+        .filter(|outcome| outcome.winner.is_none())
+        .count();
+    let seconds: f64 = outcomes
+        .iter()
+        .map(|outcome| outcome.frames as f64 / Stats::FRAMES_PER_SECOND as f64)
+        .sum();
+    let mut lines = vec![
+        format!(
+            "{} games, seeds {}..{}",
+            outcomes.len(),
+            options.seed,
+            options.seed + options.games
+        ),
+        format!("draws {}, average length {:.1}s", draws, seconds / games),
+    ];
+    for side in 0..2 {
+        let wins = outcomes
+            .iter()
+            .filter(|outcome| outcome.winner == Some(side))
+            .count();
+        let average = |stat: fn(&Stats) -> f64| {
+            outcomes
+                .iter()
+                .map(|outcome| stat(&outcome.stats[side]))
+                .sum::<f64>()
+                / games
+        };
+        lines.push(format!(
+            "{:<8} wins {:>4}  pieces {:.1}  lines {:.1}  attack {:.1}  pps {:.2}  apm {:.1}",
+            options.players[side].name(),
+            wins,
+            average(|stats| stats.pieces as f64),
+            average(|stats| stats.lines as f64),
+            average(|stats| stats.attack as f64),
+            average(Stats::pps),
+            average(Stats::apm),
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_simulation_flags() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse(&args(&["tetris", "--terminal"])), None);
+        assert_eq!(
+            parse(&args(&[
+                "tetris",
+                "--simulate",
+                "20",
+                "--seed=5",
+                "--players",
+                "hard,beginner"
+            ])),
+            Some(Ok(Options {
+                games: 20,
+                seed: 5,
+                players: [Difficulty::Hard, Difficulty::Beginner],
+            }))
+        );
+        let defaults = parse(&args(&["tetris", "--simulate=3"])).unwrap().unwrap();
+        assert_eq!(defaults.players, [Difficulty::Expert; 2]);
+        assert!(parse(&args(&["tetris", "--simulate", "many"]))
+            .unwrap()
+            .is_err());
+        assert!(
+            parse(&args(&["tetris", "--simulate", "1", "--players", "hard"]))
+                .unwrap()
+                .is_err()
+        );
+
+        let outcome = Outcome {
+            seed: 5,
+            winner: Some(1),
+            frames: 600,
+            stats: [Stats::default(); 2],
+        };
+        let report = report(&defaults, &[outcome]);
+        assert_eq!(report[1], "draws 0, average length 10.0s");
+        assert!(report[3].contains("wins    1"));
+    }
+}
@@ -0,0 +1,216 @@
+// 终端里玩的前端：服务器上、SSH 过去也能玩，和图形界面共用同一个引擎
+use std::io;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color as TermColor, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::engine::mode::Mode;
+use crate::engine::piece::Kind as PieceKind;
+use crate::engine::{Color, Engine, Matrix, MoveKind, Stats};
+use cgmath::Point2;
+
+const TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / Stats::FRAMES_PER_SECOND);
+const PREVIEWS: usize = 5;
+// 终端的字符是瘦高的，一格用两个字符宽才接近正方形
+const BLOCK: &str = "██";
+const GHOST: &str = "░░";
+const EMPTY: &str = " .";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Cell {
+    Empty,
+    Ghost(Color),
+    Block(Color),
+}
+
+pub fn run() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn run_loop(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    let mut engine = Engine::with_mode(Mode::Marathon);
+    engine.spawn_first();
+    let mut paused = false;
+    let mut next_update = Instant::now();
+    loop {
+        terminal.draw(|frame| draw(frame, &engine, paused))?;
+
+        // 等到下一个逻辑帧，期间来的按键都处理掉；终端没有松开按键的事件，按住靠系统的按键重复
+        let timeout = next_update.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('p') => paused = !paused,
+                    KeyCode::Char('r') if engine.is_game_over() || engine.is_finished() => {
+                        engine = Engine::with_mode(engine.mode());
+                        engine.spawn_first();
+                    }
+                    _ if paused => {}
+                    KeyCode::Left => {
+                        let _ = engine.move_cursor(MoveKind::Left);
+                    }
+                    KeyCode::Right => {
+                        let _ = engine.move_cursor(MoveKind::Right);
+                    }
+                    KeyCode::Down => engine.soft_drop(),
+                    KeyCode::Up => engine.rotate_clockwise(),
+                    KeyCode::Char(' ') => engine.hard_drop(),
+                    KeyCode::Char('c') => engine.hold(),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        next_update += TIMESTEP;
+        if !paused {
+            engine.tick();
+            // 终端里没有特效，事件直接丢掉
+            engine.take_events();
+        }
+    }
+}
+
+// 从上往下的每一行，已经把影子和当前方块叠上去了
+fn matrix_rows(engine: &Engine) -> Vec<[Cell; Matrix::WIDTH]> {
+    let mut rows = vec![[Cell::Empty; Matrix::WIDTH]; Matrix::HEIGHT];
+    let mut set = |coord: Point2<usize>, cell: Cell| {
+        if coord.y < Matrix::HEIGHT && coord.x < Matrix::WIDTH {
+            rows[Matrix::HEIGHT - 1 - coord.y][coord.x] = cell;
+        }
+    };
+    for (coord, color) in engine.cells() {
+        // 终端里没有半透明，invisible 模式淡完了才藏起来
+        if let (Some(color), true) = (color, engine.cell_visibility(coord) > 0.0) {
+            set(coord, Cell::Block(*color));
+        }
+    }
+    if let (Some(ghost), Some((_, color))) = (engine.ghost_cells(), engine.cursor_info()) {
+        for coord in ghost {
+            set(coord, Cell::Ghost(color));
+        }
+    }
+    if let Some((cells, color)) = engine.cursor_info() {
+        for coord in cells {
+            set(coord, Cell::Block(color));
+        }
+    }
+    rows
+}
+
+fn term_color(color: Color) -> TermColor {
+    match color {
+        Color::Yellow => TermColor::Yellow,
+        Color::Cyan => TermColor::Cyan,
+        Color::Purple => TermColor::Magenta,
+        Color::Orange => TermColor::LightRed,
+        Color::Blue => TermColor::Blue,
+        Color::Green => TermColor::Green,
+        Color::Red => TermColor::Red,
+        Color::Gray => TermColor::Gray,
+    }
+}
+
+fn cell_span(cell: Cell) -> Span<'static> {
+    match cell {
+        Cell::Empty => Span::styled(EMPTY, Style::new().fg(TermColor::DarkGray)),
+        Cell::Ghost(color) => Span::styled(GHOST, Style::new().fg(term_color(color))),
+        Cell::Block(color) => Span::styled(BLOCK, Style::new().fg(term_color(color))),
+    }
+}
+
+fn piece_name(kind: PieceKind) -> Span<'static> {
+    let name = match kind {
+        PieceKind::O => "O",
+        PieceKind::I => "I",
+        PieceKind::T => "T",
+        PieceKind::L => "L",
+        PieceKind::J => "J",
+        PieceKind::S => "S",
+        PieceKind::Z => "Z",
+    };
+    Span::styled(name, Style::new().fg(term_color(kind.color())))
+}
+
+fn draw(frame: &mut Frame, engine: &Engine, paused: bool) {
+    let [matrix_area, side_area] = Layout::horizontal([
+        Constraint::Length(Matrix::WIDTH as u16 * 2 + 2),
+        Constraint::Length(20),
+    ])
+    .areas(frame.area());
+    let [matrix_area] =
+        Layout::vertical([Constraint::Length(Matrix::HEIGHT as u16 + 2)]).areas(matrix_area);
+
+    let lines: Vec<Line> = matrix_rows(engine)
+        .into_iter()
+        .map(|row| Line::from(row.into_iter().map(cell_span).collect::<Vec<_>>()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("TETRIS")),
+        matrix_area,
+    );
+
+    let stats = engine.stats();
+    let mut side = vec![
+        Line::from(vec![
+            Span::raw("HOLD  "),
+            engine.held().map_or(Span::raw("-"), piece_name),
+        ]),
+        Line::from(
+            [Span::raw("NEXT  ")]
+                .into_iter()
+                .chain(
+                    engine
+                        .preview(PREVIEWS)
+                        .flat_map(|kind| [piece_name(kind), Span::raw(" ")]),
+                )
+                .collect::<Vec<_>>(),
+        ),
+        Line::raw(""),
+        Line::raw(format!("SCORE {}", stats.score)),
+        Line::raw(format!("LINES {}", stats.lines)),
+        Line::raw(format!("LEVEL {}", stats.level)),
+        Line::raw(""),
+    ];
+    if engine.is_game_over() || engine.is_finished() {
+        side.push(Line::raw("GAME OVER"));
+        side.push(Line::raw("R retry  Q quit"));
+    } else if paused {
+        side.push(Line::raw("PAUSED"));
+    } else {
+        side.push(Line::raw("SPACE drop  C hold"));
+        side.push(Line::raw("P pause  Q quit"));
+    }
+    frame.render_widget(Paragraph::new(side).block(Block::bordered()), side_area);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor_and_ghost_show_up_in_rows() {
+        let mut engine = Engine::new();
+        engine.spawn_first();
+        let rows = matrix_rows(&engine);
+        let count = |wanted: fn(&Cell) -> bool| rows.iter().flatten().filter(|c| wanted(c)).count();
+        assert_eq!(count(|cell| matches!(cell, Cell::Block(_))), 4);
+        assert_eq!(count(|cell| matches!(cell, Cell::Ghost(_))), 4);
+        // 影子在最底下一行
+        assert!(rows[Matrix::HEIGHT - 1]
+            .iter()
+            .any(|cell| matches!(cell, Cell::Ghost(_))));
+    }
+}
@@ -0,0 +1,151 @@
+// 网页版的入口：引擎编成 wasm，绘制和按键都在 web/main.js 里用 canvas 做
+use wasm_bindgen::prelude::*;
+
+use crate::engine::mode::Mode;
+use crate::engine::piece::Kind as PieceKind;
+use crate::engine::{Color, Engine, Matrix, MoveKind};
+
+// 格子编码：0 是空，1 开始是 `Color as u8 + 1`，影子再或上 GHOST
+const GHOST: u8 = 0x80;
+
+#[wasm_bindgen]
+pub struct Game {
+    engine: Engine,
+}
+
+#[wasm_bindgen]
+impl Game {
+    // `mode` 是 "marathon"、"sprint"、"ultra"、"zen"、"dig"、"master"、"invisible"、"classic" 或者 "survival"，不认识的当 marathon
+    #[wasm_bindgen(constructor)]
+    pub fn new(mode: &str) -> Game {
+        let mode = match mode {
+            "sprint" => Mode::Sprint,
+            "ultra" => Mode::Ultra,
+            "zen" => Mode::Zen,
+            "dig" => Mode::Dig,
+            "master" => Mode::Master,
+            "invisible" => Mode::Invisible,
+            "classic" => Mode::Classic,
+            "survival" => Mode::Survival,
+            _ => Mode::Marathon,
+        };
+        let mut engine = Engine::with_mode(mode);
+        engine.spawn_first();
+        Game { engine }
+    }
+
+    // 页面按 60 帧每秒固定步长调用
+    pub fn tick(&mut self) {
+        self.engine.tick();
+        self.engine.take_events();
+    }
+
+    // 按键由页面映射成动作名，返回 false 表示不认识
+    pub fn input(&mut self, action: &str) -> bool {
+        let engine = &mut self.engine;
+        match action {
+            "left" => {
+                let _ = engine.move_cursor(MoveKind::Left);
+            }
+            "right" => {
+                let _ = engine.move_cursor(MoveKind::Right);
+            }
+            "soft_drop" => engine.soft_drop(),
+            "hard_drop" => engine.hard_drop(),
+            "rotate" => engine.rotate_clockwise(),
+            "hold" => engine.hold(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn width() -> usize {
+        Matrix::WIDTH
+    }
+
+    pub fn height() -> usize {
+        Matrix::HEIGHT
+    }
+
+    // 从下往上一行一行，每格一个字节，当前方块和影子已经叠上去了
+    pub fn cells(&self) -> Vec<u8> {
+        board_cells(&self.engine)
+    }
+
+    // 接下来的方块，编码和 `cells` 一样用颜色
+    pub fn preview(&self, count: usize) -> Vec<u8> {
+        self.engine.preview(count).map(piece_code).collect()
+    }
+
+    // 没有 hold 的时候是 0
+    pub fn held(&self) -> u8 {
+        self.engine.held().map_or(0, piece_code)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.engine.stats().score
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.engine.stats().lines
+    }
+
+    pub fn level(&self) -> u32 {
+        self.engine.stats().level
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.engine.is_game_over() || self.engine.is_finished()
+    }
+}
+
+fn color_code(color: Color) -> u8 {
+    color as u8 + 1
+}
+
+fn piece_code(kind: PieceKind) -> u8 {
+    color_code(kind.color())
+}
+
+fn board_cells(engine: &Engine) -> Vec<u8> {
+    let mut cells: Vec<u8> = engine
+        .cells()
+        .map(|(coord, color)| match color {
+            Some(color) if engine.cell_visibility(coord) > 0.0 => color_code(*color),
+            _ => 0,
+        })
+        .collect();
+    let index = |x: usize, y: usize| y * Matrix::WIDTH + x;
+    if let (Some(ghost), Some((_, color))) = (engine.ghost_cells(), engine.cursor_info()) {
+        for coord in ghost {
+            if coord.y < Matrix::HEIGHT {
+                cells[index(coord.x, coord.y)] = color_code(color) | GHOST;
+            }
+        }
+    }
+    if let Some((cursor, color)) = engine.cursor_info() {
+        for coord in cursor {
+            if coord.y < Matrix::HEIGHT {
+                cells[index(coord.x, coord.y)] = color_code(color);
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn board_cells_encode_cursor_and_ghost() {
+        let game = Game::new("sprint");
+        let cells = game.cells();
+        assert_eq!(cells.len(), Matrix::WIDTH * Matrix::HEIGHT);
+        assert_eq!(cells.iter().filter(|&&cell| cell & GHOST != 0).count(), 4);
+        let cursor = cells.iter().filter(|&&cell| cell != 0 && cell & GHOST == 0);
+        assert_eq!(cursor.count(), 4);
+        // 影子落在最底下一行
+        assert!(cells[..Matrix::WIDTH].iter().any(|&cell| cell & GHOST != 0));
+    }
+}
@@ -0,0 +1,80 @@
+import init, { Game } from "./pkg/tetris_rs.js";
+
+// 和 engine::Color 的顺序一致，编码是下标 + 1
+const COLORS = ["#edd400", "#729fcf", "#75507b", "#f57900", "#3465a4", "#73d216", "#ef2929", "#888a85"];
+const GHOST = 0x80;
+const TIMESTEP = 1000 / 60;
+const KEYS = {
+  ArrowLeft: "left",
+  ArrowRight: "right",
+  ArrowDown: "soft_drop",
+  ArrowUp: "rotate",
+  " ": "hard_drop",
+  Shift: "hold",
+};
+
+await init();
+
+const mode = new URLSearchParams(location.search).get("mode") ?? "marathon";
+let game = new Game(mode);
+const matrix = document.getElementById("matrix").getContext("2d");
+const next = document.getElementById("next").getContext("2d");
+const width = Game.width();
+const height = Game.height();
+const cell = matrix.canvas.width / width;
+
+document.addEventListener("keydown", (event) => {
+  if (game.is_over() && event.key === "Enter") {
+    game = new Game(mode);
+    return;
+  }
+  const action = KEYS[event.key];
+  if (action && game.input(action)) {
+    event.preventDefault();
+  }
+});
+
+function drawCell(ctx, x, y, code, size) {
+  ctx.fillStyle = COLORS[(code & ~GHOST) - 1];
+  ctx.globalAlpha = code & GHOST ? 0.3 : 1;
+  ctx.fillRect(x * size + 1, y * size + 1, size - 2, size - 2);
+  ctx.globalAlpha = 1;
+}
+
+function draw() {
+  matrix.clearRect(0, 0, matrix.canvas.width, matrix.canvas.height);
+  // 引擎的第 0 行在最下面
+  game.cells().forEach((code, i) => {
+    if (code !== 0) {
+      drawCell(matrix, i % width, height - 1 - Math.floor(i / width), code, cell);
+    }
+  });
+
+  next.clearRect(0, 0, next.canvas.width, next.canvas.height);
+  game.preview(5).forEach((code, i) => drawCell(next, 1, i * 2 + 1, code, next.canvas.width / 3));
+
+  const held = game.held();
+  document.getElementById("hold").textContent = held ? "■" : "-";
+  document.getElementById("hold").style.color = held ? COLORS[held - 1] : "";
+  document.getElementById("score").textContent = game.score();
+  document.getElementById("lines").textContent = game.lines();
+  document.getElementById("level").textContent = game.level();
+  if (game.is_over()) {
+    document.getElementById("status").textContent = "GAME OVER, press Enter to retry";
+  }
+}
+
+// 和桌面版一样固定步长更新，渲染跟着 requestAnimationFrame 走
+let previous = performance.now();
+let accumulator = 0;
+function frame(now) {
+  accumulator = Math.min(accumulator + now - previous, TIMESTEP * 5);
+  previous = now;
+  while (accumulator >= TIMESTEP) {
+    game.tick();
+    accumulator -= TIMESTEP;
+  }
+  draw();
+  requestAnimationFrame(frame);
+}
+requestAnimationFrame(frame);